@@ -0,0 +1,330 @@
+//! A function builder that validates each instruction as it is added.
+//!
+//! This module is only available with the `wasmparser` feature enabled.
+
+use super::*;
+use std::collections::HashSet;
+use wasmparser::{BinaryReader, BinaryReaderError, FuncValidator, WasmFeatures};
+
+/// A [`Function`] builder that type checks every instruction as it is added.
+///
+/// Each call to [`CheckedFunction::instruction`] runs the instruction through
+/// `wasmparser`'s [`FuncValidator`], which keeps track of the operand and
+/// control stacks, and returns an error right away if the instruction is not
+/// valid at that point.
+///
+/// Invalid instructions are not added to the function, but the validator may
+/// have already popped some of their operands, so the builder can't continue
+/// after an error: every later call returns the same error again.
+///
+/// Reported error offsets are relative to the start of the function body.
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{CheckedFunction, Instruction, ValType, ValidationResources};
+///
+/// let mut resources = ValidationResources::new();
+/// let ty = resources.func_type(vec![ValType::I32], vec![ValType::I64]);
+///
+/// let mut func = CheckedFunction::new(&resources, ty, vec![], &Default::default()).unwrap();
+/// func.instruction(Instruction::LocalGet(0)).unwrap();
+/// func.instruction(Instruction::I64ExtendI32U).unwrap();
+///
+/// // Only one `i64` is on the stack, so `i64.add` is rejected.
+/// let err = func.instruction(Instruction::I64Add).err().unwrap();
+/// assert_eq!(err.offset(), 4);
+/// ```
+pub struct CheckedFunction<T> {
+    func: Function,
+    validator: FuncValidator<T>,
+    error: Option<BinaryReaderError>,
+}
+
+impl<T: wasmparser::WasmModuleResources> CheckedFunction<T> {
+    /// Create a new checked function body of type `ty` with the given locals.
+    ///
+    /// The `resources` describe the rest of the module, and `features` the
+    /// proposals the function may use.
+    pub fn new<L>(
+        resources: T,
+        ty: u32,
+        locals: L,
+        features: &WasmFeatures,
+    ) -> Result<Self, BinaryReaderError>
+    where
+        L: IntoIterator<Item = (u32, ValType)>,
+        L::IntoIter: ExactSizeIterator + Clone,
+    {
+        let locals = locals.into_iter();
+        let mut validator = FuncValidator::new(ty, 0, resources, features)?;
+        for (count, ty) in locals.clone() {
+            validator.define_locals(0, count, val_type(ty))?;
+        }
+        Ok(CheckedFunction {
+            func: Function::new(locals),
+            validator,
+            error: None,
+        })
+    }
+
+    /// Validate an instruction and, if it is valid, write it into this function
+    /// body.
+    pub fn instruction(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<&mut Self, BinaryReaderError> {
+        self.check()?;
+        let offset = self.func.bytes.len();
+        let mut bytes = vec![];
+        instruction.encode(&mut bytes);
+        let op = BinaryReader::new_with_offset(&bytes, offset).read_operator()?;
+        if let Err(e) = self.validator.op(offset, &op) {
+            self.error = Some(e.clone());
+            return Err(e);
+        }
+        self.func.bytes.extend(bytes);
+        Ok(self)
+    }
+
+    /// The height of the whole operand stack of this function.
+    pub fn operand_stack_height(&self) -> u32 {
+        self.validator.operand_stack_height()
+    }
+
+    /// Check that the function body is complete, and return it.
+    pub fn finish(mut self) -> Result<Function, BinaryReaderError> {
+        self.check()?;
+        self.validator.finish(self.func.bytes.len())?;
+        Ok(self.func)
+    }
+
+    fn check(&self) -> Result<(), BinaryReaderError> {
+        match &self.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A description of a module, for checking function bodies against with a
+/// [`CheckedFunction`].
+///
+/// Entities are added in index order, with imported entities first, just like
+/// they are numbered in the module itself.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationResources {
+    types: Vec<wasmparser::FuncType>,
+    functions: Vec<u32>,
+    tables: Vec<wasmparser::TableType>,
+    memories: Vec<wasmparser::MemoryType>,
+    globals: Vec<wasmparser::GlobalType>,
+    events: Vec<u32>,
+    elements: Vec<wasmparser::Type>,
+    data_count: u32,
+    referenced: HashSet<u32>,
+}
+
+impl ValidationResources {
+    /// Create an empty description of a module.
+    pub fn new() -> ValidationResources {
+        ValidationResources::default()
+    }
+
+    /// Add a function type, and return its type index.
+    pub fn func_type<P, R>(&mut self, params: P, results: R) -> u32
+    where
+        P: IntoIterator<Item = ValType>,
+        R: IntoIterator<Item = ValType>,
+    {
+        self.types.push(wasmparser::FuncType {
+            params: params.into_iter().map(val_type).collect(),
+            returns: results.into_iter().map(val_type).collect(),
+        });
+        u32::try_from(self.types.len() - 1).unwrap()
+    }
+
+    /// Add a function of the given type, and return its function index.
+    pub fn function(&mut self, type_index: u32) -> u32 {
+        self.functions.push(type_index);
+        u32::try_from(self.functions.len() - 1).unwrap()
+    }
+
+    /// Add a table, and return its table index.
+    pub fn table(&mut self, table_type: TableType) -> u32 {
+        self.tables.push(wasmparser::TableType {
+            element_type: val_type(table_type.element_type),
+            limits: limits(table_type.limits),
+        });
+        u32::try_from(self.tables.len() - 1).unwrap()
+    }
+
+    /// Add a memory, and return its memory index.
+    pub fn memory(&mut self, memory_type: MemoryType) -> u32 {
+        self.memories.push(if memory_type.memory64 {
+            wasmparser::MemoryType::M64 {
+                limits: wasmparser::ResizableLimits64 {
+                    initial: memory_type.limits.min.into(),
+                    maximum: memory_type.limits.max.map(Into::into),
+                },
+                shared: memory_type.shared,
+            }
+        } else {
+            wasmparser::MemoryType::M32 {
+                limits: limits(memory_type.limits),
+                shared: memory_type.shared,
+            }
+        });
+        u32::try_from(self.memories.len() - 1).unwrap()
+    }
+
+    /// Add a global, and return its global index.
+    pub fn global(&mut self, global_type: GlobalType) -> u32 {
+        self.globals.push(wasmparser::GlobalType {
+            content_type: val_type(global_type.val_type),
+            mutable: global_type.mutable,
+        });
+        u32::try_from(self.globals.len() - 1).unwrap()
+    }
+
+    /// Add an event, and return its event index.
+    pub fn event(&mut self, event_type: EventType) -> u32 {
+        self.events.push(event_type.type_index);
+        u32::try_from(self.events.len() - 1).unwrap()
+    }
+
+    /// Add an element segment of the given type, and return its index.
+    pub fn element_segment(&mut self, element_type: ValType) -> u32 {
+        self.elements.push(val_type(element_type));
+        u32::try_from(self.elements.len() - 1).unwrap()
+    }
+
+    /// Set the number of data segments, as given by the data count section.
+    pub fn data_count(&mut self, count: u32) {
+        self.data_count = count;
+    }
+
+    /// Declare that a function is referenced outside of function bodies, for
+    /// example by an export or an element segment, which `ref.func` requires.
+    pub fn reference_function(&mut self, function_index: u32) {
+        self.referenced.insert(function_index);
+    }
+}
+
+impl wasmparser::WasmModuleResources for ValidationResources {
+    type FuncType = wasmparser::FuncType;
+
+    fn table_at(&self, at: u32) -> Option<wasmparser::TableType> {
+        self.tables.get(at as usize).copied()
+    }
+    fn memory_at(&self, at: u32) -> Option<wasmparser::MemoryType> {
+        self.memories.get(at as usize).copied()
+    }
+    fn event_at(&self, at: u32) -> Option<&Self::FuncType> {
+        let ty = *self.events.get(at as usize)?;
+        self.func_type_at(ty)
+    }
+    fn global_at(&self, at: u32) -> Option<wasmparser::GlobalType> {
+        self.globals.get(at as usize).copied()
+    }
+    fn func_type_at(&self, type_idx: u32) -> Option<&Self::FuncType> {
+        self.types.get(type_idx as usize)
+    }
+    fn type_of_function(&self, func_idx: u32) -> Option<&Self::FuncType> {
+        let ty = *self.functions.get(func_idx as usize)?;
+        self.func_type_at(ty)
+    }
+    fn element_type_at(&self, at: u32) -> Option<wasmparser::Type> {
+        self.elements.get(at as usize).copied()
+    }
+    fn element_count(&self) -> u32 {
+        u32::try_from(self.elements.len()).unwrap()
+    }
+    fn data_count(&self) -> u32 {
+        self.data_count
+    }
+    fn is_function_referenced(&self, idx: u32) -> bool {
+        self.referenced.contains(&idx)
+    }
+}
+
+fn val_type(ty: ValType) -> wasmparser::Type {
+    match ty {
+        ValType::I32 => wasmparser::Type::I32,
+        ValType::I64 => wasmparser::Type::I64,
+        ValType::F32 => wasmparser::Type::F32,
+        ValType::F64 => wasmparser::Type::F64,
+        ValType::V128 => wasmparser::Type::V128,
+        ValType::FuncRef => wasmparser::Type::FuncRef,
+        ValType::ExternRef => wasmparser::Type::ExternRef,
+    }
+}
+
+fn limits(limits: Limits) -> wasmparser::ResizableLimits {
+    wasmparser::ResizableLimits {
+        initial: limits.min,
+        maximum: limits.max,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poisoned_after_error() {
+        let mut resources = ValidationResources::new();
+        let ty = resources.func_type(vec![], vec![ValType::I32]);
+        let mut func =
+            CheckedFunction::new(&resources, ty, vec![(1, ValType::I32)], &Default::default())
+                .unwrap();
+        func.instruction(Instruction::LocalGet(0)).unwrap();
+        assert_eq!(func.operand_stack_height(), 1);
+
+        let err = func.instruction(Instruction::LocalGet(1)).err().unwrap();
+        assert_eq!(err.offset(), 5);
+        assert!(func.instruction(Instruction::End).is_err());
+        assert_eq!(func.finish().err().unwrap().offset(), 5);
+    }
+
+    #[test]
+    fn finish_requires_end() {
+        let mut resources = ValidationResources::new();
+        let ty = resources.func_type(vec![], vec![]);
+        let func = CheckedFunction::new(&resources, ty, vec![], &Default::default()).unwrap();
+        assert!(func.finish().is_err());
+
+        let mut func = CheckedFunction::new(&resources, ty, vec![], &Default::default()).unwrap();
+        func.instruction(Instruction::End).unwrap();
+        let func = func.finish().unwrap();
+        assert_eq!(func.bytes, [0x00, 0x0b]);
+    }
+
+    #[test]
+    fn events_have_their_types() {
+        let mut resources = ValidationResources::new();
+        let ty = resources.func_type(vec![], vec![]);
+        let event_ty = resources.func_type(vec![ValType::I32], vec![]);
+        let event = resources.event(EventType {
+            type_index: event_ty,
+        });
+        let features = WasmFeatures {
+            exceptions: true,
+            ..Default::default()
+        };
+
+        let mut func = CheckedFunction::new(&resources, ty, vec![], &features).unwrap();
+        func.instruction(Instruction::I32Const(1)).unwrap();
+        func.instruction(Instruction::Throw(event)).unwrap();
+        func.instruction(Instruction::End).unwrap();
+        func.finish().unwrap();
+
+        // The event's `i32` parameter is missing.
+        let mut func = CheckedFunction::new(&resources, ty, vec![], &features).unwrap();
+        assert!(func.instruction(Instruction::Throw(event)).is_err());
+
+        let mut func = CheckedFunction::new(&resources, ty, vec![], &features).unwrap();
+        func.instruction(Instruction::I32Const(1)).unwrap();
+        assert!(func.instruction(Instruction::Throw(event + 1)).is_err());
+    }
+}
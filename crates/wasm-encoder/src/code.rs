@@ -1,4 +1,5 @@
 use super::*;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 /// An encoder for the code section.
 ///
@@ -34,6 +35,7 @@ use super::*;
 pub struct CodeSection {
     bytes: Vec<u8>,
     num_added: u32,
+    patchable: Vec<PatchableIndex>,
 }
 
 impl CodeSection {
@@ -42,15 +44,37 @@ impl CodeSection {
         CodeSection {
             bytes: vec![],
             num_added: 0,
+            patchable: vec![],
         }
     }
 
     /// Write a function body into this code section.
     pub fn function(&mut self, func: &Function) -> &mut Self {
+        let body_len = u32::try_from(func.bytes.len()).unwrap();
+        let body_start = self.bytes.len() + encoders::u32(body_len).len();
+        self.patchable
+            .extend(func.patchable.iter().map(|patch| PatchableIndex {
+                offset: body_start + patch.offset,
+                ..*patch
+            }));
         func.encode(&mut self.bytes);
         self.num_added += 1;
         self
     }
+
+    /// The patchable indices of all the functions in this code section.
+    ///
+    /// Offsets are relative to the start of the section's contents, just after
+    /// the section's size, which is how relocations address them. They depend
+    /// on the number of functions in the section, so only query them once all
+    /// functions have been added.
+    pub fn patchable_indices(&self) -> impl Iterator<Item = PatchableIndex> + '_ {
+        let count_len = encoders::u32(self.num_added).len();
+        self.patchable.iter().map(move |patch| PatchableIndex {
+            offset: count_len + patch.offset,
+            ..*patch
+        })
+    }
 }
 
 impl Section for CodeSection {
@@ -96,9 +120,17 @@ impl Section for CodeSection {
 /// code.function(&func);
 /// ```
 pub struct Function {
-    bytes: Vec<u8>,
+    pub(crate) bytes: Vec<u8>,
+    patchable: Vec<PatchableIndex>,
+    open_labels: Vec<OpenLabel>,
+    next_label: u32,
+    id: u32,
 }
 
+/// The source of the ids that tell apart the [`Label`]s of different
+/// functions.
+static NEXT_FUNCTION_ID: AtomicU32 = AtomicU32::new(0);
+
 impl Function {
     /// Create a new function body with the given locals.
     pub fn new<L>(locals: L) -> Self
@@ -113,7 +145,13 @@ impl Function {
             bytes.extend(encoders::u32(count));
             bytes.push(ty.into());
         }
-        Function { bytes }
+        Function {
+            bytes,
+            patchable: vec![],
+            open_labels: vec![],
+            next_label: 0,
+            id: NEXT_FUNCTION_ID.fetch_add(1, Ordering::Relaxed),
+        }
     }
 
     /// Write an instruction into this function body.
@@ -122,6 +160,42 @@ impl Function {
         self
     }
 
+    /// Write an instruction into this function body, padding its patchable
+    /// index (if any) to five bytes.
+    ///
+    /// The function index of a `call`, the type index of a `call_indirect`,
+    /// the global index of a `global.get` or `global.set`, and the static
+    /// offset of a load or store are padded; the position of the padded value
+    /// is recorded and available through [`Function::patchable_indices`]. Other
+    /// instructions are encoded exactly as with [`Function::instruction`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use wasm_encoder::{Function, Instruction, PatchableIndexKind};
+    ///
+    /// let mut func = Function::new(vec![]);
+    /// func.padded_instruction(Instruction::Call(3));
+    ///
+    /// let patch = func.patchable_indices()[0];
+    /// assert_eq!(patch.kind, PatchableIndexKind::Function);
+    /// assert_eq!(patch.value, 3);
+    /// // One byte for the empty locals vector, one for the `call` opcode.
+    /// assert_eq!(patch.offset, 2);
+    /// ```
+    pub fn padded_instruction(&mut self, instruction: Instruction) -> &mut Self {
+        if let Some(patch) = instruction.encode_padded(&mut self.bytes) {
+            self.patchable.push(patch);
+        }
+        self
+    }
+
+    /// The indices written by [`Function::padded_instruction`] so far, in the
+    /// order they were written.
+    pub fn patchable_indices(&self) -> &[PatchableIndex] {
+        &self.patchable
+    }
+
     /// Add raw bytes to this function's body.
     pub fn raw<B>(&mut self, bytes: B) -> &mut Self
     where
@@ -139,6 +213,241 @@ impl Function {
     }
 }
 
+/// Structured control flow.
+///
+/// Instead of writing `block`, `loop`, `if` and branch instructions with raw
+/// relative depths, these methods hand out a [`Label`] for each block they
+/// open, and compute the depth of a branch to that label for you. They only
+/// know about the blocks they opened themselves, so don't mix them with
+/// control instructions written through [`Function::instruction`] in the same
+/// function.
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{BlockType, Function, Instruction};
+///
+/// // (loop $l
+/// //   (block $b
+/// //     local.get 0
+/// //     br_if $b
+/// //     br $l))
+/// let mut func = Function::new(vec![]);
+/// let l = func.loop_(BlockType::Empty);
+/// let b = func.block(BlockType::Empty);
+/// func.instruction(Instruction::LocalGet(0));
+/// func.br_if(b).unwrap();
+/// func.br(l).unwrap();
+/// func.end(b).unwrap();
+/// func.end(l).unwrap();
+/// func.end_body().unwrap();
+/// ```
+impl Function {
+    /// Write a `block` and return its label.
+    pub fn block(&mut self, ty: BlockType) -> Label {
+        self.instruction(Instruction::Block(ty));
+        self.open_label(false)
+    }
+
+    /// Write a `loop` and return its label.
+    ///
+    /// Branching to a loop's label jumps back to its start.
+    pub fn loop_(&mut self, ty: BlockType) -> Label {
+        self.instruction(Instruction::Loop(ty));
+        self.open_label(false)
+    }
+
+    /// Write an `if` and return its label.
+    pub fn if_(&mut self, ty: BlockType) -> Label {
+        self.instruction(Instruction::If(ty));
+        self.open_label(true)
+    }
+
+    /// Write the `else` of the innermost block, which must be the `if` that
+    /// opened `label` and not already have an `else`.
+    pub fn else_(&mut self, label: Label) -> Result<&mut Self, LabelError> {
+        self.innermost(label)?;
+        let open = self.open_labels.last_mut().unwrap();
+        if !open.is_if || open.has_else {
+            return Err(LabelError::NotIf(label));
+        }
+        open.has_else = true;
+        Ok(self.instruction(Instruction::Else))
+    }
+
+    /// Write the `end` of the innermost block, which must be the one that
+    /// opened `label`.
+    pub fn end(&mut self, label: Label) -> Result<&mut Self, LabelError> {
+        self.innermost(label)?;
+        self.open_labels.pop();
+        Ok(self.instruction(Instruction::End))
+    }
+
+    /// Write the final `end` of the function body, checking that every block
+    /// opened with a label has been closed.
+    pub fn end_body(&mut self) -> Result<&mut Self, LabelError> {
+        if let Some(open) = self.open_labels.last() {
+            return Err(LabelError::Unclosed(open.label));
+        }
+        Ok(self.instruction(Instruction::End))
+    }
+
+    /// Write a `br` to `label`.
+    pub fn br(&mut self, label: Label) -> Result<&mut Self, LabelError> {
+        let depth = self.depth(label)?;
+        Ok(self.instruction(Instruction::Br(depth)))
+    }
+
+    /// Write a `br_if` to `label`.
+    pub fn br_if(&mut self, label: Label) -> Result<&mut Self, LabelError> {
+        let depth = self.depth(label)?;
+        Ok(self.instruction(Instruction::BrIf(depth)))
+    }
+
+    /// Write a `br_table` branching to one of `labels`, or to `default` if the
+    /// operand is out of bounds.
+    pub fn br_table(&mut self, labels: &[Label], default: Label) -> Result<&mut Self, LabelError> {
+        let depths = labels
+            .iter()
+            .map(|l| self.depth(*l))
+            .collect::<Result<Vec<_>, _>>()?;
+        let default = self.depth(default)?;
+        Ok(self.instruction(Instruction::BrTable(&depths, default)))
+    }
+
+    /// The labels of the blocks that are currently open, innermost last.
+    pub fn open_labels(&self) -> impl ExactSizeIterator<Item = Label> + '_ {
+        self.open_labels.iter().map(|open| open.label)
+    }
+
+    fn open_label(&mut self, is_if: bool) -> Label {
+        let label = Label {
+            owner: self.id,
+            index: self.next_label,
+        };
+        self.next_label += 1;
+        self.open_labels.push(OpenLabel {
+            label,
+            is_if,
+            has_else: false,
+        });
+        label
+    }
+
+    fn depth(&self, label: Label) -> Result<u32, LabelError> {
+        match self
+            .open_labels
+            .iter()
+            .rev()
+            .position(|open| open.label == label)
+        {
+            Some(depth) => Ok(u32::try_from(depth).unwrap()),
+            None => Err(self.not_open(label)),
+        }
+    }
+
+    fn innermost(&self, label: Label) -> Result<(), LabelError> {
+        match self.open_labels.last() {
+            Some(open) if open.label == label => Ok(()),
+            Some(open) if self.depth(label).is_ok() => Err(LabelError::NotInnermost {
+                label,
+                innermost: open.label,
+            }),
+            _ => Err(self.not_open(label)),
+        }
+    }
+
+    fn not_open(&self, label: Label) -> LabelError {
+        if label.owner != self.id {
+            LabelError::Unknown(label)
+        } else if label.index < self.next_label {
+            LabelError::Closed(label)
+        } else {
+            LabelError::Unknown(label)
+        }
+    }
+}
+
+/// A handle to a `block`, `loop` or `if` opened in a [`Function`].
+///
+/// Labels are only meaningful in the function that created them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Label {
+    owner: u32,
+    index: u32,
+}
+
+struct OpenLabel {
+    label: Label,
+    is_if: bool,
+    has_else: bool,
+}
+
+/// A misuse of [`Label`]s in a [`Function`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelError {
+    /// The label's block has already been closed.
+    Closed(Label),
+    /// The label was not created by this function.
+    Unknown(Label),
+    /// The label's block is open, but other blocks opened after it must be
+    /// closed first.
+    NotInnermost {
+        /// The label that was given.
+        label: Label,
+        /// The label of the innermost open block.
+        innermost: Label,
+    },
+    /// `else` was written for a label that is not an `if`, or for an `if`
+    /// that already has an `else`.
+    NotIf(Label),
+    /// The function body was ended while this label's block was still open.
+    Unclosed(Label),
+}
+
+impl std::fmt::Display for LabelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LabelError::Closed(l) => write!(f, "label {} is already closed", l.index),
+            LabelError::Unknown(l) => write!(f, "label {} is not from this function", l.index),
+            LabelError::NotInnermost { label, innermost } => write!(
+                f,
+                "label {} is not the innermost open label ({} is)",
+                label.index, innermost.index
+            ),
+            LabelError::NotIf(l) => write!(f, "label {} is not an `if` without an `else`", l.index),
+            LabelError::Unclosed(l) => write!(f, "label {} is still open", l.index),
+        }
+    }
+}
+
+impl std::error::Error for LabelError {}
+
+/// The kind of value written at a [`PatchableIndex`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PatchableIndexKind {
+    /// A function index, as in `call`.
+    Function,
+    /// A type index, as in `call_indirect`.
+    Type,
+    /// A global index, as in `global.get` and `global.set`.
+    Global,
+    /// The static offset of a load or store.
+    MemoryOffset,
+}
+
+/// A five-byte padded ULEB128 written by [`Function::padded_instruction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PatchableIndex {
+    /// What the padded value refers to.
+    pub kind: PatchableIndexKind,
+    /// The byte offset of the padded value, relative to the start of the
+    /// function body (that is, just after the body's size).
+    pub offset: usize,
+    /// The value that was written.
+    pub value: u32,
+}
+
 /// The immediate for a memory instruction.
 #[derive(Clone, Copy, Debug)]
 pub struct MemArg {
@@ -205,6 +514,18 @@ pub enum Instruction<'a> {
     Call(u32),
     CallIndirect { ty: u32, table: u32 },
 
+    // Exception handling instructions.
+    Try(BlockType),
+    Catch(u32),
+    CatchAll,
+    Throw(u32),
+    Rethrow(u32),
+    Unwind,
+
+    // Tail call instructions.
+    ReturnCall(u32),
+    ReturnCallIndirect { ty: u32, table: u32 },
+
     // Parametric instructions.
     Drop,
     Select,
@@ -390,7 +711,292 @@ pub enum Instruction<'a> {
     I64TruncSatF64U,
 
     // SIMD instructions.
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
     V128Const(i128),
+    I8x16Shuffle { lanes: [u8; 16] },
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS { lane: u8 },
+    I8x16ExtractLaneU { lane: u8 },
+    I8x16ReplaceLane { lane: u8 },
+    I16x8ExtractLaneS { lane: u8 },
+    I16x8ExtractLaneU { lane: u8 },
+    I16x8ReplaceLane { lane: u8 },
+    I32x4ExtractLane { lane: u8 },
+    I32x4ReplaceLane { lane: u8 },
+    I64x2ExtractLane { lane: u8 },
+    I64x2ReplaceLane { lane: u8 },
+    F32x4ExtractLane { lane: u8 },
+    F32x4ReplaceLane { lane: u8 },
+    F64x2ExtractLane { lane: u8 },
+    F64x2ReplaceLane { lane: u8 },
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128AndNot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128Load8Lane { memarg: MemArg, lane: u8 },
+    V128Load16Lane { memarg: MemArg, lane: u8 },
+    V128Load32Lane { memarg: MemArg, lane: u8 },
+    V128Load64Lane { memarg: MemArg, lane: u8 },
+    V128Store8Lane { memarg: MemArg, lane: u8 },
+    V128Store16Lane { memarg: MemArg, lane: u8 },
+    V128Store32Lane { memarg: MemArg, lane: u8 },
+    V128Store64Lane { memarg: MemArg, lane: u8 },
+    I8x16Abs,
+    I8x16Neg,
+    V128AnyTrue,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    I8x16RoundingAverageU,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8WidenLowI8x16S,
+    I16x8WidenHighI8x16S,
+    I16x8WidenLowI8x16U,
+    I16x8WidenHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8ExtMulLowI8x16S,
+    I16x8RoundingAverageU,
+    I16x8Q15MulrSatS,
+    I16x8ExtMulHighI8x16S,
+    I16x8ExtMulLowI8x16U,
+    I16x8ExtMulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4WidenLowI16x8S,
+    I32x4WidenHighI16x8S,
+    I32x4WidenLowI16x8U,
+    I32x4WidenHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtMulLowI16x8S,
+    I32x4ExtMulHighI16x8S,
+    I32x4ExtMulLowI16x8U,
+    I32x4ExtMulHighI16x8U,
+    I64x2Neg,
+    I64x2Bitmask,
+    I64x2WidenLowI32x4S,
+    I64x2WidenHighI32x4S,
+    I64x2WidenLowI32x4U,
+    I64x2WidenHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2ExtMulLowI32x4S,
+    I64x2ExtMulHighI32x4S,
+    I64x2Mul,
+    I64x2ExtMulLowI32x4U,
+    I64x2ExtMulHighI32x4U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    F64x2Ceil,
+    F64x2Floor,
+    F64x2Trunc,
+    F64x2Nearest,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4PMin,
+    F32x4PMax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2PMin,
+    F64x2PMax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+
+    // Atomic instructions (the threads proposal).
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
 
     // Reference types instructions.
     TypedSelect(ValType),
@@ -410,6 +1016,199 @@ pub enum Instruction<'a> {
 }
 
 impl Instruction<'_> {
+    /// Like `encode`, but pads this instruction's patchable index (if any) to
+    /// five bytes and reports where it was written.
+    fn encode_padded(&self, bytes: &mut Vec<u8>) -> Option<PatchableIndex> {
+        let padded = |bytes: &mut Vec<u8>, kind, value| {
+            let offset = bytes.len();
+            bytes.extend(encoders::u32_padded(value));
+            Some(PatchableIndex {
+                kind,
+                offset,
+                value,
+            })
+        };
+        match *self {
+            Instruction::Call(f) => {
+                bytes.push(0x10);
+                padded(bytes, PatchableIndexKind::Function, f)
+            }
+            Instruction::CallIndirect { ty, table } => {
+                bytes.push(0x11);
+                let patch = padded(bytes, PatchableIndexKind::Type, ty);
+                bytes.extend(encoders::u32(table));
+                patch
+            }
+            Instruction::ReturnCall(f) => {
+                bytes.push(0x12);
+                padded(bytes, PatchableIndexKind::Function, f)
+            }
+            Instruction::ReturnCallIndirect { ty, table } => {
+                bytes.push(0x13);
+                let patch = padded(bytes, PatchableIndexKind::Type, ty);
+                bytes.extend(encoders::u32(table));
+                patch
+            }
+            Instruction::GlobalGet(g) => {
+                bytes.push(0x23);
+                padded(bytes, PatchableIndexKind::Global, g)
+            }
+            Instruction::GlobalSet(g) => {
+                bytes.push(0x24);
+                padded(bytes, PatchableIndexKind::Global, g)
+            }
+            _ => match self.mem_arg() {
+                Some((memarg, lane)) => {
+                    // Memory instructions are their opcode followed by their
+                    // immediate and, for lane accesses, the lane, so peel the
+                    // opcode off the normal encoding.
+                    let mut opcode = vec![];
+                    self.encode(&mut opcode);
+                    let mut immediate = vec![];
+                    memarg.encode(&mut immediate);
+                    immediate.extend(lane);
+                    opcode.truncate(opcode.len() - immediate.len());
+                    bytes.extend(opcode);
+
+                    if memarg.memory_index == 0 {
+                        bytes.extend(encoders::u32(memarg.align));
+                    } else {
+                        bytes.extend(encoders::u32(memarg.align | (1 << 6)));
+                    }
+                    let patch = padded(bytes, PatchableIndexKind::MemoryOffset, memarg.offset);
+                    if memarg.memory_index != 0 {
+                        bytes.extend(encoders::u32(memarg.memory_index));
+                    }
+                    bytes.extend(lane);
+                    patch
+                }
+                None => {
+                    self.encode(bytes);
+                    None
+                }
+            },
+        }
+    }
+
+    /// The memory immediate of this instruction, if it has one, along with
+    /// the lane that follows it for lane loads and stores.
+    fn mem_arg(&self) -> Option<(MemArg, Option<u8>)> {
+        match *self {
+            Instruction::V128Load8Lane { memarg, lane }
+            | Instruction::V128Load16Lane { memarg, lane }
+            | Instruction::V128Load32Lane { memarg, lane }
+            | Instruction::V128Load64Lane { memarg, lane }
+            | Instruction::V128Store8Lane { memarg, lane }
+            | Instruction::V128Store16Lane { memarg, lane }
+            | Instruction::V128Store32Lane { memarg, lane }
+            | Instruction::V128Store64Lane { memarg, lane } => Some((memarg, Some(lane))),
+            Instruction::I32Load(m)
+            | Instruction::I64Load(m)
+            | Instruction::F32Load(m)
+            | Instruction::F64Load(m)
+            | Instruction::I32Load8_S(m)
+            | Instruction::I32Load8_U(m)
+            | Instruction::I32Load16_S(m)
+            | Instruction::I32Load16_U(m)
+            | Instruction::I64Load8_S(m)
+            | Instruction::I64Load8_U(m)
+            | Instruction::I64Load16_S(m)
+            | Instruction::I64Load16_U(m)
+            | Instruction::I64Load32_S(m)
+            | Instruction::I64Load32_U(m)
+            | Instruction::I32Store(m)
+            | Instruction::I64Store(m)
+            | Instruction::F32Store(m)
+            | Instruction::F64Store(m)
+            | Instruction::I32Store8(m)
+            | Instruction::I32Store16(m)
+            | Instruction::I64Store8(m)
+            | Instruction::I64Store16(m)
+            | Instruction::I64Store32(m)
+            | Instruction::V128Load(m)
+            | Instruction::V128Load8x8S(m)
+            | Instruction::V128Load8x8U(m)
+            | Instruction::V128Load16x4S(m)
+            | Instruction::V128Load16x4U(m)
+            | Instruction::V128Load32x2S(m)
+            | Instruction::V128Load32x2U(m)
+            | Instruction::V128Load8Splat(m)
+            | Instruction::V128Load16Splat(m)
+            | Instruction::V128Load32Splat(m)
+            | Instruction::V128Load64Splat(m)
+            | Instruction::V128Store(m)
+            | Instruction::V128Load32Zero(m)
+            | Instruction::V128Load64Zero(m)
+            | Instruction::MemoryAtomicNotify(m)
+            | Instruction::MemoryAtomicWait32(m)
+            | Instruction::MemoryAtomicWait64(m)
+            | Instruction::I32AtomicLoad(m)
+            | Instruction::I64AtomicLoad(m)
+            | Instruction::I32AtomicLoad8U(m)
+            | Instruction::I32AtomicLoad16U(m)
+            | Instruction::I64AtomicLoad8U(m)
+            | Instruction::I64AtomicLoad16U(m)
+            | Instruction::I64AtomicLoad32U(m)
+            | Instruction::I32AtomicStore(m)
+            | Instruction::I64AtomicStore(m)
+            | Instruction::I32AtomicStore8(m)
+            | Instruction::I32AtomicStore16(m)
+            | Instruction::I64AtomicStore8(m)
+            | Instruction::I64AtomicStore16(m)
+            | Instruction::I64AtomicStore32(m)
+            | Instruction::I32AtomicRmwAdd(m)
+            | Instruction::I64AtomicRmwAdd(m)
+            | Instruction::I32AtomicRmw8AddU(m)
+            | Instruction::I32AtomicRmw16AddU(m)
+            | Instruction::I64AtomicRmw8AddU(m)
+            | Instruction::I64AtomicRmw16AddU(m)
+            | Instruction::I64AtomicRmw32AddU(m)
+            | Instruction::I32AtomicRmwSub(m)
+            | Instruction::I64AtomicRmwSub(m)
+            | Instruction::I32AtomicRmw8SubU(m)
+            | Instruction::I32AtomicRmw16SubU(m)
+            | Instruction::I64AtomicRmw8SubU(m)
+            | Instruction::I64AtomicRmw16SubU(m)
+            | Instruction::I64AtomicRmw32SubU(m)
+            | Instruction::I32AtomicRmwAnd(m)
+            | Instruction::I64AtomicRmwAnd(m)
+            | Instruction::I32AtomicRmw8AndU(m)
+            | Instruction::I32AtomicRmw16AndU(m)
+            | Instruction::I64AtomicRmw8AndU(m)
+            | Instruction::I64AtomicRmw16AndU(m)
+            | Instruction::I64AtomicRmw32AndU(m)
+            | Instruction::I32AtomicRmwOr(m)
+            | Instruction::I64AtomicRmwOr(m)
+            | Instruction::I32AtomicRmw8OrU(m)
+            | Instruction::I32AtomicRmw16OrU(m)
+            | Instruction::I64AtomicRmw8OrU(m)
+            | Instruction::I64AtomicRmw16OrU(m)
+            | Instruction::I64AtomicRmw32OrU(m)
+            | Instruction::I32AtomicRmwXor(m)
+            | Instruction::I64AtomicRmwXor(m)
+            | Instruction::I32AtomicRmw8XorU(m)
+            | Instruction::I32AtomicRmw16XorU(m)
+            | Instruction::I64AtomicRmw8XorU(m)
+            | Instruction::I64AtomicRmw16XorU(m)
+            | Instruction::I64AtomicRmw32XorU(m)
+            | Instruction::I32AtomicRmwXchg(m)
+            | Instruction::I64AtomicRmwXchg(m)
+            | Instruction::I32AtomicRmw8XchgU(m)
+            | Instruction::I32AtomicRmw16XchgU(m)
+            | Instruction::I64AtomicRmw8XchgU(m)
+            | Instruction::I64AtomicRmw16XchgU(m)
+            | Instruction::I64AtomicRmw32XchgU(m)
+            | Instruction::I32AtomicRmwCmpxchg(m)
+            | Instruction::I64AtomicRmwCmpxchg(m)
+            | Instruction::I32AtomicRmw8CmpxchgU(m)
+            | Instruction::I32AtomicRmw16CmpxchgU(m)
+            | Instruction::I64AtomicRmw8CmpxchgU(m)
+            | Instruction::I64AtomicRmw16CmpxchgU(m)
+            | Instruction::I64AtomicRmw32CmpxchgU(m) => Some((m, None)),
+            _ => None,
+        }
+    }
+
     pub(crate) fn encode(&self, bytes: &mut Vec<u8>) {
         match *self {
             // Control instructions.
@@ -456,6 +1255,38 @@ impl Instruction<'_> {
                 bytes.extend(encoders::u32(table));
             }
 
+            // Exception handling instructions.
+            Instruction::Try(bt) => {
+                bytes.push(0x06);
+                bt.encode(bytes);
+            }
+            Instruction::Catch(e) => {
+                bytes.push(0x07);
+                bytes.extend(encoders::u32(e));
+            }
+            // `catch_all` shares its opcode with `else`.
+            Instruction::CatchAll => bytes.push(0x05),
+            Instruction::Throw(e) => {
+                bytes.push(0x08);
+                bytes.extend(encoders::u32(e));
+            }
+            Instruction::Rethrow(l) => {
+                bytes.push(0x09);
+                bytes.extend(encoders::u32(l));
+            }
+            Instruction::Unwind => bytes.push(0x0A),
+
+            // Tail call instructions.
+            Instruction::ReturnCall(f) => {
+                bytes.push(0x12);
+                bytes.extend(encoders::u32(f));
+            }
+            Instruction::ReturnCallIndirect { ty, table } => {
+                bytes.push(0x13);
+                bytes.extend(encoders::u32(ty));
+                bytes.extend(encoders::u32(table));
+            }
+
             // Parametric instructions.
             Instruction::Drop => bytes.push(0x1A),
             Instruction::Select => bytes.push(0x1B),
@@ -846,11 +1677,1466 @@ impl Instruction<'_> {
             }
 
             // SIMD instructions.
+            Instruction::V128Load(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x00));
+                m.encode(bytes);
+            }
+            Instruction::V128Load8x8S(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x01));
+                m.encode(bytes);
+            }
+            Instruction::V128Load8x8U(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x02));
+                m.encode(bytes);
+            }
+            Instruction::V128Load16x4S(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x03));
+                m.encode(bytes);
+            }
+            Instruction::V128Load16x4U(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x04));
+                m.encode(bytes);
+            }
+            Instruction::V128Load32x2S(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x05));
+                m.encode(bytes);
+            }
+            Instruction::V128Load32x2U(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x06));
+                m.encode(bytes);
+            }
+            Instruction::V128Load8Splat(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x07));
+                m.encode(bytes);
+            }
+            Instruction::V128Load16Splat(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x08));
+                m.encode(bytes);
+            }
+            Instruction::V128Load32Splat(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x09));
+                m.encode(bytes);
+            }
+            Instruction::V128Load64Splat(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0a));
+                m.encode(bytes);
+            }
+            Instruction::V128Store(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0b));
+                m.encode(bytes);
+            }
             Instruction::V128Const(x) => {
                 bytes.push(0xFD);
-                bytes.extend(encoders::u32(12));
+                bytes.extend(encoders::u32(0x0c));
                 bytes.extend(x.to_le_bytes().iter().copied());
             }
-        }
+            Instruction::I8x16Shuffle { lanes } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0d));
+                bytes.extend(lanes.iter().copied());
+            }
+            Instruction::I8x16Swizzle => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0e));
+            }
+            Instruction::I8x16Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x0f));
+            }
+            Instruction::I16x8Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x10));
+            }
+            Instruction::I32x4Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x11));
+            }
+            Instruction::I64x2Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x12));
+            }
+            Instruction::F32x4Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x13));
+            }
+            Instruction::F64x2Splat => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x14));
+            }
+            Instruction::I8x16ExtractLaneS { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x15));
+                bytes.push(lane);
+            }
+            Instruction::I8x16ExtractLaneU { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x16));
+                bytes.push(lane);
+            }
+            Instruction::I8x16ReplaceLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x17));
+                bytes.push(lane);
+            }
+            Instruction::I16x8ExtractLaneS { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x18));
+                bytes.push(lane);
+            }
+            Instruction::I16x8ExtractLaneU { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x19));
+                bytes.push(lane);
+            }
+            Instruction::I16x8ReplaceLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1a));
+                bytes.push(lane);
+            }
+            Instruction::I32x4ExtractLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1b));
+                bytes.push(lane);
+            }
+            Instruction::I32x4ReplaceLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1c));
+                bytes.push(lane);
+            }
+            Instruction::I64x2ExtractLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1d));
+                bytes.push(lane);
+            }
+            Instruction::I64x2ReplaceLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1e));
+                bytes.push(lane);
+            }
+            Instruction::F32x4ExtractLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x1f));
+                bytes.push(lane);
+            }
+            Instruction::F32x4ReplaceLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x20));
+                bytes.push(lane);
+            }
+            Instruction::F64x2ExtractLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x21));
+                bytes.push(lane);
+            }
+            Instruction::F64x2ReplaceLane { lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x22));
+                bytes.push(lane);
+            }
+            Instruction::I8x16Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x23));
+            }
+            Instruction::I8x16Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x24));
+            }
+            Instruction::I8x16LtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x25));
+            }
+            Instruction::I8x16LtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x26));
+            }
+            Instruction::I8x16GtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x27));
+            }
+            Instruction::I8x16GtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x28));
+            }
+            Instruction::I8x16LeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x29));
+            }
+            Instruction::I8x16LeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2a));
+            }
+            Instruction::I8x16GeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2b));
+            }
+            Instruction::I8x16GeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2c));
+            }
+            Instruction::I16x8Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2d));
+            }
+            Instruction::I16x8Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2e));
+            }
+            Instruction::I16x8LtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x2f));
+            }
+            Instruction::I16x8LtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x30));
+            }
+            Instruction::I16x8GtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x31));
+            }
+            Instruction::I16x8GtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x32));
+            }
+            Instruction::I16x8LeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x33));
+            }
+            Instruction::I16x8LeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x34));
+            }
+            Instruction::I16x8GeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x35));
+            }
+            Instruction::I16x8GeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x36));
+            }
+            Instruction::I32x4Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x37));
+            }
+            Instruction::I32x4Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x38));
+            }
+            Instruction::I32x4LtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x39));
+            }
+            Instruction::I32x4LtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3a));
+            }
+            Instruction::I32x4GtS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3b));
+            }
+            Instruction::I32x4GtU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3c));
+            }
+            Instruction::I32x4LeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3d));
+            }
+            Instruction::I32x4LeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3e));
+            }
+            Instruction::I32x4GeS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x3f));
+            }
+            Instruction::I32x4GeU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x40));
+            }
+            Instruction::F32x4Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x41));
+            }
+            Instruction::F32x4Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x42));
+            }
+            Instruction::F32x4Lt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x43));
+            }
+            Instruction::F32x4Gt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x44));
+            }
+            Instruction::F32x4Le => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x45));
+            }
+            Instruction::F32x4Ge => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x46));
+            }
+            Instruction::F64x2Eq => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x47));
+            }
+            Instruction::F64x2Ne => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x48));
+            }
+            Instruction::F64x2Lt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x49));
+            }
+            Instruction::F64x2Gt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4a));
+            }
+            Instruction::F64x2Le => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4b));
+            }
+            Instruction::F64x2Ge => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4c));
+            }
+            Instruction::V128Not => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4d));
+            }
+            Instruction::V128And => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4e));
+            }
+            Instruction::V128AndNot => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x4f));
+            }
+            Instruction::V128Or => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x50));
+            }
+            Instruction::V128Xor => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x51));
+            }
+            Instruction::V128Bitselect => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x52));
+            }
+            Instruction::V128Load8Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x58));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Load16Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x59));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Load32Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5a));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Load64Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5b));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store8Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5c));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store16Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5d));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store32Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5e));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::V128Store64Lane { memarg, lane } => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x5f));
+                memarg.encode(bytes);
+                bytes.push(lane);
+            }
+            Instruction::I8x16Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x60));
+            }
+            Instruction::I8x16Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x61));
+            }
+            Instruction::V128AnyTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x62));
+            }
+            Instruction::I8x16AllTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x63));
+            }
+            Instruction::I8x16Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x64));
+            }
+            Instruction::I8x16NarrowI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x65));
+            }
+            Instruction::I8x16NarrowI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x66));
+            }
+            Instruction::I8x16Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6b));
+            }
+            Instruction::I8x16ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6c));
+            }
+            Instruction::I8x16ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6d));
+            }
+            Instruction::I8x16Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6e));
+            }
+            Instruction::I8x16AddSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x6f));
+            }
+            Instruction::I8x16AddSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x70));
+            }
+            Instruction::I8x16Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x71));
+            }
+            Instruction::I8x16SubSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x72));
+            }
+            Instruction::I8x16SubSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x73));
+            }
+            Instruction::I8x16MinS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x76));
+            }
+            Instruction::I8x16MinU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x77));
+            }
+            Instruction::I8x16MaxS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x78));
+            }
+            Instruction::I8x16MaxU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x79));
+            }
+            Instruction::I8x16RoundingAverageU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x7b));
+            }
+            Instruction::I16x8Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x80));
+            }
+            Instruction::I16x8Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x81));
+            }
+            Instruction::I16x8AllTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x83));
+            }
+            Instruction::I16x8Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x84));
+            }
+            Instruction::I16x8NarrowI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x85));
+            }
+            Instruction::I16x8NarrowI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x86));
+            }
+            Instruction::I16x8WidenLowI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x87));
+            }
+            Instruction::I16x8WidenHighI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x88));
+            }
+            Instruction::I16x8WidenLowI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x89));
+            }
+            Instruction::I16x8WidenHighI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8a));
+            }
+            Instruction::I16x8Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8b));
+            }
+            Instruction::I16x8ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8c));
+            }
+            Instruction::I16x8ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8d));
+            }
+            Instruction::I16x8Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8e));
+            }
+            Instruction::I16x8AddSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x8f));
+            }
+            Instruction::I16x8AddSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x90));
+            }
+            Instruction::I16x8Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x91));
+            }
+            Instruction::I16x8SubSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x92));
+            }
+            Instruction::I16x8SubSatU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x93));
+            }
+            Instruction::I16x8Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x95));
+            }
+            Instruction::I16x8MinS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x96));
+            }
+            Instruction::I16x8MinU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x97));
+            }
+            Instruction::I16x8MaxS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x98));
+            }
+            Instruction::I16x8MaxU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x99));
+            }
+            Instruction::I16x8ExtMulLowI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9a));
+            }
+            Instruction::I16x8RoundingAverageU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9b));
+            }
+            Instruction::I16x8Q15MulrSatS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9c));
+            }
+            Instruction::I16x8ExtMulHighI8x16S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9d));
+            }
+            Instruction::I16x8ExtMulLowI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9e));
+            }
+            Instruction::I16x8ExtMulHighI8x16U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0x9f));
+            }
+            Instruction::I32x4Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa0));
+            }
+            Instruction::I32x4Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa1));
+            }
+            Instruction::I32x4AllTrue => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa3));
+            }
+            Instruction::I32x4Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa4));
+            }
+            Instruction::I32x4WidenLowI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa7));
+            }
+            Instruction::I32x4WidenHighI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa8));
+            }
+            Instruction::I32x4WidenLowI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xa9));
+            }
+            Instruction::I32x4WidenHighI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xaa));
+            }
+            Instruction::I32x4Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xab));
+            }
+            Instruction::I32x4ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xac));
+            }
+            Instruction::I32x4ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xad));
+            }
+            Instruction::I32x4Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xae));
+            }
+            Instruction::I32x4Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb1));
+            }
+            Instruction::I32x4Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb5));
+            }
+            Instruction::I32x4MinS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb6));
+            }
+            Instruction::I32x4MinU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb7));
+            }
+            Instruction::I32x4MaxS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb8));
+            }
+            Instruction::I32x4MaxU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xb9));
+            }
+            Instruction::I32x4DotI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xba));
+            }
+            Instruction::I32x4ExtMulLowI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbb));
+            }
+            Instruction::I32x4ExtMulHighI16x8S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbd));
+            }
+            Instruction::I32x4ExtMulLowI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbe));
+            }
+            Instruction::I32x4ExtMulHighI16x8U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xbf));
+            }
+            Instruction::I64x2Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc1));
+            }
+            Instruction::I64x2Bitmask => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc4));
+            }
+            Instruction::I64x2WidenLowI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc7));
+            }
+            Instruction::I64x2WidenHighI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc8));
+            }
+            Instruction::I64x2WidenLowI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xc9));
+            }
+            Instruction::I64x2WidenHighI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xca));
+            }
+            Instruction::I64x2Shl => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xcb));
+            }
+            Instruction::I64x2ShrS => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xcc));
+            }
+            Instruction::I64x2ShrU => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xcd));
+            }
+            Instruction::I64x2Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xce));
+            }
+            Instruction::I64x2Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd1));
+            }
+            Instruction::I64x2ExtMulLowI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd2));
+            }
+            Instruction::I64x2ExtMulHighI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd3));
+            }
+            Instruction::I64x2Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd5));
+            }
+            Instruction::I64x2ExtMulLowI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd6));
+            }
+            Instruction::I64x2ExtMulHighI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd7));
+            }
+            Instruction::F32x4Ceil => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd8));
+            }
+            Instruction::F32x4Floor => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xd9));
+            }
+            Instruction::F32x4Trunc => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xda));
+            }
+            Instruction::F32x4Nearest => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdb));
+            }
+            Instruction::F64x2Ceil => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdc));
+            }
+            Instruction::F64x2Floor => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdd));
+            }
+            Instruction::F64x2Trunc => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xde));
+            }
+            Instruction::F64x2Nearest => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xdf));
+            }
+            Instruction::F32x4Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe0));
+            }
+            Instruction::F32x4Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe1));
+            }
+            Instruction::F32x4Sqrt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe3));
+            }
+            Instruction::F32x4Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe4));
+            }
+            Instruction::F32x4Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe5));
+            }
+            Instruction::F32x4Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe6));
+            }
+            Instruction::F32x4Div => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe7));
+            }
+            Instruction::F32x4Min => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe8));
+            }
+            Instruction::F32x4Max => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xe9));
+            }
+            Instruction::F32x4PMin => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xea));
+            }
+            Instruction::F32x4PMax => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xeb));
+            }
+            Instruction::F64x2Abs => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xec));
+            }
+            Instruction::F64x2Neg => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xed));
+            }
+            Instruction::F64x2Sqrt => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xef));
+            }
+            Instruction::F64x2Add => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf0));
+            }
+            Instruction::F64x2Sub => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf1));
+            }
+            Instruction::F64x2Mul => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf2));
+            }
+            Instruction::F64x2Div => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf3));
+            }
+            Instruction::F64x2Min => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf4));
+            }
+            Instruction::F64x2Max => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf5));
+            }
+            Instruction::F64x2PMin => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf6));
+            }
+            Instruction::F64x2PMax => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf7));
+            }
+            Instruction::I32x4TruncSatF32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf8));
+            }
+            Instruction::I32x4TruncSatF32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xf9));
+            }
+            Instruction::F32x4ConvertI32x4S => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfa));
+            }
+            Instruction::F32x4ConvertI32x4U => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfb));
+            }
+            Instruction::V128Load32Zero(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfc));
+                m.encode(bytes);
+            }
+            Instruction::V128Load64Zero(m) => {
+                bytes.push(0xFD);
+                bytes.extend(encoders::u32(0xfd));
+                m.encode(bytes);
+            }
+
+            // Atomic instructions (the threads proposal).
+            Instruction::MemoryAtomicNotify(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x00));
+                m.encode(bytes);
+            }
+            Instruction::MemoryAtomicWait32(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x01));
+                m.encode(bytes);
+            }
+            Instruction::MemoryAtomicWait64(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x02));
+                m.encode(bytes);
+            }
+            Instruction::AtomicFence => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x03));
+                bytes.push(0x00);
+            }
+            Instruction::I32AtomicLoad(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x10));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicLoad(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x11));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicLoad8U(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x12));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicLoad16U(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x13));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicLoad8U(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x14));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicLoad16U(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x15));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicLoad32U(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x16));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicStore(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x17));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicStore(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x18));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicStore8(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x19));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicStore16(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1a));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicStore8(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1b));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicStore16(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1c));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicStore32(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1d));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwAdd(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1e));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwAdd(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x1f));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8AddU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x20));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16AddU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x21));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8AddU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x22));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16AddU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x23));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32AddU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x24));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwSub(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x25));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwSub(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x26));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8SubU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x27));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16SubU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x28));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8SubU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x29));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16SubU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2a));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32SubU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2b));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwAnd(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2c));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwAnd(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2d));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8AndU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2e));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16AndU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x2f));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8AndU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x30));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16AndU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x31));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32AndU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x32));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwOr(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x33));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwOr(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x34));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8OrU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x35));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16OrU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x36));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8OrU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x37));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16OrU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x38));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32OrU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x39));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwXor(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3a));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwXor(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3b));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8XorU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3c));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16XorU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3d));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8XorU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3e));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16XorU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x3f));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32XorU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x40));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwXchg(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x41));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwXchg(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x42));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8XchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x43));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16XchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x44));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8XchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x45));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16XchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x46));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32XchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x47));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmwCmpxchg(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x48));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmwCmpxchg(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x49));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw8CmpxchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4a));
+                m.encode(bytes);
+            }
+            Instruction::I32AtomicRmw16CmpxchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4b));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw8CmpxchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4c));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw16CmpxchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4d));
+                m.encode(bytes);
+            }
+            Instruction::I64AtomicRmw32CmpxchgU(m) => {
+                bytes.push(0xFE);
+                bytes.extend(encoders::u32(0x4e));
+                m.encode(bytes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padded_indices_decode() {
+        let mut func = Function::new(vec![]);
+        func.padded_instruction(Instruction::I32Const(0))
+            .padded_instruction(Instruction::I64Load(MemArg {
+                offset: 7,
+                align: 3,
+                memory_index: 0,
+            }))
+            .padded_instruction(Instruction::GlobalSet(1))
+            .padded_instruction(Instruction::V128Load16Lane {
+                memarg: MemArg {
+                    offset: 9,
+                    align: 1,
+                    memory_index: 0,
+                },
+                lane: 3,
+            })
+            .padded_instruction(Instruction::End);
+
+        let patches = func.patchable_indices().to_vec();
+        assert_eq!(
+            patches,
+            [
+                PatchableIndex {
+                    kind: PatchableIndexKind::MemoryOffset,
+                    offset: 5,
+                    value: 7,
+                },
+                PatchableIndex {
+                    kind: PatchableIndexKind::Global,
+                    offset: 11,
+                    value: 1,
+                },
+                PatchableIndex {
+                    kind: PatchableIndexKind::MemoryOffset,
+                    offset: 19,
+                    value: 9,
+                },
+            ]
+        );
+
+        let mut reader = wasmparser::BinaryReader::new(&func.bytes);
+        assert_eq!(reader.read_var_u32().unwrap(), 0);
+        let mut ops = vec![];
+        while !reader.eof() {
+            ops.push(format!("{:?}", reader.read_operator().unwrap()));
+        }
+        assert_eq!(ops.len(), 5);
+        assert!(ops[1].contains("I64Load"));
+        assert!(ops[1].contains("offset: 7"));
+        assert_eq!(ops[2], "GlobalSet { global_index: 1 }");
+        assert!(ops[3].starts_with("V128Load16Lane"));
+        assert!(ops[3].contains("offset: 9") && ops[3].contains("lane: 3"));
+
+        for patch in patches {
+            let mut reader = wasmparser::BinaryReader::new(&func.bytes[patch.offset..]);
+            assert_eq!(reader.read_var_u32().unwrap(), patch.value);
+            assert_eq!(reader.current_position(), 5);
+        }
+    }
+
+    #[test]
+    fn simd_decodes() {
+        let memarg = MemArg {
+            offset: 8,
+            align: 3,
+            memory_index: 0,
+        };
+        let mut func = Function::new(vec![]);
+        func.instruction(Instruction::V128Load8x8S(memarg))
+            .instruction(Instruction::V128Load64Lane { memarg, lane: 1 })
+            .instruction(Instruction::I8x16Shuffle { lanes: [31; 16] })
+            .instruction(Instruction::F64x2ReplaceLane { lane: 1 })
+            .instruction(Instruction::V128Load64Zero(memarg));
+
+        let mut reader = wasmparser::BinaryReader::new(&func.bytes);
+        assert_eq!(reader.read_var_u32().unwrap(), 0);
+        let mut ops = vec![];
+        while !reader.eof() {
+            ops.push(format!("{:?}", reader.read_operator().unwrap()));
+        }
+        assert_eq!(ops.len(), 5);
+        assert!(ops[0].starts_with("V128Load8x8S"));
+        assert!(ops[1].starts_with("V128Load64Lane") && ops[1].contains("lane: 1"));
+        assert!(ops[2].starts_with("I8x16Shuffle"));
+        assert_eq!(ops[3], "F64x2ReplaceLane { lane: 1 }");
+        assert!(ops[4].starts_with("V128Load64Zero") && ops[4].contains("offset: 8"));
+    }
+
+    #[test]
+    fn exceptions_and_tail_calls_validate() {
+        let mut types = TypeSection::new();
+        types.function(vec![], vec![]);
+        types.function(vec![ValType::I32], vec![]);
+        let mut funcs = FunctionSection::new();
+        funcs.function(0);
+        let mut events = EventSection::new();
+        events.event(EventType { type_index: 1 });
+        let mut exports = ExportSection::new();
+        exports.export("e", Export::Event(0));
+
+        let mut func = Function::new(vec![]);
+        for i in &[
+            Instruction::Try(BlockType::Empty),
+            Instruction::I32Const(1),
+            Instruction::Throw(0),
+            Instruction::Catch(0),
+            Instruction::Drop,
+            Instruction::CatchAll,
+            Instruction::Rethrow(0),
+            Instruction::End,
+            Instruction::ReturnCall(0),
+            Instruction::End,
+        ] {
+            func.instruction(i.clone());
+        }
+        let mut code = CodeSection::new();
+        code.function(&func);
+
+        let mut module = Module::new();
+        module
+            .section(&types)
+            .section(&funcs)
+            .section(&events)
+            .section(&exports)
+            .section(&code);
+        let wasm = module.finish();
+
+        let mut validator = wasmparser::Validator::new();
+        validator.wasm_features(wasmparser::WasmFeatures {
+            exceptions: true,
+            tail_call: true,
+            ..Default::default()
+        });
+        validator.validate_all(&wasm).unwrap();
+    }
+
+    #[test]
+    fn label_depths() {
+        let mut func = Function::new(vec![]);
+        let outer = func.block(BlockType::Empty);
+        let lp = func.loop_(BlockType::Empty);
+        let cond = func.if_(BlockType::Empty);
+        func.br(outer).unwrap();
+        func.else_(cond).unwrap();
+        func.br_if(lp).unwrap();
+        func.br_table(&[cond, lp], outer).unwrap();
+        func.end(cond).unwrap();
+        func.end(lp).unwrap();
+        func.br(outer).unwrap();
+        func.end(outer).unwrap();
+        func.end_body().unwrap();
+
+        let mut expected = Function::new(vec![]);
+        for i in &[
+            Instruction::Block(BlockType::Empty),
+            Instruction::Loop(BlockType::Empty),
+            Instruction::If(BlockType::Empty),
+            Instruction::Br(2),
+            Instruction::Else,
+            Instruction::BrIf(1),
+            Instruction::BrTable(&[0, 1], 2),
+            Instruction::End,
+            Instruction::End,
+            Instruction::Br(0),
+            Instruction::End,
+            Instruction::End,
+        ] {
+            expected.instruction(i.clone());
+        }
+        assert_eq!(func.bytes, expected.bytes);
+    }
+
+    #[test]
+    fn label_misuse() {
+        let mut func = Function::new(vec![]);
+        let a = func.block(BlockType::Empty);
+        let b = func.block(BlockType::Empty);
+        assert_eq!(
+            func.end(a).err(),
+            Some(LabelError::NotInnermost {
+                label: a,
+                innermost: b
+            })
+        );
+        assert_eq!(func.else_(b).err(), Some(LabelError::NotIf(b)));
+        func.end(b).unwrap();
+        assert_eq!(func.br(b).err(), Some(LabelError::Closed(b)));
+        assert_eq!(func.end_body().err(), Some(LabelError::Unclosed(a)));
+
+        let other = Function::new(vec![]).block(BlockType::Empty);
+        let mut func = Function::new(vec![]);
+        assert_eq!(func.br(other).err(), Some(LabelError::Unknown(other)));
+
+        // A label from another function is unknown even when this function
+        // has an open label at the same position.
+        let own = func.block(BlockType::Empty);
+        assert_eq!(func.br(other).err(), Some(LabelError::Unknown(other)));
+        assert_eq!(func.end(other).err(), Some(LabelError::Unknown(other)));
+        func.end(own).unwrap();
+        assert_eq!(func.end(other).err(), Some(LabelError::Unknown(other)));
     }
 }
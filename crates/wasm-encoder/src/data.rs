@@ -11,12 +11,10 @@ use super::*;
 /// };
 ///
 /// let mut memory = MemorySection::new();
-/// memory.memory(MemoryType {
-///     limits: Limits {
-///         min: 1,
-///         max: None,
-///     },
-/// });
+/// memory.memory(MemoryType::new(Limits {
+///     min: 1,
+///     max: None,
+/// }));
 ///
 /// let mut data = DataSection::new();
 /// let memory_index = 0;
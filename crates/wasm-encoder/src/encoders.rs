@@ -19,6 +19,43 @@ pub fn s32(x: i32) -> impl ExactSizeIterator<Item = u8> {
     Buf5Iter { buf, range: 0..n }
 }
 
+/// Encode a `u32` as a ULEB128 padded to the maximum width of five bytes.
+///
+/// Padded encodings can be overwritten in place with any other `u32`, which
+/// is what linkers and instrumentation tools rely on when patching indices.
+///
+/// ```
+/// let bytes: Vec<u8> = wasm_encoder::encoders::u32_padded(1).collect();
+/// assert_eq!(bytes, [0x81, 0x80, 0x80, 0x80, 0x00]);
+/// ```
+pub fn u32_padded(n: u32) -> impl ExactSizeIterator<Item = u8> {
+    let mut buf = [0; 5];
+    let mut n = n;
+    for byte in buf.iter_mut().take(4) {
+        *byte = (n & 0x7f) as u8 | 0x80;
+        n >>= 7;
+    }
+    buf[4] = n as u8;
+    Buf5Iter { buf, range: 0..5 }
+}
+
+/// Encode an `i32` as a SLEB128 padded to the maximum width of five bytes.
+///
+/// ```
+/// let bytes: Vec<u8> = wasm_encoder::encoders::s32_padded(-1).collect();
+/// assert_eq!(bytes, [0xff, 0xff, 0xff, 0xff, 0x7f]);
+/// ```
+pub fn s32_padded(x: i32) -> impl ExactSizeIterator<Item = u8> {
+    let mut buf = [0; 5];
+    let mut x = x;
+    for byte in buf.iter_mut().take(4) {
+        *byte = (x & 0x7f) as u8 | 0x80;
+        x >>= 7;
+    }
+    buf[4] = (x & 0x7f) as u8;
+    Buf5Iter { buf, range: 0..5 }
+}
+
 /// Encode an `i64` that uses at most 33 bits as a SLEB128.
 ///
 /// # Panics
@@ -0,0 +1,88 @@
+use super::*;
+
+/// An encoder for the event section.
+///
+/// Note that this is part of the [exception handling proposal][proposal] and
+/// is not currently part of stable WebAssembly.
+///
+/// [proposal]: https://github.com/webassembly/exception-handling
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{Module, EventSection, EventType, TypeSection, ValType};
+///
+/// let mut types = TypeSection::new();
+/// types.function(vec![ValType::I32], vec![]);
+///
+/// let mut events = EventSection::new();
+/// events.event(EventType { type_index: 0 });
+///
+/// let mut module = Module::new();
+/// module
+///     .section(&types)
+///     .section(&events);
+///
+/// let wasm_bytes = module.finish();
+/// ```
+pub struct EventSection {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl EventSection {
+    /// Create a new event section encoder.
+    pub fn new() -> EventSection {
+        EventSection {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Define an event.
+    pub fn event(&mut self, event_type: EventType) -> &mut Self {
+        event_type.encode(&mut self.bytes);
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Default for EventSection {
+    fn default() -> EventSection {
+        EventSection::new()
+    }
+}
+
+impl Section for EventSection {
+    fn id(&self) -> u8 {
+        SectionId::Event.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        let num_added = encoders::u32(self.num_added);
+        let n = num_added.len();
+        sink.extend(
+            encoders::u32(u32::try_from(n + self.bytes.len()).unwrap())
+                .chain(num_added)
+                .chain(self.bytes.iter().copied()),
+        );
+    }
+}
+
+/// An event's type.
+pub struct EventType {
+    /// The `n`th type, which is a function with no results whose parameters
+    /// are the values carried by the exception.
+    pub type_index: u32,
+}
+
+impl EventType {
+    pub(crate) fn encode(&self, bytes: &mut Vec<u8>) {
+        // The only defined attribute is `0`, meaning "exception".
+        bytes.push(0x00);
+        bytes.extend(encoders::u32(self.type_index));
+    }
+}
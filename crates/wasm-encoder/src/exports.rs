@@ -80,6 +80,13 @@ pub enum Export {
     Memory(u32),
     /// An export of the `n`th global.
     Global(u32),
+    /// An export of the `n`th event.
+    ///
+    /// Note that this is part of the [exception handling proposal][proposal]
+    /// and is not currently part of stable WebAssembly.
+    ///
+    /// [proposal]: https://github.com/webassembly/exception-handling
+    Event(u32),
     /// An export of the `n`th instance.
     ///
     /// Note that this is part of the [module linking proposal][proposal] and is
@@ -115,6 +122,10 @@ impl Export {
                 bytes.push(ItemKind::Global as u8);
                 x
             }
+            Export::Event(x) => {
+                bytes.push(ItemKind::Event as u8);
+                x
+            }
             Export::Instance(x) => {
                 bytes.push(ItemKind::Instance as u8);
                 x
@@ -136,6 +147,7 @@ pub enum ItemKind {
     Table = 0x01,
     Memory = 0x02,
     Global = 0x03,
+    Event = 0x04,
     Module = 0x05,
     Instance = 0x06,
 }
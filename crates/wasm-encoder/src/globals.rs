@@ -65,6 +65,7 @@ impl Section for GlobalSection {
 }
 
 /// A global's type.
+#[derive(Clone, Copy, Debug)]
 pub struct GlobalType {
     /// This global's value type.
     pub val_type: ValType,
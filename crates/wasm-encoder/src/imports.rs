@@ -12,12 +12,10 @@ use std::convert::TryFrom;
 /// imports.import(
 ///     "env",
 ///     Some("memory"),
-///     MemoryType {
-///         limits: Limits {
-///             min: 1,
-///             max: None,
-///         }
-///     }
+///     MemoryType::new(Limits {
+///         min: 1,
+///         max: None,
+///     }),
 /// );
 ///
 /// let mut module = Module::new();
@@ -89,6 +87,13 @@ pub enum EntityType {
     Memory(MemoryType),
     /// A global type.
     Global(GlobalType),
+    /// An event type.
+    ///
+    /// Note that this is part of the [exception handling proposal][proposal]
+    /// and is not currently part of stable WebAssembly.
+    ///
+    /// [proposal]: https://github.com/webassembly/exception-handling
+    Event(EventType),
     /// The `n`th type, which is an instance.
     Instance(u32),
     /// The `n`th type, which is a module.
@@ -117,6 +122,12 @@ impl From<GlobalType> for EntityType {
     }
 }
 
+impl From<EventType> for EntityType {
+    fn from(e: EventType) -> Self {
+        EntityType::Event(e)
+    }
+}
+
 impl EntityType {
     pub(crate) fn encode(&self, dst: &mut Vec<u8>) {
         match self {
@@ -136,6 +147,10 @@ impl EntityType {
                 dst.push(0x03);
                 ty.encode(dst);
             }
+            EntityType::Event(ty) => {
+                dst.push(0x04);
+                ty.encode(dst);
+            }
             EntityType::Module(ty) => {
                 dst.push(0x05);
                 dst.extend(encoders::u32(*ty));
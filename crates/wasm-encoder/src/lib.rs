@@ -75,13 +75,17 @@ mod code;
 mod custom;
 mod data;
 mod elements;
+mod events;
 mod exports;
 mod functions;
 mod globals;
 mod imports;
 mod instances;
+mod linking;
 mod memories;
 mod modules;
+mod names;
+mod reloc;
 mod start;
 mod tables;
 mod types;
@@ -91,19 +95,31 @@ pub use code::*;
 pub use custom::*;
 pub use data::*;
 pub use elements::*;
+pub use events::*;
 pub use exports::*;
 pub use functions::*;
 pub use globals::*;
 pub use imports::*;
 pub use instances::*;
+pub use linking::*;
 pub use memories::*;
 pub use modules::*;
+pub use names::*;
+pub use reloc::*;
 pub use start::*;
 pub use tables::*;
 pub use types::*;
 
 pub mod encoders;
 
+#[cfg(feature = "wasmparser")]
+pub mod reencode;
+
+#[cfg(feature = "wasmparser")]
+mod checked;
+#[cfg(feature = "wasmparser")]
+pub use checked::*;
+
 use std::convert::TryFrom;
 
 /// A Wasm module that is being encoded.
@@ -215,6 +231,7 @@ pub enum SectionId {
     Code = 10,
     Data = 11,
     DataCount = 12,
+    Event = 13,
     Module = 14,
     Instance = 15,
     Alias = 16,
@@ -228,6 +245,7 @@ impl From<SectionId> for u8 {
 }
 
 /// Limits for a table or memory.
+#[derive(Clone, Copy, Debug)]
 pub struct Limits {
     /// The minimum size.
     pub min: u32,
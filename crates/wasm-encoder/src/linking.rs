@@ -0,0 +1,283 @@
+use super::*;
+
+/// An encoder for the "linking" custom section of a relocatable object file.
+///
+/// The layout follows the [WebAssembly tool conventions][conventions], which
+/// is what `wasm-ld` expects. The linking section must come after the data
+/// section, and before any [`RelocSection`]s.
+///
+/// [conventions]: https://github.com/WebAssembly/tool-conventions/blob/master/Linking.md
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{LinkingSection, Module, SymbolTable};
+///
+/// let mut symbols = SymbolTable::new();
+/// symbols.function(0, 0, Some("main"));
+///
+/// let mut linking = LinkingSection::new();
+/// linking.symbol_table(&symbols);
+///
+/// let mut module = Module::new();
+/// module.section(&linking);
+///
+/// let wasm_bytes = module.finish();
+/// ```
+pub struct LinkingSection {
+    bytes: Vec<u8>,
+}
+
+impl LinkingSection {
+    /// The version of the linking metadata this encoder produces.
+    pub const VERSION: u32 = 2;
+
+    /// Create a new linking section encoder.
+    pub fn new() -> LinkingSection {
+        LinkingSection {
+            bytes: encoders::u32(Self::VERSION).collect(),
+        }
+    }
+
+    /// Add a `WASM_SEGMENT_INFO` subsection.
+    pub fn segment_info(&mut self, segments: &SegmentInfo) -> &mut Self {
+        self.subsection(5, segments.num_added, &segments.bytes)
+    }
+
+    /// Add a `WASM_INIT_FUNCS` subsection.
+    pub fn init_funcs(&mut self, funcs: &InitFuncs) -> &mut Self {
+        self.subsection(6, funcs.num_added, &funcs.bytes)
+    }
+
+    /// Add a `WASM_SYMBOL_TABLE` subsection.
+    pub fn symbol_table(&mut self, symbols: &SymbolTable) -> &mut Self {
+        self.subsection(8, symbols.num_added, &symbols.bytes)
+    }
+
+    fn subsection(&mut self, ty: u8, count: u32, bytes: &[u8]) -> &mut Self {
+        let count = encoders::u32(count);
+        self.bytes.push(ty);
+        self.bytes.extend(encoders::u32(
+            u32::try_from(count.len() + bytes.len()).unwrap(),
+        ));
+        self.bytes.extend(count);
+        self.bytes.extend(bytes.iter().copied());
+        self
+    }
+}
+
+impl Default for LinkingSection {
+    fn default() -> LinkingSection {
+        LinkingSection::new()
+    }
+}
+
+impl Section for LinkingSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        CustomSection {
+            name: "linking",
+            data: &self.bytes,
+        }
+        .encode(sink);
+    }
+}
+
+/// The symbol table of a [`LinkingSection`].
+///
+/// Symbols are numbered in the order they are added, starting at zero; these
+/// are the indices that relocations and init functions refer to.
+///
+/// Function, global, event and table symbols only encode their name if they
+/// are defined (no [`SymbolTable::WASM_SYM_UNDEFINED`] flag) or have the
+/// [`SymbolTable::WASM_SYM_EXPLICIT_NAME`] flag, so the `name` given to them
+/// must be `Some` exactly in those cases.
+pub struct SymbolTable {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl SymbolTable {
+    /// The symbol has weak binding.
+    pub const WASM_SYM_BINDING_WEAK: u32 = 0x1;
+    /// The symbol has local binding, and is not visible to other objects.
+    pub const WASM_SYM_BINDING_LOCAL: u32 = 0x2;
+    /// The symbol has hidden visibility.
+    pub const WASM_SYM_VISIBILITY_HIDDEN: u32 = 0x4;
+    /// The symbol is not defined in this object.
+    pub const WASM_SYM_UNDEFINED: u32 = 0x10;
+    /// The symbol is exported from the final linked module.
+    pub const WASM_SYM_EXPORTED: u32 = 0x20;
+    /// The symbol's name is encoded even though it is undefined.
+    pub const WASM_SYM_EXPLICIT_NAME: u32 = 0x40;
+    /// The symbol must not be stripped by the linker.
+    pub const WASM_SYM_NO_STRIP: u32 = 0x80;
+
+    /// Create a new, empty symbol table.
+    pub fn new() -> SymbolTable {
+        SymbolTable {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// The number of symbols added so far, which is also the index of the next
+    /// symbol.
+    pub fn len(&self) -> u32 {
+        self.num_added
+    }
+
+    /// Whether no symbols have been added yet.
+    pub fn is_empty(&self) -> bool {
+        self.num_added == 0
+    }
+
+    /// Add a function symbol for the function with the given index.
+    pub fn function(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(0x00, flags, index, name)
+    }
+
+    /// Add a global symbol for the global with the given index.
+    pub fn global(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(0x02, flags, index, name)
+    }
+
+    /// Add an event symbol for the event with the given index.
+    pub fn event(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(0x04, flags, index, name)
+    }
+
+    /// Add a table symbol for the table with the given index.
+    pub fn table(&mut self, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.indexed(0x05, flags, index, name)
+    }
+
+    /// Add a data symbol.
+    ///
+    /// Defined data symbols must give their location; undefined ones must
+    /// not.
+    pub fn data(
+        &mut self,
+        flags: u32,
+        name: &str,
+        definition: Option<DataSymbolDefinition>,
+    ) -> &mut Self {
+        self.bytes.push(0x01);
+        self.bytes.extend(encoders::u32(flags));
+        self.bytes.extend(encoders::str(name));
+        if let Some(def) = definition {
+            self.bytes.extend(encoders::u32(def.index));
+            self.bytes.extend(encoders::u32(def.offset));
+            self.bytes.extend(encoders::u32(def.size));
+        }
+        self.num_added += 1;
+        self
+    }
+
+    /// Add a section symbol for the section with the given index.
+    pub fn section(&mut self, flags: u32, section: u32) -> &mut Self {
+        self.bytes.push(0x03);
+        self.bytes.extend(encoders::u32(flags));
+        self.bytes.extend(encoders::u32(section));
+        self.num_added += 1;
+        self
+    }
+
+    fn indexed(&mut self, kind: u8, flags: u32, index: u32, name: Option<&str>) -> &mut Self {
+        self.bytes.push(kind);
+        self.bytes.extend(encoders::u32(flags));
+        self.bytes.extend(encoders::u32(index));
+        if let Some(name) = name {
+            self.bytes.extend(encoders::str(name));
+        }
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Default for SymbolTable {
+    fn default() -> SymbolTable {
+        SymbolTable::new()
+    }
+}
+
+/// The location of a defined data symbol.
+#[derive(Clone, Copy, Debug)]
+pub struct DataSymbolDefinition {
+    /// The index of the data segment the symbol lives in.
+    pub index: u32,
+    /// The symbol's offset within its segment.
+    pub offset: u32,
+    /// The symbol's size in bytes.
+    pub size: u32,
+}
+
+/// The segment info of a [`LinkingSection`], with one entry per data segment.
+pub struct SegmentInfo {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl SegmentInfo {
+    /// Create a new, empty segment info subsection.
+    pub fn new() -> SegmentInfo {
+        SegmentInfo {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Describe the next data segment.
+    ///
+    /// The `alignment` is expressed as the exponent of a power of two.
+    pub fn segment(&mut self, name: &str, alignment: u32, flags: u32) -> &mut Self {
+        self.bytes.extend(encoders::str(name));
+        self.bytes.extend(encoders::u32(alignment));
+        self.bytes.extend(encoders::u32(flags));
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Default for SegmentInfo {
+    fn default() -> SegmentInfo {
+        SegmentInfo::new()
+    }
+}
+
+/// The init functions of a [`LinkingSection`].
+pub struct InitFuncs {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl InitFuncs {
+    /// Create a new, empty init functions subsection.
+    pub fn new() -> InitFuncs {
+        InitFuncs {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Call the function with the given symbol index at startup; lower
+    /// priorities run first.
+    pub fn function(&mut self, priority: u32, symbol_index: u32) -> &mut Self {
+        self.bytes.extend(encoders::u32(priority));
+        self.bytes.extend(encoders::u32(symbol_index));
+        self.num_added += 1;
+        self
+    }
+}
+
+impl Default for InitFuncs {
+    fn default() -> InitFuncs {
+        InitFuncs::new()
+    }
+}
@@ -8,12 +8,10 @@ use super::*;
 /// use wasm_encoder::{Module, MemorySection, MemoryType, Limits};
 ///
 /// let mut memories = MemorySection::new();
-/// memories.memory(MemoryType {
-///     limits: Limits {
-///         min: 1,
-///         max: None,
-///     },
-/// });
+/// memories.memory(MemoryType::new(Limits {
+///     min: 1,
+///     max: None,
+/// }));
 ///
 /// let mut module = Module::new();
 /// module.section(&memories);
@@ -35,6 +33,10 @@ impl MemorySection {
     }
 
     /// Define a memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory is shared but has no maximum size.
     pub fn memory(&mut self, memory_type: MemoryType) -> &mut Self {
         memory_type.encode(&mut self.bytes);
         self.num_added += 1;
@@ -62,13 +64,72 @@ impl Section for MemorySection {
 }
 
 /// A memory's type.
+#[derive(Clone, Copy, Debug)]
 pub struct MemoryType {
     /// This memory's limits (in units of pages).
     pub limits: Limits,
+    /// Whether this memory is shared between threads.
+    ///
+    /// Shared memories must have a maximum size, and encoding one without a
+    /// maximum panics. [`MemoryType::shared`] always gives it one.
+    pub shared: bool,
+    /// Whether this is a 64-bit memory, which is indexed with `i64` addresses.
+    /// [`MemoryType::memory64`] sets this on a constructed type.
+    ///
+    /// Note that this is part of the [memory64 proposal][proposal] and is not
+    /// currently part of stable WebAssembly.
+    ///
+    /// [proposal]: https://github.com/webassembly/memory64
+    pub memory64: bool,
 }
 
 impl MemoryType {
+    /// The type of an unshared 32-bit memory with the given limits.
+    pub fn new(limits: Limits) -> MemoryType {
+        MemoryType {
+            limits,
+            shared: false,
+            memory64: false,
+        }
+    }
+
+    /// The type of a shared 32-bit memory of between `min` and `max` pages.
+    pub fn shared(min: u32, max: u32) -> MemoryType {
+        MemoryType {
+            limits: Limits {
+                min,
+                max: Some(max),
+            },
+            shared: true,
+            memory64: false,
+        }
+    }
+
+    /// Make this a 64-bit memory, which is indexed with `i64` addresses.
+    pub fn memory64(mut self) -> MemoryType {
+        self.memory64 = true;
+        self
+    }
+
     pub(crate) fn encode(&self, bytes: &mut Vec<u8>) {
-        self.limits.encode(bytes);
+        let mut flags = 0;
+        if self.limits.max.is_some() {
+            flags |= 0x01;
+        }
+        if self.shared {
+            assert!(
+                self.limits.max.is_some(),
+                "shared memories must have a maximum"
+            );
+            flags |= 0x02;
+        }
+        if self.memory64 {
+            flags |= 0x04;
+        }
+        bytes.push(flags);
+        bytes.extend(encoders::u32(self.limits.min));
+        if let Some(max) = self.limits.max {
+            bytes.extend(encoders::u32(max));
+        }
     }
 }
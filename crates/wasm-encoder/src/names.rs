@@ -0,0 +1,167 @@
+use super::*;
+
+/// An encoder for the "name" custom section.
+///
+/// The [name section][names] gives debug names to the module, its functions
+/// and their locals. Subsections must be added in that order, and each at most
+/// once.
+///
+/// [names]: https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{IndirectNameMap, Module, NameMap, NameSection};
+///
+/// let mut funcs = NameMap::new();
+/// funcs.append(0, "main");
+///
+/// let mut main_locals = NameMap::new();
+/// main_locals.append(0, "argc");
+/// let mut locals = IndirectNameMap::new();
+/// locals.append(0, &main_locals);
+///
+/// let mut names = NameSection::new();
+/// names.module("my_module");
+/// names.functions(&funcs);
+/// names.locals(&locals);
+///
+/// let mut module = Module::new();
+/// module.section(&names);
+///
+/// let wasm_bytes = module.finish();
+/// ```
+pub struct NameSection {
+    bytes: Vec<u8>,
+}
+
+impl NameSection {
+    /// Create a new name section encoder.
+    pub fn new() -> NameSection {
+        NameSection { bytes: vec![] }
+    }
+
+    /// Add the module name subsection.
+    pub fn module(&mut self, name: &str) -> &mut Self {
+        let name = encoders::str(name).collect::<Vec<_>>();
+        self.subsection(0, &name)
+    }
+
+    /// Add the function name subsection.
+    pub fn functions(&mut self, names: &NameMap) -> &mut Self {
+        self.subsection(1, &names.encoded())
+    }
+
+    /// Add the local name subsection.
+    pub fn locals(&mut self, names: &IndirectNameMap) -> &mut Self {
+        self.subsection(2, &names.encoded())
+    }
+
+    fn subsection(&mut self, id: u8, bytes: &[u8]) -> &mut Self {
+        self.bytes.push(id);
+        self.bytes
+            .extend(encoders::u32(u32::try_from(bytes.len()).unwrap()));
+        self.bytes.extend(bytes.iter().copied());
+        self
+    }
+}
+
+impl Default for NameSection {
+    fn default() -> NameSection {
+        NameSection::new()
+    }
+}
+
+impl Section for NameSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        CustomSection {
+            name: "name",
+            data: &self.bytes,
+        }
+        .encode(sink);
+    }
+}
+
+/// A map from indices to names, as used by the [`NameSection`].
+///
+/// Entries must be appended in increasing index order.
+pub struct NameMap {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl NameMap {
+    /// Create a new, empty name map.
+    pub fn new() -> NameMap {
+        NameMap {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Name the item at the given index.
+    pub fn append(&mut self, index: u32, name: &str) -> &mut Self {
+        self.bytes.extend(encoders::u32(index));
+        self.bytes.extend(encoders::str(name));
+        self.num_added += 1;
+        self
+    }
+
+    fn encoded(&self) -> Vec<u8> {
+        encoders::u32(self.num_added)
+            .chain(self.bytes.iter().copied())
+            .collect()
+    }
+}
+
+impl Default for NameMap {
+    fn default() -> NameMap {
+        NameMap::new()
+    }
+}
+
+/// A map from indices to [`NameMap`]s, as used for local names in the
+/// [`NameSection`].
+///
+/// Entries must be appended in increasing index order.
+pub struct IndirectNameMap {
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl IndirectNameMap {
+    /// Create a new, empty indirect name map.
+    pub fn new() -> IndirectNameMap {
+        IndirectNameMap {
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Give the item at the given index the names in `names`.
+    pub fn append(&mut self, index: u32, names: &NameMap) -> &mut Self {
+        self.bytes.extend(encoders::u32(index));
+        self.bytes.extend(names.encoded());
+        self.num_added += 1;
+        self
+    }
+
+    fn encoded(&self) -> Vec<u8> {
+        encoders::u32(self.num_added)
+            .chain(self.bytes.iter().copied())
+            .collect()
+    }
+}
+
+impl Default for IndirectNameMap {
+    fn default() -> IndirectNameMap {
+        IndirectNameMap::new()
+    }
+}
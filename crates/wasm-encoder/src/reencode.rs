@@ -0,0 +1,1407 @@
+//! Conversion from parsed [`wasmparser`] items into `wasm-encoder` ones.
+//!
+//! This module is only available with the `wasmparser` feature enabled.
+//!
+//! The [`Reencode`] trait has a default method for every section and for every
+//! instruction, so a transformer only needs to override the hooks for the parts
+//! of the module it wants to change. Everything else is copied through
+//! unchanged. The default implementations live in the [`utils`] module, so
+//! overridden hooks can still delegate to them.
+//!
+//! # Example
+//!
+//! Rewrite every `i32.add` in a module into an `i32.sub`:
+//!
+//! ```
+//! use wasm_encoder::reencode::{self, Reencode};
+//! use wasm_encoder::{Function, Module};
+//! use wasmparser::Operator;
+//!
+//! struct AddToSub;
+//!
+//! impl Reencode for AddToSub {
+//!     fn parse_instruction(
+//!         &mut self,
+//!         func: &mut Function,
+//!         op: Operator<'_>,
+//!         raw: &[u8],
+//!     ) -> reencode::Result<()> {
+//!         match op {
+//!             Operator::I32Add => {
+//!                 func.instruction(wasm_encoder::Instruction::I32Sub);
+//!                 Ok(())
+//!             }
+//!             op => reencode::utils::parse_instruction(self, func, op, raw),
+//!         }
+//!     }
+//! }
+//!
+//! # let wasm = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+//! let mut module = Module::new();
+//! AddToSub.parse_core_module(&mut module, &wasm)?;
+//! let bytes = module.finish();
+//! # Ok::<(), reencode::Error>(())
+//! ```
+
+use crate::*;
+use std::fmt;
+
+/// An error that occurred while re-encoding a module.
+#[derive(Debug)]
+pub enum Error {
+    /// The input could not be parsed.
+    ParseError(wasmparser::BinaryReaderError),
+    /// A value type that `wasm-encoder` cannot represent.
+    UnsupportedType(wasmparser::Type),
+    /// A memory type that `wasm-encoder` cannot represent, such as a shared
+    /// or 64-bit memory.
+    UnsupportedMemoryType(wasmparser::MemoryType),
+    /// An entity kind that `wasm-encoder` cannot represent.
+    UnsupportedEntityType(wasmparser::ImportSectionEntryType),
+    /// An export kind that `wasm-encoder` cannot represent, such as types.
+    UnsupportedExportKind(wasmparser::ExternalKind),
+    /// A constant expression that isn't a single instruction followed by
+    /// `end`, at the given offset.
+    InvalidConstExpr(usize),
+}
+
+/// A specialized `Result` for re-encoding.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl From<wasmparser::BinaryReaderError> for Error {
+    fn from(err: wasmparser::BinaryReaderError) -> Self {
+        Error::ParseError(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ParseError(e) => e.fmt(f),
+            Error::UnsupportedType(ty) => write!(f, "unsupported value type: {:?}", ty),
+            Error::UnsupportedMemoryType(ty) => write!(f, "unsupported memory type: {:?}", ty),
+            Error::UnsupportedEntityType(ty) => write!(f, "unsupported entity type: {:?}", ty),
+            Error::UnsupportedExportKind(kind) => write!(f, "unsupported export kind: {:?}", kind),
+            Error::InvalidConstExpr(offset) => {
+                write!(f, "invalid constant expression (at offset {})", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ParseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A transformation from a module parsed by `wasmparser` into one built with
+/// `wasm-encoder`.
+///
+/// Every method has a default which re-encodes its input unchanged; see the
+/// [module documentation](self) for an example.
+pub trait Reencode {
+    /// Remap a type index.
+    fn type_index(&mut self, ty: u32) -> u32 {
+        ty
+    }
+
+    /// Remap a function index.
+    fn function_index(&mut self, func: u32) -> u32 {
+        func
+    }
+
+    /// Remap a table index.
+    fn table_index(&mut self, table: u32) -> u32 {
+        table
+    }
+
+    /// Remap a memory index.
+    fn memory_index(&mut self, memory: u32) -> u32 {
+        memory
+    }
+
+    /// Remap a global index.
+    fn global_index(&mut self, global: u32) -> u32 {
+        global
+    }
+
+    /// Remap an event index.
+    fn event_index(&mut self, event: u32) -> u32 {
+        event
+    }
+
+    /// Remap an element segment index.
+    fn element_index(&mut self, element: u32) -> u32 {
+        element
+    }
+
+    /// Remap a data segment index.
+    fn data_index(&mut self, data: u32) -> u32 {
+        data
+    }
+
+    /// Remap the number of data segments in the data count section.
+    fn data_count(&mut self, count: u32) -> u32 {
+        count
+    }
+
+    /// Convert a value type.
+    fn val_type(&mut self, ty: wasmparser::Type) -> Result<ValType> {
+        utils::val_type(self, ty)
+    }
+
+    /// Convert table or memory limits.
+    fn limits(&mut self, limits: wasmparser::ResizableLimits) -> Limits {
+        utils::limits(self, limits)
+    }
+
+    /// Convert a table type.
+    fn table_type(&mut self, ty: wasmparser::TableType) -> Result<TableType> {
+        utils::table_type(self, ty)
+    }
+
+    /// Convert a memory type.
+    fn memory_type(&mut self, ty: wasmparser::MemoryType) -> Result<MemoryType> {
+        utils::memory_type(self, ty)
+    }
+
+    /// Convert a global type.
+    fn global_type(&mut self, ty: wasmparser::GlobalType) -> Result<GlobalType> {
+        utils::global_type(self, ty)
+    }
+
+    /// Convert the type of an imported or exported entity.
+    fn entity_type(&mut self, ty: wasmparser::ImportSectionEntryType) -> Result<EntityType> {
+        utils::entity_type(self, ty)
+    }
+
+    /// Convert a block type.
+    fn block_type(&mut self, ty: wasmparser::TypeOrFuncType) -> Result<BlockType> {
+        utils::block_type(self, ty)
+    }
+
+    /// Convert a memory immediate.
+    fn mem_arg(&mut self, memarg: wasmparser::MemoryImmediate) -> MemArg {
+        utils::mem_arg(self, memarg)
+    }
+
+    /// Convert the constant expression of a global initializer or a segment
+    /// offset.
+    fn init_expr(&mut self, expr: &wasmparser::InitExpr<'_>) -> Result<Instruction<'static>> {
+        utils::init_expr(self, expr)
+    }
+
+    /// Re-encode a type section.
+    fn parse_type_section(
+        &mut self,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_type_section(self, types, section)
+    }
+
+    /// Re-encode an import section.
+    fn parse_import_section(
+        &mut self,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_import_section(self, imports, section)
+    }
+
+    /// Re-encode a function section.
+    fn parse_function_section(
+        &mut self,
+        functions: &mut FunctionSection,
+        section: wasmparser::FunctionSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_function_section(self, functions, section)
+    }
+
+    /// Re-encode a table section.
+    fn parse_table_section(
+        &mut self,
+        tables: &mut TableSection,
+        section: wasmparser::TableSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_table_section(self, tables, section)
+    }
+
+    /// Re-encode a memory section.
+    fn parse_memory_section(
+        &mut self,
+        memories: &mut MemorySection,
+        section: wasmparser::MemorySectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_memory_section(self, memories, section)
+    }
+
+    /// Re-encode a global section.
+    fn parse_global_section(
+        &mut self,
+        globals: &mut GlobalSection,
+        section: wasmparser::GlobalSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_global_section(self, globals, section)
+    }
+
+    /// Re-encode an event section.
+    fn parse_event_section(
+        &mut self,
+        events: &mut EventSection,
+        section: wasmparser::EventSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_event_section(self, events, section)
+    }
+
+    /// Re-encode an export section.
+    fn parse_export_section(
+        &mut self,
+        exports: &mut ExportSection,
+        section: wasmparser::ExportSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_export_section(self, exports, section)
+    }
+
+    /// Re-encode one export into an export section.
+    fn parse_export(
+        &mut self,
+        exports: &mut ExportSection,
+        export: wasmparser::Export<'_>,
+    ) -> Result<()> {
+        utils::parse_export(self, exports, export)
+    }
+
+    /// Re-encode an element section.
+    fn parse_element_section(
+        &mut self,
+        elements: &mut ElementSection,
+        section: wasmparser::ElementSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_element_section(self, elements, section)
+    }
+
+    /// Re-encode one element segment into an element section.
+    fn parse_element(
+        &mut self,
+        elements: &mut ElementSection,
+        element: wasmparser::Element<'_>,
+    ) -> Result<()> {
+        utils::parse_element(self, elements, element)
+    }
+
+    /// Re-encode a data section.
+    fn parse_data_section(
+        &mut self,
+        data: &mut DataSection,
+        section: wasmparser::DataSectionReader<'_>,
+    ) -> Result<()> {
+        utils::parse_data_section(self, data, section)
+    }
+
+    /// Re-encode one data segment into a data section.
+    fn parse_data(&mut self, data: &mut DataSection, segment: wasmparser::Data<'_>) -> Result<()> {
+        utils::parse_data(self, data, segment)
+    }
+
+    /// Re-encode one function body into the code section.
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        body: wasmparser::FunctionBody<'_>,
+    ) -> Result<()> {
+        utils::parse_function_body(self, code, body)
+    }
+
+    /// Re-encode one instruction into a function body.
+    ///
+    /// `raw` holds the instruction's original encoding.
+    fn parse_instruction(
+        &mut self,
+        func: &mut Function,
+        op: wasmparser::Operator<'_>,
+        raw: &[u8],
+    ) -> Result<()> {
+        utils::parse_instruction(self, func, op, raw)
+    }
+
+    /// Re-encode a custom section.
+    fn parse_custom_section(&mut self, module: &mut Module, name: &str, data: &[u8]) -> Result<()> {
+        module.section(&CustomSection { name, data });
+        Ok(())
+    }
+
+    /// Re-encode a whole module.
+    fn parse_core_module(&mut self, module: &mut Module, data: &[u8]) -> Result<()> {
+        utils::parse_core_module(self, module, data)
+    }
+}
+
+/// The identity transformation, which re-encodes a module unchanged.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RoundtripReencoder;
+
+impl Reencode for RoundtripReencoder {}
+
+/// The default implementations of the [`Reencode`] methods.
+pub mod utils {
+    use super::{Error, Reencode, Result};
+    use crate::*;
+    use std::convert::TryFrom;
+    use wasmparser::{Chunk, Operator, Parser, Payload, SectionReader};
+
+    /// The default implementation of [`Reencode::val_type`].
+    pub fn val_type<T: ?Sized + Reencode>(
+        _reencoder: &mut T,
+        ty: wasmparser::Type,
+    ) -> Result<ValType> {
+        Ok(match ty {
+            wasmparser::Type::I32 => ValType::I32,
+            wasmparser::Type::I64 => ValType::I64,
+            wasmparser::Type::F32 => ValType::F32,
+            wasmparser::Type::F64 => ValType::F64,
+            wasmparser::Type::V128 => ValType::V128,
+            wasmparser::Type::FuncRef => ValType::FuncRef,
+            wasmparser::Type::ExternRef => ValType::ExternRef,
+            ty => return Err(Error::UnsupportedType(ty)),
+        })
+    }
+
+    /// The default implementation of [`Reencode::limits`].
+    pub fn limits<T: ?Sized + Reencode>(
+        _reencoder: &mut T,
+        limits: wasmparser::ResizableLimits,
+    ) -> Limits {
+        Limits {
+            min: limits.initial,
+            max: limits.maximum,
+        }
+    }
+
+    /// The default implementation of [`Reencode::table_type`].
+    pub fn table_type<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        ty: wasmparser::TableType,
+    ) -> Result<TableType> {
+        Ok(TableType {
+            element_type: reencoder.val_type(ty.element_type)?,
+            limits: reencoder.limits(ty.limits),
+        })
+    }
+
+    /// The default implementation of [`Reencode::memory_type`].
+    pub fn memory_type<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        ty: wasmparser::MemoryType,
+    ) -> Result<MemoryType> {
+        match ty {
+            wasmparser::MemoryType::M32 { limits, shared } => Ok(MemoryType {
+                limits: reencoder.limits(limits),
+                shared,
+                memory64: false,
+            }),
+            wasmparser::MemoryType::M64 { limits, shared } => {
+                let min = u32::try_from(limits.initial);
+                let max = limits.maximum.map(u32::try_from).transpose();
+                match (min, max) {
+                    (Ok(min), Ok(max)) => Ok(MemoryType {
+                        limits: Limits { min, max },
+                        shared,
+                        memory64: true,
+                    }),
+                    _ => Err(Error::UnsupportedMemoryType(ty)),
+                }
+            }
+        }
+    }
+
+    /// The default implementation of [`Reencode::global_type`].
+    pub fn global_type<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        ty: wasmparser::GlobalType,
+    ) -> Result<GlobalType> {
+        Ok(GlobalType {
+            val_type: reencoder.val_type(ty.content_type)?,
+            mutable: ty.mutable,
+        })
+    }
+
+    /// The default implementation of [`Reencode::entity_type`].
+    pub fn entity_type<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        ty: wasmparser::ImportSectionEntryType,
+    ) -> Result<EntityType> {
+        use wasmparser::ImportSectionEntryType as I;
+        Ok(match ty {
+            I::Function(i) => EntityType::Function(reencoder.type_index(i)),
+            I::Table(t) => EntityType::Table(reencoder.table_type(t)?),
+            I::Memory(m) => EntityType::Memory(reencoder.memory_type(m)?),
+            I::Global(g) => EntityType::Global(reencoder.global_type(g)?),
+            I::Module(i) => EntityType::Module(reencoder.type_index(i)),
+            I::Instance(i) => EntityType::Instance(reencoder.type_index(i)),
+            I::Event(e) => EntityType::Event(EventType {
+                type_index: reencoder.type_index(e.type_index),
+            }),
+        })
+    }
+
+    /// The default implementation of [`Reencode::block_type`].
+    pub fn block_type<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        ty: wasmparser::TypeOrFuncType,
+    ) -> Result<BlockType> {
+        Ok(match ty {
+            wasmparser::TypeOrFuncType::Type(wasmparser::Type::EmptyBlockType) => BlockType::Empty,
+            wasmparser::TypeOrFuncType::Type(ty) => BlockType::Result(reencoder.val_type(ty)?),
+            wasmparser::TypeOrFuncType::FuncType(i) => {
+                BlockType::FunctionType(reencoder.type_index(i))
+            }
+        })
+    }
+
+    /// The default implementation of [`Reencode::mem_arg`].
+    pub fn mem_arg<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        memarg: wasmparser::MemoryImmediate,
+    ) -> MemArg {
+        MemArg {
+            offset: memarg.offset,
+            align: memarg.align.into(),
+            memory_index: reencoder.memory_index(memarg.memory),
+        }
+    }
+
+    /// The default implementation of [`Reencode::init_expr`].
+    pub fn init_expr<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        expr: &wasmparser::InitExpr<'_>,
+    ) -> Result<Instruction<'static>> {
+        let mut reader = expr.get_operators_reader();
+        let offset = reader.original_position();
+        let insn = match reader.read()? {
+            Operator::I32Const { value } => Instruction::I32Const(value),
+            Operator::I64Const { value } => Instruction::I64Const(value),
+            Operator::F32Const { value } => Instruction::F32Const(f32::from_bits(value.bits())),
+            Operator::F64Const { value } => Instruction::F64Const(f64::from_bits(value.bits())),
+            Operator::V128Const { value } => {
+                Instruction::V128Const(i128::from_le_bytes(*value.bytes()))
+            }
+            Operator::RefNull { ty } => Instruction::RefNull(reencoder.val_type(ty)?),
+            Operator::RefFunc { function_index } => {
+                Instruction::RefFunc(reencoder.function_index(function_index))
+            }
+            Operator::GlobalGet { global_index } => {
+                Instruction::GlobalGet(reencoder.global_index(global_index))
+            }
+            _ => return Err(Error::InvalidConstExpr(offset)),
+        };
+        match reader.read()? {
+            Operator::End if reader.eof() => Ok(insn),
+            _ => Err(Error::InvalidConstExpr(offset)),
+        }
+    }
+
+    /// The default implementation of [`Reencode::parse_type_section`].
+    pub fn parse_type_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        types: &mut TypeSection,
+        section: wasmparser::TypeSectionReader<'_>,
+    ) -> Result<()> {
+        for ty in section {
+            match ty? {
+                wasmparser::TypeDef::Func(f) => {
+                    let params = f
+                        .params
+                        .iter()
+                        .map(|ty| reencoder.val_type(*ty))
+                        .collect::<Result<Vec<_>>>()?;
+                    let results = f
+                        .returns
+                        .iter()
+                        .map(|ty| reencoder.val_type(*ty))
+                        .collect::<Result<Vec<_>>>()?;
+                    types.function(params, results);
+                }
+                wasmparser::TypeDef::Module(m) => {
+                    let imports = m
+                        .imports
+                        .iter()
+                        .map(|i| Ok((i.module, i.field, reencoder.entity_type(i.ty)?)))
+                        .collect::<Result<Vec<_>>>()?;
+                    let exports = m
+                        .exports
+                        .iter()
+                        .map(|e| Ok((e.name, reencoder.entity_type(e.ty)?)))
+                        .collect::<Result<Vec<_>>>()?;
+                    types.module(imports, exports);
+                }
+                wasmparser::TypeDef::Instance(i) => {
+                    let exports = i
+                        .exports
+                        .iter()
+                        .map(|e| Ok((e.name, reencoder.entity_type(e.ty)?)))
+                        .collect::<Result<Vec<_>>>()?;
+                    types.instance(exports);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_import_section`].
+    pub fn parse_import_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        imports: &mut ImportSection,
+        section: wasmparser::ImportSectionReader<'_>,
+    ) -> Result<()> {
+        for import in section {
+            let import = import?;
+            let ty = reencoder.entity_type(import.ty)?;
+            imports.import(import.module, import.field, ty);
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_function_section`].
+    pub fn parse_function_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        functions: &mut FunctionSection,
+        section: wasmparser::FunctionSectionReader<'_>,
+    ) -> Result<()> {
+        for ty in section {
+            functions.function(reencoder.type_index(ty?));
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_table_section`].
+    pub fn parse_table_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        tables: &mut TableSection,
+        section: wasmparser::TableSectionReader<'_>,
+    ) -> Result<()> {
+        for ty in section {
+            tables.table(reencoder.table_type(ty?)?);
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_memory_section`].
+    pub fn parse_memory_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        memories: &mut MemorySection,
+        section: wasmparser::MemorySectionReader<'_>,
+    ) -> Result<()> {
+        for ty in section {
+            memories.memory(reencoder.memory_type(ty?)?);
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_global_section`].
+    pub fn parse_global_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        globals: &mut GlobalSection,
+        section: wasmparser::GlobalSectionReader<'_>,
+    ) -> Result<()> {
+        for global in section {
+            let global = global?;
+            let ty = reencoder.global_type(global.ty)?;
+            let init = reencoder.init_expr(&global.init_expr)?;
+            globals.global(ty, init);
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_event_section`].
+    pub fn parse_event_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        events: &mut EventSection,
+        section: wasmparser::EventSectionReader<'_>,
+    ) -> Result<()> {
+        for event in section {
+            events.event(EventType {
+                type_index: reencoder.type_index(event?.type_index),
+            });
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_export_section`].
+    pub fn parse_export_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        exports: &mut ExportSection,
+        section: wasmparser::ExportSectionReader<'_>,
+    ) -> Result<()> {
+        for export in section {
+            reencoder.parse_export(exports, export?)?;
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_export`].
+    pub fn parse_export<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        exports: &mut ExportSection,
+        export: wasmparser::Export<'_>,
+    ) -> Result<()> {
+        use wasmparser::ExternalKind as K;
+        let item = match export.kind {
+            K::Function => Export::Function(reencoder.function_index(export.index)),
+            K::Table => Export::Table(reencoder.table_index(export.index)),
+            K::Memory => Export::Memory(reencoder.memory_index(export.index)),
+            K::Global => Export::Global(reencoder.global_index(export.index)),
+            K::Instance => Export::Instance(export.index),
+            K::Module => Export::Module(export.index),
+            K::Event => Export::Event(reencoder.event_index(export.index)),
+            K::Type => return Err(Error::UnsupportedExportKind(export.kind)),
+        };
+        exports.export(export.field, item);
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_element_section`].
+    pub fn parse_element_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        elements: &mut ElementSection,
+        section: wasmparser::ElementSectionReader<'_>,
+    ) -> Result<()> {
+        for element in section {
+            reencoder.parse_element(elements, element?)?;
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_element`].
+    pub fn parse_element<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        elements: &mut ElementSection,
+        element: wasmparser::Element<'_>,
+    ) -> Result<()> {
+        let element_type = reencoder.val_type(element.ty)?;
+        let mut items = element.items.get_items_reader()?;
+        let mut funcs = Vec::new();
+        let mut exprs = Vec::new();
+        for _ in 0..items.get_count() {
+            match items.read()? {
+                wasmparser::ElementItem::Func(f) => {
+                    let f = reencoder.function_index(f);
+                    funcs.push(f);
+                    exprs.push(Element::Func(f));
+                }
+                wasmparser::ElementItem::Null(_) => exprs.push(Element::Null),
+            }
+        }
+        let elems = if items.uses_exprs() {
+            Elements::Expressions(&exprs)
+        } else {
+            Elements::Functions(&funcs)
+        };
+        match element.kind {
+            wasmparser::ElementKind::Passive => {
+                elements.passive(element_type, elems);
+            }
+            wasmparser::ElementKind::Declared => {
+                elements.declared(element_type, elems);
+            }
+            wasmparser::ElementKind::Active {
+                table_index,
+                init_expr,
+            } => {
+                let offset = reencoder.init_expr(&init_expr)?;
+                // Table 0 with `funcref` elements has a shorter encoding
+                // that doesn't mention the table at all.
+                let table = match reencoder.table_index(table_index) {
+                    0 if element_type == ValType::FuncRef => None,
+                    i => Some(i),
+                };
+                elements.segment(ElementSegment {
+                    mode: ElementMode::Active { table, offset },
+                    element_type,
+                    elements: elems,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_data_section`].
+    pub fn parse_data_section<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        data: &mut DataSection,
+        section: wasmparser::DataSectionReader<'_>,
+    ) -> Result<()> {
+        for segment in section {
+            reencoder.parse_data(data, segment?)?;
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_data`].
+    pub fn parse_data<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        data: &mut DataSection,
+        segment: wasmparser::Data<'_>,
+    ) -> Result<()> {
+        match segment.kind {
+            wasmparser::DataKind::Passive => {
+                data.passive(segment.data.iter().copied());
+            }
+            wasmparser::DataKind::Active {
+                memory_index,
+                init_expr,
+            } => {
+                let offset = reencoder.init_expr(&init_expr)?;
+                data.active(
+                    reencoder.memory_index(memory_index),
+                    offset,
+                    segment.data.iter().copied(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_function_body`].
+    pub fn parse_function_body<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        code: &mut CodeSection,
+        body: wasmparser::FunctionBody<'_>,
+    ) -> Result<()> {
+        let func = function_body(reencoder, body)?;
+        code.function(&func);
+        Ok(())
+    }
+
+    /// Re-encodes a function body into a new [`Function`], without adding it
+    /// to a code section.
+    ///
+    /// This is useful for transformers that reorder function bodies.
+    pub fn function_body<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        body: wasmparser::FunctionBody<'_>,
+    ) -> Result<Function> {
+        let mut locals = Vec::new();
+        for local in body.get_locals_reader()? {
+            let (count, ty) = local?;
+            locals.push((count, reencoder.val_type(ty)?));
+        }
+        let mut func = Function::new(locals);
+
+        let mut reader = body.get_binary_reader();
+        for _ in 0..reader.read_var_u32()? {
+            reader.read_var_u32()?;
+            reader.read_type()?;
+        }
+        while !reader.eof() {
+            let mut raw = reader.clone();
+            let start = reader.original_position();
+            let op = reader.read_operator()?;
+            let raw = raw.read_bytes(reader.original_position() - start)?;
+            reencoder.parse_instruction(&mut func, op, raw)?;
+        }
+        Ok(func)
+    }
+
+    /// The default implementation of [`Reencode::parse_instruction`].
+    ///
+    /// Operators that have no [`Instruction`] counterpart yet are copied from
+    /// `raw` verbatim. None of them refer to an index; in particular all SIMD
+    /// and atomic memory accesses go through [`Reencode::mem_arg`].
+    pub fn parse_instruction<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        func: &mut Function,
+        op: Operator<'_>,
+        raw: &[u8],
+    ) -> Result<()> {
+        macro_rules! translate {
+            ($op:expr, { $($same:ident)* }, { $($mem:ident)* }, { $($arms:tt)* }) => {
+                match $op {
+                    $(Operator::$same => Instruction::$same,)*
+                    $(Operator::$mem { memarg } => Instruction::$mem(reencoder.mem_arg(memarg)),)*
+                    $($arms)*
+                }
+            };
+        }
+
+        let targets;
+        let insn = translate!(op, {
+            Unreachable Nop Else End Return Drop Select
+            I32Eqz I32Eq I32LtS I32LtU I32GtS I32GtU I32LeS I32LeU I32GeS I32GeU
+            I64Eqz I64Eq I64LtS I64LtU I64GtS I64GtU I64LeS I64LeU I64GeS I64GeU
+            F32Eq F32Lt F32Gt F32Le F32Ge
+            F64Eq F64Lt F64Gt F64Le F64Ge
+            I32Clz I32Ctz I32Popcnt I32Add I32Sub I32Mul I32DivS I32DivU I32RemS
+            I32RemU I32And I32Or I32Xor I32Shl I32ShrS I32ShrU I32Rotl I32Rotr
+            I64Clz I64Ctz I64Popcnt I64Add I64Sub I64Mul I64DivS I64DivU I64RemS
+            I64RemU I64And I64Or I64Xor I64Shl I64ShrS I64ShrU I64Rotl I64Rotr
+            F32Abs F32Neg F32Ceil F32Floor F32Trunc F32Nearest F32Sqrt F32Add
+            F32Sub F32Mul F32Div F32Min F32Max F32Copysign
+            F64Abs F64Neg F64Ceil F64Floor F64Trunc F64Nearest F64Sqrt F64Add
+            F64Sub F64Mul F64Div F64Min F64Max F64Copysign
+            I32WrapI64 I32TruncF32S I32TruncF32U I32TruncF64S I32TruncF64U
+            I64ExtendI32S I64ExtendI32U I64TruncF32S I64TruncF32U I64TruncF64S
+            I64TruncF64U F32ConvertI32S F32ConvertI32U F32ConvertI64S
+            F32ConvertI64U F32DemoteF64 F64ConvertI32S F64ConvertI32U
+            F64ConvertI64S F64ConvertI64U F64PromoteF32 I32ReinterpretF32
+            I64ReinterpretF64 F32ReinterpretI32 F64ReinterpretI64
+            I32Extend8S I32Extend16S I64Extend8S I64Extend16S I64Extend32S
+            I32TruncSatF32S I32TruncSatF32U I32TruncSatF64S I32TruncSatF64U
+            I64TruncSatF32S I64TruncSatF32U I64TruncSatF64S I64TruncSatF64U
+            RefIsNull
+        }, {
+            V128Load V128Load8x8S V128Load8x8U V128Load16x4S V128Load16x4U
+            V128Load32x2S V128Load32x2U V128Load8Splat V128Load16Splat
+            V128Load32Splat V128Load64Splat V128Load32Zero V128Load64Zero
+            V128Store
+            MemoryAtomicNotify MemoryAtomicWait32 MemoryAtomicWait64
+            I32AtomicLoad I64AtomicLoad I32AtomicLoad8U I32AtomicLoad16U
+            I64AtomicLoad8U I64AtomicLoad16U I64AtomicLoad32U
+            I32AtomicStore I64AtomicStore I32AtomicStore8 I32AtomicStore16
+            I64AtomicStore8 I64AtomicStore16 I64AtomicStore32
+            I32AtomicRmwAdd I64AtomicRmwAdd I32AtomicRmw8AddU I32AtomicRmw16AddU
+            I64AtomicRmw8AddU I64AtomicRmw16AddU I64AtomicRmw32AddU
+            I32AtomicRmwSub I64AtomicRmwSub I32AtomicRmw8SubU I32AtomicRmw16SubU
+            I64AtomicRmw8SubU I64AtomicRmw16SubU I64AtomicRmw32SubU
+            I32AtomicRmwAnd I64AtomicRmwAnd I32AtomicRmw8AndU I32AtomicRmw16AndU
+            I64AtomicRmw8AndU I64AtomicRmw16AndU I64AtomicRmw32AndU
+            I32AtomicRmwOr I64AtomicRmwOr I32AtomicRmw8OrU I32AtomicRmw16OrU
+            I64AtomicRmw8OrU I64AtomicRmw16OrU I64AtomicRmw32OrU
+            I32AtomicRmwXor I64AtomicRmwXor I32AtomicRmw8XorU I32AtomicRmw16XorU
+            I64AtomicRmw8XorU I64AtomicRmw16XorU I64AtomicRmw32XorU
+            I32AtomicRmwXchg I64AtomicRmwXchg I32AtomicRmw8XchgU
+            I32AtomicRmw16XchgU I64AtomicRmw8XchgU I64AtomicRmw16XchgU
+            I64AtomicRmw32XchgU
+            I32AtomicRmwCmpxchg I64AtomicRmwCmpxchg I32AtomicRmw8CmpxchgU
+            I32AtomicRmw16CmpxchgU I64AtomicRmw8CmpxchgU I64AtomicRmw16CmpxchgU
+            I64AtomicRmw32CmpxchgU
+        }, {
+            Operator::I32Ne => Instruction::I32Neq,
+            Operator::I64Ne => Instruction::I64Neq,
+            Operator::F32Ne => Instruction::F32Neq,
+            Operator::F64Ne => Instruction::F64Neq,
+
+            // Control instructions.
+            Operator::Block { ty } => Instruction::Block(reencoder.block_type(ty)?),
+            Operator::Loop { ty } => Instruction::Loop(reencoder.block_type(ty)?),
+            Operator::If { ty } => Instruction::If(reencoder.block_type(ty)?),
+            Operator::Br { relative_depth } => Instruction::Br(relative_depth),
+            Operator::BrIf { relative_depth } => Instruction::BrIf(relative_depth),
+            Operator::BrTable { table } => {
+                let mut ts = Vec::with_capacity(table.len());
+                let mut default = 0;
+                for target in table.targets() {
+                    let (depth, is_default) = target?;
+                    if is_default {
+                        default = depth;
+                    } else {
+                        ts.push(depth);
+                    }
+                }
+                targets = ts;
+                Instruction::BrTable(&targets, default)
+            }
+            Operator::Call { function_index } => {
+                Instruction::Call(reencoder.function_index(function_index))
+            }
+            Operator::CallIndirect { index, table_index } => Instruction::CallIndirect {
+                ty: reencoder.type_index(index),
+                table: reencoder.table_index(table_index),
+            },
+            Operator::ReturnCall { function_index } => {
+                Instruction::ReturnCall(reencoder.function_index(function_index))
+            }
+            Operator::ReturnCallIndirect { index, table_index } => {
+                Instruction::ReturnCallIndirect {
+                    ty: reencoder.type_index(index),
+                    table: reencoder.table_index(table_index),
+                }
+            }
+
+            // Exception handling instructions.
+            Operator::Try { ty } => Instruction::Try(reencoder.block_type(ty)?),
+            Operator::Catch { index } => Instruction::Catch(reencoder.event_index(index)),
+            Operator::Throw { index } => Instruction::Throw(reencoder.event_index(index)),
+            Operator::Rethrow { relative_depth } => Instruction::Rethrow(relative_depth),
+            Operator::Unwind => Instruction::Unwind,
+
+            // Parametric instructions.
+            Operator::TypedSelect { ty } => Instruction::TypedSelect(reencoder.val_type(ty)?),
+
+            // Variable instructions.
+            Operator::LocalGet { local_index } => Instruction::LocalGet(local_index),
+            Operator::LocalSet { local_index } => Instruction::LocalSet(local_index),
+            Operator::LocalTee { local_index } => Instruction::LocalTee(local_index),
+            Operator::GlobalGet { global_index } => {
+                Instruction::GlobalGet(reencoder.global_index(global_index))
+            }
+            Operator::GlobalSet { global_index } => {
+                Instruction::GlobalSet(reencoder.global_index(global_index))
+            }
+
+            // Memory instructions.
+            Operator::I32Load { memarg } => Instruction::I32Load(reencoder.mem_arg(memarg)),
+            Operator::I64Load { memarg } => Instruction::I64Load(reencoder.mem_arg(memarg)),
+            Operator::F32Load { memarg } => Instruction::F32Load(reencoder.mem_arg(memarg)),
+            Operator::F64Load { memarg } => Instruction::F64Load(reencoder.mem_arg(memarg)),
+            Operator::I32Load8S { memarg } => Instruction::I32Load8_S(reencoder.mem_arg(memarg)),
+            Operator::I32Load8U { memarg } => Instruction::I32Load8_U(reencoder.mem_arg(memarg)),
+            Operator::I32Load16S { memarg } => {
+                Instruction::I32Load16_S(reencoder.mem_arg(memarg))
+            }
+            Operator::I32Load16U { memarg } => {
+                Instruction::I32Load16_U(reencoder.mem_arg(memarg))
+            }
+            Operator::I64Load8S { memarg } => Instruction::I64Load8_S(reencoder.mem_arg(memarg)),
+            Operator::I64Load8U { memarg } => Instruction::I64Load8_U(reencoder.mem_arg(memarg)),
+            Operator::I64Load16S { memarg } => {
+                Instruction::I64Load16_S(reencoder.mem_arg(memarg))
+            }
+            Operator::I64Load16U { memarg } => {
+                Instruction::I64Load16_U(reencoder.mem_arg(memarg))
+            }
+            Operator::I64Load32S { memarg } => {
+                Instruction::I64Load32_S(reencoder.mem_arg(memarg))
+            }
+            Operator::I64Load32U { memarg } => {
+                Instruction::I64Load32_U(reencoder.mem_arg(memarg))
+            }
+            Operator::I32Store { memarg } => Instruction::I32Store(reencoder.mem_arg(memarg)),
+            Operator::I64Store { memarg } => Instruction::I64Store(reencoder.mem_arg(memarg)),
+            Operator::F32Store { memarg } => Instruction::F32Store(reencoder.mem_arg(memarg)),
+            Operator::F64Store { memarg } => Instruction::F64Store(reencoder.mem_arg(memarg)),
+            Operator::I32Store8 { memarg } => Instruction::I32Store8(reencoder.mem_arg(memarg)),
+            Operator::I32Store16 { memarg } => Instruction::I32Store16(reencoder.mem_arg(memarg)),
+            Operator::I64Store8 { memarg } => Instruction::I64Store8(reencoder.mem_arg(memarg)),
+            Operator::I64Store16 { memarg } => Instruction::I64Store16(reencoder.mem_arg(memarg)),
+            Operator::I64Store32 { memarg } => Instruction::I64Store32(reencoder.mem_arg(memarg)),
+            Operator::MemorySize { mem, .. } => {
+                Instruction::MemorySize(reencoder.memory_index(mem))
+            }
+            Operator::MemoryGrow { mem, .. } => {
+                Instruction::MemoryGrow(reencoder.memory_index(mem))
+            }
+            Operator::MemoryInit { segment, mem } => Instruction::MemoryInit {
+                mem: reencoder.memory_index(mem),
+                data: reencoder.data_index(segment),
+            },
+            Operator::DataDrop { segment } => Instruction::DataDrop(reencoder.data_index(segment)),
+            Operator::MemoryCopy { src, dst } => Instruction::MemoryCopy {
+                src: reencoder.memory_index(src),
+                dst: reencoder.memory_index(dst),
+            },
+            Operator::MemoryFill { mem } => Instruction::MemoryFill(reencoder.memory_index(mem)),
+
+            // Numeric instructions.
+            Operator::I32Const { value } => Instruction::I32Const(value),
+            Operator::I64Const { value } => Instruction::I64Const(value),
+            Operator::F32Const { value } => Instruction::F32Const(f32::from_bits(value.bits())),
+            Operator::F64Const { value } => Instruction::F64Const(f64::from_bits(value.bits())),
+
+            // SIMD instructions.
+            Operator::V128Const { value } => {
+                Instruction::V128Const(i128::from_le_bytes(*value.bytes()))
+            }
+
+            Operator::V128Load8Lane { memarg, lane } => Instruction::V128Load8Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Load16Lane { memarg, lane } => Instruction::V128Load16Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Load32Lane { memarg, lane } => Instruction::V128Load32Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Load64Lane { memarg, lane } => Instruction::V128Load64Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Store8Lane { memarg, lane } => Instruction::V128Store8Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Store16Lane { memarg, lane } => Instruction::V128Store16Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Store32Lane { memarg, lane } => Instruction::V128Store32Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+            Operator::V128Store64Lane { memarg, lane } => Instruction::V128Store64Lane {
+                memarg: reencoder.mem_arg(memarg),
+                lane,
+            },
+
+            // Reference types instructions.
+            Operator::RefNull { ty } => Instruction::RefNull(reencoder.val_type(ty)?),
+            Operator::RefFunc { function_index } => {
+                Instruction::RefFunc(reencoder.function_index(function_index))
+            }
+
+            // Bulk memory instructions.
+            Operator::TableInit { segment, table } => Instruction::TableInit {
+                segment: reencoder.element_index(segment),
+                table: reencoder.table_index(table),
+            },
+            Operator::ElemDrop { segment } => Instruction::ElemDrop {
+                segment: reencoder.element_index(segment),
+            },
+            Operator::TableFill { table } => Instruction::TableFill {
+                table: reencoder.table_index(table),
+            },
+            Operator::TableSet { table } => Instruction::TableSet {
+                table: reencoder.table_index(table),
+            },
+            Operator::TableGet { table } => Instruction::TableGet {
+                table: reencoder.table_index(table),
+            },
+            Operator::TableGrow { table } => Instruction::TableGrow {
+                table: reencoder.table_index(table),
+            },
+            Operator::TableSize { table } => Instruction::TableSize {
+                table: reencoder.table_index(table),
+            },
+            Operator::TableCopy {
+                dst_table,
+                src_table,
+            } => Instruction::TableCopy {
+                src: reencoder.table_index(src_table),
+                dst: reencoder.table_index(dst_table),
+            },
+
+            _ => {
+                func.raw(raw.iter().copied());
+                return Ok(());
+            }
+        });
+        func.instruction(insn);
+        Ok(())
+    }
+
+    /// The default implementation of [`Reencode::parse_core_module`].
+    ///
+    /// Sections that `wasm-encoder` has no builder for are copied verbatim.
+    pub fn parse_core_module<T: ?Sized + Reencode>(
+        reencoder: &mut T,
+        module: &mut Module,
+        data: &[u8],
+    ) -> Result<()> {
+        let mut parser = Parser::new(0);
+        let mut remaining = data;
+        let mut code = CodeSection::new();
+        let mut code_left = 0;
+        loop {
+            let payload = match parser.parse(remaining, true)? {
+                Chunk::NeedMoreData(_) => unreachable!(),
+                Chunk::Parsed { payload, consumed } => {
+                    remaining = &remaining[consumed..];
+                    payload
+                }
+            };
+            match payload {
+                Payload::Version { .. } => {}
+                Payload::TypeSection(section) => {
+                    let mut types = TypeSection::new();
+                    reencoder.parse_type_section(&mut types, section)?;
+                    module.section(&types);
+                }
+                Payload::ImportSection(section) => {
+                    let mut imports = ImportSection::new();
+                    reencoder.parse_import_section(&mut imports, section)?;
+                    module.section(&imports);
+                }
+                Payload::FunctionSection(section) => {
+                    let mut functions = FunctionSection::new();
+                    reencoder.parse_function_section(&mut functions, section)?;
+                    module.section(&functions);
+                }
+                Payload::TableSection(section) => {
+                    let mut tables = TableSection::new();
+                    reencoder.parse_table_section(&mut tables, section)?;
+                    module.section(&tables);
+                }
+                Payload::MemorySection(section) => {
+                    let mut memories = MemorySection::new();
+                    reencoder.parse_memory_section(&mut memories, section)?;
+                    module.section(&memories);
+                }
+                Payload::GlobalSection(section) => {
+                    let mut globals = GlobalSection::new();
+                    reencoder.parse_global_section(&mut globals, section)?;
+                    module.section(&globals);
+                }
+                Payload::ExportSection(section) => {
+                    let mut exports = ExportSection::new();
+                    reencoder.parse_export_section(&mut exports, section)?;
+                    module.section(&exports);
+                }
+                Payload::StartSection { func, .. } => {
+                    module.section(&StartSection {
+                        function_index: reencoder.function_index(func),
+                    });
+                }
+                Payload::ElementSection(section) => {
+                    let mut elements = ElementSection::new();
+                    reencoder.parse_element_section(&mut elements, section)?;
+                    module.section(&elements);
+                }
+                Payload::DataCountSection { count, .. } => {
+                    module.section(&DataCountSection {
+                        count: reencoder.data_count(count),
+                    });
+                }
+                Payload::DataSection(section) => {
+                    let mut segments = DataSection::new();
+                    reencoder.parse_data_section(&mut segments, section)?;
+                    module.section(&segments);
+                }
+                Payload::CodeSectionStart { count, .. } => {
+                    code = CodeSection::new();
+                    code_left = count;
+                    if code_left == 0 {
+                        module.section(&code);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    reencoder.parse_function_body(&mut code, body)?;
+                    code_left -= 1;
+                    if code_left == 0 {
+                        module.section(&code);
+                    }
+                }
+                Payload::CustomSection { name, data, .. } => {
+                    reencoder.parse_custom_section(module, name, data)?;
+                }
+                Payload::EventSection(section) => {
+                    let mut events = EventSection::new();
+                    reencoder.parse_event_section(&mut events, section)?;
+                    module.section(&events);
+                }
+                Payload::AliasSection(section) => {
+                    raw_section(module, SectionId::Alias.into(), section.range().slice(data));
+                }
+                Payload::InstanceSection(section) => {
+                    raw_section(
+                        module,
+                        SectionId::Instance.into(),
+                        section.range().slice(data),
+                    );
+                }
+                Payload::ModuleSectionStart { range, size, .. } => {
+                    raw_section(module, SectionId::Module.into(), range.slice(data));
+                    parser.skip_section();
+                    remaining = &remaining[usize::try_from(size).unwrap()..];
+                }
+                Payload::ModuleSectionEntry { .. } => unreachable!(),
+                Payload::UnknownSection { id, contents, .. } => {
+                    raw_section(module, id, contents);
+                }
+                Payload::End => return Ok(()),
+            }
+        }
+    }
+
+    fn raw_section(module: &mut Module, id: u8, data: &[u8]) {
+        module.section(&RawSection { id, data });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_is_identity() {
+        let mut module = Module::new();
+
+        let mut types = TypeSection::new();
+        types.function(vec![ValType::I32], vec![ValType::I32]);
+        types.function(vec![], vec![]);
+        module.section(&types);
+
+        let mut imports = ImportSection::new();
+        imports.import("env", Some("f"), EntityType::Function(1));
+        module.section(&imports);
+
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        module.section(&functions);
+
+        let mut tables = TableSection::new();
+        tables.table(TableType {
+            element_type: ValType::FuncRef,
+            limits: Limits { min: 1, max: None },
+        });
+        module.section(&tables);
+
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            limits: Limits {
+                min: 1,
+                max: Some(2),
+            },
+            shared: false,
+            memory64: false,
+        });
+        module.section(&memories);
+
+        let mut globals = GlobalSection::new();
+        globals.global(
+            GlobalType {
+                val_type: ValType::F64,
+                mutable: true,
+            },
+            Instruction::F64Const(1.5),
+        );
+        module.section(&globals);
+
+        let mut exports = ExportSection::new();
+        exports.export("g", Export::Function(1));
+        module.section(&exports);
+
+        let mut elements = ElementSection::new();
+        elements.active(
+            None,
+            Instruction::I32Const(0),
+            ValType::FuncRef,
+            Elements::Functions(&[1]),
+        );
+        module.section(&elements);
+
+        let mut code = CodeSection::new();
+        let mut f = Function::new(vec![(2, ValType::I64)]);
+        f.instruction(Instruction::Block(BlockType::Empty));
+        f.instruction(Instruction::LocalGet(0));
+        f.instruction(Instruction::BrTable(&[0, 0], 0));
+        f.instruction(Instruction::End);
+        f.instruction(Instruction::Call(0));
+        f.instruction(Instruction::I32Const(0));
+        f.instruction(Instruction::I32Load(MemArg {
+            offset: 8,
+            align: 2,
+            memory_index: 0,
+        }));
+        f.instruction(Instruction::I32Add);
+        f.raw(vec![0xfd, 0x0c].into_iter().chain(vec![0; 16]));
+        f.instruction(Instruction::Drop);
+        f.instruction(Instruction::End);
+        code.function(&f);
+        module.section(&code);
+
+        let mut data = DataSection::new();
+        data.active(0, Instruction::I32Const(16), b"hello".iter().copied());
+        module.section(&data);
+
+        module.section(&CustomSection {
+            name: "name",
+            data: &[],
+        });
+
+        let expected = module.finish();
+        let mut actual = Module::new();
+        RoundtripReencoder
+            .parse_core_module(&mut actual, &expected)
+            .unwrap();
+        assert_eq!(expected, actual.finish());
+    }
+
+    #[test]
+    fn simd_and_atomic_memory_indices_are_remapped() {
+        struct SwapMemories;
+
+        impl Reencode for SwapMemories {
+            fn memory_index(&mut self, memory: u32) -> u32 {
+                1 - memory
+            }
+        }
+
+        fn module(memory_index: u32) -> Vec<u8> {
+            let memarg = MemArg {
+                offset: 0,
+                align: 0,
+                memory_index,
+            };
+            let mut module = Module::new();
+            let mut types = TypeSection::new();
+            types.function(vec![], vec![]);
+            module.section(&types);
+            let mut functions = FunctionSection::new();
+            functions.function(0);
+            module.section(&functions);
+            let mut code = CodeSection::new();
+            let mut f = Function::new(vec![]);
+            f.instruction(Instruction::I32Const(0));
+            f.instruction(Instruction::V128Load(memarg));
+            f.instruction(Instruction::I32Const(0));
+            f.instruction(Instruction::V128Load8Lane { memarg, lane: 3 });
+            f.instruction(Instruction::Drop);
+            f.instruction(Instruction::I32Const(0));
+            f.instruction(Instruction::I32AtomicLoad8U(memarg));
+            f.instruction(Instruction::Drop);
+            f.instruction(Instruction::End);
+            code.function(&f);
+            module.section(&code);
+            module.finish()
+        }
+
+        let mut actual = Module::new();
+        SwapMemories
+            .parse_core_module(&mut actual, &module(0))
+            .unwrap();
+        assert_eq!(actual.finish(), module(1));
+    }
+
+    #[test]
+    fn event_indices_are_remapped() {
+        struct SwapEvents;
+
+        impl Reencode for SwapEvents {
+            fn event_index(&mut self, event: u32) -> u32 {
+                1 - event
+            }
+        }
+
+        fn module(first: u32) -> Vec<u8> {
+            let mut module = Module::new();
+            let mut types = TypeSection::new();
+            types.function(vec![], vec![]);
+            types.function(vec![ValType::I32], vec![]);
+            module.section(&types);
+            let mut functions = FunctionSection::new();
+            functions.function(0);
+            module.section(&functions);
+            let mut events = EventSection::new();
+            events.event(EventType { type_index: 0 });
+            events.event(EventType { type_index: 1 });
+            module.section(&events);
+            let mut exports = ExportSection::new();
+            exports.export("e", Export::Event(first));
+            module.section(&exports);
+            let mut code = CodeSection::new();
+            let mut f = Function::new(vec![]);
+            f.instruction(Instruction::Try(BlockType::Empty));
+            f.instruction(Instruction::Throw(first));
+            f.instruction(Instruction::Catch(1 - first));
+            f.instruction(Instruction::Drop);
+            f.instruction(Instruction::End);
+            f.instruction(Instruction::End);
+            code.function(&f);
+            module.section(&code);
+            module.finish()
+        }
+
+        let mut actual = Module::new();
+        SwapEvents
+            .parse_core_module(&mut actual, &module(0))
+            .unwrap();
+        assert_eq!(actual.finish(), module(1));
+    }
+}
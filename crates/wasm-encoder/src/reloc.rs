@@ -0,0 +1,453 @@
+use super::*;
+
+/// An encoder for a "reloc.*" custom section of a relocatable object file.
+///
+/// Each relocation section applies to one other section of the module,
+/// identified by its index among all of the module's sections (custom
+/// sections included). It must come after the [`LinkingSection`].
+///
+/// # Example
+///
+/// ```
+/// use wasm_encoder::{
+///     CodeSection, Function, Instruction, LinkingSection, Module, RelocSection,
+///     SymbolTable,
+/// };
+///
+/// let mut func = Function::new(vec![]);
+/// func.padded_instruction(Instruction::Call(0));
+/// func.instruction(Instruction::End);
+/// let mut code = CodeSection::new();
+/// code.function(&func);
+///
+/// let mut symbols = SymbolTable::new();
+/// symbols.function(0, 0, Some("f"));
+/// let mut linking = LinkingSection::new();
+/// linking.symbol_table(&symbols);
+///
+/// // The code section is the first section of this module, so its index is 0.
+/// let mut relocs = RelocSection::new("CODE", 0);
+/// // Function `0` is symbol `0`.
+/// relocs.code_relocations(&code, |patch| Some(patch.value));
+///
+/// let mut module = Module::new();
+/// module.section(&code).section(&linking).section(&relocs);
+/// ```
+pub struct RelocSection {
+    name: String,
+    section_index: u32,
+    bytes: Vec<u8>,
+    num_added: u32,
+}
+
+impl RelocSection {
+    /// Create a new relocation section for the section with the given index.
+    ///
+    /// The section is named `reloc.` followed by `target`, which is
+    /// conventionally `CODE`, `DATA`, or the name of a custom section.
+    pub fn new(target: &str, section_index: u32) -> RelocSection {
+        RelocSection {
+            name: format!("reloc.{}", target),
+            section_index,
+            bytes: vec![],
+            num_added: 0,
+        }
+    }
+
+    /// Add a relocation.
+    ///
+    /// The addend is only encoded for relocation types that have one.
+    pub fn reloc(&mut self, entry: RelocEntry) -> &mut Self {
+        self.bytes.push(entry.ty as u8);
+        self.bytes.extend(encoders::u32(entry.offset));
+        self.bytes.extend(encoders::u32(entry.index));
+        if entry.ty.has_addend() {
+            self.bytes.extend(encoders::s32(entry.addend));
+        }
+        self.num_added += 1;
+        self
+    }
+
+    /// Add a relocation for every index written with
+    /// [`Function::padded_instruction`] into the functions of `code`.
+    ///
+    /// The `symbol` callback gives the symbol index each patchable index
+    /// refers to, or `None` to leave it without a relocation. A padded memory
+    /// offset becomes a [`RelocType::MemoryAddrLEB`] whose addend is the offset
+    /// that was written.
+    ///
+    /// Offsets are computed from the number of functions currently in `code`,
+    /// so call this after adding all of them.
+    pub fn code_relocations<F>(&mut self, code: &CodeSection, mut symbol: F) -> &mut Self
+    where
+        F: FnMut(&PatchableIndex) -> Option<u32>,
+    {
+        for patch in code.patchable_indices() {
+            let index = match symbol(&patch) {
+                Some(index) => index,
+                None => continue,
+            };
+            let (ty, addend) = match patch.kind {
+                PatchableIndexKind::Function => (RelocType::FunctionIndexLEB, 0),
+                PatchableIndexKind::Type => (RelocType::TypeIndexLEB, 0),
+                PatchableIndexKind::Global => (RelocType::GlobalIndexLEB, 0),
+                PatchableIndexKind::MemoryOffset => (RelocType::MemoryAddrLEB, patch.value as i32),
+            };
+            self.reloc(RelocEntry {
+                ty,
+                offset: u32::try_from(patch.offset).unwrap(),
+                index,
+                addend,
+            });
+        }
+        self
+    }
+}
+
+impl Section for RelocSection {
+    fn id(&self) -> u8 {
+        SectionId::Custom.into()
+    }
+
+    fn encode<S>(&self, sink: &mut S)
+    where
+        S: Extend<u8>,
+    {
+        let mut data = vec![];
+        data.extend(encoders::u32(self.section_index));
+        data.extend(encoders::u32(self.num_added));
+        data.extend(self.bytes.iter().copied());
+        CustomSection {
+            name: &self.name,
+            data: &data,
+        }
+        .encode(sink);
+    }
+}
+
+/// A relocation in a [`RelocSection`].
+#[derive(Clone, Copy, Debug)]
+pub struct RelocEntry {
+    /// The kind of value to patch.
+    pub ty: RelocType,
+    /// The offset of the value to patch, relative to the start of the target
+    /// section's contents.
+    pub offset: u32,
+    /// The index of the symbol (or, for [`RelocType::TypeIndexLEB`], the
+    /// type) the patched value refers to.
+    pub index: u32,
+    /// The addend, for relocation types that have one.
+    pub addend: i32,
+}
+
+/// Relocation types, as numbered by the WebAssembly tool conventions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+#[allow(missing_docs)]
+pub enum RelocType {
+    FunctionIndexLEB = 0,
+    TableIndexSLEB = 1,
+    TableIndexI32 = 2,
+    MemoryAddrLEB = 3,
+    MemoryAddrSLEB = 4,
+    MemoryAddrI32 = 5,
+    TypeIndexLEB = 6,
+    GlobalIndexLEB = 7,
+    FunctionOffsetI32 = 8,
+    SectionOffsetI32 = 9,
+    EventIndexLEB = 10,
+    MemoryAddrRelSLEB = 11,
+    TableIndexRelSLEB = 12,
+    GlobalIndexI32 = 13,
+}
+
+impl RelocType {
+    /// Whether relocations of this type carry an addend.
+    pub fn has_addend(&self) -> bool {
+        match self {
+            RelocType::MemoryAddrLEB
+            | RelocType::MemoryAddrSLEB
+            | RelocType::MemoryAddrI32
+            | RelocType::MemoryAddrRelSLEB
+            | RelocType::FunctionOffsetI32
+            | RelocType::SectionOffsetI32 => true,
+            RelocType::FunctionIndexLEB
+            | RelocType::TableIndexSLEB
+            | RelocType::TableIndexI32
+            | RelocType::TypeIndexLEB
+            | RelocType::GlobalIndexLEB
+            | RelocType::EventIndexLEB
+            | RelocType::TableIndexRelSLEB
+            | RelocType::GlobalIndexI32 => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmparser::{Linking, Parser, Payload, SymbolInfo};
+
+    #[test]
+    fn readable_by_wasmparser() {
+        let mut types = TypeSection::new();
+        types.function(vec![], vec![]);
+
+        let mut imports = ImportSection::new();
+        imports.import("env", Some("g"), EntityType::Function(0));
+
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+
+        let mut func = Function::new(vec![]);
+        func.padded_instruction(Instruction::Call(0))
+            .padded_instruction(Instruction::I32Const(0))
+            .padded_instruction(Instruction::I32Load(MemArg {
+                offset: 4,
+                align: 2,
+                memory_index: 0,
+            }))
+            .instruction(Instruction::Drop)
+            .instruction(Instruction::End);
+        let mut code = CodeSection::new();
+        code.function(&func);
+
+        let mut symbols = SymbolTable::new();
+        symbols
+            .function(SymbolTable::WASM_SYM_UNDEFINED, 0, None)
+            .function(0, 1, Some("f"))
+            .data(
+                0,
+                "d",
+                Some(DataSymbolDefinition {
+                    index: 0,
+                    offset: 0,
+                    size: 8,
+                }),
+            );
+        let mut segments = SegmentInfo::new();
+        segments.segment(".data", 2, 0);
+        let mut init = InitFuncs::new();
+        init.function(65535, 1);
+        let mut linking = LinkingSection::new();
+        linking
+            .segment_info(&segments)
+            .init_funcs(&init)
+            .symbol_table(&symbols);
+
+        let mut relocs = RelocSection::new("CODE", 3);
+        relocs.code_relocations(&code, |patch| match patch.kind {
+            PatchableIndexKind::Function => Some(patch.value),
+            PatchableIndexKind::MemoryOffset => Some(2),
+            _ => None,
+        });
+
+        let mut module = Module::new();
+        module
+            .section(&types)
+            .section(&imports)
+            .section(&functions)
+            .section(&code)
+            .section(&linking)
+            .section(&relocs);
+        let wasm = module.finish();
+
+        let mut code_start = None;
+        let mut saw_linking = false;
+        let mut saw_relocs = false;
+        for payload in Parser::new(0).parse_all(&wasm) {
+            match payload.unwrap() {
+                Payload::CodeSectionStart { range, .. } => code_start = Some(range.start),
+                Payload::CustomSection {
+                    name: "linking",
+                    data,
+                    data_offset,
+                } => {
+                    saw_linking = true;
+                    let reader = wasmparser::LinkingSectionReader::new(data, data_offset).unwrap();
+                    let subsections = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+                    assert_eq!(subsections.len(), 3);
+                    match &subsections[2] {
+                        Linking::SymbolTable(reader) => {
+                            let syms = reader.clone().collect::<Result<Vec<_>, _>>().unwrap();
+                            assert_eq!(syms.len(), 3);
+                            match syms[1] {
+                                SymbolInfo::Func { index, name, .. } => {
+                                    assert_eq!(index, 1);
+                                    assert_eq!(name, Some("f"));
+                                }
+                                _ => panic!("expected a function symbol"),
+                            }
+                        }
+                        _ => panic!("expected the symbol table last"),
+                    }
+                }
+                Payload::CustomSection {
+                    name: "reloc.CODE",
+                    data,
+                    data_offset,
+                } => {
+                    saw_relocs = true;
+                    let reader = wasmparser::RelocSectionReader::new(data, data_offset).unwrap();
+                    assert_eq!(reader.get_section_index(), 3);
+                    let relocs = reader.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+                    assert_eq!(relocs.len(), 2);
+                    assert_eq!(relocs[0].ty, wasmparser::RelocType::FunctionIndexLEB);
+                    assert_eq!(relocs[0].addend, None);
+                    assert_eq!(relocs[1].ty, wasmparser::RelocType::GlobalAddrLEB);
+                    assert_eq!(relocs[1].index, 2);
+                    assert_eq!(relocs[1].addend, Some(4));
+
+                    // Each offset points at a padded LEB holding the value.
+                    let code_start = code_start.unwrap();
+                    for (reloc, value) in relocs.iter().zip(&[0, 4]) {
+                        let at = code_start + reloc.offset as usize;
+                        let mut reader = wasmparser::BinaryReader::new(&wasm[at..at + 5]);
+                        assert_eq!(reader.read_var_u32().unwrap(), *value);
+                        assert!(reader.eof());
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert!(saw_linking && saw_relocs);
+    }
+
+    #[test]
+    fn every_reloc_type_readable_by_wasmparser() {
+        use wasmparser::RelocType as R;
+
+        let types = [
+            (RelocType::FunctionIndexLEB, R::FunctionIndexLEB),
+            (RelocType::TableIndexSLEB, R::TableIndexSLEB),
+            (RelocType::TableIndexI32, R::TableIndexI32),
+            (RelocType::MemoryAddrLEB, R::GlobalAddrLEB),
+            (RelocType::MemoryAddrSLEB, R::GlobalAddrSLEB),
+            (RelocType::MemoryAddrI32, R::GlobalAddrI32),
+            (RelocType::TypeIndexLEB, R::TypeIndexLEB),
+            (RelocType::GlobalIndexLEB, R::GlobalIndexLEB),
+            (RelocType::FunctionOffsetI32, R::FunctionOffsetI32),
+            (RelocType::SectionOffsetI32, R::SectionOffsetI32),
+            (RelocType::EventIndexLEB, R::EventIndexLEB),
+            (RelocType::MemoryAddrRelSLEB, R::GlobalAddrRelSLEB),
+            (RelocType::TableIndexRelSLEB, R::TableIndexRelSLEB),
+            (RelocType::GlobalIndexI32, R::GlobalIndexI32),
+        ];
+
+        let mut relocs = RelocSection::new("DATA", 1);
+        for (i, (ty, _)) in types.iter().enumerate() {
+            relocs.reloc(RelocEntry {
+                ty: *ty,
+                offset: i as u32 * 4,
+                index: i as u32,
+                addend: -(i as i32) - 1,
+            });
+        }
+        let mut module = Module::new();
+        module.section(&relocs);
+        let wasm = module.finish();
+
+        let mut seen = 0;
+        for payload in Parser::new(0).parse_all(&wasm) {
+            if let Payload::CustomSection {
+                name: "reloc.DATA",
+                data,
+                data_offset,
+            } = payload.unwrap()
+            {
+                let reader = wasmparser::RelocSectionReader::new(data, data_offset).unwrap();
+                assert_eq!(reader.get_section_index(), 1);
+                for (i, reloc) in reader.into_iter().enumerate() {
+                    let reloc = reloc.unwrap();
+                    let (ty, expected) = types[i];
+                    assert_eq!(reloc.ty, expected);
+                    assert_eq!(reloc.offset, i as u32 * 4);
+                    assert_eq!(reloc.index, i as u32);
+                    if ty.has_addend() {
+                        assert_eq!(reloc.addend, Some(-(i as i32) - 1));
+                    } else {
+                        assert_eq!(reloc.addend, None);
+                    }
+                    seen += 1;
+                }
+            }
+        }
+        assert_eq!(seen, types.len());
+    }
+
+    #[test]
+    fn every_symbol_kind_readable_by_wasmparser() {
+        let mut symbols = SymbolTable::new();
+        symbols
+            .global(SymbolTable::WASM_SYM_UNDEFINED, 0, None)
+            .event(SymbolTable::WASM_SYM_BINDING_WEAK, 1, Some("e"))
+            .table(
+                SymbolTable::WASM_SYM_UNDEFINED | SymbolTable::WASM_SYM_EXPLICIT_NAME,
+                2,
+                Some("t"),
+            )
+            .data(SymbolTable::WASM_SYM_UNDEFINED, "d", None)
+            .section(SymbolTable::WASM_SYM_BINDING_LOCAL, 3);
+        let mut linking = LinkingSection::new();
+        linking.symbol_table(&symbols);
+        let mut module = Module::new();
+        module.section(&linking);
+        let wasm = module.finish();
+
+        let mut syms = None;
+        for payload in Parser::new(0).parse_all(&wasm) {
+            if let Payload::CustomSection {
+                name: "linking",
+                data,
+                data_offset,
+            } = payload.unwrap()
+            {
+                let reader = wasmparser::LinkingSectionReader::new(data, data_offset).unwrap();
+                assert_eq!(reader.get_version(), LinkingSection::VERSION);
+                for subsection in reader {
+                    match subsection.unwrap() {
+                        Linking::SymbolTable(reader) => {
+                            syms = Some(reader.collect::<Result<Vec<_>, _>>().unwrap());
+                        }
+                        _ => panic!("expected only a symbol table"),
+                    }
+                }
+            }
+        }
+        let syms = syms.unwrap();
+        assert_eq!(syms.len(), 5);
+        assert!(matches!(
+            syms[0],
+            SymbolInfo::Global {
+                index: 0,
+                name: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            syms[1],
+            SymbolInfo::Event {
+                index: 1,
+                name: Some("e"),
+                ..
+            }
+        ));
+        assert!(matches!(
+            syms[2],
+            SymbolInfo::Table {
+                index: 2,
+                name: Some("t"),
+                ..
+            }
+        ));
+        assert!(matches!(
+            syms[3],
+            SymbolInfo::Data {
+                name: "d",
+                symbol: None,
+                ..
+            }
+        ));
+        assert!(matches!(syms[4], SymbolInfo::Section { section: 3, .. }));
+    }
+}
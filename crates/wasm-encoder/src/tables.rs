@@ -63,6 +63,7 @@ impl Section for TableSection {
 }
 
 /// A table's type.
+#[derive(Clone, Copy, Debug)]
 pub struct TableType {
     /// The table's element type.
     pub element_type: ValType,
@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{stdin, stdout, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use structopt::StructOpt;
+use wasm_mutate::WasmMutate;
+use wasmparser::WasmFeatures;
+
+/// A WebAssembly test case mutator.
+///
+/// Given a valid Wasm module, `wasm-mutate` applies a random rewrite to it that
+/// preserves its semantics, such as swapping two functions, injecting no-ops,
+/// splitting or merging data segments, renaming an export, or replacing an
+/// instruction with an equivalent sequence. The rewrite is chosen by the
+/// `--seed`, so the same module and seed always give the same output.
+///
+/// ## Example
+///
+/// Apply the rewrite chosen by seed 42 to `input.wasm`:
+///
+/// $ wasm-mutate input.wasm --seed 42 -o output.wasm
+///
+/// ## Exit Codes
+///
+/// * 0: Success.
+///
+/// * 1: An unexpected failure occurred, the input module isn't valid, or no
+///   rewrite applies to it.
+#[derive(StructOpt)]
+struct Options {
+    /// The Wasm module to mutate.
+    ///
+    /// `stdin` is used if this argument is not supplied.
+    #[structopt(parse(from_os_str))]
+    input: Option<PathBuf>,
+
+    /// The output file path, where the mutated module is placed.
+    ///
+    /// `stdout` is used if this argument is not supplied.
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// The seed that chooses the rewrite.
+    #[structopt(short = "s", long = "seed", default_value = "0")]
+    seed: u64,
+
+    /// Allow renaming exports, which breaks embedders that look them up by
+    /// name.
+    #[structopt(long = "rename-exports")]
+    rename_exports: bool,
+
+    /// Enable a WebAssembly proposal when validating the input.
+    ///
+    /// May be given more than once.
+    #[structopt(
+        long = "enable",
+        number_of_values = 1,
+        possible_values = &[
+            "reference-types",
+            "module-linking",
+            "simd",
+            "multi-value",
+            "threads",
+            "tail-call",
+            "bulk-memory",
+            "multi-memory",
+            "exceptions",
+            "memory64",
+        ],
+    )]
+    enable: Vec<String>,
+}
+
+fn main() {
+    let opts = Options::from_args();
+    if let Err(e) = run(&opts) {
+        eprintln!("error: {:#}", e);
+        process::exit(1);
+    }
+}
+
+fn run(opts: &Options) -> Result<()> {
+    let mut features = WasmFeatures::default();
+    for proposal in &opts.enable {
+        match proposal.as_str() {
+            "reference-types" => features.reference_types = true,
+            "module-linking" => features.module_linking = true,
+            "simd" => features.simd = true,
+            "multi-value" => features.multi_value = true,
+            "threads" => features.threads = true,
+            "tail-call" => features.tail_call = true,
+            "bulk-memory" => features.bulk_memory = true,
+            "multi-memory" => features.multi_memory = true,
+            "exceptions" => features.exceptions = true,
+            "memory64" => features.memory64 = true,
+            _ => bail!("unknown proposal `{}`", proposal),
+        }
+    }
+
+    let input = match &opts.input {
+        Some(path) => {
+            fs::read(path).with_context(|| format!("failed to read '{}'", path.display()))?
+        }
+        None => {
+            let mut input = vec![];
+            stdin()
+                .read_to_end(&mut input)
+                .context("failed to read '<stdin>'")?;
+            input
+        }
+    };
+
+    let output = WasmMutate::new()
+        .features(features)
+        .preserve_export_names(!opts.rename_exports)
+        .mutate_with_seed(&input, opts.seed)?;
+
+    match &opts.output {
+        Some(path) => fs::write(path, &output)
+            .with_context(|| format!("failed to write '{}'", path.display()))?,
+        None => stdout()
+            .write_all(&output)
+            .context("failed to write '<stdout>'")?,
+    }
+    Ok(())
+}
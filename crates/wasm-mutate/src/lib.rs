@@ -0,0 +1,829 @@
+//! A WebAssembly test case mutator.
+//!
+//! Given an existing Wasm module, `wasm-mutate` applies a random rewrite to it
+//! that preserves its semantics, driven by an [`arbitrary::Unstructured`]. This
+//! lets fuzzers explore the neighbourhood of real-world modules, rather than
+//! only the modules that `wasm-smith` generates from scratch.
+//!
+//! The rewrites are:
+//!
+//! * swapping two functions, along with every reference to them,
+//! * injecting a no-op instruction sequence,
+//! * splitting an active data segment in two, or merging two adjacent ones,
+//! * renaming an export, when enabled with
+//!   [`preserve_export_names(false)`][WasmMutate::preserve_export_names], and
+//! * replacing an instruction with an equivalent sequence of instructions.
+//!
+//! Modules are read with `wasmparser` and re-emitted with `wasm-encoder`.
+//! Function and local names in the "name" custom section follow the functions
+//! that they name.
+//!
+//! # Example
+//!
+//! ```
+//! use arbitrary::Unstructured;
+//! use wasm_mutate::WasmMutate;
+//!
+//! let wasm = wat::parse_str(
+//!     r#"
+//!     (module
+//!       (func (export "a") (result i32) i32.const 1)
+//!       (func (export "b") (result i32) i32.const 2 i32.const 3 i32.sub))
+//!     "#,
+//! )?;
+//!
+//! let mut u = Unstructured::new(&[5, 4, 3, 2, 1]);
+//! let mutated = WasmMutate::new().mutate(&wasm, &mut u)?;
+//! assert!(wasmparser::validate(&mutated).is_ok());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#![deny(missing_docs, missing_debug_implementations)]
+
+use anyhow::{bail, Result};
+use arbitrary::Unstructured;
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+use wasm_encoder::reencode::{self, utils, Reencode};
+use wasm_encoder::{
+    CodeSection, DataSection, ExportSection, Function, FunctionSection, IndirectNameMap,
+    Instruction, Module, NameMap, NameSection,
+};
+use wasmparser::{
+    Alias, Chunk, DataKind, ExternalKind, ImportSectionEntryType, MemoryType, Name,
+    NameSectionReader, Operator, Parser, Payload, Validator, WasmFeatures,
+};
+
+/// A semantics-preserving mutator for Wasm modules.
+///
+/// # Example
+///
+/// Use `wasm-mutate` as a libFuzzer custom mutator:
+///
+/// ```text
+/// #[no_mangle]
+/// pub extern "C" fn LLVMFuzzerCustomMutator(
+///     data: *mut u8,
+///     size: usize,
+///     max_size: usize,
+///     seed: u32,
+/// ) -> usize {
+///     let data = unsafe { std::slice::from_raw_parts_mut(data, max_size) };
+///     match WasmMutate::new().libfuzzer_mutate(data, size, max_size, seed) {
+///         Some(new_size) => new_size,
+///         // Fall back to libFuzzer's own byte-level mutations.
+///         None => unsafe { LLVMFuzzerMutate(data.as_mut_ptr(), size, max_size) },
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct WasmMutate {
+    features: WasmFeatures,
+    preserve_export_names: bool,
+}
+
+impl Default for WasmMutate {
+    fn default() -> WasmMutate {
+        WasmMutate {
+            features: WasmFeatures::default(),
+            preserve_export_names: true,
+        }
+    }
+}
+
+impl WasmMutate {
+    /// Create a new mutator that accepts inputs that are valid with the
+    /// default `WasmFeatures`.
+    pub fn new() -> WasmMutate {
+        WasmMutate::default()
+    }
+
+    /// Set the features that inputs must validate with.
+    pub fn features(&mut self, features: WasmFeatures) -> &mut Self {
+        self.features = features;
+        self
+    }
+
+    /// Whether to never rename exports. Defaults to `true`.
+    ///
+    /// Renaming an export doesn't change what a module computes, but it does
+    /// break embedders that look the export up by name, so it is opt-in.
+    pub fn preserve_export_names(&mut self, preserve: bool) -> &mut Self {
+        self.preserve_export_names = preserve;
+        self
+    }
+
+    /// Apply a single rewrite, chosen by `u`, to `input`.
+    ///
+    /// Returns an error if `input` isn't valid, if no rewrite applies to it,
+    /// or if it uses something that `wasm-encoder` can't re-emit.
+    pub fn mutate(&self, input: &[u8], u: &mut Unstructured<'_>) -> Result<Vec<u8>> {
+        let mut validator = Validator::new();
+        validator.wasm_features(self.features);
+        if validator.validate_all(input).is_err() {
+            bail!("the input is not a valid Wasm module");
+        }
+
+        let info = ModuleInfo::new(input)?;
+        let mutation = self.choose_mutation(&info, u)?;
+        let mut mutator = Mutator::new(mutation, &info);
+        let mut module = Module::new();
+        mutator.parse_core_module(&mut module, input)?;
+        Ok(module.finish())
+    }
+
+    /// Like [`mutate`][WasmMutate::mutate], but with the rewrite chosen by a
+    /// pseudo-random number generator seeded with `seed`.
+    pub fn mutate_with_seed(&self, input: &[u8], seed: u64) -> Result<Vec<u8>> {
+        let mut bytes = vec![0; 256];
+        SmallRng::seed_from_u64(seed).fill_bytes(&mut bytes);
+        self.mutate(input, &mut Unstructured::new(&bytes))
+    }
+
+    /// Mutate the module in `data[..size]` in place, with the signature of a
+    /// libFuzzer custom mutator.
+    ///
+    /// Returns the size of the mutated module, or `None` if the input couldn't
+    /// be mutated or the result wouldn't fit in `max_size` bytes. In that case
+    /// `data` is left untouched.
+    pub fn libfuzzer_mutate(
+        &self,
+        data: &mut [u8],
+        size: usize,
+        max_size: usize,
+        seed: u32,
+    ) -> Option<usize> {
+        let wasm = self.mutate_with_seed(&data[..size], seed.into()).ok()?;
+        if wasm.len() > max_size.min(data.len()) {
+            return None;
+        }
+        data[..wasm.len()].copy_from_slice(&wasm);
+        Some(wasm.len())
+    }
+
+    fn choose_mutation(&self, info: &ModuleInfo, u: &mut Unstructured<'_>) -> Result<Mutation> {
+        let splittable = (0..info.data.len() as u32)
+            .filter(|&i| matches!(info.data[i as usize], Some(d) if d.len >= 2))
+            .collect::<Vec<_>>();
+        let mergeable = (1..info.data.len() as u32)
+            .filter(
+                |&i| match (info.data[i as usize - 1], info.data[i as usize]) {
+                    (Some(a), Some(b)) => a.memory == b.memory && a.offset + a.len == b.offset,
+                    _ => false,
+                },
+            )
+            .map(|i| i - 1)
+            .collect::<Vec<_>>();
+
+        let mut kinds = vec![];
+        // Module linking sections are copied verbatim, and may refer to
+        // functions by index.
+        if info.num_defined_funcs >= 2 && !info.module_linking {
+            kinds.push(MutationKind::SwapFunctions);
+        }
+        if info.num_defined_funcs >= 1 {
+            kinds.push(MutationKind::InjectNop);
+        }
+        if !splittable.is_empty() {
+            kinds.push(MutationKind::SplitData);
+        }
+        if !mergeable.is_empty() {
+            kinds.push(MutationKind::MergeData);
+        }
+        if !info.exports.is_empty() && !self.preserve_export_names {
+            kinds.push(MutationKind::RenameExport);
+        }
+        if !info.rewritable.is_empty() {
+            kinds.push(MutationKind::RewriteInstruction);
+        }
+        if kinds.is_empty() {
+            bail!("no mutation applies to the input");
+        }
+
+        Ok(match u.choose(&kinds)? {
+            MutationKind::SwapFunctions => {
+                let a = u.int_in_range(0..=info.num_defined_funcs - 1)?;
+                let mut b = u.int_in_range(0..=info.num_defined_funcs - 2)?;
+                if b >= a {
+                    b += 1;
+                }
+                Mutation::SwapFunctions(a.min(b), a.max(b))
+            }
+            MutationKind::InjectNop => {
+                let func = u.int_in_range(0..=info.num_defined_funcs - 1)?;
+                let index = u.int_in_range(0..=info.instructions[func as usize] - 1)?;
+                let nop = match u.int_in_range(0..=2)? {
+                    0 => NopSequence::Nop,
+                    1 => NopSequence::ConstDrop(u.arbitrary()?),
+                    _ => NopSequence::EmptyBlock,
+                };
+                Mutation::InjectNop { func, index, nop }
+            }
+            MutationKind::SplitData => {
+                let segment = *u.choose(&splittable)?;
+                let len = info.data[segment as usize].unwrap().len;
+                let at = u.int_in_range(1..=len - 1)?;
+                Mutation::SplitData { segment, at }
+            }
+            MutationKind::MergeData => Mutation::MergeData(*u.choose(&mergeable)?),
+            MutationKind::RenameExport => {
+                let index = u.int_in_range(0..=info.exports.len() as u32 - 1)?;
+                let mut name = format!("{}_{}", info.exports[index as usize], u.arbitrary::<u8>()?);
+                while info.exports.contains(&name) {
+                    name.push('_');
+                }
+                Mutation::RenameExport { index, name }
+            }
+            MutationKind::RewriteInstruction => {
+                let (func, index) = *u.choose(&info.rewritable)?;
+                let split = u.arbitrary()?;
+                Mutation::RewriteInstruction { func, index, split }
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MutationKind {
+    SwapFunctions,
+    InjectNop,
+    SplitData,
+    MergeData,
+    RenameExport,
+    RewriteInstruction,
+}
+
+/// A single rewrite. Function indices count defined functions only.
+#[derive(Clone, Debug)]
+enum Mutation {
+    /// Swap the two functions, where the first is the smaller index.
+    SwapFunctions(u32, u32),
+    /// Insert a no-op sequence before the `index`th instruction of a function
+    /// body.
+    InjectNop {
+        func: u32,
+        index: u32,
+        nop: NopSequence,
+    },
+    /// Split an active data segment into its first `at` bytes and the rest.
+    SplitData {
+        segment: u32,
+        at: u64,
+    },
+    /// Merge an active data segment with the next one, which starts where it
+    /// ends.
+    MergeData(u32),
+    RenameExport {
+        index: u32,
+        name: String,
+    },
+    /// Replace the `index`th instruction of a function body with an
+    /// equivalent sequence. Constants are split into a sum whose first term is
+    /// `split`.
+    RewriteInstruction {
+        func: u32,
+        index: u32,
+        split: i64,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NopSequence {
+    Nop,
+    ConstDrop(i32),
+    EmptyBlock,
+}
+
+/// An active data segment with a constant offset.
+#[derive(Clone, Copy)]
+struct ActiveData {
+    memory: u32,
+    offset: u64,
+    len: u64,
+    memory64: bool,
+}
+
+/// What the mutations need to know about a module.
+#[derive(Default)]
+struct ModuleInfo {
+    num_imported_funcs: u32,
+    num_defined_funcs: u32,
+    module_linking: bool,
+    /// Whether each memory is 64-bit, or `None` if it's aliased and so its
+    /// type isn't known.
+    memories: Vec<Option<bool>>,
+    /// The minimum size in bytes of each memory, or `None` if it's imported or
+    /// aliased.
+    memory_sizes: Vec<Option<u64>>,
+    exports: Vec<String>,
+    /// Every data segment, if it's active, has a constant offset, and lies
+    /// within its defined memory's initial size.
+    data: Vec<Option<ActiveData>>,
+    /// The number of instructions in each function body.
+    instructions: Vec<u32>,
+    /// The function bodies and instructions that `RewriteInstruction` applies
+    /// to.
+    rewritable: Vec<(u32, u32)>,
+}
+
+impl ModuleInfo {
+    fn new(mut wasm: &[u8]) -> Result<ModuleInfo> {
+        let mut info = ModuleInfo::default();
+        let mut parser = Parser::new(0);
+        loop {
+            let payload = match parser.parse(wasm, true)? {
+                Chunk::NeedMoreData(_) => unreachable!(),
+                Chunk::Parsed { payload, consumed } => {
+                    wasm = &wasm[consumed..];
+                    payload
+                }
+            };
+            match payload {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        match import?.ty {
+                            ImportSectionEntryType::Function(_) => info.num_imported_funcs += 1,
+                            ImportSectionEntryType::Memory(ty) => {
+                                info.memories.push(Some(is_64(ty)));
+                                info.memory_sizes.push(None);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(funcs) => info.num_defined_funcs = funcs.get_count(),
+                Payload::MemorySection(memories) => {
+                    for ty in memories {
+                        let ty = ty?;
+                        info.memories.push(Some(is_64(ty)));
+                        info.memory_sizes.push(Some(min_size(ty)));
+                    }
+                }
+                Payload::ExportSection(exports) => {
+                    for export in exports {
+                        info.exports.push(export?.field.to_string());
+                    }
+                }
+                Payload::DataSection(data) => {
+                    for segment in data {
+                        let segment = segment?;
+                        let active = match segment.kind {
+                            DataKind::Active {
+                                memory_index,
+                                init_expr,
+                            } => {
+                                let offset = match init_expr.get_operators_reader().read()? {
+                                    Operator::I32Const { value } => Some(u64::from(value as u32)),
+                                    Operator::I64Const { value } => Some(value as u64),
+                                    _ => None,
+                                };
+                                let memory = memory_index as usize;
+                                let memory64 = info.memories.get(memory).copied().flatten();
+                                let size = info.memory_sizes.get(memory).copied().flatten();
+                                let len = segment.data.len() as u64;
+                                offset
+                                    .zip(memory64)
+                                    .filter(|&(offset, _)| fits(offset, len, size))
+                                    .map(|(offset, memory64)| ActiveData {
+                                        memory: memory_index,
+                                        offset,
+                                        len,
+                                        memory64,
+                                    })
+                            }
+                            DataKind::Passive => None,
+                        };
+                        info.data.push(active);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let func = info.instructions.len() as u32;
+                    let mut reader = body.get_operators_reader()?;
+                    let mut n = 0;
+                    while !reader.eof() {
+                        if is_rewritable(&reader.read()?) {
+                            info.rewritable.push((func, n));
+                        }
+                        n += 1;
+                    }
+                    info.instructions.push(n);
+                }
+                Payload::AliasSection(aliases) => {
+                    info.module_linking = true;
+                    for alias in aliases {
+                        if let Alias::InstanceExport {
+                            kind: ExternalKind::Memory,
+                            ..
+                        } = alias?
+                        {
+                            info.memories.push(None);
+                            info.memory_sizes.push(None);
+                        }
+                    }
+                }
+                Payload::InstanceSection(_) | Payload::ModuleSectionStart { .. } => {
+                    info.module_linking = true;
+                    if let Payload::ModuleSectionStart { size, .. } = payload {
+                        parser.skip_section();
+                        wasm = &wasm[size as usize..];
+                    }
+                }
+                Payload::End => return Ok(info),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_64(ty: MemoryType) -> bool {
+    matches!(ty, MemoryType::M64 { .. })
+}
+
+/// The size in bytes of a memory of type `ty` when it's instantiated.
+fn min_size(ty: MemoryType) -> u64 {
+    let pages = match ty {
+        MemoryType::M32 { limits, .. } => u64::from(limits.initial),
+        MemoryType::M64 { limits, .. } => limits.initial,
+    };
+    pages.saturating_mul(65536)
+}
+
+/// Returns whether a segment of `len` bytes at `offset` lies within a defined
+/// memory of `size` bytes, so that splitting or merging it can't change where
+/// instantiation fails.
+fn fits(offset: u64, len: u64, size: Option<u64>) -> bool {
+    match (offset.checked_add(len), size) {
+        (Some(end), Some(size)) => end <= size,
+        _ => false,
+    }
+}
+
+fn is_rewritable(op: &Operator<'_>) -> bool {
+    matches!(
+        op,
+        Operator::LocalTee { .. }
+            | Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::I32Eqz
+            | Operator::I64Eqz
+            | Operator::I32Sub
+            | Operator::I64Sub
+    )
+}
+
+/// A re-encoder that applies one [`Mutation`].
+struct Mutator {
+    mutation: Mutation,
+    num_imported_funcs: u32,
+    /// How many of the items that `mutation` applies to have been seen so far.
+    seen: u32,
+    /// Whether the function body being re-encoded is the one that
+    /// `Mutation::InjectNop` or `Mutation::RewriteInstruction` applies to.
+    in_target: bool,
+    next_instruction: u32,
+    /// The function bodies held back by `Mutation::SwapFunctions`.
+    pending: Vec<Function>,
+    /// The data segment held back by `Mutation::MergeData`.
+    merged: Option<(ActiveData, Vec<u8>)>,
+    data: Vec<Option<ActiveData>>,
+}
+
+impl Mutator {
+    fn new(mutation: Mutation, info: &ModuleInfo) -> Mutator {
+        Mutator {
+            mutation,
+            num_imported_funcs: info.num_imported_funcs,
+            seen: 0,
+            in_target: false,
+            next_instruction: 0,
+            pending: vec![],
+            merged: None,
+            data: info.data.clone(),
+        }
+    }
+
+    /// Returns the index of the next item that `mutation` applies to.
+    fn next(&mut self) -> u32 {
+        let seen = self.seen;
+        self.seen += 1;
+        seen
+    }
+
+    /// Re-encodes the name section with function indices remapped, and the
+    /// name maps sorted to match.
+    fn remap_names(&mut self, data: &[u8]) -> wasmparser::Result<NameSection> {
+        let mut names = NameSection::new();
+        let mut reader = NameSectionReader::new(data, 0)?;
+        while !reader.eof() {
+            match reader.read()? {
+                Name::Module(name) => {
+                    names.module(name.get_name()?);
+                }
+                Name::Function(map) => {
+                    let mut reader = map.get_map()?;
+                    let mut funcs = vec![];
+                    for _ in 0..reader.get_count() {
+                        let naming = reader.read()?;
+                        funcs.push((self.function_index(naming.index), naming.name));
+                    }
+                    funcs.sort_by_key(|(index, _)| *index);
+                    let mut map = NameMap::new();
+                    for (index, name) in funcs {
+                        map.append(index, name);
+                    }
+                    names.functions(&map);
+                }
+                Name::Local(locals) => {
+                    let mut reader = locals.get_function_local_reader()?;
+                    let mut funcs = vec![];
+                    for _ in 0..reader.get_count() {
+                        let func = reader.read()?;
+                        let mut names = func.get_map()?;
+                        let mut map = NameMap::new();
+                        for _ in 0..names.get_count() {
+                            let naming = names.read()?;
+                            map.append(naming.index, naming.name);
+                        }
+                        funcs.push((self.function_index(func.func_index), map));
+                    }
+                    funcs.sort_by_key(|(index, _)| *index);
+                    let mut map = IndirectNameMap::new();
+                    for (index, locals) in &funcs {
+                        map.append(*index, locals);
+                    }
+                    names.locals(&map);
+                }
+            }
+        }
+        Ok(names)
+    }
+}
+
+fn offset_instruction(data: ActiveData, offset: u64) -> Instruction<'static> {
+    if data.memory64 {
+        Instruction::I64Const(offset as i64)
+    } else {
+        Instruction::I32Const(offset as u32 as i32)
+    }
+}
+
+impl Reencode for Mutator {
+    fn function_index(&mut self, func: u32) -> u32 {
+        match self.mutation {
+            Mutation::SwapFunctions(a, b) => {
+                let (a, b) = (self.num_imported_funcs + a, self.num_imported_funcs + b);
+                if func == a {
+                    b
+                } else if func == b {
+                    a
+                } else {
+                    func
+                }
+            }
+            _ => func,
+        }
+    }
+
+    fn data_index(&mut self, data: u32) -> u32 {
+        match self.mutation {
+            // The two halves of a split segment are both active, so they're
+            // both dropped once the module is instantiated, just like the
+            // original segment. That makes either of them a valid stand-in for
+            // `memory.init` and `data.drop`, and similarly for merged segments.
+            Mutation::SplitData { segment, .. } if data > segment => data + 1,
+            Mutation::MergeData(segment) if data > segment => data - 1,
+            _ => data,
+        }
+    }
+
+    fn data_count(&mut self, count: u32) -> u32 {
+        match self.mutation {
+            Mutation::SplitData { .. } => count + 1,
+            Mutation::MergeData(_) => count - 1,
+            _ => count,
+        }
+    }
+
+    fn parse_function_section(
+        &mut self,
+        functions: &mut FunctionSection,
+        section: wasmparser::FunctionSectionReader<'_>,
+    ) -> reencode::Result<()> {
+        let (a, b) = match self.mutation {
+            Mutation::SwapFunctions(a, b) => (a as usize, b as usize),
+            _ => return utils::parse_function_section(self, functions, section),
+        };
+        let mut types = vec![];
+        for ty in section {
+            types.push(self.type_index(ty?));
+        }
+        types.swap(a, b);
+        for ty in types {
+            functions.function(ty);
+        }
+        Ok(())
+    }
+
+    fn parse_export(
+        &mut self,
+        exports: &mut ExportSection,
+        export: wasmparser::Export<'_>,
+    ) -> reencode::Result<()> {
+        let index = match self.mutation {
+            Mutation::RenameExport { index, .. } => index,
+            _ => return utils::parse_export(self, exports, export),
+        };
+        if self.next() != index {
+            return utils::parse_export(self, exports, export);
+        }
+        let name = match &self.mutation {
+            Mutation::RenameExport { name, .. } => name.clone(),
+            _ => unreachable!(),
+        };
+        utils::parse_export(
+            self,
+            exports,
+            wasmparser::Export {
+                field: &name,
+                ..export
+            },
+        )
+    }
+
+    fn parse_data(
+        &mut self,
+        data: &mut DataSection,
+        segment: wasmparser::Data<'_>,
+    ) -> reencode::Result<()> {
+        match self.mutation {
+            Mutation::SplitData { segment: i, at } => {
+                if self.next() != i {
+                    return utils::parse_data(self, data, segment);
+                }
+                let active = self.data[i as usize].unwrap();
+                let (first, rest) = segment.data.split_at(at as usize);
+                data.active(
+                    active.memory,
+                    offset_instruction(active, active.offset),
+                    first.iter().copied(),
+                );
+                data.active(
+                    active.memory,
+                    offset_instruction(active, active.offset + at),
+                    rest.iter().copied(),
+                );
+                Ok(())
+            }
+            Mutation::MergeData(i) => {
+                let index = self.next();
+                if index == i {
+                    self.merged = Some((self.data[i as usize].unwrap(), segment.data.to_vec()));
+                    Ok(())
+                } else if index == i + 1 {
+                    let (active, mut bytes) = self.merged.take().unwrap();
+                    bytes.extend_from_slice(segment.data);
+                    data.active(
+                        active.memory,
+                        offset_instruction(active, active.offset),
+                        bytes,
+                    );
+                    Ok(())
+                } else {
+                    utils::parse_data(self, data, segment)
+                }
+            }
+            _ => utils::parse_data(self, data, segment),
+        }
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        body: wasmparser::FunctionBody<'_>,
+    ) -> reencode::Result<()> {
+        match self.mutation {
+            Mutation::SwapFunctions(a, b) => {
+                let index = self.next();
+                if index < a || index > b {
+                    return utils::parse_function_body(self, code, body);
+                }
+                // Hold back the bodies from `a` to `b`, then emit them with
+                // the first and last swapped.
+                let func = utils::function_body(self, body)?;
+                self.pending.push(func);
+                if index == b {
+                    let last = self.pending.len() - 1;
+                    code.function(&self.pending[last]);
+                    for func in &self.pending[1..last] {
+                        code.function(func);
+                    }
+                    code.function(&self.pending[0]);
+                    self.pending.clear();
+                }
+                Ok(())
+            }
+            Mutation::InjectNop { func, .. } | Mutation::RewriteInstruction { func, .. } => {
+                if self.next() != func {
+                    return utils::parse_function_body(self, code, body);
+                }
+                self.in_target = true;
+                let result = utils::parse_function_body(self, code, body);
+                self.in_target = false;
+                result
+            }
+            _ => utils::parse_function_body(self, code, body),
+        }
+    }
+
+    fn parse_instruction(
+        &mut self,
+        func: &mut Function,
+        op: Operator<'_>,
+        raw: &[u8],
+    ) -> reencode::Result<()> {
+        if !self.in_target {
+            return utils::parse_instruction(self, func, op, raw);
+        }
+        let index = self.next_instruction;
+        self.next_instruction += 1;
+        match self.mutation {
+            Mutation::InjectNop {
+                index: target, nop, ..
+            } if index == target => {
+                match nop {
+                    NopSequence::Nop => {
+                        func.instruction(Instruction::Nop);
+                    }
+                    NopSequence::ConstDrop(value) => {
+                        func.instruction(Instruction::I32Const(value));
+                        func.instruction(Instruction::Drop);
+                    }
+                    NopSequence::EmptyBlock => {
+                        func.instruction(Instruction::Block(wasm_encoder::BlockType::Empty));
+                        func.instruction(Instruction::End);
+                    }
+                }
+                utils::parse_instruction(self, func, op, raw)
+            }
+            Mutation::RewriteInstruction {
+                index: target,
+                split,
+                ..
+            } if index == target => {
+                let insts = match op {
+                    Operator::LocalTee { local_index } => vec![
+                        Instruction::LocalSet(local_index),
+                        Instruction::LocalGet(local_index),
+                    ],
+                    Operator::I32Const { value } => vec![
+                        Instruction::I32Const(split as i32),
+                        Instruction::I32Const(value.wrapping_sub(split as i32)),
+                        Instruction::I32Add,
+                    ],
+                    Operator::I64Const { value } => vec![
+                        Instruction::I64Const(split),
+                        Instruction::I64Const(value.wrapping_sub(split)),
+                        Instruction::I64Add,
+                    ],
+                    Operator::I32Eqz => vec![Instruction::I32Const(0), Instruction::I32Eq],
+                    Operator::I64Eqz => vec![Instruction::I64Const(0), Instruction::I64Eq],
+                    // `x - y` is `x + y * -1` in wrapping arithmetic.
+                    Operator::I32Sub => vec![
+                        Instruction::I32Const(-1),
+                        Instruction::I32Mul,
+                        Instruction::I32Add,
+                    ],
+                    Operator::I64Sub => vec![
+                        Instruction::I64Const(-1),
+                        Instruction::I64Mul,
+                        Instruction::I64Add,
+                    ],
+                    _ => unreachable!(),
+                };
+                for inst in insts {
+                    func.instruction(inst);
+                }
+                Ok(())
+            }
+            _ => utils::parse_instruction(self, func, op, raw),
+        }
+    }
+
+    fn parse_custom_section(
+        &mut self,
+        module: &mut Module,
+        name: &str,
+        data: &[u8],
+    ) -> reencode::Result<()> {
+        if let (Mutation::SwapFunctions(..), "name") = (&self.mutation, name) {
+            // A name section that can't be parsed is copied verbatim, like
+            // any other custom section.
+            if let Ok(names) = self.remap_names(data) {
+                module.section(&names);
+                return Ok(());
+            }
+        }
+        module.section(&wasm_encoder::CustomSection { name, data });
+        Ok(())
+    }
+}
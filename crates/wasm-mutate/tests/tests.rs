@@ -0,0 +1,202 @@
+use anyhow::Result;
+use wasm_mutate::WasmMutate;
+use wasmparser::{Name, NameSectionReader, Operator, Parser, Payload, WasmFeatures};
+
+const MODULE: &str = r#"
+(module
+  (import "env" "f" (func $imported (param i32)))
+  (memory 1)
+  (data (i32.const 0) "hello")
+  (data (i32.const 5) "world")
+  (data (i32.const 100) "x")
+  (table 2 funcref)
+  (elem (i32.const 0) $clz $sub)
+  (func $clz (export "clz") (param i32) (result i32)
+    local.get 0
+    i32.clz)
+  (func $sub (export "sub") (param i64 i64) (result i64)
+    (local i64)
+    local.get 0
+    local.get 1
+    i64.sub
+    local.tee 2)
+  (func $main (export "main") (result i32)
+    i32.const 42
+    call $imported
+    i32.const 7
+    call $clz
+    i32.eqz)
+  (start $start)
+  (func $start
+    i64.const 1
+    i64.const 2
+    call $sub
+    drop))
+"#;
+
+/// Returns the name section's name of every defined function that contains an
+/// `i32.clz`.
+fn clz_function_names(wasm: &[u8]) -> Result<Vec<String>> {
+    let mut num_imported = 0;
+    let mut clz_funcs = vec![];
+    let mut names = vec![];
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::ImportSection(imports) => num_imported = imports.get_count(),
+            Payload::CodeSectionEntry(body) => {
+                let mut ops = body.get_operators_reader()?;
+                let mut has_clz = false;
+                while !ops.eof() {
+                    has_clz |= matches!(ops.read()?, Operator::I32Clz);
+                }
+                clz_funcs.push(has_clz);
+            }
+            Payload::CustomSection {
+                name: "name",
+                data,
+                data_offset,
+            } => {
+                let mut reader = NameSectionReader::new(data, data_offset)?;
+                while !reader.eof() {
+                    if let Name::Function(map) = reader.read()? {
+                        let mut map = map.get_map()?;
+                        for _ in 0..map.get_count() {
+                            let naming = map.read()?;
+                            names.push((naming.index, naming.name.to_string()));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(names
+        .into_iter()
+        .filter(|(index, _)| matches!(index.checked_sub(num_imported), Some(i) if clz_funcs[i as usize]))
+        .map(|(_, name)| name)
+        .collect())
+}
+
+#[test]
+fn mutations_preserve_validity_and_names() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    assert_eq!(clz_function_names(&wasm)?, ["clz"]);
+
+    let mut num_changed = 0;
+    for seed in 0..500 {
+        let mutated = WasmMutate::new().mutate_with_seed(&wasm, seed)?;
+        wasmparser::validate(&mutated)?;
+        assert_eq!(clz_function_names(&mutated)?, ["clz"]);
+        if mutated != wasm {
+            num_changed += 1;
+        }
+    }
+    assert!(num_changed > 400);
+    Ok(())
+}
+
+#[test]
+fn preserve_export_names() -> Result<()> {
+    let wasm = wat::parse_str(MODULE)?;
+    let export_names = |wasm: &[u8]| -> Result<Vec<String>> {
+        let mut names = vec![];
+        for payload in Parser::new(0).parse_all(wasm) {
+            if let Payload::ExportSection(exports) = payload? {
+                for export in exports {
+                    names.push(export?.field.to_string());
+                }
+            }
+        }
+        Ok(names)
+    };
+
+    // Export names are preserved by default.
+    for seed in 0..100 {
+        let mutated = WasmMutate::new().mutate_with_seed(&wasm, seed)?;
+        assert_eq!(export_names(&mutated)?, ["clz", "sub", "main"]);
+    }
+
+    let mut mutate = WasmMutate::new();
+    mutate.preserve_export_names(false);
+    let mut renamed = false;
+    for seed in 0..100 {
+        let mutated = mutate.mutate_with_seed(&wasm, seed)?;
+        renamed |= export_names(&mutated)? != ["clz", "sub", "main"];
+    }
+    assert!(renamed);
+    Ok(())
+}
+
+#[test]
+fn data_outside_defined_memory_is_untouched() -> Result<()> {
+    let data_segments = |wasm: &[u8]| -> Result<Vec<Vec<u8>>> {
+        let mut segments = vec![];
+        for payload in Parser::new(0).parse_all(wasm) {
+            if let Payload::DataSection(data) = payload? {
+                for segment in data {
+                    segments.push(segment?.data.to_vec());
+                }
+            }
+        }
+        Ok(segments)
+    };
+
+    // Splitting or merging these segments could change how much of them is
+    // written before instantiation fails, or, for the imported memory,
+    // whether it fails at all.
+    for module in &[
+        r#"(module
+             (memory 1)
+             (data (i32.const 65534) "abcd")
+             (data (i32.const 65538) "ef")
+             (func))"#,
+        r#"(module
+             (import "env" "memory" (memory 1))
+             (data (i32.const 0) "abcd")
+             (data (i32.const 4) "ef")
+             (func))"#,
+    ] {
+        let wasm = wat::parse_str(module)?;
+        let expected = data_segments(&wasm)?;
+        for seed in 0..100 {
+            let mutated = WasmMutate::new().mutate_with_seed(&wasm, seed)?;
+            assert_eq!(data_segments(&mutated)?, expected);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn data_in_aliased_memory_is_untouched() -> Result<()> {
+    let wasm = wat::parse_str(
+        r#"(module
+             (import "i" (instance $i (export "memory" (memory 1))))
+             (alias $memory (memory $i "memory"))
+             (data (i32.const 0) "abcd")
+             (data (i32.const 4) "ef")
+             (func))"#,
+    )?;
+    let mut mutate = WasmMutate::new();
+    mutate.features(WasmFeatures {
+        module_linking: true,
+        ..WasmFeatures::default()
+    });
+    for seed in 0..100 {
+        let mutated = mutate.mutate_with_seed(&wasm, seed)?;
+        let mut segments = 0;
+        for payload in Parser::new(0).parse_all(&mutated) {
+            if let Payload::DataSection(data) = payload? {
+                segments += data.get_count();
+            }
+        }
+        assert_eq!(segments, 2);
+    }
+    Ok(())
+}
+
+#[test]
+fn invalid_input_is_an_error() {
+    assert!(WasmMutate::new()
+        .mutate_with_seed(b"\0asm\x01\0\0\0\x7f", 0)
+        .is_err());
+}
@@ -0,0 +1,122 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use structopt::StructOpt;
+use wasm_shrink::{CommandPredicate, WasmShrink};
+use wasmparser::WasmFeatures;
+
+/// A WebAssembly test case reducer.
+///
+/// Given a Wasm module and a predicate command, `wasm-shrink` repeatedly tries
+/// smaller versions of the module, and keeps those that are still valid and
+/// that the predicate finds "interesting". The predicate is run with the path
+/// of a candidate module as its last argument, and a candidate is interesting
+/// when the predicate exits with status 0.
+///
+/// ## Example
+///
+/// Reduce `crash.wasm` for as long as `crashes.sh` reproduces the crash:
+///
+/// $ wasm-shrink ./crashes.sh crash.wasm -o reduced.wasm
+///
+/// Arguments after `--` are passed to the predicate, before the path of the
+/// candidate module:
+///
+/// $ wasm-shrink wasmtime crash.wasm -- run --wasm-features simd
+///
+/// ## Exit Codes
+///
+/// * 0: Success.
+///
+/// * 1: An unexpected failure occurred, or the input module isn't valid or
+///   isn't interesting.
+#[derive(StructOpt)]
+struct Options {
+    /// The predicate command.
+    #[structopt(parse(from_os_str))]
+    predicate: PathBuf,
+
+    /// The Wasm module to reduce.
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+
+    /// The output file path, where the reduced module is placed.
+    ///
+    /// Defaults to the input path with a `.shrunken.wasm` extension.
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// Enable a WebAssembly proposal when validating candidates.
+    ///
+    /// May be given more than once.
+    #[structopt(
+        long = "enable",
+        number_of_values = 1,
+        possible_values = &[
+            "reference-types",
+            "module-linking",
+            "simd",
+            "multi-value",
+            "threads",
+            "tail-call",
+            "bulk-memory",
+            "multi-memory",
+            "exceptions",
+            "memory64",
+        ],
+    )]
+    enable: Vec<String>,
+
+    /// Arguments to pass to the predicate command, before the path of the
+    /// candidate module.
+    #[structopt(last = true, parse(from_os_str))]
+    predicate_args: Vec<OsString>,
+}
+
+fn main() {
+    let opts = Options::from_args();
+    if let Err(e) = run(&opts) {
+        eprintln!("error: {:#}", e);
+        process::exit(1);
+    }
+}
+
+fn run(opts: &Options) -> Result<()> {
+    let mut features = WasmFeatures::default();
+    for proposal in &opts.enable {
+        match proposal.as_str() {
+            "reference-types" => features.reference_types = true,
+            "module-linking" => features.module_linking = true,
+            "simd" => features.simd = true,
+            "multi-value" => features.multi_value = true,
+            "threads" => features.threads = true,
+            "tail-call" => features.tail_call = true,
+            "bulk-memory" => features.bulk_memory = true,
+            "multi-memory" => features.multi_memory = true,
+            "exceptions" => features.exceptions = true,
+            "memory64" => features.memory64 = true,
+            _ => bail!("unknown proposal `{}`", proposal),
+        }
+    }
+
+    let input = fs::read(&opts.input)
+        .with_context(|| format!("failed to read '{}'", opts.input.display()))?;
+    let predicate = CommandPredicate::new(&opts.predicate, opts.predicate_args.clone());
+    let info = WasmShrink::new().features(features).run(input, predicate)?;
+
+    let output = match &opts.output {
+        Some(output) => output.clone(),
+        None => opts.input.with_extension("shrunken.wasm"),
+    };
+    fs::write(&output, &info.output)
+        .with_context(|| format!("failed to write '{}'", output.display()))?;
+    eprintln!(
+        "shrank {} bytes down to {} bytes, written to '{}'",
+        info.input_size,
+        info.output_size,
+        output.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,697 @@
+//! A WebAssembly test case reducer.
+//!
+//! Given a Wasm module and a predicate that decides whether a module is
+//! "interesting" -- for example, whether it still crashes the runtime under
+//! test -- `wasm-shrink` repeatedly tries smaller versions of the module and
+//! keeps the ones that are still valid and interesting, until none of its
+//! reductions make progress.
+//!
+//! The reductions are removing custom sections, exports, element segments,
+//! data segments, functions and single instructions (or whole `block`s,
+//! `loop`s, `if`s and `try`s), replacing function bodies with `unreachable`,
+//! and cutting function bodies short with `unreachable`. Modules are read with
+//! `wasmparser` and re-emitted with `wasm-encoder`, and every candidate is
+//! validated before the predicate sees it.
+//!
+//! # Example
+//!
+//! ```
+//! use wasm_shrink::WasmShrink;
+//!
+//! let wasm = wat::parse_str(
+//!     r#"
+//!     (module
+//!       (func (export "a") (result i32) i32.const 1)
+//!       (func (export "b") (result i32) i32.const 2 i32.const 3 i32.add))
+//!     "#,
+//! )?;
+//!
+//! // Only care that there's still an export named "b".
+//! let info = WasmShrink::new().run(wasm, |wasm: &[u8]| {
+//!     Ok(wasmparser::Parser::new(0).parse_all(wasm).any(|payload| {
+//!         match payload {
+//!             Ok(wasmparser::Payload::ExportSection(exports)) => exports
+//!                 .into_iter()
+//!                 .any(|export| export.map_or(false, |e| e.field == "b")),
+//!             _ => false,
+//!         }
+//!     }))
+//! })?;
+//! assert!(info.output_size < info.input_size);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#![deny(missing_docs, missing_debug_implementations)]
+
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use wasm_encoder::reencode::{self, utils, Reencode};
+use wasm_encoder::{
+    CodeSection, DataSection, ElementSection, ExportSection, Function, FunctionSection,
+    IndirectNameMap, Instruction, Module, NameMap, NameSection,
+};
+use wasmparser::{
+    Alias, Chunk, ExternalKind, ImportSectionEntryType, Name, NameSectionReader, NamingReader,
+    Operator, Parser, Payload, Validator, WasmFeatures,
+};
+
+/// A predicate that decides whether a Wasm module is interesting, i.e. whether
+/// it still exhibits the behavior being reduced.
+///
+/// This is implemented for closures taking the module's bytes, and for
+/// [`CommandPredicate`].
+pub trait IsInteresting {
+    /// Returns whether `wasm` is interesting.
+    fn is_interesting(&mut self, wasm: &[u8]) -> Result<bool>;
+}
+
+impl<F> IsInteresting for F
+where
+    F: FnMut(&[u8]) -> Result<bool>,
+{
+    fn is_interesting(&mut self, wasm: &[u8]) -> Result<bool> {
+        self(wasm)
+    }
+}
+
+/// A predicate that runs a command with the path of a file holding the
+/// candidate module as its last argument.
+///
+/// A candidate is interesting when the command exits successfully.
+#[derive(Clone, Debug)]
+pub struct CommandPredicate {
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+impl CommandPredicate {
+    /// Create a predicate that runs `program` with the given arguments.
+    pub fn new(program: impl Into<OsString>, args: Vec<OsString>) -> CommandPredicate {
+        CommandPredicate {
+            program: program.into(),
+            args,
+        }
+    }
+}
+
+impl IsInteresting for CommandPredicate {
+    fn is_interesting(&mut self, wasm: &[u8]) -> Result<bool> {
+        let mut file = tempfile::Builder::new()
+            .prefix("wasm-shrink")
+            .suffix(".wasm")
+            .tempfile()
+            .context("failed to create a temporary file")?;
+        file.write_all(wasm)?;
+        file.flush()?;
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .arg(file.path())
+            .stdin(Stdio::null())
+            .status()
+            .with_context(|| format!("failed to run {:?}", self.program))?;
+        Ok(status.success())
+    }
+}
+
+/// A test case reducer for Wasm modules.
+///
+/// # Example
+///
+/// Reduce a module for as long as `./crashes.sh <module>` exits successfully:
+///
+/// ```no_run
+/// use wasm_shrink::{CommandPredicate, WasmShrink};
+/// use wasmparser::WasmFeatures;
+///
+/// let wasm = std::fs::read("crash.wasm")?;
+/// let info = WasmShrink::new()
+///     .features(WasmFeatures {
+///         simd: true,
+///         ..WasmFeatures::default()
+///     })
+///     .run(wasm, CommandPredicate::new("./crashes.sh", vec![]))?;
+/// std::fs::write("reduced.wasm", &info.output)?;
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct WasmShrink {
+    features: WasmFeatures,
+}
+
+/// The result of a successful [`WasmShrink::run`].
+#[derive(Clone, Debug)]
+pub struct ShrinkInfo {
+    /// The size of the input module, in bytes.
+    pub input_size: usize,
+    /// The size of the reduced module, in bytes.
+    pub output_size: usize,
+    /// The reduced module.
+    pub output: Vec<u8>,
+}
+
+impl WasmShrink {
+    /// Create a new reducer that validates candidates with the default
+    /// `WasmFeatures`.
+    pub fn new() -> WasmShrink {
+        WasmShrink::default()
+    }
+
+    /// Set the features that every candidate must validate with.
+    pub fn features(&mut self, features: WasmFeatures) -> &mut Self {
+        self.features = features;
+        self
+    }
+
+    /// Reduce `input` for as long as `predicate` still finds the result
+    /// interesting.
+    ///
+    /// Returns an error if `input` isn't valid, isn't interesting, or if the
+    /// predicate fails.
+    pub fn run<P>(&self, input: Vec<u8>, mut predicate: P) -> Result<ShrinkInfo>
+    where
+        P: IsInteresting,
+    {
+        if !self.is_valid(&input) {
+            bail!("the input is not a valid Wasm module");
+        }
+        if !predicate.is_interesting(&input)? {
+            bail!("the input is not interesting");
+        }
+
+        let input_size = input.len();
+        let mut best = input;
+        loop {
+            let mut progress = false;
+            // Within each kind of reduction, indices are tried from last to
+            // first, so that a successful reduction doesn't shift the indices
+            // that are still to be tried.
+            for kind in &[
+                ShrinkKind::RemoveCustomSection,
+                ShrinkKind::RemoveExport,
+                ShrinkKind::RemoveElementSegment,
+                ShrinkKind::RemoveDataSegment,
+                ShrinkKind::RemoveFunction,
+                ShrinkKind::StubFunction,
+            ] {
+                for i in (0..Counts::new(&best)?.get(*kind)).rev() {
+                    progress |= self.try_shrink(&mut best, kind.with_index(i), &mut predicate)?;
+                }
+            }
+            let num_funcs = Counts::new(&best)?.num_defined_funcs;
+            for func in (0..num_funcs).rev() {
+                // Truncating the body's final `end` wouldn't shrink anything.
+                let num_instructions = Counts::new(&best)?.instructions[func as usize];
+                for len in 0..num_instructions.saturating_sub(1) {
+                    let shrink = Shrink::TruncateFunction { func, len };
+                    if self.try_shrink(&mut best, shrink, &mut predicate)? {
+                        progress = true;
+                        break;
+                    }
+                }
+                let num_instructions = Counts::new(&best)?.instructions[func as usize];
+                for index in (0..num_instructions).rev() {
+                    let shrink = Shrink::RemoveInstruction { func, index };
+                    progress |= self.try_shrink(&mut best, shrink, &mut predicate)?;
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+
+        Ok(ShrinkInfo {
+            input_size,
+            output_size: best.len(),
+            output: best,
+        })
+    }
+
+    /// Applies `shrink` to `best`, and replaces `best` with the result if it
+    /// is smaller, valid and interesting.
+    fn try_shrink<P>(&self, best: &mut Vec<u8>, shrink: Shrink, predicate: &mut P) -> Result<bool>
+    where
+        P: IsInteresting,
+    {
+        let mut shrinker = Shrinker::new(shrink, best)?;
+        let mut module = Module::new();
+        if shrinker.parse_core_module(&mut module, best).is_err() || shrinker.invalid {
+            return Ok(false);
+        }
+        let candidate = module.finish();
+        if candidate.len() >= best.len() || !self.is_valid(&candidate) {
+            return Ok(false);
+        }
+        if !predicate.is_interesting(&candidate)? {
+            return Ok(false);
+        }
+        *best = candidate;
+        Ok(true)
+    }
+
+    fn is_valid(&self, wasm: &[u8]) -> bool {
+        let mut validator = Validator::new();
+        validator.wasm_features(self.features);
+        validator.validate_all(wasm).is_ok()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ShrinkKind {
+    RemoveCustomSection,
+    RemoveExport,
+    RemoveElementSegment,
+    RemoveDataSegment,
+    RemoveFunction,
+    StubFunction,
+}
+
+impl ShrinkKind {
+    fn with_index(self, i: u32) -> Shrink {
+        match self {
+            ShrinkKind::RemoveCustomSection => Shrink::RemoveCustomSection(i),
+            ShrinkKind::RemoveExport => Shrink::RemoveExport(i),
+            ShrinkKind::RemoveElementSegment => Shrink::RemoveElementSegment(i),
+            ShrinkKind::RemoveDataSegment => Shrink::RemoveDataSegment(i),
+            ShrinkKind::RemoveFunction => Shrink::RemoveFunction(i),
+            ShrinkKind::StubFunction => Shrink::StubFunction(i),
+        }
+    }
+}
+
+/// A single reduction. Function indices count defined functions only.
+#[derive(Clone, Copy, Debug)]
+enum Shrink {
+    RemoveCustomSection(u32),
+    RemoveExport(u32),
+    RemoveElementSegment(u32),
+    RemoveDataSegment(u32),
+    RemoveFunction(u32),
+    StubFunction(u32),
+    /// Keep only the first `len` instructions of a function body, followed by
+    /// `unreachable` and the `end`s of the blocks that are still open.
+    TruncateFunction {
+        func: u32,
+        len: u32,
+    },
+    /// Remove the `index`th instruction of a function body, along with the
+    /// rest of its block if it starts one.
+    RemoveInstruction {
+        func: u32,
+        index: u32,
+    },
+}
+
+/// How many of each reducible item a module has.
+#[derive(Default)]
+struct Counts {
+    /// The number of imported and aliased functions, which come before the
+    /// defined functions in the function index space.
+    num_imported_funcs: u32,
+    num_defined_funcs: u32,
+    custom_sections: u32,
+    exports: u32,
+    element_segments: u32,
+    data_segments: u32,
+    /// The number of instructions in each function body.
+    instructions: Vec<u32>,
+}
+
+impl Counts {
+    fn new(mut wasm: &[u8]) -> Result<Counts> {
+        let mut counts = Counts::default();
+        let mut parser = Parser::new(0);
+        loop {
+            let payload = match parser.parse(wasm, true)? {
+                Chunk::NeedMoreData(_) => unreachable!(),
+                Chunk::Parsed { payload, consumed } => {
+                    wasm = &wasm[consumed..];
+                    payload
+                }
+            };
+            match payload {
+                Payload::CustomSection { .. } => counts.custom_sections += 1,
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        if let ImportSectionEntryType::Function(_) = import?.ty {
+                            counts.num_imported_funcs += 1;
+                        }
+                    }
+                }
+                Payload::AliasSection(aliases) => {
+                    for alias in aliases {
+                        if let Alias::InstanceExport {
+                            kind: ExternalKind::Function,
+                            ..
+                        } = alias?
+                        {
+                            counts.num_imported_funcs += 1;
+                        }
+                    }
+                }
+                Payload::FunctionSection(funcs) => counts.num_defined_funcs = funcs.get_count(),
+                Payload::ExportSection(exports) => counts.exports = exports.get_count(),
+                Payload::ElementSection(elements) => counts.element_segments = elements.get_count(),
+                Payload::DataSection(data) => counts.data_segments = data.get_count(),
+                Payload::CodeSectionEntry(body) => {
+                    let mut reader = body.get_operators_reader()?;
+                    let mut n = 0;
+                    while !reader.eof() {
+                        reader.read()?;
+                        n += 1;
+                    }
+                    counts.instructions.push(n);
+                }
+                // Nested modules are copied verbatim, so they aren't reduced.
+                Payload::ModuleSectionStart { size, .. } => {
+                    parser.skip_section();
+                    wasm = &wasm[size as usize..];
+                }
+                Payload::End => return Ok(counts),
+                _ => {}
+            }
+        }
+    }
+
+    fn get(&self, kind: ShrinkKind) -> u32 {
+        match kind {
+            ShrinkKind::RemoveCustomSection => self.custom_sections,
+            ShrinkKind::RemoveExport => self.exports,
+            ShrinkKind::RemoveElementSegment => self.element_segments,
+            ShrinkKind::RemoveDataSegment => self.data_segments,
+            ShrinkKind::RemoveFunction | ShrinkKind::StubFunction => self.num_defined_funcs,
+        }
+    }
+}
+
+/// A re-encoder that applies one [`Shrink`].
+struct Shrinker {
+    shrink: Shrink,
+    num_imported_funcs: u32,
+    /// How many of the items that `shrink` applies to have been seen so far.
+    seen: u32,
+    /// Whether something refers to an item that was removed.
+    invalid: bool,
+    /// Whether the function body being re-encoded is the one that
+    /// `Shrink::TruncateFunction` or `Shrink::RemoveInstruction` applies to.
+    in_target: bool,
+    next_instruction: u32,
+    /// The number of blocks that the instructions kept so far leave open.
+    depth: u32,
+    /// Whether the rest of the function body is being dropped.
+    truncated: bool,
+    /// The nesting depth within a removed block, if one is being skipped.
+    skip_depth: Option<u32>,
+}
+
+impl Shrinker {
+    fn new(shrink: Shrink, wasm: &[u8]) -> Result<Shrinker> {
+        Ok(Shrinker {
+            shrink,
+            num_imported_funcs: Counts::new(wasm)?.num_imported_funcs,
+            seen: 0,
+            invalid: false,
+            in_target: false,
+            next_instruction: 0,
+            depth: 0,
+            truncated: false,
+            skip_depth: None,
+        })
+    }
+
+    /// Returns whether the next item that `shrink` applies to is the `i`th.
+    fn next_is(&mut self, i: u32) -> bool {
+        let seen = self.seen;
+        self.seen += 1;
+        seen == i
+    }
+
+    /// Remaps an index into a space that the item at `removed` was removed
+    /// from.
+    fn remap(&mut self, index: u32, removed: u32) -> u32 {
+        if index == removed {
+            self.invalid = true;
+        }
+        if index > removed {
+            index - 1
+        } else {
+            index
+        }
+    }
+
+    /// Re-encodes a name section, dropping the names of a removed function
+    /// and the local names of a removed or stubbed function, and remapping
+    /// the function indices after a removed function.
+    fn names(&self, data: &[u8]) -> wasmparser::Result<NameSection> {
+        let (removed, stubbed) = match self.shrink {
+            Shrink::RemoveFunction(i) => (Some(self.num_imported_funcs + i), None),
+            Shrink::StubFunction(i) => (None, Some(self.num_imported_funcs + i)),
+            _ => (None, None),
+        };
+        // The new index of function `func`, or `None` if it's removed.
+        let func_index = |func: u32| match removed {
+            Some(removed) if func == removed => None,
+            Some(removed) if func > removed => Some(func - 1),
+            _ => Some(func),
+        };
+        let name_map = |mut reader: NamingReader<'_>| -> wasmparser::Result<NameMap> {
+            let mut names = NameMap::new();
+            for _ in 0..reader.get_count() {
+                let naming = reader.read()?;
+                names.append(naming.index, naming.name);
+            }
+            Ok(names)
+        };
+
+        let mut section = NameSection::new();
+        for name in NameSectionReader::new(data, 0)? {
+            match name? {
+                Name::Module(name) => {
+                    section.module(name.get_name()?);
+                }
+                Name::Function(names) => {
+                    let mut reader = names.get_map()?;
+                    let mut funcs = NameMap::new();
+                    for _ in 0..reader.get_count() {
+                        let naming = reader.read()?;
+                        if let Some(index) = func_index(naming.index) {
+                            funcs.append(index, naming.name);
+                        }
+                    }
+                    section.functions(&funcs);
+                }
+                Name::Local(names) => {
+                    let mut reader = names.get_function_local_reader()?;
+                    let mut locals = IndirectNameMap::new();
+                    for _ in 0..reader.get_count() {
+                        let func = reader.read()?;
+                        if stubbed == Some(func.func_index) {
+                            continue;
+                        }
+                        if let Some(index) = func_index(func.func_index) {
+                            locals.append(index, &name_map(func.get_map()?)?);
+                        }
+                    }
+                    section.locals(&locals);
+                }
+            }
+        }
+        Ok(section)
+    }
+}
+
+impl Reencode for Shrinker {
+    fn function_index(&mut self, func: u32) -> u32 {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveFunction(i) => self.remap(func, self.num_imported_funcs + i),
+            _ => func,
+        }
+    }
+
+    fn element_index(&mut self, element: u32) -> u32 {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveElementSegment(i) => self.remap(element, i),
+            _ => element,
+        }
+    }
+
+    fn data_index(&mut self, data: u32) -> u32 {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveDataSegment(i) => self.remap(data, i),
+            _ => data,
+        }
+    }
+
+    fn data_count(&mut self, count: u32) -> u32 {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveDataSegment(_) => count - 1,
+            _ => count,
+        }
+    }
+
+    fn parse_function_section(
+        &mut self,
+        functions: &mut FunctionSection,
+        section: wasmparser::FunctionSectionReader<'_>,
+    ) -> reencode::Result<()> {
+        let removed = match self.shrink {
+            Shrink::RemoveFunction(i) => i,
+            _ => return utils::parse_function_section(self, functions, section),
+        };
+        for (i, ty) in section.into_iter().enumerate() {
+            let ty = ty?;
+            if i as u32 != removed {
+                functions.function(self.type_index(ty));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_export(
+        &mut self,
+        exports: &mut ExportSection,
+        export: wasmparser::Export<'_>,
+    ) -> reencode::Result<()> {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveExport(i) if self.next_is(i) => Ok(()),
+            _ => utils::parse_export(self, exports, export),
+        }
+    }
+
+    fn parse_element(
+        &mut self,
+        elements: &mut ElementSection,
+        element: wasmparser::Element<'_>,
+    ) -> reencode::Result<()> {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveElementSegment(i) if self.next_is(i) => Ok(()),
+            _ => utils::parse_element(self, elements, element),
+        }
+    }
+
+    fn parse_data(
+        &mut self,
+        data: &mut DataSection,
+        segment: wasmparser::Data<'_>,
+    ) -> reencode::Result<()> {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveDataSegment(i) if self.next_is(i) => Ok(()),
+            _ => utils::parse_data(self, data, segment),
+        }
+    }
+
+    fn parse_function_body(
+        &mut self,
+        code: &mut CodeSection,
+        body: wasmparser::FunctionBody<'_>,
+    ) -> reencode::Result<()> {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveFunction(i) if self.next_is(i) => Ok(()),
+            Shrink::StubFunction(i) if self.next_is(i) => {
+                let mut func = Function::new(vec![]);
+                func.instruction(Instruction::Unreachable);
+                func.instruction(Instruction::End);
+                code.function(&func);
+                Ok(())
+            }
+            Shrink::TruncateFunction { func, .. } | Shrink::RemoveInstruction { func, .. }
+                if self.next_is(func) =>
+            {
+                self.in_target = true;
+                let result = utils::parse_function_body(self, code, body);
+                self.in_target = false;
+                result
+            }
+            _ => utils::parse_function_body(self, code, body),
+        }
+    }
+
+    fn parse_instruction(
+        &mut self,
+        func: &mut Function,
+        op: Operator<'_>,
+        raw: &[u8],
+    ) -> reencode::Result<()> {
+        if self.in_target {
+            let starts_block = matches!(
+                op,
+                Operator::Block { .. }
+                    | Operator::Loop { .. }
+                    | Operator::If { .. }
+                    | Operator::Try { .. }
+            );
+            if self.truncated {
+                return Ok(());
+            }
+            if let Some(depth) = self.skip_depth {
+                self.skip_depth = match op {
+                    _ if starts_block => Some(depth + 1),
+                    Operator::End if depth == 0 => None,
+                    Operator::End => Some(depth - 1),
+                    _ => Some(depth),
+                };
+                return Ok(());
+            }
+            let index = self.next_instruction;
+            self.next_instruction += 1;
+            match self.shrink {
+                Shrink::RemoveInstruction { index: removed, .. } if index == removed => {
+                    if starts_block {
+                        self.skip_depth = Some(0);
+                    }
+                    return Ok(());
+                }
+                Shrink::TruncateFunction { len, .. } if index == len => {
+                    func.instruction(Instruction::Unreachable);
+                    // One more `end` for the function body itself.
+                    for _ in 0..=self.depth {
+                        func.instruction(Instruction::End);
+                    }
+                    self.truncated = true;
+                    return Ok(());
+                }
+                _ => {}
+            }
+            if starts_block {
+                self.depth += 1;
+            } else if let Operator::End = op {
+                self.depth = self.depth.saturating_sub(1);
+            }
+        }
+        utils::parse_instruction(self, func, op, raw)
+    }
+
+    fn parse_custom_section(
+        &mut self,
+        module: &mut Module,
+        name: &str,
+        data: &[u8],
+    ) -> reencode::Result<()> {
+        let shrink = self.shrink;
+        match shrink {
+            Shrink::RemoveCustomSection(i) if self.next_is(i) => Ok(()),
+            // The name section refers to functions by index, so it follows
+            // their removal. If it can't be parsed, its indices can't be
+            // remapped and it's dropped instead.
+            Shrink::RemoveFunction(_) | Shrink::StubFunction(_) if name == "name" => {
+                if let Ok(names) = self.names(data) {
+                    module.section(&names);
+                }
+                Ok(())
+            }
+            _ => {
+                module.section(&wasm_encoder::CustomSection { name, data });
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::Result;
+use wasm_shrink::{CommandPredicate, IsInteresting, WasmShrink};
+use wasmparser::{
+    Name, NameSectionReader, Naming, Operator, Parser, Payload, Validator, WasmFeatures,
+};
+
+fn has_operator(wasm: &[u8], f: impl Fn(&Operator) -> bool) -> Result<bool> {
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CodeSectionEntry(body) = payload? {
+            let mut ops = body.get_operators_reader()?;
+            while !ops.eof() {
+                if f(&ops.read()?) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+    Ok(false)
+}
+
+#[test]
+fn shrink_to_interesting_instruction() -> Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+        (module
+          (memory 1)
+          (data (i32.const 0) "hello")
+          (table 1 funcref)
+          (elem (i32.const 0) $helper)
+          (func $helper (param i32) (result i32)
+            local.get 0
+            i32.const 1
+            i32.add)
+          (func (export "run") (param i32 i32) (result i32)
+            (local i64)
+            block
+              local.get 0
+              call $helper
+              drop
+            end
+            local.get 0
+            local.get 1
+            i32.div_s
+            i32.const 7
+            i32.mul)
+          (func (export "unused") (result i64)
+            i64.const 42)
+          (@custom "producers" "wasm-shrink test"))
+        "#,
+    )?;
+    let is_div = |op: &Operator| matches!(op, Operator::I32DivS);
+
+    let info = WasmShrink::new().run(wasm.clone(), |wasm: &[u8]| has_operator(wasm, is_div))?;
+
+    assert_eq!(info.input_size, wasm.len());
+    assert_eq!(info.output_size, info.output.len());
+    assert!(info.output_size < info.input_size / 2);
+    assert!(has_operator(&info.output, is_div)?);
+    Validator::new().validate_all(&info.output)?;
+    assert!(!has_operator(&info.output, |op| matches!(
+        op,
+        Operator::Call { .. } | Operator::I32Mul | Operator::I64Const { .. }
+    ))?);
+    Ok(())
+}
+
+#[test]
+fn uninteresting_input_is_an_error() -> Result<()> {
+    let wasm = wat::parse_str("(module (func))")?;
+    assert!(WasmShrink::new().run(wasm, |_: &[u8]| Ok(false)).is_err());
+    Ok(())
+}
+
+#[test]
+fn shrink_past_aliased_functions() -> Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+        (module
+          (import "i" (instance $i (export "f" (func))))
+          (alias $f (func $i "f"))
+          (func $unused)
+          (func (export "run") call $f))
+        "#,
+    )?;
+    let features = WasmFeatures {
+        module_linking: true,
+        ..WasmFeatures::default()
+    };
+    let is_call = |op: &Operator| matches!(op, Operator::Call { function_index: 0 });
+
+    let info = WasmShrink::new()
+        .features(features)
+        .run(wasm, |wasm: &[u8]| has_operator(wasm, is_call))?;
+
+    let mut validator = Validator::new();
+    validator.wasm_features(features);
+    validator.validate_all(&info.output)?;
+    let num_defined_funcs = Parser::new(0)
+        .parse_all(&info.output)
+        .map(|payload| match payload? {
+            Payload::FunctionSection(funcs) => Ok(funcs.get_count()),
+            _ => Ok(0),
+        })
+        .sum::<Result<u32>>()?;
+    assert_eq!(num_defined_funcs, 1);
+    Ok(())
+}
+
+/// `(index, name)` pairs, as in a name map.
+type NameMap = Vec<(u32, String)>;
+
+/// The local names of each function, keyed by function index.
+type LocalNames = Vec<(u32, NameMap)>;
+
+/// The function names and the local names of each function in `wasm`.
+fn names(wasm: &[u8]) -> Result<(NameMap, LocalNames)> {
+    let naming = |naming: Naming| (naming.index, naming.name.to_string());
+    let mut funcs = Vec::new();
+    let mut locals = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        if let Payload::CustomSection {
+            name: "name",
+            data,
+            data_offset,
+        } = payload?
+        {
+            for name in NameSectionReader::new(data, data_offset)? {
+                match name? {
+                    Name::Function(names) => {
+                        let mut map = names.get_map()?;
+                        for _ in 0..map.get_count() {
+                            funcs.push(naming(map.read()?));
+                        }
+                    }
+                    Name::Local(names) => {
+                        let mut reader = names.get_function_local_reader()?;
+                        for _ in 0..reader.get_count() {
+                            let func = reader.read()?;
+                            let mut map = func.get_map()?;
+                            let mut names = Vec::new();
+                            for _ in 0..map.get_count() {
+                                names.push(naming(map.read()?));
+                            }
+                            locals.push((func.func_index, names));
+                        }
+                    }
+                    Name::Module(_) => {}
+                }
+            }
+        }
+    }
+    Ok((funcs, locals))
+}
+
+#[test]
+fn removing_functions_remaps_names() -> Result<()> {
+    let wasm = wat::parse_str(
+        r#"
+        (module
+          (func $a (param $p i32))
+          (func $b (export "b") (param $x i32) (result i32)
+            local.get $x))
+        "#,
+    )?;
+
+    // Keep the function and local names and the "b" export around.
+    let info = WasmShrink::new().run(wasm, |wasm: &[u8]| {
+        let (funcs, locals) = names(wasm)?;
+        Ok(!funcs.is_empty()
+            && !locals.is_empty()
+            && Parser::new(0).parse_all(wasm).any(|payload| match payload {
+                Ok(Payload::ExportSection(exports)) => exports
+                    .into_iter()
+                    .any(|export| export.is_ok_and(|e| e.field == "b")),
+                _ => false,
+            }))
+    })?;
+
+    let (funcs, locals) = names(&info.output)?;
+    assert_eq!(funcs, vec![(0, "b".to_string())]);
+    assert_eq!(locals, vec![(0, vec![(0, "x".to_string())])]);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn command_predicate() -> Result<()> {
+    let wasm = wat::parse_str("(module)")?;
+    // The candidate's path is passed last, so it is `$0` of the script.
+    let mut non_empty = CommandPredicate::new("sh", vec!["-c".into(), "test -s \"$0\"".into()]);
+    assert!(non_empty.is_interesting(&wasm)?);
+    assert!(!non_empty.is_interesting(&[])?);
+
+    let mut missing = CommandPredicate::new("wasm-shrink-test-missing-command", vec![]);
+    assert!(missing.is_interesting(&wasm).is_err());
+    Ok(())
+}
@@ -1,10 +1,16 @@
-use arbitrary::Arbitrary;
+use arbitrary::{Arbitrary, Unstructured};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs;
 use std::io::{stdin, stdout, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
 use structopt::StructOpt;
-use wasm_smith::Module;
+use wasm_encoder::{GlobalType, Limits, MemoryType, TableType, ValType};
+use wasm_smith::{
+    Config, ConfiguredModule, DefaultConfig, FuelConfig, FuelMetering, FuelType, ImportType,
+    RequiredExport,
+};
 
 /// A WebAssembly test case generator.
 ///
@@ -24,14 +30,24 @@ use wasm_smith::Module;
 ///
 /// $ head -c 100 /dev/urandom | wasm-smith -o test.wasm
 ///
+/// Generate a module for every seed in `corpus/`, with the options in
+/// `config.toml`:
+///
+/// $ wasm-smith --config config.toml --seed-dir corpus --output-dir modules
+///
+/// Generate a `.wast` script that invokes the exports of a module:
+///
+/// $ head -c 1000 /dev/urandom | wasm-smith --wast -o test.wast
+///
 /// ## Exit Codes
 ///
 /// * 0: Success.
 ///
 /// * 1: An unexpected failure occurred.
 ///
-/// * 2: Failed to generate a Webassembly module from the input seed. (Happens
-///      rarely; try again with a new input.)
+/// * 2: Failed to generate a Webassembly module from the input seed, or from
+///   every seed in `--seed-dir`. (Happens rarely; try again with a new
+///   input.)
 #[derive(StructOpt)]
 struct Options {
     /// The arbitrary input seed.
@@ -47,10 +63,50 @@ struct Options {
     #[structopt(short = "o", long = "output", parse(from_os_str))]
     output: Option<PathBuf>,
 
+    /// A JSON or TOML file of configuration options, chosen by its extension.
+    ///
+    /// Each key is the name of a `wasm_smith::Config` method, such as
+    /// `max_funcs` or `simd_enabled`, and its value is what that method
+    /// returns. Methods without a key keep their default.
+    #[structopt(short = "c", long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Generate a module from every file in this directory of input seeds,
+    /// instead of a single module from `input`.
+    #[structopt(
+        long = "seed-dir",
+        parse(from_os_str),
+        conflicts_with_all = &["input", "output"],
+        requires = "output-dir"
+    )]
+    seed_dir: Option<PathBuf>,
+
+    /// The directory where the modules generated with `--seed-dir` are placed.
+    ///
+    /// Each module is named after its seed file, with a `.wasm` suffix, or a
+    /// `.wast` suffix with `--wast`.
+    #[structopt(long = "output-dir", parse(from_os_str), requires = "seed-dir")]
+    output_dir: Option<PathBuf>,
+
+    /// Generate at most this many modules with `--seed-dir`, from the first
+    /// seeds in file name order.
+    #[structopt(short = "n", long = "count")]
+    count: Option<usize>,
+
+    /// Generate a `.wast` script instead of a binary module.
+    ///
+    /// The script holds the module as a `(module binary ...)` directive,
+    /// followed by `invoke` directives of its exported functions with
+    /// arguments taken from the rest of the input seed. Each invocation is
+    /// followed by a `;; expected: ...` placeholder comment with its result
+    /// types.
+    #[structopt(long = "wast")]
+    wast: bool,
+
     /// Ensure that execution of generated Wasm modules will always terminate.
     ///
     /// This inserts a global "fuel" counter that is decremented at loop headers
-    /// and in function prologues. When the fuel reaches 0, a trap is raised to
+    /// and in function prologues. When the fuel runs out, a trap is raised to
     /// terminate execution. Control the default amount of fuel with the
     /// `--fuel` flag.
     #[structopt(short = "t", long = "ensure-termination")]
@@ -59,14 +115,65 @@ struct Options {
     /// The default amount of fuel used with `--ensure-termination`.
     ///
     /// This is roughly the number of loop iterations and function calls that
-    /// will be executed before a trap is raised to prevent infinite loops.
+    /// will be executed before a trap is raised to prevent infinite loops. With
+    /// `--fuel-per-block`, it is measured in instruction weights instead.
     #[structopt(short = "f", long = "fuel", default_value = "100")]
-    fuel: u32,
+    fuel: u64,
+
+    /// Use an `i64` fuel counter instead of an `i32` with
+    /// `--ensure-termination`.
+    #[structopt(long = "fuel-i64")]
+    fuel_i64: bool,
+
+    /// Charge fuel on entry to every basic block, rather than only at loop
+    /// headers and in function prologues, with `--ensure-termination`.
+    ///
+    /// Each block is charged the summed weights of its instructions, and at
+    /// least one unit of fuel.
+    #[structopt(long = "fuel-per-block")]
+    fuel_per_block: bool,
+
+    /// A file of per-instruction weights for `--fuel-per-block`.
+    ///
+    /// Each line holds an instruction name and its weight, for example
+    /// `I64DivU 20`. Empty lines and lines starting with `#` are ignored.
+    #[structopt(long = "fuel-weights", parse(from_os_str))]
+    fuel_weights: Option<PathBuf>,
+
+    /// The weight of instructions that aren't listed in `--fuel-weights`.
+    #[structopt(long = "fuel-default-weight", default_value = "1")]
+    fuel_default_weight: u32,
 }
 
 fn main() {
     let opts = Options::from_args();
 
+    let config = match &opts.config {
+        Some(f) => read_config(f).unwrap_or_else(|e| {
+            eprintln!("error: failed to read '{}': {}", f.display(), e);
+            process::exit(1);
+        }),
+        None => FileConfig::default(),
+    };
+    let weights = match &opts.fuel_weights {
+        Some(f) => parse_fuel_weights(f).unwrap_or_else(|e| {
+            eprintln!("error: failed to read '{}': {}", f.display(), e);
+            process::exit(1);
+        }),
+        None => HashMap::new(),
+    };
+
+    if let (Some(seed_dir), Some(output_dir)) = (&opts.seed_dir, &opts.output_dir) {
+        if generate_corpus(&opts, &config, &weights, seed_dir, output_dir) == 0 {
+            eprintln!(
+                "error: failed to generate any module from '{}'",
+                seed_dir.display()
+            );
+            process::exit(2);
+        }
+        process::exit(0);
+    }
+
     let stdin = stdin();
     let (mut input, input_name): (Box<dyn Read>, _) = match &opts.input {
         Some(f) => {
@@ -103,17 +210,11 @@ fn main() {
         process::exit(1);
     });
 
-    let mut u = arbitrary::Unstructured::new(&seed);
-    let mut module = Module::arbitrary(&mut u).unwrap_or_else(|e| {
+    let wasm_bytes = generate(&opts, &config, &weights, &seed).unwrap_or_else(|e| {
         eprintln!("error: failed to generate module: {}", e);
         process::exit(2);
     });
 
-    if opts.ensure_termination {
-        module.ensure_termination(opts.fuel);
-    }
-
-    let wasm_bytes = module.to_bytes();
     output.write_all(&wasm_bytes).unwrap_or_else(|e| {
         eprintln!("error: failed to write to '{}': {}", output_name, e);
         process::exit(1);
@@ -122,3 +223,358 @@ fn main() {
     drop(output);
     process::exit(0);
 }
+
+fn generate(
+    opts: &Options,
+    config: &FileConfig,
+    weights: &HashMap<String, u32>,
+    seed: &[u8],
+) -> arbitrary::Result<Vec<u8>> {
+    let mut u = Unstructured::new(seed);
+    let mut module = ConfiguredModule::new(config.clone(), &mut u)?;
+
+    if opts.ensure_termination {
+        module.ensure_termination_with(&FuelConfig {
+            fuel: opts.fuel,
+            fuel_type: if opts.fuel_i64 {
+                FuelType::I64
+            } else {
+                FuelType::I32
+            },
+            metering: if opts.fuel_per_block {
+                FuelMetering::BasicBlock
+            } else {
+                FuelMetering::Loop
+            },
+            weights: weights.clone(),
+            default_weight: opts.fuel_default_weight,
+        });
+    }
+
+    if opts.wast {
+        Ok(module.to_wast(&mut u, None)?.into_bytes())
+    } else {
+        Ok(module.to_bytes())
+    }
+}
+
+/// Generates a module for each file in `seed_dir`, in file name order.
+///
+/// Seeds that fail to generate a module are reported and skipped. Returns how
+/// many modules were written.
+fn generate_corpus(
+    opts: &Options,
+    config: &FileConfig,
+    weights: &HashMap<String, u32>,
+    seed_dir: &Path,
+    output_dir: &Path,
+) -> usize {
+    let mut seeds = fs::read_dir(seed_dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("error: failed to read '{}': {}", seed_dir.display(), e);
+            process::exit(1);
+        });
+    seeds.retain(|path| path.is_file());
+    seeds.sort();
+
+    fs::create_dir_all(output_dir).unwrap_or_else(|e| {
+        eprintln!("error: failed to create '{}': {}", output_dir.display(), e);
+        process::exit(1);
+    });
+
+    let mut written = 0;
+    for path in seeds.iter().take(opts.count.unwrap_or(usize::MAX)) {
+        let seed = fs::read(path).unwrap_or_else(|e| {
+            eprintln!("error: failed to read '{}': {}", path.display(), e);
+            process::exit(1);
+        });
+        let wasm_bytes = match generate(opts, config, weights, &seed) {
+            Ok(wasm_bytes) => wasm_bytes,
+            Err(e) => {
+                eprintln!(
+                    "warning: failed to generate module from '{}': {}",
+                    path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        let mut name = path.file_name().unwrap().to_os_string();
+        name.push(if opts.wast { ".wast" } else { ".wasm" });
+        let output = output_dir.join(name);
+        fs::write(&output, wasm_bytes).unwrap_or_else(|e| {
+            eprintln!("error: failed to write to '{}': {}", output.display(), e);
+            process::exit(1);
+        });
+        written += 1;
+    }
+    written
+}
+
+fn read_config(path: &Path) -> Result<FileConfig, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+        Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string()),
+        _ => Err("expected a `.json` or `.toml` extension".to_string()),
+    }
+}
+
+fn parse_fuel_weights(path: &Path) -> Result<HashMap<String, u32>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut weights = HashMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let (name, weight) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), Some(weight), None) => (name, weight),
+            _ => return Err(format!("line {}: expected `<instruction> <weight>`", i + 1)),
+        };
+        if !FuelConfig::is_instruction_name(name) {
+            return Err(format!("line {}: unknown instruction `{}`", i + 1, name));
+        }
+        let weight = weight
+            .parse()
+            .map_err(|e| format!("line {}: invalid weight: {}", i + 1, e))?;
+        weights.insert(name.to_string(), weight);
+    }
+    Ok(weights)
+}
+
+macro_rules! file_config {
+    ($($name:ident: $ty:ty,)*) => {
+        /// The `Config` options from a `--config` file.
+        ///
+        /// Every option is the name of a `Config` method, and options that
+        /// aren't given fall back to `DefaultConfig`.
+        #[derive(Clone, Debug, Default, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct FileConfig {
+            $($name: Option<$ty>,)*
+            available_imports: Option<Vec<FileImport>>,
+            required_exports: Option<Vec<FileExport>>,
+        }
+
+        impl Config for FileConfig {
+            $(
+                fn $name(&self) -> $ty {
+                    self.$name.unwrap_or_else(|| DefaultConfig.$name())
+                }
+            )*
+
+            fn available_imports(&self) -> Option<Vec<(String, String, ImportType)>> {
+                let imports = self.available_imports.as_ref()?;
+                Some(
+                    imports
+                        .iter()
+                        .map(|i| (i.module.clone(), i.name.clone(), i.ty.to_import_type()))
+                        .collect(),
+                )
+            }
+
+            fn required_exports(&self) -> Vec<RequiredExport> {
+                let exports = match &self.required_exports {
+                    Some(exports) => exports,
+                    None => return DefaultConfig.required_exports(),
+                };
+                exports
+                    .iter()
+                    .map(|e| RequiredExport {
+                        name: e.name.clone(),
+                        params: val_types(&e.params),
+                        results: val_types(&e.results),
+                    })
+                    .collect()
+            }
+        }
+    };
+}
+
+file_config! {
+    min_types: usize,
+    max_types: usize,
+    min_imports: usize,
+    max_imports: usize,
+    min_funcs: usize,
+    max_funcs: usize,
+    min_globals: usize,
+    max_globals: usize,
+    min_exports: usize,
+    max_exports: usize,
+    min_element_segments: usize,
+    max_element_segments: usize,
+    min_elements: usize,
+    max_elements: usize,
+    min_data_segments: usize,
+    max_data_segments: usize,
+    max_instructions: usize,
+    min_memories: u32,
+    max_memories: usize,
+    min_tables: u32,
+    max_tables: usize,
+    max_memory_pages: u32,
+    max_events: usize,
+    memory_max_size_required: bool,
+    max_instances: usize,
+    max_modules: usize,
+    memory_offset_choices: (u32, u32, u32),
+    min_uleb_size: u8,
+    bulk_memory_enabled: bool,
+    reference_types_enabled: bool,
+    module_linking_enabled: bool,
+    simd_enabled: bool,
+    threads_enabled: bool,
+    memory64_enabled: bool,
+    multi_memory_enabled: bool,
+    exceptions_enabled: bool,
+    tail_call_enabled: bool,
+    int_instructions_enabled: bool,
+    float_instructions_enabled: bool,
+    conversion_instructions_enabled: bool,
+    memory_instructions_enabled: bool,
+    control_instructions_enabled: bool,
+    call_instructions_enabled: bool,
+    table_instructions_enabled: bool,
+    reference_instructions_enabled: bool,
+    bulk_memory_instructions_enabled: bool,
+    deterministic_only: bool,
+    disallow_traps: bool,
+    allow_start_export: bool,
+    name_section_enabled: bool,
+    max_custom_sections: usize,
+    max_aliases: usize,
+    max_nesting_depth: usize,
+}
+
+// A `FileConfig` always comes from a file, and is never generated.
+impl Arbitrary for FileConfig {
+    fn arbitrary(_: &mut Unstructured) -> arbitrary::Result<Self> {
+        Ok(FileConfig::default())
+    }
+}
+
+/// An entry of `available_imports` in a `--config` file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileImport {
+    module: String,
+    name: String,
+    #[serde(rename = "type")]
+    ty: FileImportType,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum FileImportType {
+    Func {
+        #[serde(default)]
+        params: Vec<FileValType>,
+        #[serde(default)]
+        results: Vec<FileValType>,
+    },
+    Global {
+        val_type: FileValType,
+        #[serde(default)]
+        mutable: bool,
+    },
+    Memory {
+        min: u32,
+        max: Option<u32>,
+        #[serde(default)]
+        shared: bool,
+        #[serde(default)]
+        memory64: bool,
+    },
+    Table {
+        element_type: FileValType,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+impl FileImportType {
+    fn to_import_type(&self) -> ImportType {
+        match self {
+            FileImportType::Func { params, results } => {
+                ImportType::Func(val_types(params), val_types(results))
+            }
+            FileImportType::Global { val_type, mutable } => ImportType::Global(GlobalType {
+                val_type: val_type.to_val_type(),
+                mutable: *mutable,
+            }),
+            FileImportType::Memory {
+                min,
+                max,
+                shared,
+                memory64,
+            } => ImportType::Memory(MemoryType {
+                limits: Limits {
+                    min: *min,
+                    max: *max,
+                },
+                shared: *shared,
+                memory64: *memory64,
+            }),
+            FileImportType::Table {
+                element_type,
+                min,
+                max,
+            } => ImportType::Table(TableType {
+                element_type: element_type.to_val_type(),
+                limits: Limits {
+                    min: *min,
+                    max: *max,
+                },
+            }),
+        }
+    }
+}
+
+/// An entry of `required_exports` in a `--config` file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileExport {
+    name: String,
+    #[serde(default)]
+    params: Vec<FileValType>,
+    #[serde(default)]
+    results: Vec<FileValType>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FileValType {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+impl FileValType {
+    fn to_val_type(self) -> ValType {
+        match self {
+            FileValType::I32 => ValType::I32,
+            FileValType::I64 => ValType::I64,
+            FileValType::F32 => ValType::F32,
+            FileValType::F64 => ValType::F64,
+            FileValType::V128 => ValType::V128,
+            FileValType::FuncRef => ValType::FuncRef,
+            FileValType::ExternRef => ValType::ExternRef,
+        }
+    }
+}
+
+fn val_types(tys: &[FileValType]) -> Vec<ValType> {
+    tys.iter().map(|ty| ty.to_val_type()).collect()
+}
@@ -1,13 +1,15 @@
 use super::{
-    BlockType, Config, ConfiguredModule, Elements, FuncType, Instruction, MemArg, ValType,
+    BlockType, Config, ConfiguredModule, ElementKind, Elements, FuncType, Instruction, MemArg,
+    ValType,
 };
 use arbitrary::{Result, Unstructured};
 use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
 
 macro_rules! instructions {
 	(
         $(
-            ($predicate:expr, $generator_fn:ident),
+            ($predicate:expr, $generator_fn:ident $(, $category:ident)?),
         )*
     ) => {
         static NUM_OPTIONS: usize = instructions!(
@@ -31,8 +33,9 @@ macro_rules! instructions {
             // predicted and even inlined. This saved us about 30% of time in
             // the `corpus` benchmark.
             $(
+                let enabled = true $( && builder.allocs.categories.$category )?;
                 let predicate: Option<fn(&ConfiguredModule<C>, &mut CodeBuilder<C>) -> bool> = $predicate;
-                if predicate.map_or(true, |f| f(module, builder)) {
+                if enabled && predicate.map_or(true, |f| f(module, builder)) {
                     builder.allocs.options.push($generator_fn);
                 }
             )*
@@ -50,28 +53,31 @@ macro_rules! instructions {
 // The static set of options of instruction to generate that could be valid at
 // some given time. One entry per Wasm instruction.
 //
-// Each entry is made up of two parts:
+// Each entry is made up of up to three parts:
 //
 // 1. A predicate for whether this is a valid choice, if any. `None` means that
 //    the choice is always applicable.
 //
 // 2. The function to generate the instruction, given that we've made this
 //    choice.
+//
+// 3. The category of the instruction, if any, which must be enabled in the
+//    `Config` for this choice to be considered. See `InstructionCategories`.
 instructions! {
     // Control instructions.
-    (None, unreachable),
+    (Some(unreachable_valid), unreachable, control),
     (None, nop),
-    (None, block),
-    (None, r#loop),
-    (Some(if_valid), r#if),
-    (Some(else_valid), r#else),
+    (None, block, control),
+    (None, r#loop, control),
+    (Some(if_valid), r#if, control),
+    (Some(else_valid), r#else, control),
     (Some(end_valid), end),
-    (Some(br_valid), br),
-    (Some(br_if_valid), br_if),
-    (Some(br_table_valid), br_table),
-    (Some(return_valid), r#return),
-    (Some(call_valid), call),
-    (Some(call_indirect_valid), call_indirect),
+    (Some(br_valid), br, control),
+    (Some(br_if_valid), br_if, control),
+    (Some(br_table_valid), br_table, control),
+    (Some(return_valid), r#return, control),
+    (Some(call_valid), call, call),
+    (Some(call_indirect_valid), call_indirect, call),
     // Parametric instructions.
     (Some(drop_valid), drop),
     (Some(select_valid), select),
@@ -82,188 +88,484 @@ instructions! {
     (Some(global_get_valid), global_get),
     (Some(global_set_valid), global_set),
     // Memory instructions.
-    (Some(have_memory_and_offset), i32_load),
-    (Some(have_memory_and_offset), i64_load),
-    (Some(have_memory_and_offset), f32_load),
-    (Some(have_memory_and_offset), f64_load),
-    (Some(have_memory_and_offset), i32_load_8_s),
-    (Some(have_memory_and_offset), i32_load_8_u),
-    (Some(have_memory_and_offset), i32_load_16_s),
-    (Some(have_memory_and_offset), i32_load_16_u),
-    (Some(have_memory_and_offset), i64_load_8_s),
-    (Some(have_memory_and_offset), i64_load_16_s),
-    (Some(have_memory_and_offset), i64_load_32_s),
-    (Some(have_memory_and_offset), i64_load_8_u),
-    (Some(have_memory_and_offset), i64_load_16_u),
-    (Some(have_memory_and_offset), i64_load_32_u),
-    (Some(i32_store_valid), i32_store),
-    (Some(i64_store_valid), i64_store),
-    (Some(f32_store_valid), f32_store),
-    (Some(f64_store_valid), f64_store),
-    (Some(i32_store_valid), i32_store_8),
-    (Some(i32_store_valid), i32_store_16),
-    (Some(i64_store_valid), i64_store_8),
-    (Some(i64_store_valid), i64_store_16),
-    (Some(i64_store_valid), i64_store_32),
-    (Some(have_memory), memory_size),
-    (Some(memory_grow_valid), memory_grow),
-    (Some(memory_init_valid), memory_init),
-    (Some(data_drop_valid), data_drop),
-    (Some(memory_copy_valid), memory_copy),
-    (Some(memory_fill_valid), memory_fill),
+    (Some(have_memory_and_offset), i32_load, memory),
+    (Some(have_memory_and_offset), i64_load, memory),
+    (Some(float_load_valid), f32_load, memory),
+    (Some(float_load_valid), f64_load, memory),
+    (Some(have_memory_and_offset), i32_load_8_s, memory),
+    (Some(have_memory_and_offset), i32_load_8_u, memory),
+    (Some(have_memory_and_offset), i32_load_16_s, memory),
+    (Some(have_memory_and_offset), i32_load_16_u, memory),
+    (Some(have_memory_and_offset), i64_load_8_s, memory),
+    (Some(have_memory_and_offset), i64_load_16_s, memory),
+    (Some(have_memory_and_offset), i64_load_32_s, memory),
+    (Some(have_memory_and_offset), i64_load_8_u, memory),
+    (Some(have_memory_and_offset), i64_load_16_u, memory),
+    (Some(have_memory_and_offset), i64_load_32_u, memory),
+    (Some(i32_store_valid), i32_store, memory),
+    (Some(i64_store_valid), i64_store, memory),
+    (Some(f32_store_valid), f32_store, memory),
+    (Some(f64_store_valid), f64_store, memory),
+    (Some(i32_store_valid), i32_store_8, memory),
+    (Some(i32_store_valid), i32_store_16, memory),
+    (Some(i64_store_valid), i64_store_8, memory),
+    (Some(i64_store_valid), i64_store_16, memory),
+    (Some(i64_store_valid), i64_store_32, memory),
+    (Some(have_memory), memory_size, memory),
+    (Some(memory_grow_valid), memory_grow, memory),
+    (Some(memory_init_valid), memory_init, bulk_memory),
+    (Some(data_drop_valid), data_drop, bulk_memory),
+    (Some(memory_copy_valid), memory_copy, bulk_memory),
+    (Some(memory_fill_valid), memory_fill, bulk_memory),
     // Numeric instructions.
-    (None, i32_const),
-    (None, i64_const),
-    (None, f32_const),
-    (None, f64_const),
-    (Some(i32_on_stack), i32_eqz),
-    (Some(i32_i32_on_stack), i32_eq),
-    (Some(i32_i32_on_stack), i32_neq),
-    (Some(i32_i32_on_stack), i32_lt_s),
-    (Some(i32_i32_on_stack), i32_lt_u),
-    (Some(i32_i32_on_stack), i32_gt_s),
-    (Some(i32_i32_on_stack), i32_gt_u),
-    (Some(i32_i32_on_stack), i32_le_s),
-    (Some(i32_i32_on_stack), i32_le_u),
-    (Some(i32_i32_on_stack), i32_ge_s),
-    (Some(i32_i32_on_stack), i32_ge_u),
-    (Some(i64_on_stack), i64_eqz),
-    (Some(i64_i64_on_stack), i64_eq),
-    (Some(i64_i64_on_stack), i64_neq),
-    (Some(i64_i64_on_stack), i64_lt_s),
-    (Some(i64_i64_on_stack), i64_lt_u),
-    (Some(i64_i64_on_stack), i64_gt_s),
-    (Some(i64_i64_on_stack), i64_gt_u),
-    (Some(i64_i64_on_stack), i64_le_s),
-    (Some(i64_i64_on_stack), i64_le_u),
-    (Some(i64_i64_on_stack), i64_ge_s),
-    (Some(i64_i64_on_stack), i64_ge_u),
-    (Some(f32_f32_on_stack), f32_eq),
-    (Some(f32_f32_on_stack), f32_neq),
-    (Some(f32_f32_on_stack), f32_lt),
-    (Some(f32_f32_on_stack), f32_gt),
-    (Some(f32_f32_on_stack), f32_le),
-    (Some(f32_f32_on_stack), f32_ge),
-    (Some(f64_f64_on_stack), f64_eq),
-    (Some(f64_f64_on_stack), f64_neq),
-    (Some(f64_f64_on_stack), f64_lt),
-    (Some(f64_f64_on_stack), f64_gt),
-    (Some(f64_f64_on_stack), f64_le),
-    (Some(f64_f64_on_stack), f64_ge),
-    (Some(i32_on_stack), i32_clz),
-    (Some(i32_on_stack), i32_ctz),
-    (Some(i32_on_stack), i32_popcnt),
-    (Some(i32_i32_on_stack), i32_add),
-    (Some(i32_i32_on_stack), i32_sub),
-    (Some(i32_i32_on_stack), i32_mul),
-    (Some(i32_i32_on_stack), i32_div_s),
-    (Some(i32_i32_on_stack), i32_div_u),
-    (Some(i32_i32_on_stack), i32_rem_s),
-    (Some(i32_i32_on_stack), i32_rem_u),
-    (Some(i32_i32_on_stack), i32_and),
-    (Some(i32_i32_on_stack), i32_or),
-    (Some(i32_i32_on_stack), i32_xor),
-    (Some(i32_i32_on_stack), i32_shl),
-    (Some(i32_i32_on_stack), i32_shr_s),
-    (Some(i32_i32_on_stack), i32_shr_u),
-    (Some(i32_i32_on_stack), i32_rotl),
-    (Some(i32_i32_on_stack), i32_rotr),
-    (Some(i64_on_stack), i64_clz),
-    (Some(i64_on_stack), i64_ctz),
-    (Some(i64_on_stack), i64_popcnt),
-    (Some(i64_i64_on_stack), i64_add),
-    (Some(i64_i64_on_stack), i64_sub),
-    (Some(i64_i64_on_stack), i64_mul),
-    (Some(i64_i64_on_stack), i64_div_s),
-    (Some(i64_i64_on_stack), i64_div_u),
-    (Some(i64_i64_on_stack), i64_rem_s),
-    (Some(i64_i64_on_stack), i64_rem_u),
-    (Some(i64_i64_on_stack), i64_and),
-    (Some(i64_i64_on_stack), i64_or),
-    (Some(i64_i64_on_stack), i64_xor),
-    (Some(i64_i64_on_stack), i64_shl),
-    (Some(i64_i64_on_stack), i64_shr_s),
-    (Some(i64_i64_on_stack), i64_shr_u),
-    (Some(i64_i64_on_stack), i64_rotl),
-    (Some(i64_i64_on_stack), i64_rotr),
-    (Some(f32_on_stack), f32_abs),
-    (Some(f32_on_stack), f32_neg),
-    (Some(f32_on_stack), f32_ceil),
-    (Some(f32_on_stack), f32_floor),
-    (Some(f32_on_stack), f32_trunc),
-    (Some(f32_on_stack), f32_nearest),
-    (Some(f32_on_stack), f32_sqrt),
-    (Some(f32_f32_on_stack), f32_add),
-    (Some(f32_f32_on_stack), f32_sub),
-    (Some(f32_f32_on_stack), f32_mul),
-    (Some(f32_f32_on_stack), f32_div),
-    (Some(f32_f32_on_stack), f32_min),
-    (Some(f32_f32_on_stack), f32_max),
-    (Some(f32_f32_on_stack), f32_copysign),
-    (Some(f64_on_stack), f64_abs),
-    (Some(f64_on_stack), f64_neg),
-    (Some(f64_on_stack), f64_ceil),
-    (Some(f64_on_stack), f64_floor),
-    (Some(f64_on_stack), f64_trunc),
-    (Some(f64_on_stack), f64_nearest),
-    (Some(f64_on_stack), f64_sqrt),
-    (Some(f64_f64_on_stack), f64_add),
-    (Some(f64_f64_on_stack), f64_sub),
-    (Some(f64_f64_on_stack), f64_mul),
-    (Some(f64_f64_on_stack), f64_div),
-    (Some(f64_f64_on_stack), f64_min),
-    (Some(f64_f64_on_stack), f64_max),
-    (Some(f64_f64_on_stack), f64_copysign),
-    (Some(i64_on_stack), i32_wrap_i64),
-    (Some(f32_on_stack), i32_trunc_f32_s),
-    (Some(f32_on_stack), i32_trunc_f32_u),
-    (Some(f64_on_stack), i32_trunc_f64_s),
-    (Some(f64_on_stack), i32_trunc_f64_u),
-    (Some(i32_on_stack), i64_extend_i32_s),
-    (Some(i32_on_stack), i64_extend_i32_u),
-    (Some(f32_on_stack), i64_trunc_f32_s),
-    (Some(f32_on_stack), i64_trunc_f32_u),
-    (Some(f64_on_stack), i64_trunc_f64_s),
-    (Some(f64_on_stack), i64_trunc_f64_u),
-    (Some(i32_on_stack), f32_convert_i32_s),
-    (Some(i32_on_stack), f32_convert_i32_u),
-    (Some(i64_on_stack), f32_convert_i64_s),
-    (Some(i64_on_stack), f32_convert_i64_u),
-    (Some(f64_on_stack), f32_demote_f64),
-    (Some(i32_on_stack), f64_convert_i32_s),
-    (Some(i32_on_stack), f64_convert_i32_u),
-    (Some(i64_on_stack), f64_convert_i64_s),
-    (Some(i64_on_stack), f64_convert_i64_u),
-    (Some(f32_on_stack), f64_promote_f32),
-    (Some(f32_on_stack), i32_reinterpret_f32),
-    (Some(f64_on_stack), i64_reinterpret_f64),
-    (Some(i32_on_stack), f32_reinterpret_i32),
-    (Some(i64_on_stack), f64_reinterpret_i64),
-    (Some(i32_on_stack), i32_extend_8_s),
-    (Some(i32_on_stack), i32_extend_16_s),
-    (Some(i64_on_stack), i64_extend_8_s),
-    (Some(i64_on_stack), i64_extend_16_s),
-    (Some(i64_on_stack), i64_extend_32_s),
-    (Some(f32_on_stack), i32_trunc_sat_f32_s),
-    (Some(f32_on_stack), i32_trunc_sat_f32_u),
-    (Some(f64_on_stack), i32_trunc_sat_f64_s),
-    (Some(f64_on_stack), i32_trunc_sat_f64_u),
-    (Some(f32_on_stack), i64_trunc_sat_f32_s),
-    (Some(f32_on_stack), i64_trunc_sat_f32_u),
-    (Some(f64_on_stack), i64_trunc_sat_f64_s),
-    (Some(f64_on_stack), i64_trunc_sat_f64_u),
+    (None, i32_const, int),
+    (None, i64_const, int),
+    (Some(floats_enabled), f32_const, float),
+    (Some(floats_enabled), f64_const, float),
+    (Some(i32_on_stack), i32_eqz, int),
+    (Some(i32_i32_on_stack), i32_eq, int),
+    (Some(i32_i32_on_stack), i32_neq, int),
+    (Some(i32_i32_on_stack), i32_lt_s, int),
+    (Some(i32_i32_on_stack), i32_lt_u, int),
+    (Some(i32_i32_on_stack), i32_gt_s, int),
+    (Some(i32_i32_on_stack), i32_gt_u, int),
+    (Some(i32_i32_on_stack), i32_le_s, int),
+    (Some(i32_i32_on_stack), i32_le_u, int),
+    (Some(i32_i32_on_stack), i32_ge_s, int),
+    (Some(i32_i32_on_stack), i32_ge_u, int),
+    (Some(i64_on_stack), i64_eqz, int),
+    (Some(i64_i64_on_stack), i64_eq, int),
+    (Some(i64_i64_on_stack), i64_neq, int),
+    (Some(i64_i64_on_stack), i64_lt_s, int),
+    (Some(i64_i64_on_stack), i64_lt_u, int),
+    (Some(i64_i64_on_stack), i64_gt_s, int),
+    (Some(i64_i64_on_stack), i64_gt_u, int),
+    (Some(i64_i64_on_stack), i64_le_s, int),
+    (Some(i64_i64_on_stack), i64_le_u, int),
+    (Some(i64_i64_on_stack), i64_ge_s, int),
+    (Some(i64_i64_on_stack), i64_ge_u, int),
+    (Some(f32_f32_on_stack), f32_eq, float),
+    (Some(f32_f32_on_stack), f32_neq, float),
+    (Some(f32_f32_on_stack), f32_lt, float),
+    (Some(f32_f32_on_stack), f32_gt, float),
+    (Some(f32_f32_on_stack), f32_le, float),
+    (Some(f32_f32_on_stack), f32_ge, float),
+    (Some(f64_f64_on_stack), f64_eq, float),
+    (Some(f64_f64_on_stack), f64_neq, float),
+    (Some(f64_f64_on_stack), f64_lt, float),
+    (Some(f64_f64_on_stack), f64_gt, float),
+    (Some(f64_f64_on_stack), f64_le, float),
+    (Some(f64_f64_on_stack), f64_ge, float),
+    (Some(i32_on_stack), i32_clz, int),
+    (Some(i32_on_stack), i32_ctz, int),
+    (Some(i32_on_stack), i32_popcnt, int),
+    (Some(i32_i32_on_stack), i32_add, int),
+    (Some(i32_i32_on_stack), i32_sub, int),
+    (Some(i32_i32_on_stack), i32_mul, int),
+    (Some(i32_i32_on_stack), i32_div_s, int),
+    (Some(i32_i32_on_stack), i32_div_u, int),
+    (Some(i32_i32_on_stack), i32_rem_s, int),
+    (Some(i32_i32_on_stack), i32_rem_u, int),
+    (Some(i32_i32_on_stack), i32_and, int),
+    (Some(i32_i32_on_stack), i32_or, int),
+    (Some(i32_i32_on_stack), i32_xor, int),
+    (Some(i32_i32_on_stack), i32_shl, int),
+    (Some(i32_i32_on_stack), i32_shr_s, int),
+    (Some(i32_i32_on_stack), i32_shr_u, int),
+    (Some(i32_i32_on_stack), i32_rotl, int),
+    (Some(i32_i32_on_stack), i32_rotr, int),
+    (Some(i64_on_stack), i64_clz, int),
+    (Some(i64_on_stack), i64_ctz, int),
+    (Some(i64_on_stack), i64_popcnt, int),
+    (Some(i64_i64_on_stack), i64_add, int),
+    (Some(i64_i64_on_stack), i64_sub, int),
+    (Some(i64_i64_on_stack), i64_mul, int),
+    (Some(i64_i64_on_stack), i64_div_s, int),
+    (Some(i64_i64_on_stack), i64_div_u, int),
+    (Some(i64_i64_on_stack), i64_rem_s, int),
+    (Some(i64_i64_on_stack), i64_rem_u, int),
+    (Some(i64_i64_on_stack), i64_and, int),
+    (Some(i64_i64_on_stack), i64_or, int),
+    (Some(i64_i64_on_stack), i64_xor, int),
+    (Some(i64_i64_on_stack), i64_shl, int),
+    (Some(i64_i64_on_stack), i64_shr_s, int),
+    (Some(i64_i64_on_stack), i64_shr_u, int),
+    (Some(i64_i64_on_stack), i64_rotl, int),
+    (Some(i64_i64_on_stack), i64_rotr, int),
+    (Some(f32_on_stack), f32_abs, float),
+    (Some(f32_on_stack), f32_neg, float),
+    (Some(f32_on_stack), f32_ceil, float),
+    (Some(f32_on_stack), f32_floor, float),
+    (Some(f32_on_stack), f32_trunc, float),
+    (Some(f32_on_stack), f32_nearest, float),
+    (Some(f32_on_stack), f32_sqrt, float),
+    (Some(f32_f32_on_stack), f32_add, float),
+    (Some(f32_f32_on_stack), f32_sub, float),
+    (Some(f32_f32_on_stack), f32_mul, float),
+    (Some(f32_f32_on_stack), f32_div, float),
+    (Some(f32_f32_on_stack), f32_min, float),
+    (Some(f32_f32_on_stack), f32_max, float),
+    (Some(f32_f32_on_stack), f32_copysign, float),
+    (Some(f64_on_stack), f64_abs, float),
+    (Some(f64_on_stack), f64_neg, float),
+    (Some(f64_on_stack), f64_ceil, float),
+    (Some(f64_on_stack), f64_floor, float),
+    (Some(f64_on_stack), f64_trunc, float),
+    (Some(f64_on_stack), f64_nearest, float),
+    (Some(f64_on_stack), f64_sqrt, float),
+    (Some(f64_f64_on_stack), f64_add, float),
+    (Some(f64_f64_on_stack), f64_sub, float),
+    (Some(f64_f64_on_stack), f64_mul, float),
+    (Some(f64_f64_on_stack), f64_div, float),
+    (Some(f64_f64_on_stack), f64_min, float),
+    (Some(f64_f64_on_stack), f64_max, float),
+    (Some(f64_f64_on_stack), f64_copysign, float),
+    (Some(i64_on_stack), i32_wrap_i64, conversion),
+    (Some(trunc_f32_valid), i32_trunc_f32_s, conversion),
+    (Some(trunc_f32_valid), i32_trunc_f32_u, conversion),
+    (Some(trunc_f64_valid), i32_trunc_f64_s, conversion),
+    (Some(trunc_f64_valid), i32_trunc_f64_u, conversion),
+    (Some(i32_on_stack), i64_extend_i32_s, conversion),
+    (Some(i32_on_stack), i64_extend_i32_u, conversion),
+    (Some(trunc_f32_valid), i64_trunc_f32_s, conversion),
+    (Some(trunc_f32_valid), i64_trunc_f32_u, conversion),
+    (Some(trunc_f64_valid), i64_trunc_f64_s, conversion),
+    (Some(trunc_f64_valid), i64_trunc_f64_u, conversion),
+    (Some(float_i32_on_stack), f32_convert_i32_s, conversion),
+    (Some(float_i32_on_stack), f32_convert_i32_u, conversion),
+    (Some(float_i64_on_stack), f32_convert_i64_s, conversion),
+    (Some(float_i64_on_stack), f32_convert_i64_u, conversion),
+    (Some(f64_on_stack), f32_demote_f64, conversion),
+    (Some(float_i32_on_stack), f64_convert_i32_s, conversion),
+    (Some(float_i32_on_stack), f64_convert_i32_u, conversion),
+    (Some(float_i64_on_stack), f64_convert_i64_s, conversion),
+    (Some(float_i64_on_stack), f64_convert_i64_u, conversion),
+    (Some(f32_on_stack), f64_promote_f32, conversion),
+    (Some(f32_on_stack), i32_reinterpret_f32, conversion),
+    (Some(f64_on_stack), i64_reinterpret_f64, conversion),
+    (Some(float_i32_on_stack), f32_reinterpret_i32, conversion),
+    (Some(float_i64_on_stack), f64_reinterpret_i64, conversion),
+    (Some(i32_on_stack), i32_extend_8_s, conversion),
+    (Some(i32_on_stack), i32_extend_16_s, conversion),
+    (Some(i64_on_stack), i64_extend_8_s, conversion),
+    (Some(i64_on_stack), i64_extend_16_s, conversion),
+    (Some(i64_on_stack), i64_extend_32_s, conversion),
+    (Some(f32_on_stack), i32_trunc_sat_f32_s, conversion),
+    (Some(f32_on_stack), i32_trunc_sat_f32_u, conversion),
+    (Some(f64_on_stack), i32_trunc_sat_f64_s, conversion),
+    (Some(f64_on_stack), i32_trunc_sat_f64_u, conversion),
+    (Some(f32_on_stack), i64_trunc_sat_f32_s, conversion),
+    (Some(f32_on_stack), i64_trunc_sat_f32_u, conversion),
+    (Some(f64_on_stack), i64_trunc_sat_f64_s, conversion),
+    (Some(f64_on_stack), i64_trunc_sat_f64_u, conversion),
     // reference types proposal
-    (Some(ref_null_valid), ref_null),
-    (Some(ref_func_valid), ref_func),
-    (Some(ref_is_null_valid), ref_is_null),
-    (Some(table_fill_valid), table_fill),
-    (Some(table_set_valid), table_set),
-    (Some(table_get_valid), table_get),
-    (Some(table_size_valid), table_size),
-    (Some(table_grow_valid), table_grow),
-    (Some(table_copy_valid), table_copy),
-    (Some(table_init_valid), table_init),
-    (Some(elem_drop_valid), elem_drop),
+    (Some(ref_null_valid), ref_null, reference),
+    (Some(ref_func_valid), ref_func, reference),
+    (Some(ref_is_null_valid), ref_is_null, reference),
+    (Some(table_fill_valid), table_fill, table),
+    (Some(table_set_valid), table_set, table),
+    (Some(table_get_valid), table_get, table),
+    (Some(table_size_valid), table_size, table),
+    (Some(table_grow_valid), table_grow, table),
+    (Some(table_copy_valid), table_copy, table),
+    (Some(table_init_valid), table_init, table),
+    (Some(elem_drop_valid), elem_drop, table),
+    // SIMD proposal
+    (Some(simd_have_memory_and_offset), v128_load, memory),
+    (Some(simd_have_memory_and_offset), v128_load8x8_s, memory),
+    (Some(simd_have_memory_and_offset), v128_load8x8_u, memory),
+    (Some(simd_have_memory_and_offset), v128_load16x4_s, memory),
+    (Some(simd_have_memory_and_offset), v128_load16x4_u, memory),
+    (Some(simd_have_memory_and_offset), v128_load32x2_s, memory),
+    (Some(simd_have_memory_and_offset), v128_load32x2_u, memory),
+    (Some(simd_have_memory_and_offset), v128_load8_splat, memory),
+    (Some(simd_have_memory_and_offset), v128_load16_splat, memory),
+    (Some(simd_have_memory_and_offset), v128_load32_splat, memory),
+    (Some(simd_have_memory_and_offset), v128_load64_splat, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_store, memory),
+    (Some(simd_enabled), v128_const),
+    (Some(simd_v128_v128_on_stack), i8x16_shuffle),
+    (Some(simd_v128_v128_on_stack), i8x16_swizzle),
+    (Some(simd_i32_on_stack), i8x16_splat),
+    (Some(simd_i32_on_stack), i16x8_splat),
+    (Some(simd_i32_on_stack), i32x4_splat),
+    (Some(simd_i64_on_stack), i64x2_splat),
+    (Some(simd_f32_on_stack), f32x4_splat),
+    (Some(simd_f64_on_stack), f64x2_splat),
+    (Some(simd_v128_on_stack), i8x16_extract_lane_s),
+    (Some(simd_v128_on_stack), i8x16_extract_lane_u),
+    (Some(simd_v128_i32_on_stack), i8x16_replace_lane),
+    (Some(simd_v128_on_stack), i16x8_extract_lane_s),
+    (Some(simd_v128_on_stack), i16x8_extract_lane_u),
+    (Some(simd_v128_i32_on_stack), i16x8_replace_lane),
+    (Some(simd_v128_on_stack), i32x4_extract_lane),
+    (Some(simd_v128_i32_on_stack), i32x4_replace_lane),
+    (Some(simd_v128_on_stack), i64x2_extract_lane),
+    (Some(simd_v128_i64_on_stack), i64x2_replace_lane),
+    (Some(simd_float_v128_on_stack), f32x4_extract_lane),
+    (Some(simd_v128_f32_on_stack), f32x4_replace_lane),
+    (Some(simd_float_v128_on_stack), f64x2_extract_lane),
+    (Some(simd_v128_f64_on_stack), f64x2_replace_lane),
+    (Some(simd_v128_v128_on_stack), i8x16_eq),
+    (Some(simd_v128_v128_on_stack), i8x16_ne),
+    (Some(simd_v128_v128_on_stack), i8x16_lt_s),
+    (Some(simd_v128_v128_on_stack), i8x16_lt_u),
+    (Some(simd_v128_v128_on_stack), i8x16_gt_s),
+    (Some(simd_v128_v128_on_stack), i8x16_gt_u),
+    (Some(simd_v128_v128_on_stack), i8x16_le_s),
+    (Some(simd_v128_v128_on_stack), i8x16_le_u),
+    (Some(simd_v128_v128_on_stack), i8x16_ge_s),
+    (Some(simd_v128_v128_on_stack), i8x16_ge_u),
+    (Some(simd_v128_v128_on_stack), i16x8_eq),
+    (Some(simd_v128_v128_on_stack), i16x8_ne),
+    (Some(simd_v128_v128_on_stack), i16x8_lt_s),
+    (Some(simd_v128_v128_on_stack), i16x8_lt_u),
+    (Some(simd_v128_v128_on_stack), i16x8_gt_s),
+    (Some(simd_v128_v128_on_stack), i16x8_gt_u),
+    (Some(simd_v128_v128_on_stack), i16x8_le_s),
+    (Some(simd_v128_v128_on_stack), i16x8_le_u),
+    (Some(simd_v128_v128_on_stack), i16x8_ge_s),
+    (Some(simd_v128_v128_on_stack), i16x8_ge_u),
+    (Some(simd_v128_v128_on_stack), i32x4_eq),
+    (Some(simd_v128_v128_on_stack), i32x4_ne),
+    (Some(simd_v128_v128_on_stack), i32x4_lt_s),
+    (Some(simd_v128_v128_on_stack), i32x4_lt_u),
+    (Some(simd_v128_v128_on_stack), i32x4_gt_s),
+    (Some(simd_v128_v128_on_stack), i32x4_gt_u),
+    (Some(simd_v128_v128_on_stack), i32x4_le_s),
+    (Some(simd_v128_v128_on_stack), i32x4_le_u),
+    (Some(simd_v128_v128_on_stack), i32x4_ge_s),
+    (Some(simd_v128_v128_on_stack), i32x4_ge_u),
+    (Some(simd_float_v128_v128_on_stack), f32x4_eq),
+    (Some(simd_float_v128_v128_on_stack), f32x4_ne),
+    (Some(simd_float_v128_v128_on_stack), f32x4_lt),
+    (Some(simd_float_v128_v128_on_stack), f32x4_gt),
+    (Some(simd_float_v128_v128_on_stack), f32x4_le),
+    (Some(simd_float_v128_v128_on_stack), f32x4_ge),
+    (Some(simd_float_v128_v128_on_stack), f64x2_eq),
+    (Some(simd_float_v128_v128_on_stack), f64x2_ne),
+    (Some(simd_float_v128_v128_on_stack), f64x2_lt),
+    (Some(simd_float_v128_v128_on_stack), f64x2_gt),
+    (Some(simd_float_v128_v128_on_stack), f64x2_le),
+    (Some(simd_float_v128_v128_on_stack), f64x2_ge),
+    (Some(simd_v128_on_stack), v128_not),
+    (Some(simd_v128_v128_on_stack), v128_and),
+    (Some(simd_v128_v128_on_stack), v128_and_not),
+    (Some(simd_v128_v128_on_stack), v128_or),
+    (Some(simd_v128_v128_on_stack), v128_xor),
+    (Some(simd_v128_v128_v128_on_stack), v128_bitselect),
+    (Some(simd_have_memory_and_offset_and_v128), v128_load8_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_load16_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_load32_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_load64_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_store8_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_store16_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_store32_lane, memory),
+    (Some(simd_have_memory_and_offset_and_v128), v128_store64_lane, memory),
+    (Some(simd_v128_on_stack), i8x16_abs),
+    (Some(simd_v128_on_stack), i8x16_neg),
+    (Some(simd_v128_on_stack), v128_any_true),
+    (Some(simd_v128_on_stack), i8x16_all_true),
+    (Some(simd_v128_on_stack), i8x16_bitmask),
+    (Some(simd_v128_v128_on_stack), i8x16_narrow_i16x8_s),
+    (Some(simd_v128_v128_on_stack), i8x16_narrow_i16x8_u),
+    (Some(simd_v128_i32_on_stack), i8x16_shl),
+    (Some(simd_v128_i32_on_stack), i8x16_shr_s),
+    (Some(simd_v128_i32_on_stack), i8x16_shr_u),
+    (Some(simd_v128_v128_on_stack), i8x16_add),
+    (Some(simd_v128_v128_on_stack), i8x16_add_sat_s),
+    (Some(simd_v128_v128_on_stack), i8x16_add_sat_u),
+    (Some(simd_v128_v128_on_stack), i8x16_sub),
+    (Some(simd_v128_v128_on_stack), i8x16_sub_sat_s),
+    (Some(simd_v128_v128_on_stack), i8x16_sub_sat_u),
+    (Some(simd_v128_v128_on_stack), i8x16_min_s),
+    (Some(simd_v128_v128_on_stack), i8x16_min_u),
+    (Some(simd_v128_v128_on_stack), i8x16_max_s),
+    (Some(simd_v128_v128_on_stack), i8x16_max_u),
+    (Some(simd_v128_v128_on_stack), i8x16_rounding_average_u),
+    (Some(simd_v128_on_stack), i16x8_abs),
+    (Some(simd_v128_on_stack), i16x8_neg),
+    (Some(simd_v128_on_stack), i16x8_all_true),
+    (Some(simd_v128_on_stack), i16x8_bitmask),
+    (Some(simd_v128_v128_on_stack), i16x8_narrow_i32x4_s),
+    (Some(simd_v128_v128_on_stack), i16x8_narrow_i32x4_u),
+    (Some(simd_v128_on_stack), i16x8_widen_low_i8x16_s),
+    (Some(simd_v128_on_stack), i16x8_widen_high_i8x16_s),
+    (Some(simd_v128_on_stack), i16x8_widen_low_i8x16_u),
+    (Some(simd_v128_on_stack), i16x8_widen_high_i8x16_u),
+    (Some(simd_v128_i32_on_stack), i16x8_shl),
+    (Some(simd_v128_i32_on_stack), i16x8_shr_s),
+    (Some(simd_v128_i32_on_stack), i16x8_shr_u),
+    (Some(simd_v128_v128_on_stack), i16x8_add),
+    (Some(simd_v128_v128_on_stack), i16x8_add_sat_s),
+    (Some(simd_v128_v128_on_stack), i16x8_add_sat_u),
+    (Some(simd_v128_v128_on_stack), i16x8_sub),
+    (Some(simd_v128_v128_on_stack), i16x8_sub_sat_s),
+    (Some(simd_v128_v128_on_stack), i16x8_sub_sat_u),
+    (Some(simd_v128_v128_on_stack), i16x8_mul),
+    (Some(simd_v128_v128_on_stack), i16x8_min_s),
+    (Some(simd_v128_v128_on_stack), i16x8_min_u),
+    (Some(simd_v128_v128_on_stack), i16x8_max_s),
+    (Some(simd_v128_v128_on_stack), i16x8_max_u),
+    (Some(simd_v128_v128_on_stack), i16x8_ext_mul_low_i8x16_s),
+    (Some(simd_v128_v128_on_stack), i16x8_rounding_average_u),
+    (Some(simd_v128_v128_on_stack), i16x8_q15_mulr_sat_s),
+    (Some(simd_v128_v128_on_stack), i16x8_ext_mul_high_i8x16_s),
+    (Some(simd_v128_v128_on_stack), i16x8_ext_mul_low_i8x16_u),
+    (Some(simd_v128_v128_on_stack), i16x8_ext_mul_high_i8x16_u),
+    (Some(simd_v128_on_stack), i32x4_abs),
+    (Some(simd_v128_on_stack), i32x4_neg),
+    (Some(simd_v128_on_stack), i32x4_all_true),
+    (Some(simd_v128_on_stack), i32x4_bitmask),
+    (Some(simd_v128_on_stack), i32x4_widen_low_i16x8_s),
+    (Some(simd_v128_on_stack), i32x4_widen_high_i16x8_s),
+    (Some(simd_v128_on_stack), i32x4_widen_low_i16x8_u),
+    (Some(simd_v128_on_stack), i32x4_widen_high_i16x8_u),
+    (Some(simd_v128_i32_on_stack), i32x4_shl),
+    (Some(simd_v128_i32_on_stack), i32x4_shr_s),
+    (Some(simd_v128_i32_on_stack), i32x4_shr_u),
+    (Some(simd_v128_v128_on_stack), i32x4_add),
+    (Some(simd_v128_v128_on_stack), i32x4_sub),
+    (Some(simd_v128_v128_on_stack), i32x4_mul),
+    (Some(simd_v128_v128_on_stack), i32x4_min_s),
+    (Some(simd_v128_v128_on_stack), i32x4_min_u),
+    (Some(simd_v128_v128_on_stack), i32x4_max_s),
+    (Some(simd_v128_v128_on_stack), i32x4_max_u),
+    (Some(simd_v128_v128_on_stack), i32x4_dot_i16x8_s),
+    (Some(simd_v128_v128_on_stack), i32x4_ext_mul_low_i16x8_s),
+    (Some(simd_v128_v128_on_stack), i32x4_ext_mul_high_i16x8_s),
+    (Some(simd_v128_v128_on_stack), i32x4_ext_mul_low_i16x8_u),
+    (Some(simd_v128_v128_on_stack), i32x4_ext_mul_high_i16x8_u),
+    (Some(simd_v128_on_stack), i64x2_neg),
+    (Some(simd_v128_on_stack), i64x2_bitmask),
+    (Some(simd_v128_on_stack), i64x2_widen_low_i32x4_s),
+    (Some(simd_v128_on_stack), i64x2_widen_high_i32x4_s),
+    (Some(simd_v128_on_stack), i64x2_widen_low_i32x4_u),
+    (Some(simd_v128_on_stack), i64x2_widen_high_i32x4_u),
+    (Some(simd_v128_i32_on_stack), i64x2_shl),
+    (Some(simd_v128_i32_on_stack), i64x2_shr_s),
+    (Some(simd_v128_i32_on_stack), i64x2_shr_u),
+    (Some(simd_v128_v128_on_stack), i64x2_add),
+    (Some(simd_v128_v128_on_stack), i64x2_sub),
+    (Some(simd_v128_v128_on_stack), i64x2_ext_mul_low_i32x4_s),
+    (Some(simd_v128_v128_on_stack), i64x2_ext_mul_high_i32x4_s),
+    (Some(simd_v128_v128_on_stack), i64x2_mul),
+    (Some(simd_v128_v128_on_stack), i64x2_ext_mul_low_i32x4_u),
+    (Some(simd_v128_v128_on_stack), i64x2_ext_mul_high_i32x4_u),
+    (Some(simd_float_v128_on_stack), f32x4_ceil),
+    (Some(simd_float_v128_on_stack), f32x4_floor),
+    (Some(simd_float_v128_on_stack), f32x4_trunc),
+    (Some(simd_float_v128_on_stack), f32x4_nearest),
+    (Some(simd_float_v128_on_stack), f64x2_ceil),
+    (Some(simd_float_v128_on_stack), f64x2_floor),
+    (Some(simd_float_v128_on_stack), f64x2_trunc),
+    (Some(simd_float_v128_on_stack), f64x2_nearest),
+    (Some(simd_float_v128_on_stack), f32x4_abs),
+    (Some(simd_float_v128_on_stack), f32x4_neg),
+    (Some(simd_float_v128_on_stack), f32x4_sqrt),
+    (Some(simd_float_v128_v128_on_stack), f32x4_add),
+    (Some(simd_float_v128_v128_on_stack), f32x4_sub),
+    (Some(simd_float_v128_v128_on_stack), f32x4_mul),
+    (Some(simd_float_v128_v128_on_stack), f32x4_div),
+    (Some(simd_float_v128_v128_on_stack), f32x4_min),
+    (Some(simd_float_v128_v128_on_stack), f32x4_max),
+    (Some(simd_float_v128_v128_on_stack), f32x4_pmin),
+    (Some(simd_float_v128_v128_on_stack), f32x4_pmax),
+    (Some(simd_float_v128_on_stack), f64x2_abs),
+    (Some(simd_float_v128_on_stack), f64x2_neg),
+    (Some(simd_float_v128_on_stack), f64x2_sqrt),
+    (Some(simd_float_v128_v128_on_stack), f64x2_add),
+    (Some(simd_float_v128_v128_on_stack), f64x2_sub),
+    (Some(simd_float_v128_v128_on_stack), f64x2_mul),
+    (Some(simd_float_v128_v128_on_stack), f64x2_div),
+    (Some(simd_float_v128_v128_on_stack), f64x2_min),
+    (Some(simd_float_v128_v128_on_stack), f64x2_max),
+    (Some(simd_float_v128_v128_on_stack), f64x2_pmin),
+    (Some(simd_float_v128_v128_on_stack), f64x2_pmax),
+    (Some(simd_float_v128_on_stack), i32x4_trunc_sat_f32x4_s),
+    (Some(simd_float_v128_on_stack), i32x4_trunc_sat_f32x4_u),
+    (Some(simd_float_v128_on_stack), f32x4_convert_i32x4_s),
+    (Some(simd_float_v128_on_stack), f32x4_convert_i32x4_u),
+    (Some(simd_have_memory_and_offset), v128_load32_zero, memory),
+    (Some(simd_have_memory_and_offset), v128_load64_zero, memory),
+    // Threads proposal
+    (Some(threads_i32_store_valid), memory_atomic_notify, memory),
+    (Some(memory_atomic_wait32_valid), memory_atomic_wait32, memory),
+    (Some(memory_atomic_wait64_valid), memory_atomic_wait64, memory),
+    (Some(threads_enabled), atomic_fence, memory),
+    (Some(threads_have_memory_and_offset), i32_atomic_load, memory),
+    (Some(threads_have_memory_and_offset), i64_atomic_load, memory),
+    (Some(threads_have_memory_and_offset), i32_atomic_load8_u, memory),
+    (Some(threads_have_memory_and_offset), i32_atomic_load16_u, memory),
+    (Some(threads_have_memory_and_offset), i64_atomic_load8_u, memory),
+    (Some(threads_have_memory_and_offset), i64_atomic_load16_u, memory),
+    (Some(threads_have_memory_and_offset), i64_atomic_load32_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_store, memory),
+    (Some(threads_i64_store_valid), i64_atomic_store, memory),
+    (Some(threads_i32_store_valid), i32_atomic_store8, memory),
+    (Some(threads_i32_store_valid), i32_atomic_store16, memory),
+    (Some(threads_i64_store_valid), i64_atomic_store8, memory),
+    (Some(threads_i64_store_valid), i64_atomic_store16, memory),
+    (Some(threads_i64_store_valid), i64_atomic_store32, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw_add, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw_add, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw8_add_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw16_add_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw8_add_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw16_add_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw32_add_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw_sub, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw_sub, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw8_sub_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw16_sub_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw8_sub_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw16_sub_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw32_sub_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw_and, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw_and, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw8_and_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw16_and_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw8_and_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw16_and_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw32_and_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw_or, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw_or, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw8_or_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw16_or_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw8_or_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw16_or_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw32_or_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw_xor, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw_xor, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw8_xor_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw16_xor_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw8_xor_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw16_xor_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw32_xor_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw_xchg, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw_xchg, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw8_xchg_u, memory),
+    (Some(threads_i32_store_valid), i32_atomic_rmw16_xchg_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw8_xchg_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw16_xchg_u, memory),
+    (Some(threads_i64_store_valid), i64_atomic_rmw32_xchg_u, memory),
+    (Some(threads_i32_cmpxchg_valid), i32_atomic_rmw_cmpxchg, memory),
+    (Some(threads_i64_cmpxchg_valid), i64_atomic_rmw_cmpxchg, memory),
+    (Some(threads_i32_cmpxchg_valid), i32_atomic_rmw8_cmpxchg_u, memory),
+    (Some(threads_i32_cmpxchg_valid), i32_atomic_rmw16_cmpxchg_u, memory),
+    (Some(threads_i64_cmpxchg_valid), i64_atomic_rmw8_cmpxchg_u, memory),
+    (Some(threads_i64_cmpxchg_valid), i64_atomic_rmw16_cmpxchg_u, memory),
+    (Some(threads_i64_cmpxchg_valid), i64_atomic_rmw32_cmpxchg_u, memory),
+    // Exception handling proposal
+    (Some(exceptions_enabled), r#try, control),
+    (Some(catch_valid), catch, control),
+    (Some(catch_all_valid), catch_all, control),
+    (Some(unwind_valid), unwind, control),
+    (Some(throw_valid), throw, control),
+    (Some(rethrow_valid), rethrow, control),
+    // Tail call proposal
+    (Some(return_call_valid), return_call, call),
+    (Some(return_call_indirect_valid), return_call_indirect, call),
 }
 
 pub(crate) struct CodeBuilderAllocations<C>
@@ -295,12 +597,59 @@ where
     // Tables in this module which have a funcref element type.
     funcref_tables: Vec<u32>,
 
+    // Memories in this module, split by whether they are addressed with `i32`
+    // or `i64` values.
+    memory32: Vec<u32>,
+    memory64: Vec<u32>,
+
     // Functions that are referenced in the module through globals and segments.
     referenced_functions: Vec<u32>,
 
     // Flag that indicates if any element segments have the same type as any
     // table
     table_init_possible: bool,
+
+    // The `(table, index, type)` of table entries that are known to hold a
+    // function of that type after instantiation, used for `call_indirect`s
+    // that can't trap when `Config::disallow_traps` is enabled.
+    indirect_callees: Vec<(u32, u32, u32)>,
+
+    // Instructions that must be emitted before the next generated instruction
+    // to guard it against trapping.
+    guard: Vec<Instruction>,
+
+    // The categories of instructions that the config allows us to generate.
+    categories: InstructionCategories,
+}
+
+/// Which categories of instructions may be generated, cached from the
+/// `Config`'s `*_instructions_enabled` methods.
+struct InstructionCategories {
+    int: bool,
+    float: bool,
+    conversion: bool,
+    memory: bool,
+    control: bool,
+    call: bool,
+    table: bool,
+    reference: bool,
+    bulk_memory: bool,
+}
+
+impl InstructionCategories {
+    fn new(config: &impl Config) -> Self {
+        InstructionCategories {
+            int: config.int_instructions_enabled(),
+            float: config.float_instructions_enabled(),
+            conversion: config.conversion_instructions_enabled(),
+            memory: config.memory_instructions_enabled(),
+            control: config.control_instructions_enabled(),
+            call: config.call_instructions_enabled(),
+            table: config.table_instructions_enabled(),
+            reference: config.reference_instructions_enabled(),
+            bulk_memory: config.bulk_memory_instructions_enabled(),
+        }
+    }
 }
 
 pub(crate) struct CodeBuilder<'a, C>
@@ -340,6 +689,10 @@ enum ControlKind {
     Block,
     If,
     Loop,
+    Try,
+    Catch,
+    CatchAll,
+    Unwind,
 }
 
 impl<C> CodeBuilderAllocations<C>
@@ -392,8 +745,55 @@ where
             }
         }
 
+        let mut memory32 = Vec::new();
+        let mut memory64 = Vec::new();
+        for (i, mem) in module.memories.iter().enumerate() {
+            // Every access to a memory whose minimum size is zero may trap.
+            if module.config.disallow_traps() && mem.limits.min == 0 {
+                continue;
+            }
+            if mem.memory64 {
+                memory64.push(i as u32);
+            } else {
+                memory32.push(i as u32);
+            }
+        }
+
         let table_init_possible = module.elems.iter().any(|e| table_tys.contains(&e.ty));
 
+        let mut indirect_callees = Vec::new();
+        if module.config.disallow_traps() {
+            // Replay the active element segments to find the entries of the
+            // module's own funcref tables after instantiation. Imported tables
+            // may hold anything.
+            let num_imported_tables = module.tables.len() - module.num_defined_tables;
+            let mut entries = BTreeMap::new();
+            for elem in module.elems.iter() {
+                let (table, offset) = match &elem.kind {
+                    ElementKind::Active {
+                        table,
+                        offset: Instruction::I32Const(offset),
+                    } => (table.unwrap_or(0), *offset as u32),
+                    _ => continue,
+                };
+                let items: Vec<Option<u32>> = match &elem.items {
+                    Elements::Functions(funcs) => funcs.iter().map(|f| Some(*f)).collect(),
+                    Elements::Expressions(exprs) => exprs.clone(),
+                };
+                for (i, item) in items.into_iter().enumerate() {
+                    entries.insert((table, offset + i as u32), item);
+                }
+            }
+            indirect_callees = entries
+                .into_iter()
+                .filter(|((table, _), _)| *table as usize >= num_imported_tables)
+                .filter_map(|((table, index), func)| {
+                    let (ty, _) = module.funcs[func? as usize];
+                    Some((table, index, ty?))
+                })
+                .collect();
+        }
+
         CodeBuilderAllocations {
             controls: Vec::with_capacity(4),
             operands: Vec::with_capacity(16),
@@ -401,8 +801,13 @@ where
             functions,
             mutable_globals,
             funcref_tables,
+            memory32,
+            memory64,
             referenced_functions: referenced_functions.into_iter().collect(),
             table_init_possible,
+            indirect_callees,
+            guard: Vec::new(),
+            categories: InstructionCategories::new(&module.config),
         }
     }
 
@@ -421,6 +826,7 @@ where
 
         self.operands.clear();
         self.options.clear();
+        self.guard.clear();
 
         CodeBuilder {
             func_ty,
@@ -465,6 +871,58 @@ where
         }
     }
 
+    /// Whether the operand `depth` slots below the top of the stack can be
+    /// used as an address into one of this module's memories.
+    fn address_on_stack(&self, depth: usize) -> bool {
+        let operands = self.operands();
+        if operands.len() <= depth {
+            return false;
+        }
+        match operands[operands.len() - 1 - depth] {
+            None => !self.allocs.memory32.is_empty() || !self.allocs.memory64.is_empty(),
+            Some(ValType::I32) => !self.allocs.memory32.is_empty(),
+            Some(ValType::I64) => !self.allocs.memory64.is_empty(),
+            Some(_) => false,
+        }
+    }
+
+    /// A local of type `ty` other than the `taken` ones, to temporarily hold a
+    /// value while guarding an instruction. Such scratch locals are added to
+    /// every function when `Config::disallow_traps` is enabled.
+    fn scratch_local(&self, ty: ValType, taken: &[u32]) -> u32 {
+        let num_params = self.func_ty.params.len();
+        (0..self.locals.len())
+            .rev()
+            .filter(|i| self.locals[*i] == ty)
+            .map(|i| (num_params + i) as u32)
+            .find(|i| !taken.contains(i))
+            .expect("missing scratch local")
+    }
+
+    /// Adds `guard` to the instructions emitted before the next instruction,
+    /// applying it to the operand just below the `above` operands. Those are
+    /// moved into scratch locals around the guard.
+    fn guard_operand(&mut self, above: &[ValType], guard: &[Instruction]) {
+        let mut scratch = Vec::with_capacity(above.len());
+        for ty in above.iter().rev() {
+            let local = self.scratch_local(*ty, &scratch);
+            scratch.push(local);
+            self.allocs.guard.push(Instruction::LocalSet(local));
+        }
+        self.allocs.guard.extend_from_slice(guard);
+        for local in scratch.iter().rev() {
+            self.allocs.guard.push(Instruction::LocalGet(*local));
+        }
+    }
+
+    /// The memories which are addressed with values of type `ty`.
+    fn memories_of_index_type(&self, ty: ValType) -> &[u32] {
+        match ty {
+            ValType::I64 => &self.allocs.memory64,
+            _ => &self.allocs.memory32,
+        }
+    }
+
     fn types_on_stack(&self, types: &[ValType]) -> bool {
         self.operands().len() >= types.len()
             && self
@@ -511,7 +969,7 @@ where
             let keep_going =
                 instructions.len() < max_instructions && u.arbitrary().unwrap_or(false);
             if !keep_going {
-                self.end_active_control_frames(&mut instructions);
+                self.end_active_control_frames(module, &mut instructions);
                 break;
             }
 
@@ -519,29 +977,56 @@ where
 
             let f = u.choose(&self.allocs.options)?;
             let inst = f(u, module, &mut self)?;
+            instructions.append(&mut self.allocs.guard);
             instructions.push(inst);
         }
 
         Ok(instructions)
     }
 
-    fn end_active_control_frames(&mut self, instructions: &mut Vec<Instruction>) {
+    fn end_active_control_frames(
+        &mut self,
+        module: &ConfiguredModule<C>,
+        instructions: &mut Vec<Instruction>,
+    ) {
+        let disallow_traps = module.config.disallow_traps();
         while !self.allocs.controls.is_empty() {
             let num_operands = self.operands().len();
             let label = self.allocs.controls.pop().unwrap();
 
             // If we don't have the right operands on the stack for this
-            // control frame, add an `unreachable`.
+            // control frame, add an `unreachable`, or replace the operands
+            // with the results' default values if traps are disallowed.
             if label.results.len() != num_operands || !self.types_on_stack(&label.results) {
-                self.allocs.operands.push(None);
-                instructions.push(Instruction::Unreachable);
+                if disallow_traps {
+                    instructions.extend((0..num_operands).map(|_| Instruction::Drop));
+                    instructions.extend(label.results.iter().map(|ty| default_value(*ty)));
+                } else {
+                    self.allocs.operands.push(None);
+                    instructions.push(Instruction::Unreachable);
+                }
             }
 
             // If this is an `if` that is not stack neutral, then it
             // must have an `else`.
             if label.kind == ControlKind::If && label.params != label.results {
                 instructions.push(Instruction::Else);
-                instructions.push(Instruction::Unreachable);
+                if disallow_traps {
+                    instructions.extend(label.params.iter().map(|_| Instruction::Drop));
+                    instructions.extend(label.results.iter().map(|ty| default_value(*ty)));
+                } else {
+                    instructions.push(Instruction::Unreachable);
+                }
+            }
+
+            // A `try` must have at least one handler before its `end`.
+            if label.kind == ControlKind::Try {
+                instructions.push(Instruction::CatchAll);
+                if disallow_traps {
+                    instructions.extend(label.results.iter().map(|ty| default_value(*ty)));
+                } else {
+                    instructions.push(Instruction::Unreachable);
+                }
             }
 
             // The last control frame for the function return does not
@@ -558,6 +1043,23 @@ where
     }
 }
 
+/// A constant instruction producing the default value of `ty`.
+fn default_value(ty: ValType) -> Instruction {
+    match ty {
+        ValType::I32 => Instruction::I32Const(0),
+        ValType::I64 => Instruction::I64Const(0),
+        ValType::F32 => Instruction::F32Const(0.0),
+        ValType::F64 => Instruction::F64Const(0.0),
+        ValType::V128 => Instruction::V128Const(0),
+        ValType::FuncRef | ValType::ExternRef => Instruction::RefNull(ty),
+    }
+}
+
+#[inline]
+fn unreachable_valid<C: Config>(module: &ConfiguredModule<C>, _: &mut CodeBuilder<C>) -> bool {
+    !module.config.disallow_traps()
+}
+
 fn unreachable<C: Config>(
     _: &mut Unstructured,
     _: &ConfiguredModule<C>,
@@ -668,6 +1170,8 @@ fn end_valid<C: Config>(_: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -
         // `if`s that don't leave the stack as they found it must have an
         // `else`.
         && !(control.kind == ControlKind::If && control.params != control.results)
+        // `try`s must have a handler.
+        && control.kind != ControlKind::Try
 }
 
 fn end<C: Config>(
@@ -864,6 +1368,9 @@ fn call_indirect_valid<C: Config>(
     if builder.allocs.funcref_tables.is_empty() || !builder.type_on_stack(ValType::I32) {
         return false;
     }
+    if module.config.disallow_traps() {
+        return !indirect_callees(module, builder, None).is_empty();
+    }
     let ty = builder.allocs.operands.pop().unwrap();
     let is_valid = module
         .func_types()
@@ -877,6 +1384,13 @@ fn call_indirect<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    if module.config.disallow_traps() {
+        let (table, ty) = guarded_indirect_callee(u, module, builder, None)?;
+        let func_ty = module.func_type(ty);
+        builder.pop_operands(&func_ty.params);
+        builder.push_operands(&func_ty.results);
+        return Ok(Instruction::CallIndirect { ty, table });
+    }
     builder.pop_operands(&[ValType::I32]);
 
     let choices = module
@@ -893,6 +1407,47 @@ fn call_indirect<C: Config>(
     })
 }
 
+/// The `indirect_callees` whose parameters are on the stack below the table
+/// index on top of it, and whose results are `results` if given.
+fn indirect_callees<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+    results: Option<&[ValType]>,
+) -> Vec<(u32, u32, u32)> {
+    let index = builder.allocs.operands.pop().unwrap();
+    let callees = builder
+        .allocs
+        .indirect_callees
+        .iter()
+        .copied()
+        .filter(|(_, _, ty)| {
+            let ty = module.func_type(*ty);
+            builder.types_on_stack(&ty.params) && results.iter().all(|r| ty.results == *r)
+        })
+        .collect();
+    builder.allocs.operands.push(index);
+    callees
+}
+
+/// Chooses one of the `indirect_callees` and replaces the table index on top of
+/// the stack with its index, so that the indirect call can't trap. Returns the
+/// callee's table and type.
+fn guarded_indirect_callee<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+    results: Option<&[ValType]>,
+) -> Result<(u32, u32)> {
+    let callees = indirect_callees(module, builder, results);
+    let (table, index, ty) = *u.choose(&callees)?;
+    builder.pop_operands(&[ValType::I32]);
+    builder
+        .allocs
+        .guard
+        .extend_from_slice(&[Instruction::Drop, Instruction::I32Const(index as i32)]);
+    Ok((table, ty))
+}
+
 #[inline]
 fn drop_valid<C: Config>(_: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
     !builder.operands().is_empty()
@@ -932,7 +1487,7 @@ fn select<C: Config>(
             Ok(Instruction::TypedSelect(ty))
         }
         Some(ValType::I32) | Some(ValType::I64) | Some(ValType::F32) | Some(ValType::F64)
-        | None => Ok(Instruction::Select),
+        | Some(ValType::V128) | None => Ok(Instruction::Select),
     }
 }
 
@@ -1073,10 +1628,10 @@ fn have_memory<C: Config>(module: &ConfiguredModule<C>, _: &mut CodeBuilder<C>)
 
 #[inline]
 fn have_memory_and_offset<C: Config>(
-    module: &ConfiguredModule<C>,
+    _: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> bool {
-    have_memory(module, builder) && builder.type_on_stack(ValType::I32)
+    builder.address_on_stack(0)
 }
 
 #[inline]
@@ -1089,9 +1644,9 @@ fn i32_load<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2])?;
     builder.allocs.operands.push(Some(ValType::I32));
-    Ok(Instruction::I32Load(mem_arg(u, module, &[0, 1])?))
+    Ok(Instruction::I32Load(memarg))
 }
 
 fn i64_load<C: Config>(
@@ -1099,9 +1654,14 @@ fn i64_load<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load(mem_arg(u, module, &[0, 1, 2])?))
+    Ok(Instruction::I64Load(memarg))
+}
+
+#[inline]
+fn float_load_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    floats_enabled(module, builder) && have_memory_and_offset(module, builder)
 }
 
 fn f32_load<C: Config>(
@@ -1109,9 +1669,9 @@ fn f32_load<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2])?;
     builder.allocs.operands.push(Some(ValType::F32));
-    Ok(Instruction::F32Load(mem_arg(u, module, &[0, 1])?))
+    Ok(Instruction::F32Load(memarg))
 }
 
 fn f64_load<C: Config>(
@@ -1119,9 +1679,9 @@ fn f64_load<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
     builder.allocs.operands.push(Some(ValType::F64));
-    Ok(Instruction::F64Load(mem_arg(u, module, &[0, 1, 2])?))
+    Ok(Instruction::F64Load(memarg))
 }
 
 fn i32_load_8_s<C: Config>(
@@ -1129,9 +1689,9 @@ fn i32_load_8_s<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0])?;
     builder.allocs.operands.push(Some(ValType::I32));
-    Ok(Instruction::I32Load8_S(mem_arg(u, module, &[0])?))
+    Ok(Instruction::I32Load8_S(memarg))
 }
 
 fn i32_load_8_u<C: Config>(
@@ -1139,9 +1699,9 @@ fn i32_load_8_u<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0])?;
     builder.allocs.operands.push(Some(ValType::I32));
-    Ok(Instruction::I32Load8_U(mem_arg(u, module, &[0])?))
+    Ok(Instruction::I32Load8_U(memarg))
 }
 
 fn i32_load_16_s<C: Config>(
@@ -1149,9 +1709,9 @@ fn i32_load_16_s<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1])?;
     builder.allocs.operands.push(Some(ValType::I32));
-    Ok(Instruction::I32Load16_S(mem_arg(u, module, &[0, 1])?))
+    Ok(Instruction::I32Load16_S(memarg))
 }
 
 fn i32_load_16_u<C: Config>(
@@ -1159,9 +1719,9 @@ fn i32_load_16_u<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1])?;
     builder.allocs.operands.push(Some(ValType::I32));
-    Ok(Instruction::I32Load16_U(mem_arg(u, module, &[0, 1])?))
+    Ok(Instruction::I32Load16_U(memarg))
 }
 
 fn i64_load_8_s<C: Config>(
@@ -1169,9 +1729,9 @@ fn i64_load_8_s<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load8_S(mem_arg(u, module, &[0])?))
+    Ok(Instruction::I64Load8_S(memarg))
 }
 
 fn i64_load_16_s<C: Config>(
@@ -1179,9 +1739,9 @@ fn i64_load_16_s<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load16_S(mem_arg(u, module, &[0, 1])?))
+    Ok(Instruction::I64Load16_S(memarg))
 }
 
 fn i64_load_32_s<C: Config>(
@@ -1189,9 +1749,9 @@ fn i64_load_32_s<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load32_S(mem_arg(u, module, &[0, 1, 2])?))
+    Ok(Instruction::I64Load32_S(memarg))
 }
 
 fn i64_load_8_u<C: Config>(
@@ -1199,9 +1759,9 @@ fn i64_load_8_u<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load8_U(mem_arg(u, module, &[0])?))
+    Ok(Instruction::I64Load8_U(memarg))
 }
 
 fn i64_load_16_u<C: Config>(
@@ -1209,9 +1769,9 @@ fn i64_load_16_u<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load16_U(mem_arg(u, module, &[0, 1])?))
+    Ok(Instruction::I64Load16_U(memarg))
 }
 
 fn i64_load_32_u<C: Config>(
@@ -1219,9 +1779,9 @@ fn i64_load_32_u<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2])?;
     builder.allocs.operands.push(Some(ValType::I64));
-    Ok(Instruction::I64Load32_U(mem_arg(u, module, &[0, 1, 2])?))
+    Ok(Instruction::I64Load32_U(memarg))
 }
 
 #[inline]
@@ -1230,7 +1790,7 @@ fn store_valid<C: Config>(
     builder: &mut CodeBuilder<C>,
     f: impl FnOnce() -> ValType,
 ) -> bool {
-    have_memory(module, builder) && builder.types_on_stack(&[ValType::I32, f()])
+    have_memory(module, builder) && builder.type_on_stack(f()) && builder.address_on_stack(1)
 }
 
 #[inline]
@@ -1243,8 +1803,8 @@ fn i32_store<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I32]);
-    Ok(Instruction::I32Store(mem_arg(u, module, &[0, 1])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I32], &[0, 1, 2])?;
+    Ok(Instruction::I32Store(memarg))
 }
 
 #[inline]
@@ -1257,8 +1817,8 @@ fn i64_store<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I64]);
-    Ok(Instruction::I64Store(mem_arg(u, module, &[0, 1, 2])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I64], &[0, 1, 2, 3])?;
+    Ok(Instruction::I64Store(memarg))
 }
 
 #[inline]
@@ -1271,8 +1831,8 @@ fn f32_store<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::F32]);
-    Ok(Instruction::F32Store(mem_arg(u, module, &[0, 1])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::F32], &[0, 1, 2])?;
+    Ok(Instruction::F32Store(memarg))
 }
 
 #[inline]
@@ -1285,8 +1845,8 @@ fn f64_store<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::F64]);
-    Ok(Instruction::F64Store(mem_arg(u, module, &[0, 1, 2])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::F64], &[0, 1, 2, 3])?;
+    Ok(Instruction::F64Store(memarg))
 }
 
 fn i32_store_8<C: Config>(
@@ -1294,8 +1854,8 @@ fn i32_store_8<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I32]);
-    Ok(Instruction::I32Store8(mem_arg(u, module, &[0])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I32], &[0])?;
+    Ok(Instruction::I32Store8(memarg))
 }
 
 fn i32_store_16<C: Config>(
@@ -1303,8 +1863,8 @@ fn i32_store_16<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I32]);
-    Ok(Instruction::I32Store16(mem_arg(u, module, &[0, 1])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I32], &[0, 1])?;
+    Ok(Instruction::I32Store16(memarg))
 }
 
 fn i64_store_8<C: Config>(
@@ -1312,8 +1872,8 @@ fn i64_store_8<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I64]);
-    Ok(Instruction::I64Store8(mem_arg(u, module, &[0])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I64], &[0])?;
+    Ok(Instruction::I64Store8(memarg))
 }
 
 fn i64_store_16<C: Config>(
@@ -1321,8 +1881,8 @@ fn i64_store_16<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I64]);
-    Ok(Instruction::I64Store16(mem_arg(u, module, &[0, 1])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I64], &[0, 1])?;
+    Ok(Instruction::I64Store16(memarg))
 }
 
 fn i64_store_32<C: Config>(
@@ -1330,8 +1890,8 @@ fn i64_store_32<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32, ValType::I64]);
-    Ok(Instruction::I64Store32(mem_arg(u, module, &[0, 1, 2])?))
+    let memarg = mem_arg(u, module, builder, &[ValType::I64], &[0, 1, 2])?;
+    Ok(Instruction::I64Store32(memarg))
 }
 
 fn memory_size<C: Config>(
@@ -1339,16 +1899,14 @@ fn memory_size<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.push_operands(&[ValType::I32]);
-    Ok(Instruction::MemorySize(memory_index(u, module)?))
+    let mem = memory_index(u, module)?;
+    builder.push_operands(&[module.memories[mem as usize].index_type()]);
+    Ok(Instruction::MemorySize(mem))
 }
 
 #[inline]
-fn memory_grow_valid<C: Config>(
-    module: &ConfiguredModule<C>,
-    builder: &mut CodeBuilder<C>,
-) -> bool {
-    have_memory(module, builder) && builder.type_on_stack(ValType::I32)
+fn memory_grow_valid<C: Config>(_: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    builder.address_on_stack(0)
 }
 
 fn memory_grow<C: Config>(
@@ -1356,9 +1914,9 @@ fn memory_grow<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    builder.pop_operands(&[ValType::I32]);
-    builder.push_operands(&[ValType::I32]);
-    Ok(Instruction::MemoryGrow(memory_index(u, module)?))
+    let mem = pop_address(u, builder)?;
+    builder.push_operands(&[module.memories[mem as usize].index_type()]);
+    Ok(Instruction::MemoryGrow(mem))
 }
 
 #[inline]
@@ -1366,10 +1924,11 @@ fn memory_init_valid<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> bool {
-    have_memory(module, builder)
+    !module.config.disallow_traps()
         && have_data(module, builder)
         && module.config.bulk_memory_enabled()
-        && builder.types_on_stack(&[ValType::I32, ValType::I32, ValType::I32])
+        && builder.types_on_stack(&[ValType::I32, ValType::I32])
+        && builder.address_on_stack(2)
 }
 
 fn memory_init<C: Config>(
@@ -1377,48 +1936,92 @@ fn memory_init<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    let mem = memory_index(u, module)?;
     let data = data_index(u, module)?;
-    builder.pop_operands(&[ValType::I32, ValType::I32, ValType::I32]);
+    builder.pop_operands(&[ValType::I32, ValType::I32]);
+    let mem = pop_address(u, builder)?;
     Ok(Instruction::MemoryInit { mem, data })
 }
 
+/// The index types of memories that `memory.fill` could target given the
+/// current operand stack: `[address, i32 value, length]` where the address
+/// and length have the memory's index type.
+fn memory_fill_index_types<'a, C: Config>(
+    builder: &'a CodeBuilder<'a, C>,
+) -> impl Iterator<Item = ValType> + 'a {
+    [ValType::I32, ValType::I64]
+        .iter()
+        .copied()
+        .filter(move |ty| {
+            !builder.memories_of_index_type(*ty).is_empty()
+                && builder.types_on_stack(&[*ty, ValType::I32, *ty])
+        })
+}
+
 #[inline]
 fn memory_fill_valid<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> bool {
-    have_memory(module, builder)
+    !module.config.disallow_traps()
         && module.config.bulk_memory_enabled()
-        && builder.types_on_stack(&[ValType::I32, ValType::I32, ValType::I32])
+        && memory_fill_index_types(builder).next().is_some()
 }
 
 fn memory_fill<C: Config>(
     u: &mut Unstructured,
-    module: &ConfiguredModule<C>,
+    _: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    let mem = memory_index(u, module)?;
-    builder.pop_operands(&[ValType::I32, ValType::I32, ValType::I32]);
+    let tys = memory_fill_index_types(builder).collect::<Vec<_>>();
+    let ty = *u.choose(&tys)?;
+    let mem = *u.choose(builder.memories_of_index_type(ty))?;
+    builder.pop_operands(&[ty, ValType::I32, ty]);
     Ok(Instruction::MemoryFill(mem))
 }
 
+/// The `(dst, src)` index types of memories that `memory.copy` could target
+/// given the current operand stack. The length is an `i64` only when both
+/// memories are 64-bit.
+fn memory_copy_index_types<'a, C: Config>(
+    builder: &'a CodeBuilder<'a, C>,
+) -> impl Iterator<Item = (ValType, ValType)> + 'a {
+    use ValType::{I32, I64};
+    [(I32, I32), (I32, I64), (I64, I32), (I64, I64)]
+        .iter()
+        .copied()
+        .filter(move |(dst, src)| {
+            let len = if *dst == I64 && *src == I64 { I64 } else { I32 };
+            !builder.memories_of_index_type(*dst).is_empty()
+                && !builder.memories_of_index_type(*src).is_empty()
+                && builder.types_on_stack(&[*dst, *src, len])
+        })
+}
+
 #[inline]
 fn memory_copy_valid<C: Config>(
     module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> bool {
-    memory_fill_valid(module, builder)
+    !module.config.disallow_traps()
+        && module.config.bulk_memory_enabled()
+        && memory_copy_index_types(builder).next().is_some()
 }
 
 fn memory_copy<C: Config>(
     u: &mut Unstructured,
-    module: &ConfiguredModule<C>,
+    _: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
-    let src = memory_index(u, module)?;
-    let dst = memory_index(u, module)?;
-    builder.pop_operands(&[ValType::I32, ValType::I32, ValType::I32]);
+    let tys = memory_copy_index_types(builder).collect::<Vec<_>>();
+    let (dst_ty, src_ty) = *u.choose(&tys)?;
+    let dst = *u.choose(builder.memories_of_index_type(dst_ty))?;
+    let src = *u.choose(builder.memories_of_index_type(src_ty))?;
+    let len = if dst_ty == ValType::I64 && src_ty == ValType::I64 {
+        ValType::I64
+    } else {
+        ValType::I32
+    };
+    builder.pop_operands(&[dst_ty, src_ty, len]);
     Ok(Instruction::MemoryCopy { dst, src })
 }
 
@@ -1455,6 +2058,11 @@ fn i64_const<C: Config>(
     Ok(Instruction::I64Const(x))
 }
 
+#[inline]
+fn floats_enabled<C: Config>(module: &ConfiguredModule<C>, _: &mut CodeBuilder<C>) -> bool {
+    !module.config.deterministic_only()
+}
+
 fn f32_const<C: Config>(
     u: &mut Unstructured,
     _: &ConfiguredModule<C>,
@@ -1903,11 +2511,50 @@ fn i32_mul<C: Config>(
     Ok(Instruction::I32Mul)
 }
 
+/// When traps are disallowed, guards the divisor of type `ty` on top of the
+/// stack by replacing `0` and `-1` with `1`, which avoids both division by zero
+/// and signed overflow.
+fn guard_divisor<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+    ty: ValType,
+) {
+    if !module.config.disallow_traps() {
+        return;
+    }
+    let local = builder.scratch_local(ty, &[]);
+    let (one, add, gt_u) = match ty {
+        ValType::I64 => (
+            Instruction::I64Const(1),
+            Instruction::I64Add,
+            Instruction::I64GtU,
+        ),
+        _ => (
+            Instruction::I32Const(1),
+            Instruction::I32Add,
+            Instruction::I32GtU,
+        ),
+    };
+    // `select(x, 1, x + 1 > 1)` with unsigned comparison.
+    builder.allocs.guard.extend_from_slice(&[
+        Instruction::LocalSet(local),
+        Instruction::LocalGet(local),
+        one.clone(),
+        Instruction::LocalGet(local),
+        one.clone(),
+        add,
+        one,
+        gt_u,
+        Instruction::Select,
+    ]);
+}
+
 fn i32_div_s<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I32);
     builder.pop_operands(&[ValType::I32, ValType::I32]);
     builder.push_operands(&[ValType::I32]);
     Ok(Instruction::I32DivS)
@@ -1915,9 +2562,10 @@ fn i32_div_s<C: Config>(
 
 fn i32_div_u<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I32);
     builder.pop_operands(&[ValType::I32, ValType::I32]);
     builder.push_operands(&[ValType::I32]);
     Ok(Instruction::I32DivU)
@@ -1925,9 +2573,10 @@ fn i32_div_u<C: Config>(
 
 fn i32_rem_s<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I32);
     builder.pop_operands(&[ValType::I32, ValType::I32]);
     builder.push_operands(&[ValType::I32]);
     Ok(Instruction::I32RemS)
@@ -1935,9 +2584,10 @@ fn i32_rem_s<C: Config>(
 
 fn i32_rem_u<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I32);
     builder.pop_operands(&[ValType::I32, ValType::I32]);
     builder.push_operands(&[ValType::I32]);
     Ok(Instruction::I32RemU)
@@ -2085,9 +2735,10 @@ fn i64_mul<C: Config>(
 
 fn i64_div_s<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I64);
     builder.pop_operands(&[ValType::I64, ValType::I64]);
     builder.push_operands(&[ValType::I64]);
     Ok(Instruction::I64DivS)
@@ -2095,9 +2746,10 @@ fn i64_div_s<C: Config>(
 
 fn i64_div_u<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I64);
     builder.pop_operands(&[ValType::I64, ValType::I64]);
     builder.push_operands(&[ValType::I64]);
     Ok(Instruction::I64DivU)
@@ -2105,9 +2757,10 @@ fn i64_div_u<C: Config>(
 
 fn i64_rem_s<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I64);
     builder.pop_operands(&[ValType::I64, ValType::I64]);
     builder.push_operands(&[ValType::I64]);
     Ok(Instruction::I64RemS)
@@ -2115,9 +2768,10 @@ fn i64_rem_s<C: Config>(
 
 fn i64_rem_u<C: Config>(
     _: &mut Unstructured,
-    _: &ConfiguredModule<C>,
+    module: &ConfiguredModule<C>,
     builder: &mut CodeBuilder<C>,
 ) -> Result<Instruction> {
+    guard_divisor(module, builder, ValType::I64);
     builder.pop_operands(&[ValType::I64, ValType::I64]);
     builder.push_operands(&[ValType::I64]);
     Ok(Instruction::I64RemU)
@@ -2503,6 +3157,17 @@ fn i32_wrap_i64<C: Config>(
     Ok(Instruction::I32WrapI64)
 }
 
+// Converting NaN or an out-of-range float to an integer traps.
+#[inline]
+fn trunc_f32_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    !module.config.disallow_traps() && f32_on_stack(module, builder)
+}
+
+#[inline]
+fn trunc_f64_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    !module.config.disallow_traps() && f64_on_stack(module, builder)
+}
+
 fn i32_trunc_f32_s<C: Config>(
     _: &mut Unstructured,
     _: &ConfiguredModule<C>,
@@ -2603,6 +3268,22 @@ fn i64_trunc_f64_u<C: Config>(
     Ok(Instruction::I64TruncF64U)
 }
 
+#[inline]
+fn float_i32_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    floats_enabled(module, builder) && i32_on_stack(module, builder)
+}
+
+#[inline]
+fn float_i64_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    floats_enabled(module, builder) && i64_on_stack(module, builder)
+}
+
 fn f32_convert_i32_s<C: Config>(
     _: &mut Unstructured,
     _: &ConfiguredModule<C>,
@@ -2898,14 +3579,40 @@ fn memory_offset<C: Config>(
     }
 }
 
+/// Pops the operands `above` the address, then the address itself, off the
+/// operand stack and creates a `MemArg` for one of the memories it can address.
+///
+/// When traps are disallowed, this also guards the address so that the access
+/// is in bounds of the memory's minimum size.
 fn mem_arg<C: Config>(
     u: &mut Unstructured,
     module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+    above: &[ValType],
     alignments: &[u32],
 ) -> Result<MemArg> {
-    let memory_index = memory_index(u, module)?;
-    let offset = memory_offset(u, module, memory_index)?;
+    builder.pop_operands(above);
+    let memory_index = pop_address(u, builder)?;
+    let mut offset = memory_offset(u, module, memory_index)?;
     let align = *u.choose(alignments)?;
+    if module.config.disallow_traps() {
+        // The largest alignment is the natural alignment, i.e. the access size.
+        let size = 1u64 << alignments.last().unwrap();
+        let memory = &module.memories[memory_index as usize];
+        let bound = u64::from(memory.limits.min) * 65536;
+        offset = offset.min(u32::try_from(bound - size).unwrap_or(u32::MAX));
+        // Only atomic accesses have a single alignment of more than one byte,
+        // and they trap when the effective address, offset included, is
+        // misaligned.
+        let align_to = if alignments.len() == 1 { size } else { 1 };
+        offset -= offset % align_to as u32;
+        let guard = address_guard(
+            memory.index_type(),
+            bound - u64::from(offset) - size + 1,
+            align_to,
+        );
+        builder.guard_operand(above, &guard);
+    }
     Ok(MemArg {
         memory_index,
         offset,
@@ -2913,10 +3620,46 @@ fn mem_arg<C: Config>(
     })
 }
 
+/// Instructions that map an address of type `ty` into `0..n`, rounded down to
+/// a multiple of `align`.
+fn address_guard(ty: ValType, n: u64, align: u64) -> Vec<Instruction> {
+    let mut guard = Vec::new();
+    if ty == ValType::I64 {
+        guard.push(Instruction::I64Const(n as i64));
+        guard.push(Instruction::I64RemU);
+        if align > 1 {
+            guard.push(Instruction::I64Const(-(align as i64)));
+            guard.push(Instruction::I64And);
+        }
+    } else {
+        // Every `i32` is already below `n` if it doesn't fit in an `i32`.
+        if n <= u64::from(u32::MAX) {
+            guard.push(Instruction::I32Const(n as i32));
+            guard.push(Instruction::I32RemU);
+        }
+        if align > 1 {
+            guard.push(Instruction::I32Const(-(align as i32)));
+            guard.push(Instruction::I32And);
+        }
+    }
+    guard
+}
+
 fn memory_index<C: Config>(u: &mut Unstructured, module: &ConfiguredModule<C>) -> Result<u32> {
     u.int_in_range(0..=module.memories.len() as u32 - 1)
 }
 
+/// Pops an address off the operand stack and returns the index of a memory
+/// it can address.
+fn pop_address<C: Config>(u: &mut Unstructured, builder: &mut CodeBuilder<C>) -> Result<u32> {
+    let ty = match builder.allocs.operands.pop().unwrap() {
+        Some(ty) => ty,
+        None if builder.allocs.memory32.is_empty() => ValType::I64,
+        None => ValType::I32,
+    };
+    Ok(*u.choose(builder.memories_of_index_type(ty))?)
+}
+
 fn data_index<C: Config>(u: &mut Unstructured, module: &ConfiguredModule<C>) -> Result<u32> {
     let data = module.data.len() as u32;
     assert!(data > 0);
@@ -2978,7 +3721,8 @@ fn ref_is_null<C: Config>(
 
 #[inline]
 fn table_fill_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
-    module.config.reference_types_enabled()
+    !module.config.disallow_traps()
+        && module.config.reference_types_enabled()
         && module.config.bulk_memory_enabled()
         && [ValType::ExternRef, ValType::FuncRef].iter().any(|ty| {
             builder.types_on_stack(&[ValType::I32, *ty, ValType::I32])
@@ -3000,7 +3744,8 @@ fn table_fill<C: Config>(
 
 #[inline]
 fn table_set_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
-    module.config.reference_types_enabled()
+    !module.config.disallow_traps()
+        && module.config.reference_types_enabled()
         && [ValType::ExternRef, ValType::FuncRef].iter().any(|ty| {
             builder.types_on_stack(&[ValType::I32, *ty])
                 && module.tables.iter().any(|t| t.elem_ty == *ty)
@@ -3020,7 +3765,8 @@ fn table_set<C: Config>(
 
 #[inline]
 fn table_get_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
-    module.config.reference_types_enabled()
+    !module.config.disallow_traps()
+        && module.config.reference_types_enabled()
         && builder.type_on_stack(ValType::I32)
         && module.tables.len() > 0
 }
@@ -3075,7 +3821,8 @@ fn table_grow<C: Config>(
 
 #[inline]
 fn table_copy_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
-    module.config.reference_types_enabled()
+    !module.config.disallow_traps()
+        && module.config.reference_types_enabled()
         && module.tables.len() > 0
         && builder.types_on_stack(&[ValType::I32, ValType::I32, ValType::I32])
 }
@@ -3093,7 +3840,8 @@ fn table_copy<C: Config>(
 
 #[inline]
 fn table_init_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
-    module.config.reference_types_enabled()
+    !module.config.disallow_traps()
+        && module.config.reference_types_enabled()
         && builder.allocs.table_init_possible
         && builder.types_on_stack(&[ValType::I32, ValType::I32, ValType::I32])
 }
@@ -3157,3 +3905,1083 @@ fn table_index<C: Config>(
         .collect::<Vec<_>>();
     Ok(*u.choose(&tables)?)
 }
+
+#[inline]
+fn simd_enabled<C: Config>(module: &ConfiguredModule<C>, _: &mut CodeBuilder<C>) -> bool {
+    module.config.simd_enabled()
+}
+
+#[inline]
+fn simd_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+    types: &[ValType],
+) -> bool {
+    module.config.simd_enabled() && builder.types_on_stack(types)
+}
+
+#[inline]
+fn simd_i32_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::I32])
+}
+
+#[inline]
+fn simd_i64_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::I64])
+}
+
+#[inline]
+fn simd_f32_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::F32])
+}
+
+#[inline]
+fn simd_f64_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::F64])
+}
+
+#[inline]
+fn simd_v128_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::V128])
+}
+
+#[inline]
+fn simd_v128_v128_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::V128, ValType::V128])
+}
+
+#[inline]
+fn simd_float_v128_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    floats_enabled(module, builder) && simd_v128_on_stack(module, builder)
+}
+
+#[inline]
+fn simd_float_v128_v128_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    floats_enabled(module, builder) && simd_v128_v128_on_stack(module, builder)
+}
+
+#[inline]
+fn simd_v128_v128_v128_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(
+        module,
+        builder,
+        &[ValType::V128, ValType::V128, ValType::V128],
+    )
+}
+
+#[inline]
+fn simd_v128_i32_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::V128, ValType::I32])
+}
+
+#[inline]
+fn simd_v128_i64_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::V128, ValType::I64])
+}
+
+#[inline]
+fn simd_v128_f32_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::V128, ValType::F32])
+}
+
+#[inline]
+fn simd_v128_f64_on_stack<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    simd_on_stack(module, builder, &[ValType::V128, ValType::F64])
+}
+
+#[inline]
+fn simd_have_memory_and_offset<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    module.config.simd_enabled() && have_memory_and_offset(module, builder)
+}
+
+#[inline]
+fn simd_have_memory_and_offset_and_v128<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    module.config.simd_enabled()
+        && builder.type_on_stack(ValType::V128)
+        && builder.address_on_stack(1)
+}
+
+// Most SIMD instructions only differ in their opcode and the types they pop
+// and push, so generate their generator functions with these macros.
+
+macro_rules! simd_unop {
+    ($instruction:ident, $generator_fn_name:ident) => {
+        simd_op!($instruction, $generator_fn_name, [V128] -> [V128]);
+    };
+}
+
+macro_rules! simd_binop {
+    ($instruction:ident, $generator_fn_name:ident) => {
+        simd_op!($instruction, $generator_fn_name, [V128, V128] -> [V128]);
+    };
+}
+
+macro_rules! simd_ternop {
+    ($instruction:ident, $generator_fn_name:ident) => {
+        simd_op!($instruction, $generator_fn_name, [V128, V128, V128] -> [V128]);
+    };
+}
+
+macro_rules! simd_shift {
+    ($instruction:ident, $generator_fn_name:ident) => {
+        simd_op!($instruction, $generator_fn_name, [V128, I32] -> [V128]);
+    };
+}
+
+macro_rules! simd_test {
+    ($instruction:ident, $generator_fn_name:ident) => {
+        simd_op!($instruction, $generator_fn_name, [V128] -> [I32]);
+    };
+}
+
+macro_rules! simd_splat {
+    ($instruction:ident, $generator_fn_name:ident, $ty:ident) => {
+        simd_op!($instruction, $generator_fn_name, [$ty] -> [V128]);
+    };
+}
+
+macro_rules! simd_op {
+    ($instruction:ident, $generator_fn_name:ident, [$($pop:ident),*] -> [$push:ident]) => {
+        fn $generator_fn_name<C: Config>(
+            _: &mut Unstructured,
+            _: &ConfiguredModule<C>,
+            builder: &mut CodeBuilder<C>,
+        ) -> Result<Instruction> {
+            builder.pop_operands(&[$(ValType::$pop),*]);
+            builder.push_operands(&[ValType::$push]);
+            Ok(Instruction::$instruction)
+        }
+    };
+}
+
+macro_rules! simd_extract_lane {
+    ($instruction:ident, $generator_fn_name:ident, $lanes:expr, $ty:ident) => {
+        fn $generator_fn_name<C: Config>(
+            u: &mut Unstructured,
+            _: &ConfiguredModule<C>,
+            builder: &mut CodeBuilder<C>,
+        ) -> Result<Instruction> {
+            builder.pop_operands(&[ValType::V128]);
+            builder.push_operands(&[ValType::$ty]);
+            Ok(Instruction::$instruction {
+                lane: u.int_in_range(0..=$lanes - 1)?,
+            })
+        }
+    };
+}
+
+macro_rules! simd_replace_lane {
+    ($instruction:ident, $generator_fn_name:ident, $lanes:expr, $ty:ident) => {
+        fn $generator_fn_name<C: Config>(
+            u: &mut Unstructured,
+            _: &ConfiguredModule<C>,
+            builder: &mut CodeBuilder<C>,
+        ) -> Result<Instruction> {
+            builder.pop_operands(&[ValType::V128, ValType::$ty]);
+            builder.push_operands(&[ValType::V128]);
+            Ok(Instruction::$instruction {
+                lane: u.int_in_range(0..=$lanes - 1)?,
+            })
+        }
+    };
+}
+
+fn v128_load<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3, 4])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load(memarg))
+}
+
+fn v128_load8x8_s<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load8x8S(memarg))
+}
+
+fn v128_load8x8_u<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load8x8U(memarg))
+}
+
+fn v128_load16x4_s<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load16x4S(memarg))
+}
+
+fn v128_load16x4_u<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load16x4U(memarg))
+}
+
+fn v128_load32x2_s<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load32x2S(memarg))
+}
+
+fn v128_load32x2_u<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load32x2U(memarg))
+}
+
+fn v128_load8_splat<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load8Splat(memarg))
+}
+
+fn v128_load16_splat<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load16Splat(memarg))
+}
+
+fn v128_load32_splat<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load32Splat(memarg))
+}
+
+fn v128_load64_splat<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load64Splat(memarg))
+}
+
+fn v128_store<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1, 2, 3, 4])?;
+    Ok(Instruction::V128Store(memarg))
+}
+
+fn v128_const<C: Config>(
+    u: &mut Unstructured,
+    _: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Const(u.arbitrary()?))
+}
+
+fn i8x16_shuffle<C: Config>(
+    u: &mut Unstructured,
+    _: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    builder.pop_operands(&[ValType::V128, ValType::V128]);
+    builder.push_operands(&[ValType::V128]);
+    let mut lanes = [0; 16];
+    for lane in &mut lanes {
+        *lane = u.int_in_range(0..=31)?;
+    }
+    Ok(Instruction::I8x16Shuffle { lanes })
+}
+
+fn v128_load8_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load8Lane {
+        memarg,
+        lane: u.int_in_range(0..=15)?,
+    })
+}
+
+fn v128_load16_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load16Lane {
+        memarg,
+        lane: u.int_in_range(0..=7)?,
+    })
+}
+
+fn v128_load32_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1, 2])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load32Lane {
+        memarg,
+        lane: u.int_in_range(0..=3)?,
+    })
+}
+
+fn v128_load64_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load64Lane {
+        memarg,
+        lane: u.int_in_range(0..=1)?,
+    })
+}
+
+fn v128_store8_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0])?;
+    Ok(Instruction::V128Store8Lane {
+        memarg,
+        lane: u.int_in_range(0..=15)?,
+    })
+}
+
+fn v128_store16_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1])?;
+    Ok(Instruction::V128Store16Lane {
+        memarg,
+        lane: u.int_in_range(0..=7)?,
+    })
+}
+
+fn v128_store32_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1, 2])?;
+    Ok(Instruction::V128Store32Lane {
+        memarg,
+        lane: u.int_in_range(0..=3)?,
+    })
+}
+
+fn v128_store64_lane<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[ValType::V128], &[0, 1, 2, 3])?;
+    Ok(Instruction::V128Store64Lane {
+        memarg,
+        lane: u.int_in_range(0..=1)?,
+    })
+}
+
+fn v128_load32_zero<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load32Zero(memarg))
+}
+
+fn v128_load64_zero<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let memarg = mem_arg(u, module, builder, &[], &[0, 1, 2, 3])?;
+    builder.push_operands(&[ValType::V128]);
+    Ok(Instruction::V128Load64Zero(memarg))
+}
+
+simd_binop!(I8x16Swizzle, i8x16_swizzle);
+simd_splat!(I8x16Splat, i8x16_splat, I32);
+simd_splat!(I16x8Splat, i16x8_splat, I32);
+simd_splat!(I32x4Splat, i32x4_splat, I32);
+simd_splat!(I64x2Splat, i64x2_splat, I64);
+simd_splat!(F32x4Splat, f32x4_splat, F32);
+simd_splat!(F64x2Splat, f64x2_splat, F64);
+simd_extract_lane!(I8x16ExtractLaneS, i8x16_extract_lane_s, 16, I32);
+simd_extract_lane!(I8x16ExtractLaneU, i8x16_extract_lane_u, 16, I32);
+simd_replace_lane!(I8x16ReplaceLane, i8x16_replace_lane, 16, I32);
+simd_extract_lane!(I16x8ExtractLaneS, i16x8_extract_lane_s, 8, I32);
+simd_extract_lane!(I16x8ExtractLaneU, i16x8_extract_lane_u, 8, I32);
+simd_replace_lane!(I16x8ReplaceLane, i16x8_replace_lane, 8, I32);
+simd_extract_lane!(I32x4ExtractLane, i32x4_extract_lane, 4, I32);
+simd_replace_lane!(I32x4ReplaceLane, i32x4_replace_lane, 4, I32);
+simd_extract_lane!(I64x2ExtractLane, i64x2_extract_lane, 2, I64);
+simd_replace_lane!(I64x2ReplaceLane, i64x2_replace_lane, 2, I64);
+simd_extract_lane!(F32x4ExtractLane, f32x4_extract_lane, 4, F32);
+simd_replace_lane!(F32x4ReplaceLane, f32x4_replace_lane, 4, F32);
+simd_extract_lane!(F64x2ExtractLane, f64x2_extract_lane, 2, F64);
+simd_replace_lane!(F64x2ReplaceLane, f64x2_replace_lane, 2, F64);
+simd_binop!(I8x16Eq, i8x16_eq);
+simd_binop!(I8x16Ne, i8x16_ne);
+simd_binop!(I8x16LtS, i8x16_lt_s);
+simd_binop!(I8x16LtU, i8x16_lt_u);
+simd_binop!(I8x16GtS, i8x16_gt_s);
+simd_binop!(I8x16GtU, i8x16_gt_u);
+simd_binop!(I8x16LeS, i8x16_le_s);
+simd_binop!(I8x16LeU, i8x16_le_u);
+simd_binop!(I8x16GeS, i8x16_ge_s);
+simd_binop!(I8x16GeU, i8x16_ge_u);
+simd_binop!(I16x8Eq, i16x8_eq);
+simd_binop!(I16x8Ne, i16x8_ne);
+simd_binop!(I16x8LtS, i16x8_lt_s);
+simd_binop!(I16x8LtU, i16x8_lt_u);
+simd_binop!(I16x8GtS, i16x8_gt_s);
+simd_binop!(I16x8GtU, i16x8_gt_u);
+simd_binop!(I16x8LeS, i16x8_le_s);
+simd_binop!(I16x8LeU, i16x8_le_u);
+simd_binop!(I16x8GeS, i16x8_ge_s);
+simd_binop!(I16x8GeU, i16x8_ge_u);
+simd_binop!(I32x4Eq, i32x4_eq);
+simd_binop!(I32x4Ne, i32x4_ne);
+simd_binop!(I32x4LtS, i32x4_lt_s);
+simd_binop!(I32x4LtU, i32x4_lt_u);
+simd_binop!(I32x4GtS, i32x4_gt_s);
+simd_binop!(I32x4GtU, i32x4_gt_u);
+simd_binop!(I32x4LeS, i32x4_le_s);
+simd_binop!(I32x4LeU, i32x4_le_u);
+simd_binop!(I32x4GeS, i32x4_ge_s);
+simd_binop!(I32x4GeU, i32x4_ge_u);
+simd_binop!(F32x4Eq, f32x4_eq);
+simd_binop!(F32x4Ne, f32x4_ne);
+simd_binop!(F32x4Lt, f32x4_lt);
+simd_binop!(F32x4Gt, f32x4_gt);
+simd_binop!(F32x4Le, f32x4_le);
+simd_binop!(F32x4Ge, f32x4_ge);
+simd_binop!(F64x2Eq, f64x2_eq);
+simd_binop!(F64x2Ne, f64x2_ne);
+simd_binop!(F64x2Lt, f64x2_lt);
+simd_binop!(F64x2Gt, f64x2_gt);
+simd_binop!(F64x2Le, f64x2_le);
+simd_binop!(F64x2Ge, f64x2_ge);
+simd_unop!(V128Not, v128_not);
+simd_binop!(V128And, v128_and);
+simd_binop!(V128AndNot, v128_and_not);
+simd_binop!(V128Or, v128_or);
+simd_binop!(V128Xor, v128_xor);
+simd_ternop!(V128Bitselect, v128_bitselect);
+simd_unop!(I8x16Abs, i8x16_abs);
+simd_unop!(I8x16Neg, i8x16_neg);
+simd_test!(V128AnyTrue, v128_any_true);
+simd_test!(I8x16AllTrue, i8x16_all_true);
+simd_test!(I8x16Bitmask, i8x16_bitmask);
+simd_binop!(I8x16NarrowI16x8S, i8x16_narrow_i16x8_s);
+simd_binop!(I8x16NarrowI16x8U, i8x16_narrow_i16x8_u);
+simd_shift!(I8x16Shl, i8x16_shl);
+simd_shift!(I8x16ShrS, i8x16_shr_s);
+simd_shift!(I8x16ShrU, i8x16_shr_u);
+simd_binop!(I8x16Add, i8x16_add);
+simd_binop!(I8x16AddSatS, i8x16_add_sat_s);
+simd_binop!(I8x16AddSatU, i8x16_add_sat_u);
+simd_binop!(I8x16Sub, i8x16_sub);
+simd_binop!(I8x16SubSatS, i8x16_sub_sat_s);
+simd_binop!(I8x16SubSatU, i8x16_sub_sat_u);
+simd_binop!(I8x16MinS, i8x16_min_s);
+simd_binop!(I8x16MinU, i8x16_min_u);
+simd_binop!(I8x16MaxS, i8x16_max_s);
+simd_binop!(I8x16MaxU, i8x16_max_u);
+simd_binop!(I8x16RoundingAverageU, i8x16_rounding_average_u);
+simd_unop!(I16x8Abs, i16x8_abs);
+simd_unop!(I16x8Neg, i16x8_neg);
+simd_test!(I16x8AllTrue, i16x8_all_true);
+simd_test!(I16x8Bitmask, i16x8_bitmask);
+simd_binop!(I16x8NarrowI32x4S, i16x8_narrow_i32x4_s);
+simd_binop!(I16x8NarrowI32x4U, i16x8_narrow_i32x4_u);
+simd_unop!(I16x8WidenLowI8x16S, i16x8_widen_low_i8x16_s);
+simd_unop!(I16x8WidenHighI8x16S, i16x8_widen_high_i8x16_s);
+simd_unop!(I16x8WidenLowI8x16U, i16x8_widen_low_i8x16_u);
+simd_unop!(I16x8WidenHighI8x16U, i16x8_widen_high_i8x16_u);
+simd_shift!(I16x8Shl, i16x8_shl);
+simd_shift!(I16x8ShrS, i16x8_shr_s);
+simd_shift!(I16x8ShrU, i16x8_shr_u);
+simd_binop!(I16x8Add, i16x8_add);
+simd_binop!(I16x8AddSatS, i16x8_add_sat_s);
+simd_binop!(I16x8AddSatU, i16x8_add_sat_u);
+simd_binop!(I16x8Sub, i16x8_sub);
+simd_binop!(I16x8SubSatS, i16x8_sub_sat_s);
+simd_binop!(I16x8SubSatU, i16x8_sub_sat_u);
+simd_binop!(I16x8Mul, i16x8_mul);
+simd_binop!(I16x8MinS, i16x8_min_s);
+simd_binop!(I16x8MinU, i16x8_min_u);
+simd_binop!(I16x8MaxS, i16x8_max_s);
+simd_binop!(I16x8MaxU, i16x8_max_u);
+simd_binop!(I16x8ExtMulLowI8x16S, i16x8_ext_mul_low_i8x16_s);
+simd_binop!(I16x8RoundingAverageU, i16x8_rounding_average_u);
+simd_binop!(I16x8Q15MulrSatS, i16x8_q15_mulr_sat_s);
+simd_binop!(I16x8ExtMulHighI8x16S, i16x8_ext_mul_high_i8x16_s);
+simd_binop!(I16x8ExtMulLowI8x16U, i16x8_ext_mul_low_i8x16_u);
+simd_binop!(I16x8ExtMulHighI8x16U, i16x8_ext_mul_high_i8x16_u);
+simd_unop!(I32x4Abs, i32x4_abs);
+simd_unop!(I32x4Neg, i32x4_neg);
+simd_test!(I32x4AllTrue, i32x4_all_true);
+simd_test!(I32x4Bitmask, i32x4_bitmask);
+simd_unop!(I32x4WidenLowI16x8S, i32x4_widen_low_i16x8_s);
+simd_unop!(I32x4WidenHighI16x8S, i32x4_widen_high_i16x8_s);
+simd_unop!(I32x4WidenLowI16x8U, i32x4_widen_low_i16x8_u);
+simd_unop!(I32x4WidenHighI16x8U, i32x4_widen_high_i16x8_u);
+simd_shift!(I32x4Shl, i32x4_shl);
+simd_shift!(I32x4ShrS, i32x4_shr_s);
+simd_shift!(I32x4ShrU, i32x4_shr_u);
+simd_binop!(I32x4Add, i32x4_add);
+simd_binop!(I32x4Sub, i32x4_sub);
+simd_binop!(I32x4Mul, i32x4_mul);
+simd_binop!(I32x4MinS, i32x4_min_s);
+simd_binop!(I32x4MinU, i32x4_min_u);
+simd_binop!(I32x4MaxS, i32x4_max_s);
+simd_binop!(I32x4MaxU, i32x4_max_u);
+simd_binop!(I32x4DotI16x8S, i32x4_dot_i16x8_s);
+simd_binop!(I32x4ExtMulLowI16x8S, i32x4_ext_mul_low_i16x8_s);
+simd_binop!(I32x4ExtMulHighI16x8S, i32x4_ext_mul_high_i16x8_s);
+simd_binop!(I32x4ExtMulLowI16x8U, i32x4_ext_mul_low_i16x8_u);
+simd_binop!(I32x4ExtMulHighI16x8U, i32x4_ext_mul_high_i16x8_u);
+simd_unop!(I64x2Neg, i64x2_neg);
+simd_test!(I64x2Bitmask, i64x2_bitmask);
+simd_unop!(I64x2WidenLowI32x4S, i64x2_widen_low_i32x4_s);
+simd_unop!(I64x2WidenHighI32x4S, i64x2_widen_high_i32x4_s);
+simd_unop!(I64x2WidenLowI32x4U, i64x2_widen_low_i32x4_u);
+simd_unop!(I64x2WidenHighI32x4U, i64x2_widen_high_i32x4_u);
+simd_shift!(I64x2Shl, i64x2_shl);
+simd_shift!(I64x2ShrS, i64x2_shr_s);
+simd_shift!(I64x2ShrU, i64x2_shr_u);
+simd_binop!(I64x2Add, i64x2_add);
+simd_binop!(I64x2Sub, i64x2_sub);
+simd_binop!(I64x2ExtMulLowI32x4S, i64x2_ext_mul_low_i32x4_s);
+simd_binop!(I64x2ExtMulHighI32x4S, i64x2_ext_mul_high_i32x4_s);
+simd_binop!(I64x2Mul, i64x2_mul);
+simd_binop!(I64x2ExtMulLowI32x4U, i64x2_ext_mul_low_i32x4_u);
+simd_binop!(I64x2ExtMulHighI32x4U, i64x2_ext_mul_high_i32x4_u);
+simd_unop!(F32x4Ceil, f32x4_ceil);
+simd_unop!(F32x4Floor, f32x4_floor);
+simd_unop!(F32x4Trunc, f32x4_trunc);
+simd_unop!(F32x4Nearest, f32x4_nearest);
+simd_unop!(F64x2Ceil, f64x2_ceil);
+simd_unop!(F64x2Floor, f64x2_floor);
+simd_unop!(F64x2Trunc, f64x2_trunc);
+simd_unop!(F64x2Nearest, f64x2_nearest);
+simd_unop!(F32x4Abs, f32x4_abs);
+simd_unop!(F32x4Neg, f32x4_neg);
+simd_unop!(F32x4Sqrt, f32x4_sqrt);
+simd_binop!(F32x4Add, f32x4_add);
+simd_binop!(F32x4Sub, f32x4_sub);
+simd_binop!(F32x4Mul, f32x4_mul);
+simd_binop!(F32x4Div, f32x4_div);
+simd_binop!(F32x4Min, f32x4_min);
+simd_binop!(F32x4Max, f32x4_max);
+simd_binop!(F32x4PMin, f32x4_pmin);
+simd_binop!(F32x4PMax, f32x4_pmax);
+simd_unop!(F64x2Abs, f64x2_abs);
+simd_unop!(F64x2Neg, f64x2_neg);
+simd_unop!(F64x2Sqrt, f64x2_sqrt);
+simd_binop!(F64x2Add, f64x2_add);
+simd_binop!(F64x2Sub, f64x2_sub);
+simd_binop!(F64x2Mul, f64x2_mul);
+simd_binop!(F64x2Div, f64x2_div);
+simd_binop!(F64x2Min, f64x2_min);
+simd_binop!(F64x2Max, f64x2_max);
+simd_binop!(F64x2PMin, f64x2_pmin);
+simd_binop!(F64x2PMax, f64x2_pmax);
+simd_unop!(I32x4TruncSatF32x4S, i32x4_trunc_sat_f32x4_s);
+simd_unop!(I32x4TruncSatF32x4U, i32x4_trunc_sat_f32x4_u);
+simd_unop!(F32x4ConvertI32x4S, f32x4_convert_i32x4_s);
+simd_unop!(F32x4ConvertI32x4U, f32x4_convert_i32x4_u);
+
+#[inline]
+fn threads_enabled<C: Config>(module: &ConfiguredModule<C>, _: &mut CodeBuilder<C>) -> bool {
+    module.config.threads_enabled()
+}
+
+#[inline]
+fn threads_have_memory_and_offset<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    module.config.threads_enabled() && have_memory_and_offset(module, builder)
+}
+
+#[inline]
+fn threads_memory_and_operands<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+    types: &[ValType],
+) -> bool {
+    // `types` are the operands above the address.
+    module.config.threads_enabled()
+        && builder.types_on_stack(types)
+        && builder.address_on_stack(types.len())
+}
+
+#[inline]
+fn threads_i32_store_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    threads_memory_and_operands(module, builder, &[ValType::I32])
+}
+
+#[inline]
+fn threads_i64_store_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    threads_memory_and_operands(module, builder, &[ValType::I64])
+}
+
+#[inline]
+fn threads_i32_cmpxchg_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    threads_memory_and_operands(module, builder, &[ValType::I32, ValType::I32])
+}
+
+#[inline]
+fn threads_i64_cmpxchg_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    threads_memory_and_operands(module, builder, &[ValType::I64, ValType::I64])
+}
+
+#[inline]
+fn memory_atomic_wait32_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    !module.config.disallow_traps()
+        && threads_memory_and_operands(module, builder, &[ValType::I32, ValType::I64])
+}
+
+#[inline]
+fn memory_atomic_wait64_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    !module.config.disallow_traps()
+        && threads_memory_and_operands(module, builder, &[ValType::I64, ValType::I64])
+}
+
+fn atomic_fence<C: Config>(
+    _: &mut Unstructured,
+    _: &ConfiguredModule<C>,
+    _: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    Ok(Instruction::AtomicFence)
+}
+
+// Atomic memory accesses must be naturally aligned, so unlike the other memory
+// instructions these always use exactly one alignment. The popped operands are
+// those above the address, whose type depends on the memory chosen.
+macro_rules! atomic_op {
+    ($instruction:ident, $generator_fn_name:ident, $align:expr, [$($pop:ident),*] -> [$($push:ident),*]) => {
+        fn $generator_fn_name<C: Config>(
+            u: &mut Unstructured,
+            module: &ConfiguredModule<C>,
+            builder: &mut CodeBuilder<C>,
+        ) -> Result<Instruction> {
+            let memarg = mem_arg(u, module, builder, &[$(ValType::$pop),*], &[$align])?;
+            builder.push_operands(&[$(ValType::$push),*]);
+            Ok(Instruction::$instruction(memarg))
+        }
+    };
+}
+
+atomic_op!(MemoryAtomicNotify, memory_atomic_notify, 2, [I32] -> [I32]);
+atomic_op!(MemoryAtomicWait32, memory_atomic_wait32, 2, [I32, I64] -> [I32]);
+atomic_op!(MemoryAtomicWait64, memory_atomic_wait64, 3, [I64, I64] -> [I32]);
+atomic_op!(I32AtomicLoad, i32_atomic_load, 2, [] -> [I32]);
+atomic_op!(I64AtomicLoad, i64_atomic_load, 3, [] -> [I64]);
+atomic_op!(I32AtomicLoad8U, i32_atomic_load8_u, 0, [] -> [I32]);
+atomic_op!(I32AtomicLoad16U, i32_atomic_load16_u, 1, [] -> [I32]);
+atomic_op!(I64AtomicLoad8U, i64_atomic_load8_u, 0, [] -> [I64]);
+atomic_op!(I64AtomicLoad16U, i64_atomic_load16_u, 1, [] -> [I64]);
+atomic_op!(I64AtomicLoad32U, i64_atomic_load32_u, 2, [] -> [I64]);
+atomic_op!(I32AtomicStore, i32_atomic_store, 2, [I32] -> []);
+atomic_op!(I64AtomicStore, i64_atomic_store, 3, [I64] -> []);
+atomic_op!(I32AtomicStore8, i32_atomic_store8, 0, [I32] -> []);
+atomic_op!(I32AtomicStore16, i32_atomic_store16, 1, [I32] -> []);
+atomic_op!(I64AtomicStore8, i64_atomic_store8, 0, [I64] -> []);
+atomic_op!(I64AtomicStore16, i64_atomic_store16, 1, [I64] -> []);
+atomic_op!(I64AtomicStore32, i64_atomic_store32, 2, [I64] -> []);
+atomic_op!(I32AtomicRmwAdd, i32_atomic_rmw_add, 2, [I32] -> [I32]);
+atomic_op!(I64AtomicRmwAdd, i64_atomic_rmw_add, 3, [I64] -> [I64]);
+atomic_op!(I32AtomicRmw8AddU, i32_atomic_rmw8_add_u, 0, [I32] -> [I32]);
+atomic_op!(I32AtomicRmw16AddU, i32_atomic_rmw16_add_u, 1, [I32] -> [I32]);
+atomic_op!(I64AtomicRmw8AddU, i64_atomic_rmw8_add_u, 0, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw16AddU, i64_atomic_rmw16_add_u, 1, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw32AddU, i64_atomic_rmw32_add_u, 2, [I64] -> [I64]);
+atomic_op!(I32AtomicRmwSub, i32_atomic_rmw_sub, 2, [I32] -> [I32]);
+atomic_op!(I64AtomicRmwSub, i64_atomic_rmw_sub, 3, [I64] -> [I64]);
+atomic_op!(I32AtomicRmw8SubU, i32_atomic_rmw8_sub_u, 0, [I32] -> [I32]);
+atomic_op!(I32AtomicRmw16SubU, i32_atomic_rmw16_sub_u, 1, [I32] -> [I32]);
+atomic_op!(I64AtomicRmw8SubU, i64_atomic_rmw8_sub_u, 0, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw16SubU, i64_atomic_rmw16_sub_u, 1, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw32SubU, i64_atomic_rmw32_sub_u, 2, [I64] -> [I64]);
+atomic_op!(I32AtomicRmwAnd, i32_atomic_rmw_and, 2, [I32] -> [I32]);
+atomic_op!(I64AtomicRmwAnd, i64_atomic_rmw_and, 3, [I64] -> [I64]);
+atomic_op!(I32AtomicRmw8AndU, i32_atomic_rmw8_and_u, 0, [I32] -> [I32]);
+atomic_op!(I32AtomicRmw16AndU, i32_atomic_rmw16_and_u, 1, [I32] -> [I32]);
+atomic_op!(I64AtomicRmw8AndU, i64_atomic_rmw8_and_u, 0, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw16AndU, i64_atomic_rmw16_and_u, 1, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw32AndU, i64_atomic_rmw32_and_u, 2, [I64] -> [I64]);
+atomic_op!(I32AtomicRmwOr, i32_atomic_rmw_or, 2, [I32] -> [I32]);
+atomic_op!(I64AtomicRmwOr, i64_atomic_rmw_or, 3, [I64] -> [I64]);
+atomic_op!(I32AtomicRmw8OrU, i32_atomic_rmw8_or_u, 0, [I32] -> [I32]);
+atomic_op!(I32AtomicRmw16OrU, i32_atomic_rmw16_or_u, 1, [I32] -> [I32]);
+atomic_op!(I64AtomicRmw8OrU, i64_atomic_rmw8_or_u, 0, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw16OrU, i64_atomic_rmw16_or_u, 1, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw32OrU, i64_atomic_rmw32_or_u, 2, [I64] -> [I64]);
+atomic_op!(I32AtomicRmwXor, i32_atomic_rmw_xor, 2, [I32] -> [I32]);
+atomic_op!(I64AtomicRmwXor, i64_atomic_rmw_xor, 3, [I64] -> [I64]);
+atomic_op!(I32AtomicRmw8XorU, i32_atomic_rmw8_xor_u, 0, [I32] -> [I32]);
+atomic_op!(I32AtomicRmw16XorU, i32_atomic_rmw16_xor_u, 1, [I32] -> [I32]);
+atomic_op!(I64AtomicRmw8XorU, i64_atomic_rmw8_xor_u, 0, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw16XorU, i64_atomic_rmw16_xor_u, 1, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw32XorU, i64_atomic_rmw32_xor_u, 2, [I64] -> [I64]);
+atomic_op!(I32AtomicRmwXchg, i32_atomic_rmw_xchg, 2, [I32] -> [I32]);
+atomic_op!(I64AtomicRmwXchg, i64_atomic_rmw_xchg, 3, [I64] -> [I64]);
+atomic_op!(I32AtomicRmw8XchgU, i32_atomic_rmw8_xchg_u, 0, [I32] -> [I32]);
+atomic_op!(I32AtomicRmw16XchgU, i32_atomic_rmw16_xchg_u, 1, [I32] -> [I32]);
+atomic_op!(I64AtomicRmw8XchgU, i64_atomic_rmw8_xchg_u, 0, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw16XchgU, i64_atomic_rmw16_xchg_u, 1, [I64] -> [I64]);
+atomic_op!(I64AtomicRmw32XchgU, i64_atomic_rmw32_xchg_u, 2, [I64] -> [I64]);
+atomic_op!(I32AtomicRmwCmpxchg, i32_atomic_rmw_cmpxchg, 2, [I32, I32] -> [I32]);
+atomic_op!(I64AtomicRmwCmpxchg, i64_atomic_rmw_cmpxchg, 3, [I64, I64] -> [I64]);
+atomic_op!(I32AtomicRmw8CmpxchgU, i32_atomic_rmw8_cmpxchg_u, 0, [I32, I32] -> [I32]);
+atomic_op!(I32AtomicRmw16CmpxchgU, i32_atomic_rmw16_cmpxchg_u, 1, [I32, I32] -> [I32]);
+atomic_op!(I64AtomicRmw8CmpxchgU, i64_atomic_rmw8_cmpxchg_u, 0, [I64, I64] -> [I64]);
+atomic_op!(I64AtomicRmw16CmpxchgU, i64_atomic_rmw16_cmpxchg_u, 1, [I64, I64] -> [I64]);
+atomic_op!(I64AtomicRmw32CmpxchgU, i64_atomic_rmw32_cmpxchg_u, 2, [I64, I64] -> [I64]);
+
+#[inline]
+fn exceptions_enabled<C: Config>(module: &ConfiguredModule<C>, _: &mut CodeBuilder<C>) -> bool {
+    module.config.exceptions_enabled()
+}
+
+fn r#try<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let block_ty = builder.arbitrary_block_type(u, module)?;
+    let (params, results) = block_ty.params_results(module);
+    let height = builder.allocs.operands.len() - params.len();
+    builder.allocs.controls.push(Control {
+        kind: ControlKind::Try,
+        params,
+        results,
+        height,
+    });
+    Ok(Instruction::Try(block_ty))
+}
+
+/// Whether the innermost control frame is a `try` or `catch` whose results
+/// are exactly what is on the stack, so that a new handler can begin.
+#[inline]
+fn handler_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    if !module.config.exceptions_enabled() {
+        return false;
+    }
+    let control = builder.allocs.controls.last().unwrap();
+    (control.kind == ControlKind::Try || control.kind == ControlKind::Catch)
+        && builder.operands().len() == control.results.len()
+        && builder.types_on_stack(&control.results)
+}
+
+#[inline]
+fn catch_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    !module.events.is_empty() && handler_valid(module, builder)
+}
+
+fn catch<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let control = builder.allocs.controls.pop().unwrap();
+    builder.pop_operands(&control.results);
+    builder.allocs.controls.push(Control {
+        kind: ControlKind::Catch,
+        ..control
+    });
+    let event = u.int_in_range(0..=module.events.len() - 1)?;
+    builder.push_operands(&module.events[event].func_type.params);
+    Ok(Instruction::Catch(event as u32))
+}
+
+#[inline]
+fn catch_all_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    handler_valid(module, builder)
+}
+
+fn catch_all<C: Config>(
+    _: &mut Unstructured,
+    _: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let control = builder.allocs.controls.pop().unwrap();
+    builder.pop_operands(&control.results);
+    builder.allocs.controls.push(Control {
+        kind: ControlKind::CatchAll,
+        ..control
+    });
+    Ok(Instruction::CatchAll)
+}
+
+#[inline]
+fn unwind_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    if !module.config.exceptions_enabled() {
+        return false;
+    }
+    // An `unwind` block never produces results, so the `try` must not either.
+    let control = builder.allocs.controls.last().unwrap();
+    control.kind == ControlKind::Try && control.results.is_empty() && builder.operands().is_empty()
+}
+
+fn unwind<C: Config>(
+    _: &mut Unstructured,
+    _: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let control = builder.allocs.controls.pop().unwrap();
+    builder.allocs.controls.push(Control {
+        kind: ControlKind::Unwind,
+        params: vec![],
+        ..control
+    });
+    Ok(Instruction::Unwind)
+}
+
+#[inline]
+fn throw_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    !module.config.disallow_traps()
+        && module.config.exceptions_enabled()
+        && module
+            .events
+            .iter()
+            .any(|e| builder.types_on_stack(&e.func_type.params))
+}
+
+fn throw<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let candidates = module
+        .events
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| builder.types_on_stack(&e.func_type.params))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let i = *u.choose(&candidates)?;
+    builder.pop_operands(&module.events[i].func_type.params);
+    Ok(Instruction::Throw(i as u32))
+}
+
+#[inline]
+fn rethrow_valid<C: Config>(module: &ConfiguredModule<C>, builder: &mut CodeBuilder<C>) -> bool {
+    !module.config.disallow_traps()
+        && module.config.exceptions_enabled()
+        && builder
+            .allocs
+            .controls
+            .iter()
+            .any(|c| c.kind == ControlKind::Catch || c.kind == ControlKind::CatchAll)
+}
+
+fn rethrow<C: Config>(
+    u: &mut Unstructured,
+    _: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let candidates = builder
+        .allocs
+        .controls
+        .iter()
+        .rev()
+        .enumerate()
+        .filter(|(_, c)| c.kind == ControlKind::Catch || c.kind == ControlKind::CatchAll)
+        .map(|(depth, _)| depth as u32)
+        .collect::<Vec<_>>();
+    Ok(Instruction::Rethrow(*u.choose(&candidates)?))
+}
+
+#[inline]
+fn return_call_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    module.config.tail_call_enabled()
+        && module.funcs().any(|(_, ty)| {
+            ty.results == builder.func_ty.results && builder.types_on_stack(&ty.params)
+        })
+}
+
+fn return_call<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    let candidates = module
+        .funcs()
+        .filter(|(_, ty)| {
+            ty.results == builder.func_ty.results && builder.types_on_stack(&ty.params)
+        })
+        .collect::<Vec<_>>();
+    let (func_idx, ty) = *u.choose(&candidates)?;
+    builder.pop_operands(&ty.params);
+    Ok(Instruction::ReturnCall(func_idx))
+}
+
+#[inline]
+fn return_call_indirect_valid<C: Config>(
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> bool {
+    if !module.config.tail_call_enabled()
+        || builder.allocs.funcref_tables.is_empty()
+        || !builder.type_on_stack(ValType::I32)
+    {
+        return false;
+    }
+    if module.config.disallow_traps() {
+        let results = builder.func_ty.results.clone();
+        return !indirect_callees(module, builder, Some(&results)).is_empty();
+    }
+    let ty = builder.allocs.operands.pop().unwrap();
+    let is_valid = module
+        .func_types()
+        .any(|(_, ty)| ty.results == builder.func_ty.results && builder.types_on_stack(&ty.params));
+    builder.allocs.operands.push(ty);
+    is_valid
+}
+
+fn return_call_indirect<C: Config>(
+    u: &mut Unstructured,
+    module: &ConfiguredModule<C>,
+    builder: &mut CodeBuilder<C>,
+) -> Result<Instruction> {
+    if module.config.disallow_traps() {
+        let results = builder.func_ty.results.clone();
+        let (table, ty) = guarded_indirect_callee(u, module, builder, Some(&results))?;
+        builder.pop_operands(&module.func_type(ty).params);
+        return Ok(Instruction::ReturnCallIndirect { ty, table });
+    }
+    builder.pop_operands(&[ValType::I32]);
+
+    let choices = module
+        .func_types()
+        .filter(|(_, ty)| {
+            ty.results == builder.func_ty.results && builder.types_on_stack(&ty.params)
+        })
+        .collect::<Vec<_>>();
+    let (type_idx, ty) = u.choose(&choices)?;
+    builder.pop_operands(&ty.params);
+    let table = *u.choose(&builder.allocs.funcref_tables)?;
+    Ok(Instruction::ReturnCallIndirect {
+        ty: *type_idx,
+        table,
+    })
+}
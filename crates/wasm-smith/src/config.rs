@@ -1,6 +1,7 @@
 //! Configuring the shape of generated Wasm modules.
 
 use arbitrary::{Arbitrary, Result, Unstructured};
+use wasm_encoder::{GlobalType, MemoryType, TableType, ValType};
 
 /// Configuration for a generated module.
 ///
@@ -133,7 +134,8 @@ pub trait Config: Arbitrary + Default + Clone {
     /// imported memories.
     ///
     /// Note that more than one memory is in the realm of the multi-memory wasm
-    /// proposal.
+    /// proposal, so this is capped at 1 unless
+    /// [`Config::multi_memory_enabled`] returns `true`.
     fn max_memories(&self) -> usize {
         1
     }
@@ -155,10 +157,21 @@ pub trait Config: Arbitrary + Default + Clone {
 
     /// The maximum, in 64k Wasm pages, of any memory's initial or maximum size.
     /// Defaults to 2^16 = 65536 (the maximum possible for 32-bit Wasm).
+    ///
+    /// This also applies to 64-bit memories.
     fn max_memory_pages(&self) -> u32 {
         65536
     }
 
+    /// The maximum number of events to use. Defaults to 100. This includes
+    /// imported events.
+    ///
+    /// Note that this is irrelevant unless the exception handling proposal is
+    /// enabled.
+    fn max_events(&self) -> usize {
+        100
+    }
+
     /// Whether every Wasm memory must have a maximum size specified. Defaults
     /// to `false`.
     fn memory_max_size_required(&self) -> bool {
@@ -239,11 +252,183 @@ pub trait Config: Arbitrary + Default + Clone {
         false
     }
 
+    /// Determines whether the SIMD proposal is enabled for generating
+    /// instructions and `v128` values. Defaults to `false`.
+    fn simd_enabled(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the threads proposal is enabled for generating
+    /// shared memories and atomic instructions. Defaults to `false`.
+    ///
+    /// Shared memories always have a maximum size, regardless of
+    /// [`Config::memory_max_size_required`].
+    fn threads_enabled(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the memory64 proposal is enabled for generating
+    /// 64-bit memories, which are addressed with `i64` values. Defaults to
+    /// `false`.
+    fn memory64_enabled(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the multi-memory proposal is enabled, allowing up
+    /// to [`Config::max_memories`] memories with each memory instruction and
+    /// active data segment targeting any of them. Defaults to `false`.
+    fn multi_memory_enabled(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the exception handling proposal is enabled for
+    /// generating events and `try`/`catch`/`throw`/`rethrow`/`unwind`
+    /// instructions. Defaults to `false`.
+    fn exceptions_enabled(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the tail call proposal is enabled for generating
+    /// `return_call` and `return_call_indirect` instructions. Defaults to
+    /// `false`.
+    fn tail_call_enabled(&self) -> bool {
+        false
+    }
+
+    /// Determines whether integer numeric instructions, such as `i32.const`,
+    /// `i64.add` or `i32.eqz`, are generated. Defaults to `true`.
+    fn int_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether floating-point numeric instructions, such as
+    /// `f32.const`, `f64.mul` or `f32.lt`, are generated. Defaults to `true`.
+    ///
+    /// These are never generated when [`Config::deterministic_only`] is
+    /// enabled.
+    fn float_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether conversion instructions, such as `i32.wrap_i64`,
+    /// `f64.convert_i32_s` or `i32.extend8_s`, are generated. Defaults to
+    /// `true`.
+    fn conversion_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether memory instructions, i.e. loads, stores (including
+    /// SIMD and atomic ones), `memory.size` and `memory.grow`, are generated.
+    /// Defaults to `true`.
+    fn memory_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether control flow instructions, such as `block`, `if`,
+    /// `br_table`, `return` or `try`, are generated. Defaults to `true`.
+    ///
+    /// `nop` is always available.
+    fn control_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether `call`, `call_indirect`, `return_call` and
+    /// `return_call_indirect` instructions are generated. Defaults to `true`.
+    fn call_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether table instructions, such as `table.get`,
+    /// `table.grow` or `elem.drop`, are generated. Defaults to `true`.
+    fn table_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether `ref.null`, `ref.func` and `ref.is_null`
+    /// instructions are generated. Defaults to `true`.
+    fn reference_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether `memory.init`, `data.drop`, `memory.copy` and
+    /// `memory.fill` instructions are generated. Defaults to `true`.
+    fn bulk_memory_instructions_enabled(&self) -> bool {
+        true
+    }
+
+    /// Determines whether only deterministic modules are generated, i.e.
+    /// modules without `f32` or `f64` value types and without any
+    /// floating-point instructions. Such modules validate with
+    /// `WasmFeatures::deterministic_only`. Defaults to `false`.
+    fn deterministic_only(&self) -> bool {
+        false
+    }
+
+    /// Determines whether the generated code avoids trapping at runtime.
+    /// Defaults to `false`.
+    ///
+    /// When enabled, memory addresses are wrapped into the bounds of the
+    /// memory's minimum size, divisors of `0` and `-1` are replaced with `1`,
+    /// and `call_indirect` only calls table entries known to hold a function
+    /// of the right type. Active data and element segments are kept in bounds,
+    /// and instructions that can't be guarded this way, such as `unreachable`,
+    /// trapping float-to-int conversions, `throw` and bulk memory and table
+    /// operations, are not generated at all.
+    ///
+    /// Calls to imported functions, stack overflow from deep recursion, and
+    /// running out of fuel with
+    /// [`ensure_termination`][crate::ConfiguredModule::ensure_termination]
+    /// may still trap.
+    fn disallow_traps(&self) -> bool {
+        false
+    }
+
+    /// The imports that the host provides, as `(module, name, type)` triples.
+    ///
+    /// When this returns `Some`, the generated module imports only entities
+    /// from this list, each at most once, instead of inventing arbitrary
+    /// import names and types, so that it can be instantiated against the
+    /// host. The code builder calls imported functions like any other
+    /// function. Imports that use a value type or proposal that isn't enabled
+    /// are never chosen, and neither are function imports whose type doesn't
+    /// fit within [`Config::max_types`]. This is ignored when module linking
+    /// is enabled. Defaults to `None`.
+    fn available_imports(&self) -> Option<Vec<(String, String, ImportType)>> {
+        None
+    }
+
+    /// The functions that the generated module must export.
+    ///
+    /// A function with each given signature is always defined and exported
+    /// under the given name, with an arbitrary body like any other defined
+    /// function. These are in addition to [`Config::max_funcs`] and
+    /// [`Config::max_exports`], and their types are in addition to
+    /// [`Config::max_types`]. Defaults to no required exports.
+    fn required_exports(&self) -> Vec<RequiredExport> {
+        Vec::new()
+    }
+
     /// Determines whether a `start` export may be included. Defaults to `true`.
     fn allow_start_export(&self) -> bool {
         true
     }
 
+    /// Determines whether a `name` section is generated. Defaults to `false`.
+    ///
+    /// The name section gives arbitrary names to the outermost module, some
+    /// functions and some of their locals. Names may be empty, need escaping in
+    /// the text format, or be duplicates of each other.
+    fn name_section_enabled(&self) -> bool {
+        false
+    }
+
+    /// Returns the maximal number of custom sections with arbitrary names and
+    /// contents, which are placed between, before or after the known sections.
+    /// Defaults to `0`.
+    fn max_custom_sections(&self) -> usize {
+        0
+    }
+
     /// Returns the maximal size of the `alias` section.
     fn max_aliases(&self) -> usize {
         1_000
@@ -256,6 +441,32 @@ pub trait Config: Arbitrary + Default + Clone {
     }
 }
 
+/// The type of an import provided by the host, see
+/// [`Config::available_imports`].
+#[derive(Clone, Debug)]
+pub enum ImportType {
+    /// A function with the given parameter and result types.
+    Func(Vec<ValType>, Vec<ValType>),
+    /// A global.
+    Global(GlobalType),
+    /// A memory.
+    Memory(MemoryType),
+    /// A table.
+    Table(TableType),
+}
+
+/// A function that the generated module must export, see
+/// [`Config::required_exports`].
+#[derive(Clone, Debug)]
+pub struct RequiredExport {
+    /// The name of the export.
+    pub name: String,
+    /// The parameter types of the function.
+    pub params: Vec<ValType>,
+    /// The result types of the function.
+    pub results: Vec<ValType>,
+}
+
 /// The default configuration.
 #[derive(Arbitrary, Debug, Default, Copy, Clone)]
 pub struct DefaultConfig;
@@ -289,9 +500,29 @@ pub struct SwarmConfig {
     min_uleb_size: u8,
     max_tables: usize,
     max_memory_pages: u32,
+    max_events: usize,
     bulk_memory_enabled: bool,
     reference_types_enabled: bool,
     module_linking_enabled: bool,
+    simd_enabled: bool,
+    threads_enabled: bool,
+    memory64_enabled: bool,
+    multi_memory_enabled: bool,
+    exceptions_enabled: bool,
+    tail_call_enabled: bool,
+    int_instructions_enabled: bool,
+    float_instructions_enabled: bool,
+    conversion_instructions_enabled: bool,
+    memory_instructions_enabled: bool,
+    control_instructions_enabled: bool,
+    call_instructions_enabled: bool,
+    table_instructions_enabled: bool,
+    reference_instructions_enabled: bool,
+    bulk_memory_instructions_enabled: bool,
+    deterministic_only: bool,
+    disallow_traps: bool,
+    name_section_enabled: bool,
+    max_custom_sections: usize,
     max_aliases: usize,
     max_nesting_depth: usize,
 }
@@ -316,10 +547,30 @@ impl Arbitrary for SwarmConfig {
             max_memories: u.int_in_range(0..=100)?,
             max_tables,
             max_memory_pages: u.int_in_range(0..=65536)?,
+            max_events: u.int_in_range(0..=MAX_MAXIMUM)?,
             min_uleb_size: u.int_in_range(0..=5)?,
             bulk_memory_enabled: u.arbitrary()?,
             reference_types_enabled,
             module_linking_enabled: u.arbitrary()?,
+            simd_enabled: u.arbitrary()?,
+            threads_enabled: u.arbitrary()?,
+            memory64_enabled: u.arbitrary()?,
+            multi_memory_enabled: u.arbitrary()?,
+            exceptions_enabled: u.arbitrary()?,
+            tail_call_enabled: u.arbitrary()?,
+            int_instructions_enabled: u.arbitrary()?,
+            float_instructions_enabled: u.arbitrary()?,
+            conversion_instructions_enabled: u.arbitrary()?,
+            memory_instructions_enabled: u.arbitrary()?,
+            control_instructions_enabled: u.arbitrary()?,
+            call_instructions_enabled: u.arbitrary()?,
+            table_instructions_enabled: u.arbitrary()?,
+            reference_instructions_enabled: u.arbitrary()?,
+            bulk_memory_instructions_enabled: u.arbitrary()?,
+            deterministic_only: u.arbitrary()?,
+            disallow_traps: u.arbitrary()?,
+            name_section_enabled: u.arbitrary()?,
+            max_custom_sections: u.int_in_range(0..=10)?,
             max_aliases: u.int_in_range(0..=MAX_MAXIMUM)?,
             max_nesting_depth: u.int_in_range(0..=10)?,
         })
@@ -375,6 +626,10 @@ impl Config for SwarmConfig {
         self.max_memory_pages
     }
 
+    fn max_events(&self) -> usize {
+        self.max_events
+    }
+
     fn min_uleb_size(&self) -> u8 {
         self.min_uleb_size
     }
@@ -391,6 +646,82 @@ impl Config for SwarmConfig {
         self.module_linking_enabled
     }
 
+    fn simd_enabled(&self) -> bool {
+        self.simd_enabled
+    }
+
+    fn threads_enabled(&self) -> bool {
+        self.threads_enabled
+    }
+
+    fn memory64_enabled(&self) -> bool {
+        self.memory64_enabled
+    }
+
+    fn multi_memory_enabled(&self) -> bool {
+        self.multi_memory_enabled
+    }
+
+    fn exceptions_enabled(&self) -> bool {
+        self.exceptions_enabled
+    }
+
+    fn tail_call_enabled(&self) -> bool {
+        self.tail_call_enabled
+    }
+
+    fn int_instructions_enabled(&self) -> bool {
+        self.int_instructions_enabled
+    }
+
+    fn float_instructions_enabled(&self) -> bool {
+        self.float_instructions_enabled
+    }
+
+    fn conversion_instructions_enabled(&self) -> bool {
+        self.conversion_instructions_enabled
+    }
+
+    fn memory_instructions_enabled(&self) -> bool {
+        self.memory_instructions_enabled
+    }
+
+    fn control_instructions_enabled(&self) -> bool {
+        self.control_instructions_enabled
+    }
+
+    fn call_instructions_enabled(&self) -> bool {
+        self.call_instructions_enabled
+    }
+
+    fn table_instructions_enabled(&self) -> bool {
+        self.table_instructions_enabled
+    }
+
+    fn reference_instructions_enabled(&self) -> bool {
+        self.reference_instructions_enabled
+    }
+
+    fn bulk_memory_instructions_enabled(&self) -> bool {
+        self.bulk_memory_instructions_enabled
+    }
+
+    fn deterministic_only(&self) -> bool {
+        self.deterministic_only
+    }
+
+    fn disallow_traps(&self) -> bool {
+        self.disallow_traps
+    }
+
+    fn name_section_enabled(&self) -> bool {
+        self.name_section_enabled
+    }
+
+    fn max_custom_sections(&self) -> usize {
+        self.max_custom_sections
+    }
+
     fn max_aliases(&self) -> usize {
         self.max_aliases
     }
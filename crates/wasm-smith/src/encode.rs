@@ -20,21 +20,71 @@ where
     fn encoded(&self) -> wasm_encoder::Module {
         let mut module = wasm_encoder::Module::new();
 
-        self.encode_initializers(&mut module);
-        self.encode_funcs(&mut module);
-        self.encode_tables(&mut module);
-        self.encode_memories(&mut module);
-        self.encode_globals(&mut module);
-        self.encode_exports(&mut module);
-        self.encode_start(&mut module);
-        self.encode_elems(&mut module);
-        self.encode_data_count(&mut module);
-        self.encode_code(&mut module);
-        self.encode_data(&mut module);
+        // Custom sections are placed before each of these and after the last
+        // one, which gives `CUSTOM_SECTION_PLACES` places in total.
+        let sections: [fn(&Self, &mut wasm_encoder::Module); CUSTOM_SECTION_PLACES - 1] = [
+            Self::encode_initializers,
+            Self::encode_funcs,
+            Self::encode_tables,
+            Self::encode_memories,
+            Self::encode_events,
+            Self::encode_globals,
+            Self::encode_exports,
+            Self::encode_start,
+            Self::encode_elems,
+            Self::encode_data_count,
+            Self::encode_code,
+            Self::encode_data,
+        ];
+        for (place, encode_section) in sections.iter().enumerate() {
+            self.encode_custom_sections(&mut module, place);
+            encode_section(self, &mut module);
+        }
+        self.encode_custom_sections(&mut module, sections.len());
+        self.encode_names(&mut module);
 
         module
     }
 
+    fn encode_custom_sections(&self, module: &mut wasm_encoder::Module, place: usize) {
+        for section in self.custom_sections.iter().filter(|s| s.place == place) {
+            module.section(&wasm_encoder::CustomSection {
+                name: &section.name,
+                data: &section.data,
+            });
+        }
+    }
+
+    fn encode_names(&self, module: &mut wasm_encoder::Module) {
+        let names = match &self.names {
+            Some(names) => names,
+            None => return,
+        };
+        let mut section = wasm_encoder::NameSection::new();
+        if let Some(name) = &names.module {
+            section.module(name);
+        }
+        if !names.funcs.is_empty() {
+            let mut funcs = wasm_encoder::NameMap::new();
+            for (index, name) in &names.funcs {
+                funcs.append(*index, name);
+            }
+            section.functions(&funcs);
+        }
+        if !names.locals.is_empty() {
+            let mut locals = wasm_encoder::IndirectNameMap::new();
+            for (func, names) in &names.locals {
+                let mut map = wasm_encoder::NameMap::new();
+                for (index, name) in names {
+                    map.append(*index, name);
+                }
+                locals.append(*func, &map);
+            }
+            section.locals(&locals);
+        }
+        module.section(&section);
+    }
+
     fn encode_initializers(&self, module: &mut wasm_encoder::Module) {
         for init in self.initial_sections.iter() {
             match init {
@@ -170,6 +220,17 @@ where
         module.section(&mems);
     }
 
+    fn encode_events(&self, module: &mut wasm_encoder::Module) {
+        if self.num_defined_events == 0 {
+            return;
+        }
+        let mut events = wasm_encoder::EventSection::new();
+        for e in self.events[self.events.len() - self.num_defined_events..].iter() {
+            events.event(translate_event_type(e));
+        }
+        module.section(&events);
+    }
+
     fn encode_globals(&self, module: &mut wasm_encoder::Module) {
         if self.globals.is_empty() {
             return;
@@ -305,6 +366,7 @@ fn translate_val_type(ty: ValType) -> wasm_encoder::ValType {
         ValType::I64 => wasm_encoder::ValType::I64,
         ValType::F32 => wasm_encoder::ValType::F32,
         ValType::F64 => wasm_encoder::ValType::F64,
+        ValType::V128 => wasm_encoder::ValType::V128,
         ValType::FuncRef => wasm_encoder::ValType::FuncRef,
         ValType::ExternRef => wasm_encoder::ValType::ExternRef,
     }
@@ -318,6 +380,7 @@ fn translate_entity_type(ty: &EntityType) -> wasm_encoder::EntityType {
         EntityType::Table(ty) => translate_table_type(ty).into(),
         EntityType::Memory(m) => translate_memory_type(m).into(),
         EntityType::Global(g) => translate_global_type(g).into(),
+        EntityType::Event(e) => translate_event_type(e).into(),
     }
 }
 
@@ -338,6 +401,8 @@ fn translate_table_type(ty: &TableType) -> wasm_encoder::TableType {
 fn translate_memory_type(ty: &MemoryType) -> wasm_encoder::MemoryType {
     wasm_encoder::MemoryType {
         limits: translate_limits(&ty.limits),
+        shared: ty.shared,
+        memory64: ty.memory64,
     }
 }
 
@@ -348,6 +413,12 @@ fn translate_global_type(ty: &GlobalType) -> wasm_encoder::GlobalType {
     }
 }
 
+fn translate_event_type(ty: &EventType) -> wasm_encoder::EventType {
+    wasm_encoder::EventType {
+        type_index: ty.func_type_idx,
+    }
+}
+
 fn translate_block_type(ty: BlockType) -> wasm_encoder::BlockType {
     match ty {
         BlockType::Empty => wasm_encoder::BlockType::Empty,
@@ -370,6 +441,7 @@ fn translate_item_kind(kind: &ItemKind) -> wasm_encoder::ItemKind {
         ItemKind::Table => wasm_encoder::ItemKind::Table,
         ItemKind::Memory => wasm_encoder::ItemKind::Memory,
         ItemKind::Global => wasm_encoder::ItemKind::Global,
+        ItemKind::Event => wasm_encoder::ItemKind::Event,
         ItemKind::Instance => wasm_encoder::ItemKind::Instance,
         ItemKind::Module => wasm_encoder::ItemKind::Module,
     }
@@ -381,6 +453,7 @@ fn translate_export(export: &Export) -> wasm_encoder::Export {
         Export::Table(idx) => wasm_encoder::Export::Table(*idx),
         Export::Memory(idx) => wasm_encoder::Export::Memory(*idx),
         Export::Global(idx) => wasm_encoder::Export::Global(*idx),
+        Export::Event(idx) => wasm_encoder::Export::Event(*idx),
         Export::Instance(idx) => wasm_encoder::Export::Instance(*idx),
         Export::Module(idx) => wasm_encoder::Export::Module(*idx),
     }
@@ -404,6 +477,20 @@ fn translate_instruction(inst: &Instruction) -> wasm_encoder::Instruction {
         Call(x) => wasm_encoder::Instruction::Call(x),
         CallIndirect { ty, table } => wasm_encoder::Instruction::CallIndirect { ty, table },
 
+        // Exception handling instructions.
+        Try(bt) => wasm_encoder::Instruction::Try(translate_block_type(bt)),
+        Catch(x) => wasm_encoder::Instruction::Catch(x),
+        CatchAll => wasm_encoder::Instruction::CatchAll,
+        Throw(x) => wasm_encoder::Instruction::Throw(x),
+        Rethrow(x) => wasm_encoder::Instruction::Rethrow(x),
+        Unwind => wasm_encoder::Instruction::Unwind,
+
+        // Tail call instructions.
+        ReturnCall(x) => wasm_encoder::Instruction::ReturnCall(x),
+        ReturnCallIndirect { ty, table } => {
+            wasm_encoder::Instruction::ReturnCallIndirect { ty, table }
+        }
+
         // Parametric instructions.
         Drop => wasm_encoder::Instruction::Drop,
         Select => wasm_encoder::Instruction::Select,
@@ -599,5 +686,371 @@ fn translate_instruction(inst: &Instruction) -> wasm_encoder::Instruction {
         TableGrow { table } => wasm_encoder::Instruction::TableGrow { table },
         TableSize { table } => wasm_encoder::Instruction::TableSize { table },
         TableCopy { src, dst } => wasm_encoder::Instruction::TableCopy { src, dst },
+
+        // SIMD instructions.
+        V128Load(m) => wasm_encoder::Instruction::V128Load(translate_mem_arg(m)),
+        V128Load8x8S(m) => wasm_encoder::Instruction::V128Load8x8S(translate_mem_arg(m)),
+        V128Load8x8U(m) => wasm_encoder::Instruction::V128Load8x8U(translate_mem_arg(m)),
+        V128Load16x4S(m) => wasm_encoder::Instruction::V128Load16x4S(translate_mem_arg(m)),
+        V128Load16x4U(m) => wasm_encoder::Instruction::V128Load16x4U(translate_mem_arg(m)),
+        V128Load32x2S(m) => wasm_encoder::Instruction::V128Load32x2S(translate_mem_arg(m)),
+        V128Load32x2U(m) => wasm_encoder::Instruction::V128Load32x2U(translate_mem_arg(m)),
+        V128Load8Splat(m) => wasm_encoder::Instruction::V128Load8Splat(translate_mem_arg(m)),
+        V128Load16Splat(m) => wasm_encoder::Instruction::V128Load16Splat(translate_mem_arg(m)),
+        V128Load32Splat(m) => wasm_encoder::Instruction::V128Load32Splat(translate_mem_arg(m)),
+        V128Load64Splat(m) => wasm_encoder::Instruction::V128Load64Splat(translate_mem_arg(m)),
+        V128Store(m) => wasm_encoder::Instruction::V128Store(translate_mem_arg(m)),
+        V128Const(x) => wasm_encoder::Instruction::V128Const(x),
+        I8x16Shuffle { lanes } => wasm_encoder::Instruction::I8x16Shuffle { lanes },
+        I8x16Swizzle => wasm_encoder::Instruction::I8x16Swizzle,
+        I8x16Splat => wasm_encoder::Instruction::I8x16Splat,
+        I16x8Splat => wasm_encoder::Instruction::I16x8Splat,
+        I32x4Splat => wasm_encoder::Instruction::I32x4Splat,
+        I64x2Splat => wasm_encoder::Instruction::I64x2Splat,
+        F32x4Splat => wasm_encoder::Instruction::F32x4Splat,
+        F64x2Splat => wasm_encoder::Instruction::F64x2Splat,
+        I8x16ExtractLaneS { lane } => wasm_encoder::Instruction::I8x16ExtractLaneS { lane },
+        I8x16ExtractLaneU { lane } => wasm_encoder::Instruction::I8x16ExtractLaneU { lane },
+        I8x16ReplaceLane { lane } => wasm_encoder::Instruction::I8x16ReplaceLane { lane },
+        I16x8ExtractLaneS { lane } => wasm_encoder::Instruction::I16x8ExtractLaneS { lane },
+        I16x8ExtractLaneU { lane } => wasm_encoder::Instruction::I16x8ExtractLaneU { lane },
+        I16x8ReplaceLane { lane } => wasm_encoder::Instruction::I16x8ReplaceLane { lane },
+        I32x4ExtractLane { lane } => wasm_encoder::Instruction::I32x4ExtractLane { lane },
+        I32x4ReplaceLane { lane } => wasm_encoder::Instruction::I32x4ReplaceLane { lane },
+        I64x2ExtractLane { lane } => wasm_encoder::Instruction::I64x2ExtractLane { lane },
+        I64x2ReplaceLane { lane } => wasm_encoder::Instruction::I64x2ReplaceLane { lane },
+        F32x4ExtractLane { lane } => wasm_encoder::Instruction::F32x4ExtractLane { lane },
+        F32x4ReplaceLane { lane } => wasm_encoder::Instruction::F32x4ReplaceLane { lane },
+        F64x2ExtractLane { lane } => wasm_encoder::Instruction::F64x2ExtractLane { lane },
+        F64x2ReplaceLane { lane } => wasm_encoder::Instruction::F64x2ReplaceLane { lane },
+        I8x16Eq => wasm_encoder::Instruction::I8x16Eq,
+        I8x16Ne => wasm_encoder::Instruction::I8x16Ne,
+        I8x16LtS => wasm_encoder::Instruction::I8x16LtS,
+        I8x16LtU => wasm_encoder::Instruction::I8x16LtU,
+        I8x16GtS => wasm_encoder::Instruction::I8x16GtS,
+        I8x16GtU => wasm_encoder::Instruction::I8x16GtU,
+        I8x16LeS => wasm_encoder::Instruction::I8x16LeS,
+        I8x16LeU => wasm_encoder::Instruction::I8x16LeU,
+        I8x16GeS => wasm_encoder::Instruction::I8x16GeS,
+        I8x16GeU => wasm_encoder::Instruction::I8x16GeU,
+        I16x8Eq => wasm_encoder::Instruction::I16x8Eq,
+        I16x8Ne => wasm_encoder::Instruction::I16x8Ne,
+        I16x8LtS => wasm_encoder::Instruction::I16x8LtS,
+        I16x8LtU => wasm_encoder::Instruction::I16x8LtU,
+        I16x8GtS => wasm_encoder::Instruction::I16x8GtS,
+        I16x8GtU => wasm_encoder::Instruction::I16x8GtU,
+        I16x8LeS => wasm_encoder::Instruction::I16x8LeS,
+        I16x8LeU => wasm_encoder::Instruction::I16x8LeU,
+        I16x8GeS => wasm_encoder::Instruction::I16x8GeS,
+        I16x8GeU => wasm_encoder::Instruction::I16x8GeU,
+        I32x4Eq => wasm_encoder::Instruction::I32x4Eq,
+        I32x4Ne => wasm_encoder::Instruction::I32x4Ne,
+        I32x4LtS => wasm_encoder::Instruction::I32x4LtS,
+        I32x4LtU => wasm_encoder::Instruction::I32x4LtU,
+        I32x4GtS => wasm_encoder::Instruction::I32x4GtS,
+        I32x4GtU => wasm_encoder::Instruction::I32x4GtU,
+        I32x4LeS => wasm_encoder::Instruction::I32x4LeS,
+        I32x4LeU => wasm_encoder::Instruction::I32x4LeU,
+        I32x4GeS => wasm_encoder::Instruction::I32x4GeS,
+        I32x4GeU => wasm_encoder::Instruction::I32x4GeU,
+        F32x4Eq => wasm_encoder::Instruction::F32x4Eq,
+        F32x4Ne => wasm_encoder::Instruction::F32x4Ne,
+        F32x4Lt => wasm_encoder::Instruction::F32x4Lt,
+        F32x4Gt => wasm_encoder::Instruction::F32x4Gt,
+        F32x4Le => wasm_encoder::Instruction::F32x4Le,
+        F32x4Ge => wasm_encoder::Instruction::F32x4Ge,
+        F64x2Eq => wasm_encoder::Instruction::F64x2Eq,
+        F64x2Ne => wasm_encoder::Instruction::F64x2Ne,
+        F64x2Lt => wasm_encoder::Instruction::F64x2Lt,
+        F64x2Gt => wasm_encoder::Instruction::F64x2Gt,
+        F64x2Le => wasm_encoder::Instruction::F64x2Le,
+        F64x2Ge => wasm_encoder::Instruction::F64x2Ge,
+        V128Not => wasm_encoder::Instruction::V128Not,
+        V128And => wasm_encoder::Instruction::V128And,
+        V128AndNot => wasm_encoder::Instruction::V128AndNot,
+        V128Or => wasm_encoder::Instruction::V128Or,
+        V128Xor => wasm_encoder::Instruction::V128Xor,
+        V128Bitselect => wasm_encoder::Instruction::V128Bitselect,
+        V128Load8Lane { memarg, lane } => wasm_encoder::Instruction::V128Load8Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Load16Lane { memarg, lane } => wasm_encoder::Instruction::V128Load16Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Load32Lane { memarg, lane } => wasm_encoder::Instruction::V128Load32Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Load64Lane { memarg, lane } => wasm_encoder::Instruction::V128Load64Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Store8Lane { memarg, lane } => wasm_encoder::Instruction::V128Store8Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Store16Lane { memarg, lane } => wasm_encoder::Instruction::V128Store16Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Store32Lane { memarg, lane } => wasm_encoder::Instruction::V128Store32Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        V128Store64Lane { memarg, lane } => wasm_encoder::Instruction::V128Store64Lane {
+            memarg: translate_mem_arg(memarg),
+            lane,
+        },
+        I8x16Abs => wasm_encoder::Instruction::I8x16Abs,
+        I8x16Neg => wasm_encoder::Instruction::I8x16Neg,
+        V128AnyTrue => wasm_encoder::Instruction::V128AnyTrue,
+        I8x16AllTrue => wasm_encoder::Instruction::I8x16AllTrue,
+        I8x16Bitmask => wasm_encoder::Instruction::I8x16Bitmask,
+        I8x16NarrowI16x8S => wasm_encoder::Instruction::I8x16NarrowI16x8S,
+        I8x16NarrowI16x8U => wasm_encoder::Instruction::I8x16NarrowI16x8U,
+        I8x16Shl => wasm_encoder::Instruction::I8x16Shl,
+        I8x16ShrS => wasm_encoder::Instruction::I8x16ShrS,
+        I8x16ShrU => wasm_encoder::Instruction::I8x16ShrU,
+        I8x16Add => wasm_encoder::Instruction::I8x16Add,
+        I8x16AddSatS => wasm_encoder::Instruction::I8x16AddSatS,
+        I8x16AddSatU => wasm_encoder::Instruction::I8x16AddSatU,
+        I8x16Sub => wasm_encoder::Instruction::I8x16Sub,
+        I8x16SubSatS => wasm_encoder::Instruction::I8x16SubSatS,
+        I8x16SubSatU => wasm_encoder::Instruction::I8x16SubSatU,
+        I8x16MinS => wasm_encoder::Instruction::I8x16MinS,
+        I8x16MinU => wasm_encoder::Instruction::I8x16MinU,
+        I8x16MaxS => wasm_encoder::Instruction::I8x16MaxS,
+        I8x16MaxU => wasm_encoder::Instruction::I8x16MaxU,
+        I8x16RoundingAverageU => wasm_encoder::Instruction::I8x16RoundingAverageU,
+        I16x8Abs => wasm_encoder::Instruction::I16x8Abs,
+        I16x8Neg => wasm_encoder::Instruction::I16x8Neg,
+        I16x8AllTrue => wasm_encoder::Instruction::I16x8AllTrue,
+        I16x8Bitmask => wasm_encoder::Instruction::I16x8Bitmask,
+        I16x8NarrowI32x4S => wasm_encoder::Instruction::I16x8NarrowI32x4S,
+        I16x8NarrowI32x4U => wasm_encoder::Instruction::I16x8NarrowI32x4U,
+        I16x8WidenLowI8x16S => wasm_encoder::Instruction::I16x8WidenLowI8x16S,
+        I16x8WidenHighI8x16S => wasm_encoder::Instruction::I16x8WidenHighI8x16S,
+        I16x8WidenLowI8x16U => wasm_encoder::Instruction::I16x8WidenLowI8x16U,
+        I16x8WidenHighI8x16U => wasm_encoder::Instruction::I16x8WidenHighI8x16U,
+        I16x8Shl => wasm_encoder::Instruction::I16x8Shl,
+        I16x8ShrS => wasm_encoder::Instruction::I16x8ShrS,
+        I16x8ShrU => wasm_encoder::Instruction::I16x8ShrU,
+        I16x8Add => wasm_encoder::Instruction::I16x8Add,
+        I16x8AddSatS => wasm_encoder::Instruction::I16x8AddSatS,
+        I16x8AddSatU => wasm_encoder::Instruction::I16x8AddSatU,
+        I16x8Sub => wasm_encoder::Instruction::I16x8Sub,
+        I16x8SubSatS => wasm_encoder::Instruction::I16x8SubSatS,
+        I16x8SubSatU => wasm_encoder::Instruction::I16x8SubSatU,
+        I16x8Mul => wasm_encoder::Instruction::I16x8Mul,
+        I16x8MinS => wasm_encoder::Instruction::I16x8MinS,
+        I16x8MinU => wasm_encoder::Instruction::I16x8MinU,
+        I16x8MaxS => wasm_encoder::Instruction::I16x8MaxS,
+        I16x8MaxU => wasm_encoder::Instruction::I16x8MaxU,
+        I16x8ExtMulLowI8x16S => wasm_encoder::Instruction::I16x8ExtMulLowI8x16S,
+        I16x8RoundingAverageU => wasm_encoder::Instruction::I16x8RoundingAverageU,
+        I16x8Q15MulrSatS => wasm_encoder::Instruction::I16x8Q15MulrSatS,
+        I16x8ExtMulHighI8x16S => wasm_encoder::Instruction::I16x8ExtMulHighI8x16S,
+        I16x8ExtMulLowI8x16U => wasm_encoder::Instruction::I16x8ExtMulLowI8x16U,
+        I16x8ExtMulHighI8x16U => wasm_encoder::Instruction::I16x8ExtMulHighI8x16U,
+        I32x4Abs => wasm_encoder::Instruction::I32x4Abs,
+        I32x4Neg => wasm_encoder::Instruction::I32x4Neg,
+        I32x4AllTrue => wasm_encoder::Instruction::I32x4AllTrue,
+        I32x4Bitmask => wasm_encoder::Instruction::I32x4Bitmask,
+        I32x4WidenLowI16x8S => wasm_encoder::Instruction::I32x4WidenLowI16x8S,
+        I32x4WidenHighI16x8S => wasm_encoder::Instruction::I32x4WidenHighI16x8S,
+        I32x4WidenLowI16x8U => wasm_encoder::Instruction::I32x4WidenLowI16x8U,
+        I32x4WidenHighI16x8U => wasm_encoder::Instruction::I32x4WidenHighI16x8U,
+        I32x4Shl => wasm_encoder::Instruction::I32x4Shl,
+        I32x4ShrS => wasm_encoder::Instruction::I32x4ShrS,
+        I32x4ShrU => wasm_encoder::Instruction::I32x4ShrU,
+        I32x4Add => wasm_encoder::Instruction::I32x4Add,
+        I32x4Sub => wasm_encoder::Instruction::I32x4Sub,
+        I32x4Mul => wasm_encoder::Instruction::I32x4Mul,
+        I32x4MinS => wasm_encoder::Instruction::I32x4MinS,
+        I32x4MinU => wasm_encoder::Instruction::I32x4MinU,
+        I32x4MaxS => wasm_encoder::Instruction::I32x4MaxS,
+        I32x4MaxU => wasm_encoder::Instruction::I32x4MaxU,
+        I32x4DotI16x8S => wasm_encoder::Instruction::I32x4DotI16x8S,
+        I32x4ExtMulLowI16x8S => wasm_encoder::Instruction::I32x4ExtMulLowI16x8S,
+        I32x4ExtMulHighI16x8S => wasm_encoder::Instruction::I32x4ExtMulHighI16x8S,
+        I32x4ExtMulLowI16x8U => wasm_encoder::Instruction::I32x4ExtMulLowI16x8U,
+        I32x4ExtMulHighI16x8U => wasm_encoder::Instruction::I32x4ExtMulHighI16x8U,
+        I64x2Neg => wasm_encoder::Instruction::I64x2Neg,
+        I64x2Bitmask => wasm_encoder::Instruction::I64x2Bitmask,
+        I64x2WidenLowI32x4S => wasm_encoder::Instruction::I64x2WidenLowI32x4S,
+        I64x2WidenHighI32x4S => wasm_encoder::Instruction::I64x2WidenHighI32x4S,
+        I64x2WidenLowI32x4U => wasm_encoder::Instruction::I64x2WidenLowI32x4U,
+        I64x2WidenHighI32x4U => wasm_encoder::Instruction::I64x2WidenHighI32x4U,
+        I64x2Shl => wasm_encoder::Instruction::I64x2Shl,
+        I64x2ShrS => wasm_encoder::Instruction::I64x2ShrS,
+        I64x2ShrU => wasm_encoder::Instruction::I64x2ShrU,
+        I64x2Add => wasm_encoder::Instruction::I64x2Add,
+        I64x2Sub => wasm_encoder::Instruction::I64x2Sub,
+        I64x2ExtMulLowI32x4S => wasm_encoder::Instruction::I64x2ExtMulLowI32x4S,
+        I64x2ExtMulHighI32x4S => wasm_encoder::Instruction::I64x2ExtMulHighI32x4S,
+        I64x2Mul => wasm_encoder::Instruction::I64x2Mul,
+        I64x2ExtMulLowI32x4U => wasm_encoder::Instruction::I64x2ExtMulLowI32x4U,
+        I64x2ExtMulHighI32x4U => wasm_encoder::Instruction::I64x2ExtMulHighI32x4U,
+        F32x4Ceil => wasm_encoder::Instruction::F32x4Ceil,
+        F32x4Floor => wasm_encoder::Instruction::F32x4Floor,
+        F32x4Trunc => wasm_encoder::Instruction::F32x4Trunc,
+        F32x4Nearest => wasm_encoder::Instruction::F32x4Nearest,
+        F64x2Ceil => wasm_encoder::Instruction::F64x2Ceil,
+        F64x2Floor => wasm_encoder::Instruction::F64x2Floor,
+        F64x2Trunc => wasm_encoder::Instruction::F64x2Trunc,
+        F64x2Nearest => wasm_encoder::Instruction::F64x2Nearest,
+        F32x4Abs => wasm_encoder::Instruction::F32x4Abs,
+        F32x4Neg => wasm_encoder::Instruction::F32x4Neg,
+        F32x4Sqrt => wasm_encoder::Instruction::F32x4Sqrt,
+        F32x4Add => wasm_encoder::Instruction::F32x4Add,
+        F32x4Sub => wasm_encoder::Instruction::F32x4Sub,
+        F32x4Mul => wasm_encoder::Instruction::F32x4Mul,
+        F32x4Div => wasm_encoder::Instruction::F32x4Div,
+        F32x4Min => wasm_encoder::Instruction::F32x4Min,
+        F32x4Max => wasm_encoder::Instruction::F32x4Max,
+        F32x4PMin => wasm_encoder::Instruction::F32x4PMin,
+        F32x4PMax => wasm_encoder::Instruction::F32x4PMax,
+        F64x2Abs => wasm_encoder::Instruction::F64x2Abs,
+        F64x2Neg => wasm_encoder::Instruction::F64x2Neg,
+        F64x2Sqrt => wasm_encoder::Instruction::F64x2Sqrt,
+        F64x2Add => wasm_encoder::Instruction::F64x2Add,
+        F64x2Sub => wasm_encoder::Instruction::F64x2Sub,
+        F64x2Mul => wasm_encoder::Instruction::F64x2Mul,
+        F64x2Div => wasm_encoder::Instruction::F64x2Div,
+        F64x2Min => wasm_encoder::Instruction::F64x2Min,
+        F64x2Max => wasm_encoder::Instruction::F64x2Max,
+        F64x2PMin => wasm_encoder::Instruction::F64x2PMin,
+        F64x2PMax => wasm_encoder::Instruction::F64x2PMax,
+        I32x4TruncSatF32x4S => wasm_encoder::Instruction::I32x4TruncSatF32x4S,
+        I32x4TruncSatF32x4U => wasm_encoder::Instruction::I32x4TruncSatF32x4U,
+        F32x4ConvertI32x4S => wasm_encoder::Instruction::F32x4ConvertI32x4S,
+        F32x4ConvertI32x4U => wasm_encoder::Instruction::F32x4ConvertI32x4U,
+        V128Load32Zero(m) => wasm_encoder::Instruction::V128Load32Zero(translate_mem_arg(m)),
+        V128Load64Zero(m) => wasm_encoder::Instruction::V128Load64Zero(translate_mem_arg(m)),
+
+        // Atomic instructions (the threads proposal).
+        MemoryAtomicNotify(m) => {
+            wasm_encoder::Instruction::MemoryAtomicNotify(translate_mem_arg(m))
+        }
+        MemoryAtomicWait32(m) => {
+            wasm_encoder::Instruction::MemoryAtomicWait32(translate_mem_arg(m))
+        }
+        MemoryAtomicWait64(m) => {
+            wasm_encoder::Instruction::MemoryAtomicWait64(translate_mem_arg(m))
+        }
+        AtomicFence => wasm_encoder::Instruction::AtomicFence,
+        I32AtomicLoad(m) => wasm_encoder::Instruction::I32AtomicLoad(translate_mem_arg(m)),
+        I64AtomicLoad(m) => wasm_encoder::Instruction::I64AtomicLoad(translate_mem_arg(m)),
+        I32AtomicLoad8U(m) => wasm_encoder::Instruction::I32AtomicLoad8U(translate_mem_arg(m)),
+        I32AtomicLoad16U(m) => wasm_encoder::Instruction::I32AtomicLoad16U(translate_mem_arg(m)),
+        I64AtomicLoad8U(m) => wasm_encoder::Instruction::I64AtomicLoad8U(translate_mem_arg(m)),
+        I64AtomicLoad16U(m) => wasm_encoder::Instruction::I64AtomicLoad16U(translate_mem_arg(m)),
+        I64AtomicLoad32U(m) => wasm_encoder::Instruction::I64AtomicLoad32U(translate_mem_arg(m)),
+        I32AtomicStore(m) => wasm_encoder::Instruction::I32AtomicStore(translate_mem_arg(m)),
+        I64AtomicStore(m) => wasm_encoder::Instruction::I64AtomicStore(translate_mem_arg(m)),
+        I32AtomicStore8(m) => wasm_encoder::Instruction::I32AtomicStore8(translate_mem_arg(m)),
+        I32AtomicStore16(m) => wasm_encoder::Instruction::I32AtomicStore16(translate_mem_arg(m)),
+        I64AtomicStore8(m) => wasm_encoder::Instruction::I64AtomicStore8(translate_mem_arg(m)),
+        I64AtomicStore16(m) => wasm_encoder::Instruction::I64AtomicStore16(translate_mem_arg(m)),
+        I64AtomicStore32(m) => wasm_encoder::Instruction::I64AtomicStore32(translate_mem_arg(m)),
+        I32AtomicRmwAdd(m) => wasm_encoder::Instruction::I32AtomicRmwAdd(translate_mem_arg(m)),
+        I64AtomicRmwAdd(m) => wasm_encoder::Instruction::I64AtomicRmwAdd(translate_mem_arg(m)),
+        I32AtomicRmw8AddU(m) => wasm_encoder::Instruction::I32AtomicRmw8AddU(translate_mem_arg(m)),
+        I32AtomicRmw16AddU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw16AddU(translate_mem_arg(m))
+        }
+        I64AtomicRmw8AddU(m) => wasm_encoder::Instruction::I64AtomicRmw8AddU(translate_mem_arg(m)),
+        I64AtomicRmw16AddU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw16AddU(translate_mem_arg(m))
+        }
+        I64AtomicRmw32AddU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw32AddU(translate_mem_arg(m))
+        }
+        I32AtomicRmwSub(m) => wasm_encoder::Instruction::I32AtomicRmwSub(translate_mem_arg(m)),
+        I64AtomicRmwSub(m) => wasm_encoder::Instruction::I64AtomicRmwSub(translate_mem_arg(m)),
+        I32AtomicRmw8SubU(m) => wasm_encoder::Instruction::I32AtomicRmw8SubU(translate_mem_arg(m)),
+        I32AtomicRmw16SubU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw16SubU(translate_mem_arg(m))
+        }
+        I64AtomicRmw8SubU(m) => wasm_encoder::Instruction::I64AtomicRmw8SubU(translate_mem_arg(m)),
+        I64AtomicRmw16SubU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw16SubU(translate_mem_arg(m))
+        }
+        I64AtomicRmw32SubU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw32SubU(translate_mem_arg(m))
+        }
+        I32AtomicRmwAnd(m) => wasm_encoder::Instruction::I32AtomicRmwAnd(translate_mem_arg(m)),
+        I64AtomicRmwAnd(m) => wasm_encoder::Instruction::I64AtomicRmwAnd(translate_mem_arg(m)),
+        I32AtomicRmw8AndU(m) => wasm_encoder::Instruction::I32AtomicRmw8AndU(translate_mem_arg(m)),
+        I32AtomicRmw16AndU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw16AndU(translate_mem_arg(m))
+        }
+        I64AtomicRmw8AndU(m) => wasm_encoder::Instruction::I64AtomicRmw8AndU(translate_mem_arg(m)),
+        I64AtomicRmw16AndU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw16AndU(translate_mem_arg(m))
+        }
+        I64AtomicRmw32AndU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw32AndU(translate_mem_arg(m))
+        }
+        I32AtomicRmwOr(m) => wasm_encoder::Instruction::I32AtomicRmwOr(translate_mem_arg(m)),
+        I64AtomicRmwOr(m) => wasm_encoder::Instruction::I64AtomicRmwOr(translate_mem_arg(m)),
+        I32AtomicRmw8OrU(m) => wasm_encoder::Instruction::I32AtomicRmw8OrU(translate_mem_arg(m)),
+        I32AtomicRmw16OrU(m) => wasm_encoder::Instruction::I32AtomicRmw16OrU(translate_mem_arg(m)),
+        I64AtomicRmw8OrU(m) => wasm_encoder::Instruction::I64AtomicRmw8OrU(translate_mem_arg(m)),
+        I64AtomicRmw16OrU(m) => wasm_encoder::Instruction::I64AtomicRmw16OrU(translate_mem_arg(m)),
+        I64AtomicRmw32OrU(m) => wasm_encoder::Instruction::I64AtomicRmw32OrU(translate_mem_arg(m)),
+        I32AtomicRmwXor(m) => wasm_encoder::Instruction::I32AtomicRmwXor(translate_mem_arg(m)),
+        I64AtomicRmwXor(m) => wasm_encoder::Instruction::I64AtomicRmwXor(translate_mem_arg(m)),
+        I32AtomicRmw8XorU(m) => wasm_encoder::Instruction::I32AtomicRmw8XorU(translate_mem_arg(m)),
+        I32AtomicRmw16XorU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw16XorU(translate_mem_arg(m))
+        }
+        I64AtomicRmw8XorU(m) => wasm_encoder::Instruction::I64AtomicRmw8XorU(translate_mem_arg(m)),
+        I64AtomicRmw16XorU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw16XorU(translate_mem_arg(m))
+        }
+        I64AtomicRmw32XorU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw32XorU(translate_mem_arg(m))
+        }
+        I32AtomicRmwXchg(m) => wasm_encoder::Instruction::I32AtomicRmwXchg(translate_mem_arg(m)),
+        I64AtomicRmwXchg(m) => wasm_encoder::Instruction::I64AtomicRmwXchg(translate_mem_arg(m)),
+        I32AtomicRmw8XchgU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw8XchgU(translate_mem_arg(m))
+        }
+        I32AtomicRmw16XchgU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw16XchgU(translate_mem_arg(m))
+        }
+        I64AtomicRmw8XchgU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw8XchgU(translate_mem_arg(m))
+        }
+        I64AtomicRmw16XchgU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw16XchgU(translate_mem_arg(m))
+        }
+        I64AtomicRmw32XchgU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw32XchgU(translate_mem_arg(m))
+        }
+        I32AtomicRmwCmpxchg(m) => {
+            wasm_encoder::Instruction::I32AtomicRmwCmpxchg(translate_mem_arg(m))
+        }
+        I64AtomicRmwCmpxchg(m) => {
+            wasm_encoder::Instruction::I64AtomicRmwCmpxchg(translate_mem_arg(m))
+        }
+        I32AtomicRmw8CmpxchgU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw8CmpxchgU(translate_mem_arg(m))
+        }
+        I32AtomicRmw16CmpxchgU(m) => {
+            wasm_encoder::Instruction::I32AtomicRmw16CmpxchgU(translate_mem_arg(m))
+        }
+        I64AtomicRmw8CmpxchgU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw8CmpxchgU(translate_mem_arg(m))
+        }
+        I64AtomicRmw16CmpxchgU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw16CmpxchgU(translate_mem_arg(m))
+        }
+        I64AtomicRmw32CmpxchgU(m) => {
+            wasm_encoder::Instruction::I64AtomicRmw32CmpxchgU(translate_mem_arg(m))
+        }
     }
 }
@@ -0,0 +1,202 @@
+use super::*;
+
+/// The validation rule that an [`InvalidModule`] violates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// An instruction's operands have the wrong types.
+    TypeMismatch,
+    /// An instruction refers to a function, local or global that doesn't
+    /// exist.
+    IndexOutOfBounds,
+    /// A function body is missing the `end` of one of its blocks.
+    MissingEnd,
+    /// An instruction belongs to a proposal that the module's `Config` does
+    /// not enable. This is only applied for the SIMD, threads, exceptions and
+    /// tail call proposals.
+    DisabledFeature,
+    /// The limits of a memory or table are out of range, e.g. its minimum is
+    /// greater than its maximum.
+    LimitExceeded,
+}
+
+/// A module that violates exactly one validation rule.
+///
+/// This starts from a valid [`ConfiguredModule`] and applies one invalidating
+/// mutation to it, so that validators can be checked to reject it for the
+/// right reason. Unlike [`MaybeInvalidModule`], the rule that is violated is
+/// known, see [`InvalidModule::violation`].
+#[derive(Debug)]
+pub struct InvalidModule<C>
+where
+    C: Config,
+{
+    module: ConfiguredModule<C>,
+    violation: Violation,
+}
+
+impl<C> InvalidModule<C>
+where
+    C: Config,
+{
+    /// Make `module` invalid with one arbitrary mutation.
+    ///
+    /// Fails if no mutation applies to `module`, e.g. because it defines no
+    /// functions, memories or tables.
+    pub fn new(mut module: ConfiguredModule<C>, u: &mut Unstructured) -> Result<Self> {
+        let bodies = module.generated_bodies();
+        let mut choices = Vec::new();
+        if !bodies.is_empty() {
+            choices.push(Violation::TypeMismatch);
+            choices.push(Violation::IndexOutOfBounds);
+            choices.push(Violation::MissingEnd);
+            if !module.disabled_feature_instructions().is_empty() {
+                choices.push(Violation::DisabledFeature);
+            }
+        }
+        let limits = module.defined_limits();
+        if !limits.is_empty() {
+            choices.push(Violation::LimitExceeded);
+        }
+
+        if choices.is_empty() {
+            return Err(arbitrary::Error::IncorrectFormat);
+        }
+        let violation = *u.choose(&choices)?;
+        match violation {
+            Violation::TypeMismatch => {
+                let i = *u.choose(&bodies)?;
+                let insts = match u.int_in_range(0..=1)? {
+                    0 => [Instruction::I32Const(u.arbitrary()?), Instruction::I64Eqz],
+                    _ => [Instruction::I64Const(u.arbitrary()?), Instruction::I32Eqz],
+                };
+                module.body_mut(i).splice(0..0, insts.iter().cloned());
+            }
+            Violation::IndexOutOfBounds => {
+                let i = *u.choose(&bodies)?;
+                let num_funcs = module.funcs.len() as u32;
+                let num_globals = module.globals.len() as u32;
+                let num_locals = module.num_locals(i);
+                let inst = match u.int_in_range(0..=2)? {
+                    0 => Instruction::LocalGet(u.int_in_range(num_locals..=u32::MAX)?),
+                    1 => Instruction::GlobalGet(u.int_in_range(num_globals..=u32::MAX)?),
+                    _ => Instruction::Call(u.int_in_range(num_funcs..=u32::MAX)?),
+                };
+                module.body_mut(i).insert(0, inst);
+            }
+            Violation::MissingEnd => {
+                // The `end` of the function body closes this block instead,
+                // which leaves the function's own frame open.
+                let i = *u.choose(&bodies)?;
+                module
+                    .body_mut(i)
+                    .push(Instruction::Block(BlockType::Empty));
+            }
+            Violation::DisabledFeature => {
+                let i = *u.choose(&bodies)?;
+                let insts = u.choose(&module.disabled_feature_instructions())?.clone();
+                module.body_mut(i).splice(0..0, insts);
+            }
+            Violation::LimitExceeded => {
+                let limits = match *u.choose(&limits)? {
+                    (true, i) => &mut module.memories[i].limits,
+                    (false, i) => &mut module.tables[i].limits,
+                };
+                let max = u.int_in_range(0..=limits.min)?;
+                limits.max = Some(max);
+                limits.min = max + 1;
+            }
+        }
+
+        Ok(InvalidModule { module, violation })
+    }
+
+    /// The rule that this module violates.
+    pub fn violation(&self) -> Violation {
+        self.violation
+    }
+
+    /// The invalid module, e.g. to get at its configuration.
+    pub fn module(&self) -> &ConfiguredModule<C> {
+        &self.module
+    }
+
+    /// Encode this Wasm module into bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.module.to_bytes()
+    }
+}
+
+impl<C: Config> Arbitrary for InvalidModule<C> {
+    fn arbitrary(u: &mut Unstructured) -> Result<Self> {
+        let module = ConfiguredModule::arbitrary(u)?;
+        InvalidModule::new(module, u)
+    }
+}
+
+impl<C> ConfiguredModule<C>
+where
+    C: Config,
+{
+    /// The indices into `self.code` of the generated function bodies.
+    fn generated_bodies(&self) -> Vec<usize> {
+        self.code
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| matches!(c.instructions, Instructions::Generated(_)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn body_mut(&mut self, i: usize) -> &mut Vec<Instruction> {
+        match &mut self.code[i].instructions {
+            Instructions::Generated(insts) => insts,
+            Instructions::Arbitrary(_) => unreachable!(),
+        }
+    }
+
+    /// The number of parameters and locals of the `i`th defined function.
+    fn num_locals(&self, i: usize) -> u32 {
+        let (_, ty) = &self.funcs[self.funcs.len() - self.num_defined_funcs + i];
+        (ty.params.len() + self.code[i].locals.len()) as u32
+    }
+
+    /// Stack-neutral instruction sequences from proposals that the config
+    /// does not enable.
+    fn disabled_feature_instructions(&self) -> Vec<Vec<Instruction>> {
+        let mut choices = Vec::new();
+        if !self.config.simd_enabled() {
+            choices.push(vec![Instruction::V128Const(0), Instruction::Drop]);
+        }
+        if !self.config.threads_enabled() {
+            choices.push(vec![Instruction::AtomicFence]);
+        }
+        if !self.config.exceptions_enabled() {
+            choices.push(vec![
+                Instruction::Try(BlockType::Empty),
+                Instruction::CatchAll,
+                Instruction::End,
+            ]);
+        }
+        if !self.config.tail_call_enabled() {
+            // The feature is checked before the callee's type.
+            choices.push(vec![Instruction::ReturnCall(0)]);
+        }
+        choices
+    }
+
+    /// The defined memories and tables whose limits can be made invalid, as
+    /// `(is_memory, index)` pairs.
+    fn defined_limits(&self) -> Vec<(bool, usize)> {
+        let memories = self.memories.len() - self.num_defined_memories..self.memories.len();
+        let tables = self.tables.len() - self.num_defined_tables..self.tables.len();
+        memories
+            .filter(|i| self.memories[*i].limits.min < u32::MAX)
+            .map(|i| (true, i))
+            .chain(
+                tables
+                    .filter(|i| self.tables[*i].limits.min < u32::MAX)
+                    .map(|i| (false, i)),
+            )
+            .collect()
+    }
+}
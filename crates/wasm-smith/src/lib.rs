@@ -50,12 +50,14 @@
 
 #![deny(missing_docs, missing_debug_implementations)]
 // Needed for the `instructions!` macro in `src/code_builder.rs`.
-#![recursion_limit = "256"]
+#![recursion_limit = "512"]
 
 mod code_builder;
 mod config;
 mod encode;
+mod invalid;
 mod terminate;
+mod wast;
 
 use crate::code_builder::CodeBuilderAllocations;
 use arbitrary::{Arbitrary, Result, Unstructured};
@@ -63,7 +65,10 @@ use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::str;
 
-pub use config::{Config, DefaultConfig, SwarmConfig};
+pub use config::{Config, DefaultConfig, ImportType, RequiredExport, SwarmConfig};
+pub use invalid::{InvalidModule, Violation};
+pub use terminate::{FuelConfig, FuelMetering, FuelType};
+pub use wast::{Evaluator, Outcome, Value};
 
 /// A pseudo-random WebAssembly module.
 ///
@@ -153,6 +158,10 @@ where
     /// aliased).
     num_defined_memories: usize,
 
+    /// The number of events defined in this module (not imported or
+    /// aliased).
+    num_defined_events: usize,
+
     /// The indexes and initialization expressions of globals defined in this
     /// module.
     defined_globals: Vec<(u32, Instruction)>,
@@ -178,6 +187,10 @@ where
     /// entry is the type of each memory.
     memories: Vec<MemoryType>,
 
+    /// All events available to this module, sorted by their index. The list
+    /// entry is the type of each event.
+    events: Vec<EventType>,
+
     /// All instances available to this module, sorted by their index. The list
     /// entry is the type of the instance.
     instances: Vec<Rc<InstanceType>>,
@@ -191,9 +204,25 @@ where
     elems: Vec<ElementSegment>,
     code: Vec<Code>,
     data: Vec<DataSegment>,
+
+    /// The contents of the `name` section, if one is generated.
+    names: Option<Names>,
+
+    /// Custom sections, other than the `name` section, in the order that they
+    /// are encoded.
+    custom_sections: Vec<CustomSection>,
 }
 
 impl<C: Config> ConfiguredModule<C> {
+    /// Generate a module with the given configuration, rather than an
+    /// arbitrary one.
+    pub fn new(config: C, u: &mut Unstructured) -> Result<Self> {
+        let mut module = ConfiguredModule::<C>::default();
+        module.config = config;
+        module.build(u, false)?;
+        Ok(module)
+    }
+
     /// Returns a reference to the internal configuration.
     pub fn config(&self) -> &C {
         &self.config
@@ -202,9 +231,8 @@ impl<C: Config> ConfiguredModule<C> {
 
 impl<C: Config> Arbitrary for ConfiguredModule<C> {
     fn arbitrary(u: &mut Unstructured) -> Result<Self> {
-        let mut module = ConfiguredModule::<C>::default();
-        module.build(u, false)?;
-        Ok(module)
+        let config = C::arbitrary(u)?;
+        ConfiguredModule::new(config, u)
     }
 }
 
@@ -227,6 +255,7 @@ impl MaybeInvalidModule {
 impl Arbitrary for MaybeInvalidModule {
     fn arbitrary(u: &mut Unstructured) -> Result<Self> {
         let mut module = Module::default();
+        module.inner.config = DefaultConfig::arbitrary(u)?;
         module.inner.build(u, true)?;
         Ok(MaybeInvalidModule { module })
     }
@@ -274,6 +303,7 @@ enum EntityType {
     Global(GlobalType),
     Table(TableType),
     Memory(MemoryType),
+    Event(EventType),
     Func(u32, Rc<FuncType>),
     Instance(u32, Rc<InstanceType>),
     Module(u32, Rc<ModuleType>),
@@ -285,10 +315,25 @@ enum ValType {
     I64,
     F32,
     F64,
+    V128,
     FuncRef,
     ExternRef,
 }
 
+impl From<wasm_encoder::ValType> for ValType {
+    fn from(ty: wasm_encoder::ValType) -> ValType {
+        match ty {
+            wasm_encoder::ValType::I32 => ValType::I32,
+            wasm_encoder::ValType::I64 => ValType::I64,
+            wasm_encoder::ValType::F32 => ValType::F32,
+            wasm_encoder::ValType::F64 => ValType::F64,
+            wasm_encoder::ValType::V128 => ValType::V128,
+            wasm_encoder::ValType::FuncRef => ValType::FuncRef,
+            wasm_encoder::ValType::ExternRef => ValType::ExternRef,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct TableType {
     limits: Limits,
@@ -298,6 +343,19 @@ struct TableType {
 #[derive(Clone, Debug)]
 struct MemoryType {
     limits: Limits,
+    shared: bool,
+    memory64: bool,
+}
+
+impl MemoryType {
+    /// The type of addresses into this memory.
+    fn index_type(&self) -> ValType {
+        if self.memory64 {
+            ValType::I64
+        } else {
+            ValType::I32
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -328,6 +386,14 @@ struct GlobalType {
     mutable: bool,
 }
 
+#[derive(Clone, Debug)]
+struct EventType {
+    /// The index of this event's function type, which is `u32::max_value()`
+    /// for events that were aliased or exported without a local type.
+    func_type_idx: u32,
+    func_type: Rc<FuncType>,
+}
+
 #[derive(Clone, Debug)]
 enum Alias {
     InstanceExport {
@@ -357,6 +423,7 @@ enum ItemKind {
     Table,
     Memory,
     Global,
+    Event,
     Instance,
     Module,
 }
@@ -367,6 +434,7 @@ enum Export {
     Table(u32),
     Memory(u32),
     Global(u32),
+    Event(u32),
     Instance(u32),
     Module(u32),
 }
@@ -436,9 +504,37 @@ struct MemArg {
     memory_index: u32,
 }
 
-#[derive(Clone, Debug)]
-#[allow(non_camel_case_types)]
-enum Instruction {
+macro_rules! define_instructions {
+    ($(
+        $variant:ident
+        $(( $($tuple:ty),* ))?
+        $({ $($field:ident : $field_ty:ty),* })?
+    ),* $(,)?) => {
+        #[derive(Clone, Debug)]
+        #[allow(non_camel_case_types)]
+        enum Instruction {
+            $(
+                $variant
+                $(( $($tuple),* ))?
+                $({ $($field : $field_ty),* })?
+            ),*
+        }
+
+        impl Instruction {
+            /// The names of every variant, as returned by `Instruction::name`.
+            const NAMES: &'static [&'static str] = &[$(stringify!($variant)),*];
+
+            /// The name of this instruction's variant, for example `"I32Add"`.
+            fn name(&self) -> &'static str {
+                match self {
+                    $(Instruction::$variant { .. } => stringify!($variant),)*
+                }
+            }
+        }
+    };
+}
+
+define_instructions! {
     // Control instructions.
     Unreachable,
     Nop,
@@ -454,6 +550,18 @@ enum Instruction {
     Call(u32),
     CallIndirect { ty: u32, table: u32 },
 
+    // Exception handling instructions.
+    Try(BlockType),
+    Catch(u32),
+    CatchAll,
+    Throw(u32),
+    Rethrow(u32),
+    Unwind,
+
+    // Tail call instructions.
+    ReturnCall(u32),
+    ReturnCallIndirect { ty: u32, table: u32 },
+
     // Parametric instructions.
     Drop,
     Select,
@@ -649,6 +757,294 @@ enum Instruction {
     TableGrow { table: u32 },
     TableSize { table: u32 },
     TableCopy { src: u32, dst: u32 },
+
+    // SIMD instructions.
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
+    V128Const(i128),
+    I8x16Shuffle { lanes: [u8; 16] },
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS { lane: u8 },
+    I8x16ExtractLaneU { lane: u8 },
+    I8x16ReplaceLane { lane: u8 },
+    I16x8ExtractLaneS { lane: u8 },
+    I16x8ExtractLaneU { lane: u8 },
+    I16x8ReplaceLane { lane: u8 },
+    I32x4ExtractLane { lane: u8 },
+    I32x4ReplaceLane { lane: u8 },
+    I64x2ExtractLane { lane: u8 },
+    I64x2ReplaceLane { lane: u8 },
+    F32x4ExtractLane { lane: u8 },
+    F32x4ReplaceLane { lane: u8 },
+    F64x2ExtractLane { lane: u8 },
+    F64x2ReplaceLane { lane: u8 },
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128AndNot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128Load8Lane { memarg: MemArg, lane: u8 },
+    V128Load16Lane { memarg: MemArg, lane: u8 },
+    V128Load32Lane { memarg: MemArg, lane: u8 },
+    V128Load64Lane { memarg: MemArg, lane: u8 },
+    V128Store8Lane { memarg: MemArg, lane: u8 },
+    V128Store16Lane { memarg: MemArg, lane: u8 },
+    V128Store32Lane { memarg: MemArg, lane: u8 },
+    V128Store64Lane { memarg: MemArg, lane: u8 },
+    I8x16Abs,
+    I8x16Neg,
+    V128AnyTrue,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    I8x16RoundingAverageU,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8WidenLowI8x16S,
+    I16x8WidenHighI8x16S,
+    I16x8WidenLowI8x16U,
+    I16x8WidenHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8ExtMulLowI8x16S,
+    I16x8RoundingAverageU,
+    I16x8Q15MulrSatS,
+    I16x8ExtMulHighI8x16S,
+    I16x8ExtMulLowI8x16U,
+    I16x8ExtMulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4WidenLowI16x8S,
+    I32x4WidenHighI16x8S,
+    I32x4WidenLowI16x8U,
+    I32x4WidenHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtMulLowI16x8S,
+    I32x4ExtMulHighI16x8S,
+    I32x4ExtMulLowI16x8U,
+    I32x4ExtMulHighI16x8U,
+    I64x2Neg,
+    I64x2Bitmask,
+    I64x2WidenLowI32x4S,
+    I64x2WidenHighI32x4S,
+    I64x2WidenLowI32x4U,
+    I64x2WidenHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2ExtMulLowI32x4S,
+    I64x2ExtMulHighI32x4S,
+    I64x2Mul,
+    I64x2ExtMulLowI32x4U,
+    I64x2ExtMulHighI32x4U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    F64x2Ceil,
+    F64x2Floor,
+    F64x2Trunc,
+    F64x2Nearest,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4PMin,
+    F32x4PMax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2PMin,
+    F64x2PMax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+
+    // Atomic instructions (the threads proposal).
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
 }
 
 #[derive(Debug)]
@@ -666,16 +1062,61 @@ enum DataSegmentKind {
     },
 }
 
+#[derive(Debug, Default)]
+struct Names {
+    module: Option<String>,
+    /// Function names, sorted by function index.
+    funcs: Vec<(u32, String)>,
+    /// Local names, sorted by function index and then by local index.
+    locals: Vec<(u32, Vec<(u32, String)>)>,
+}
+
+#[derive(Debug)]
+struct CustomSection {
+    /// How many of the known sections, in the order that they are encoded,
+    /// come before this custom section. See `ConfiguredModule::encoded`.
+    place: usize,
+    name: String,
+    data: Vec<u8>,
+}
+
+/// The number of places around the known sections where a custom section can
+/// be put.
+const CUSTOM_SECTION_PLACES: usize = 13;
+
+/// Names that need escaping or are otherwise unusual in the text format.
+const UNUSUAL_NAMES: &[&str] = &[
+    "",
+    " ",
+    "$",
+    "\"",
+    "\\",
+    "a b",
+    "(",
+    ")",
+    ";;",
+    "0",
+    "\u{0}",
+    "\n",
+    "\u{7f}",
+    "\u{fffd}",
+    "\u{4f60}\u{597d}",
+];
+
 impl<C> ConfiguredModule<C>
 where
     C: Config,
 {
     fn build(&mut self, u: &mut Unstructured, allow_invalid: bool) -> Result<()> {
-        self.config = C::arbitrary(u)?;
         self.valtypes.push(ValType::I32);
         self.valtypes.push(ValType::I64);
-        self.valtypes.push(ValType::F32);
-        self.valtypes.push(ValType::F64);
+        if !self.config.deterministic_only() {
+            self.valtypes.push(ValType::F32);
+            self.valtypes.push(ValType::F64);
+        }
+        if self.config.simd_enabled() {
+            self.valtypes.push(ValType::V128);
+        }
         if self.config.reference_types_enabled() {
             self.valtypes.push(ValType::ExternRef);
             self.valtypes.push(ValType::FuncRef);
@@ -684,12 +1125,17 @@ where
         self.arbitrary_funcs(u)?;
         self.arbitrary_tables(u)?;
         self.arbitrary_memories(u)?;
+        self.arbitrary_events(u)?;
         self.arbitrary_globals(u)?;
         self.arbitrary_exports(u)?;
         self.arbitrary_start(u)?;
         self.arbitrary_elems(u)?;
         self.arbitrary_data(u)?;
         self.arbitrary_code(u, allow_invalid)?;
+        if self.config.name_section_enabled() {
+            self.arbitrary_names(u)?;
+        }
+        self.arbitrary_custom_sections(u)?;
         Ok(())
     }
 
@@ -747,6 +1193,18 @@ where
         if self.num_imports < self.config.min_imports() {
             self.arbitrary_imports(self.config.min_imports() - self.num_imports, u)?;
         }
+
+        // With module linking, type sections may follow imports, so the types
+        // for required exports can be added last.
+        let required = self.required_func_types();
+        if required
+            .iter()
+            .any(|ty| !self.func_types().any(|(_, f)| f == ty))
+        {
+            let section_idx = self.initial_sections.len();
+            self.initial_sections.push(InitialSection::Type(Vec::new()));
+            self.push_required_func_types(section_idx);
+        }
         Ok(())
     }
 
@@ -757,18 +1215,15 @@ where
         // succeed.
         let section_idx = self.initial_sections.len();
         self.initial_sections.push(InitialSection::Type(Vec::new()));
-        arbitrary_loop(u, min, self.config.max_types() - self.types.len(), |u| {
+
+        if !self.config.module_linking_enabled() {
+            self.push_required_func_types(section_idx);
+        }
+
+        let max = self.config.max_types().saturating_sub(self.types.len());
+        arbitrary_loop(u, min, max, |u| {
             let ty = self.arbitrary_type(u)?;
-            self.record_type(&ty);
-            let types = match self.initial_sections.last_mut().unwrap() {
-                InitialSection::Type(list) => list,
-                _ => unreachable!(),
-            };
-            self.types.push(LocalType::Defined {
-                section: section_idx,
-                nth: types.len(),
-            });
-            types.push(ty);
+            self.push_type(section_idx, ty);
             Ok(true)
         })?;
         let types = match self.initial_sections.last_mut().unwrap() {
@@ -781,6 +1236,63 @@ where
         Ok(())
     }
 
+    /// The function types needed by the required exports and then by the
+    /// available imports, see [`Config::required_exports`] and
+    /// [`Config::available_imports`].
+    fn required_func_types(&self) -> Vec<FuncType> {
+        let exports = self
+            .required_exports()
+            .into_iter()
+            .map(|e| (e.params, e.results));
+        let imports = self
+            .available_imports()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(_, _, ty)| match ty {
+                ImportType::Func(params, results) => Some((params, results)),
+                _ => None,
+            });
+        exports
+            .chain(imports)
+            .map(|(params, results)| FuncType {
+                params: params.into_iter().map(ValType::from).collect(),
+                results: results.into_iter().map(ValType::from).collect(),
+            })
+            .collect()
+    }
+
+    /// Adds the `required_func_types` that aren't defined yet to the type
+    /// section at `section_idx`, which must be the last initial section.
+    ///
+    /// The types of required exports are always added, even past
+    /// [`Config::max_types`]. The types of available imports are only added
+    /// while there is room, and function imports without a type can't be
+    /// chosen.
+    fn push_required_func_types(&mut self, section_idx: usize) {
+        let num_exports = self.required_exports().len();
+        for (i, ty) in self.required_func_types().into_iter().enumerate() {
+            if i >= num_exports && self.types.len() >= self.config.max_types() {
+                break;
+            }
+            if !self.func_types().any(|(_, f)| *f == ty) {
+                self.push_type(section_idx, Type::Func(Rc::new(ty)));
+            }
+        }
+    }
+
+    fn push_type(&mut self, section_idx: usize, ty: Type) {
+        self.record_type(&ty);
+        let types = match self.initial_sections.last_mut().unwrap() {
+            InitialSection::Type(list) => list,
+            _ => unreachable!(),
+        };
+        self.types.push(LocalType::Defined {
+            section: section_idx,
+            nth: types.len(),
+        });
+        types.push(ty);
+    }
+
     fn record_type(&mut self, ty: &Type) {
         let list = match &ty {
             Type::Func(_) => &mut self.func_types,
@@ -884,7 +1396,7 @@ where
                 Ok(EntityType::Global(m.arbitrary_global_type(u)?))
             });
         }
-        if entities.memories < self.config.max_memories() {
+        if entities.memories < max_memories(&self.config) {
             choices.push(|u, m, e| {
                 e.memories += 1;
                 Ok(EntityType::Memory(m.arbitrary_memtype(u)?))
@@ -896,6 +1408,12 @@ where
                 Ok(EntityType::Table(m.arbitrary_table_type(u)?))
             });
         }
+        if entities.events < self.config.max_events() && self.has_event_func_type() {
+            choices.push(|u, m, e| {
+                e.events += 1;
+                Ok(EntityType::Event(m.arbitrary_event_type(u)?))
+            });
+        }
         if entities.funcs < self.config.max_funcs() && self.func_types.len() > 0 {
             choices.push(|u, m, e| {
                 e.funcs += 1;
@@ -944,10 +1462,61 @@ where
     }
 
     fn can_add_local_or_import_memory(&self) -> bool {
-        self.memories.len() < self.config.max_memories()
+        self.memories.len() < max_memories(&self.config)
+    }
+
+    fn can_add_local_or_import_event(&self) -> bool {
+        self.events.len() < self.config.max_events() && self.has_event_func_type()
+    }
+
+    /// Whether there is a function type without results, which is required
+    /// for defining events.
+    fn has_event_func_type(&self) -> bool {
+        self.config.exceptions_enabled() && self.func_types().any(|(_, ty)| ty.results.is_empty())
+    }
+
+    /// The imports from [`Config::available_imports`], if this module should
+    /// import from them.
+    fn available_imports(&self) -> Option<Vec<(String, String, ImportType)>> {
+        if self.config.module_linking_enabled() {
+            return None;
+        }
+        let mut imports = self.config.available_imports()?;
+        imports.retain(|(_, _, ty)| self.can_import(ty));
+        Some(imports)
+    }
+
+    /// Whether an import of type `ty` only uses value types and proposals
+    /// that are enabled.
+    fn can_import(&self, ty: &ImportType) -> bool {
+        let valtype = |ty: &wasm_encoder::ValType| self.valtypes.contains(&ValType::from(*ty));
+        match ty {
+            ImportType::Func(params, results) => params.iter().chain(results).all(valtype),
+            ImportType::Global(ty) => valtype(&ty.val_type),
+            ImportType::Memory(ty) => {
+                (!ty.shared || self.config.threads_enabled())
+                    && (!ty.memory64 || self.config.memory64_enabled())
+            }
+            ImportType::Table(ty) => {
+                ty.element_type == wasm_encoder::ValType::FuncRef || valtype(&ty.element_type)
+            }
+        }
+    }
+
+    /// The exports from [`Config::required_exports`], which only apply to the
+    /// top-level module.
+    fn required_exports(&self) -> Vec<RequiredExport> {
+        if !self.outers.is_empty() {
+            return Vec::new();
+        }
+        self.config.required_exports()
     }
 
     fn arbitrary_imports(&mut self, min: usize, u: &mut Unstructured) -> Result<()> {
+        if let Some(available) = self.available_imports() {
+            return self.arbitrary_available_imports(available, min, u);
+        }
+
         let mut choices: Vec<
             fn(&mut Unstructured, &mut ConfiguredModule<C>) -> Result<EntityType>,
         > = Vec::with_capacity(4);
@@ -1000,6 +1569,13 @@ where
                     Ok(EntityType::Table(ty))
                 });
             }
+            if self.can_add_local_or_import_event() {
+                choices.push(|u, m| {
+                    let ty = m.arbitrary_event_type(u)?;
+                    m.events.push(ty.clone());
+                    Ok(EntityType::Event(ty))
+                });
+            }
 
             if choices.is_empty() {
                 // We are out of choices. If we have not have reached the minimum yet, then we
@@ -1056,6 +1632,95 @@ where
         Ok(())
     }
 
+    fn arbitrary_available_imports(
+        &mut self,
+        mut available: Vec<(String, String, ImportType)>,
+        min: usize,
+        u: &mut Unstructured,
+    ) -> Result<()> {
+        let mut candidates = Vec::new();
+        let mut imports = Vec::new();
+        arbitrary_loop(u, min, self.config.max_imports() - self.num_imports, |u| {
+            candidates.clear();
+            for (i, (_, _, ty)) in available.iter().enumerate() {
+                let importable = match ty {
+                    ImportType::Func(params, results) => {
+                        let ty = FuncType {
+                            params: params.iter().copied().map(ValType::from).collect(),
+                            results: results.iter().copied().map(ValType::from).collect(),
+                        };
+                        self.funcs.len() < self.config.max_funcs()
+                            && self.func_types().any(|(_, f)| *f == ty)
+                    }
+                    ImportType::Global(_) => self.can_add_local_or_import_global(),
+                    ImportType::Memory(_) => self.can_add_local_or_import_memory(),
+                    ImportType::Table(_) => self.can_add_local_or_import_table(),
+                };
+                if importable {
+                    candidates.push(i);
+                }
+            }
+            if candidates.is_empty() {
+                return Ok(false);
+            }
+
+            let (module, name, ty) = available.swap_remove(*u.choose(&candidates)?);
+            let ty = match ty {
+                ImportType::Func(params, results) => {
+                    let ty = FuncType {
+                        params: params.into_iter().map(ValType::from).collect(),
+                        results: results.into_iter().map(ValType::from).collect(),
+                    };
+                    let (idx, _) = self
+                        .func_types()
+                        .find(|(_, f)| **f == ty)
+                        .expect("only imports with a defined type are candidates");
+                    let ty = self.func_type(idx).clone();
+                    self.funcs.push((Some(idx), ty.clone()));
+                    EntityType::Func(idx, ty)
+                }
+                ImportType::Global(ty) => {
+                    let ty = GlobalType {
+                        val_type: ty.val_type.into(),
+                        mutable: ty.mutable,
+                    };
+                    self.globals.push(ty.clone());
+                    EntityType::Global(ty)
+                }
+                ImportType::Memory(ty) => {
+                    let ty = MemoryType {
+                        limits: Limits {
+                            min: ty.limits.min,
+                            max: ty.limits.max,
+                        },
+                        shared: ty.shared,
+                        memory64: ty.memory64,
+                    };
+                    self.memories.push(ty.clone());
+                    EntityType::Memory(ty)
+                }
+                ImportType::Table(ty) => {
+                    let ty = TableType {
+                        limits: Limits {
+                            min: ty.limits.min,
+                            max: ty.limits.max,
+                        },
+                        elem_ty: ty.element_type.into(),
+                    };
+                    self.tables.push(ty.clone());
+                    EntityType::Table(ty)
+                }
+            };
+            self.num_imports += 1;
+            imports.push((module, Some(name), ty));
+            Ok(true)
+        })?;
+        if !imports.is_empty() {
+            self.initial_sections.push(InitialSection::Import(imports));
+        }
+        Ok(())
+    }
+
     fn arbitrary_aliases(
         &mut self,
         available: &mut AvailableAliases,
@@ -1096,6 +1761,13 @@ where
                             };
                             self.memories.push(ty);
                         }
+                        ItemKind::Event => {
+                            let ty = match &ty.exports[name] {
+                                EntityType::Event(t) => t.clone(),
+                                _ => unreachable!(),
+                            };
+                            self.events.push(ty);
+                        }
                         ItemKind::Func => {
                             let (i, ty) = match &ty.exports[name] {
                                 EntityType::Func(i, t) => (*i, t),
@@ -1246,6 +1918,10 @@ where
         match *item {
             Export::Global(idx) => EntityType::Global(self.globals[idx as usize].clone()),
             Export::Memory(idx) => EntityType::Memory(self.memories[idx as usize].clone()),
+            Export::Event(idx) => EntityType::Event(EventType {
+                func_type_idx: u32::max_value(),
+                func_type: self.events[idx as usize].func_type.clone(),
+            }),
             Export::Table(idx) => EntityType::Table(self.tables[idx as usize].clone()),
             Export::Func(idx) => {
                 let (_idx, ty) = &self.funcs[idx as usize];
@@ -1330,6 +2006,21 @@ where
     }
 
     fn arbitrary_funcs(&mut self, u: &mut Unstructured) -> Result<()> {
+        // Required exports are the first defined functions, see
+        // `arbitrary_exports`.
+        for export in self.required_exports() {
+            let ty = FuncType {
+                params: export.params.into_iter().map(ValType::from).collect(),
+                results: export.results.into_iter().map(ValType::from).collect(),
+            };
+            let (idx, _) = self
+                .func_types()
+                .find(|(_, f)| **f == ty)
+                .expect("the types of required exports are always defined");
+            self.funcs.push((Some(idx), self.func_type(idx).clone()));
+            self.num_defined_funcs += 1;
+        }
+
         if self.func_types.is_empty() {
             return Ok(());
         }
@@ -1364,12 +2055,18 @@ where
     }
 
     fn arbitrary_memtype(&self, u: &mut Unstructured) -> Result<MemoryType> {
+        let shared = self.config.threads_enabled() && u.arbitrary()?;
+        let memory64 = self.config.memory64_enabled() && u.arbitrary()?;
         let limits = Limits::limited(
             u,
             self.config.max_memory_pages(),
-            self.config.memory_max_size_required(),
+            self.config.memory_max_size_required() || shared,
         )?;
-        Ok(MemoryType { limits })
+        Ok(MemoryType {
+            limits,
+            shared,
+            memory64,
+        })
     }
 
     fn arbitrary_memories(&mut self, u: &mut Unstructured) -> Result<()> {
@@ -1388,6 +2085,34 @@ where
         )
     }
 
+    fn arbitrary_event_type(&self, u: &mut Unstructured) -> Result<EventType> {
+        let candidates = self
+            .func_types()
+            .filter(|(_, ty)| ty.results.is_empty())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let func_type_idx = *u.choose(&candidates)?;
+        Ok(EventType {
+            func_type_idx,
+            func_type: self.func_type(func_type_idx).clone(),
+        })
+    }
+
+    fn arbitrary_events(&mut self, u: &mut Unstructured) -> Result<()> {
+        if !self.config.exceptions_enabled() {
+            return Ok(());
+        }
+
+        arbitrary_loop(u, 0, self.config.max_events(), |u| {
+            if !self.can_add_local_or_import_event() {
+                return Ok(false);
+            }
+            self.num_defined_events += 1;
+            self.events.push(self.arbitrary_event_type(u)?);
+            Ok(true)
+        })
+    }
+
     fn arbitrary_globals(&mut self, u: &mut Unstructured) -> Result<()> {
         let mut choices: Vec<Box<dyn Fn(&mut Unstructured, ValType) -> Result<Instruction>>> =
             vec![];
@@ -1411,6 +2136,7 @@ where
                         ValType::I64 => Instruction::I64Const(u.arbitrary()?),
                         ValType::F32 => Instruction::F32Const(u.arbitrary()?),
                         ValType::F64 => Instruction::F64Const(u.arbitrary()?),
+                        ValType::V128 => Instruction::V128Const(u.arbitrary()?),
                         ValType::ExternRef => Instruction::RefNull(ValType::ExternRef),
                         ValType::FuncRef => {
                             if num_funcs > 0 && u.arbitrary()? {
@@ -1471,6 +2197,13 @@ where
             });
         }
 
+        if !self.events.is_empty() {
+            choices.push(|u, m| {
+                let idx = u.int_in_range(0..=m.events.len() - 1)?;
+                Ok(Export::Event(idx as u32))
+            });
+        }
+
         if self.instances.len() > 0 {
             choices.push(|u, m| {
                 let idx = u.int_in_range(0..=m.instances.len() - 1)?;
@@ -1485,11 +2218,18 @@ where
             });
         }
 
+        let mut export_names = HashSet::new();
+        let first_defined_func = self.funcs.len() - self.num_defined_funcs;
+        for (i, export) in self.required_exports().into_iter().enumerate() {
+            export_names.insert(export.name.clone());
+            self.exports
+                .push((export.name, Export::Func((first_defined_func + i) as u32)));
+        }
+
         if choices.is_empty() {
             return Ok(());
         }
 
-        let mut export_names = HashSet::new();
         arbitrary_loop(
             u,
             self.config.min_exports(),
@@ -1602,8 +2342,8 @@ where
             |u| {
                 // Choose whether to generate a segment whose elements are initialized via
                 // expressions, or one whose elements are initialized via function indices.
-                let (kind, ty) = u.choose(&choices)?(u)?;
-                let items = if ty == ValType::ExternRef
+                let (mut kind, ty) = u.choose(&choices)?(u)?;
+                let mut items = if ty == ValType::ExternRef
                     || (self.config.reference_types_enabled() && u.arbitrary()?)
                 {
                     let mut init = vec![];
@@ -1640,6 +2380,26 @@ where
                     Elements::Functions(init)
                 };
 
+                if let ElementKind::Active { table, offset } = &mut kind {
+                    if self.config.disallow_traps() {
+                        // Keep the segment within the table's minimum size so
+                        // that instantiation can't trap.
+                        let bound = self.tables[table.unwrap_or(0) as usize].limits.min;
+                        let len = match &mut items {
+                            Elements::Functions(init) => {
+                                init.truncate(bound as usize);
+                                init.len()
+                            }
+                            Elements::Expressions(init) => {
+                                init.truncate(bound as usize);
+                                init.len()
+                            }
+                        };
+                        let start = u.int_in_range(0..=bound - len as u32)?;
+                        *offset = Instruction::I32Const(start as i32);
+                    }
+                }
+
                 self.elems.push(ElementSegment { kind, ty, items });
                 Ok(true)
             },
@@ -1663,7 +2423,14 @@ where
         allocs: &mut CodeBuilderAllocations<C>,
         allow_invalid: bool,
     ) -> Result<Code> {
-        let locals = self.arbitrary_locals(u)?;
+        let mut locals = self.arbitrary_locals(u)?;
+        if self.config.disallow_traps() {
+            // Scratch locals for guarding instructions, see
+            // `CodeBuilder::guard_operand`.
+            for ty in self.valtypes.iter() {
+                locals.extend_from_slice(&[*ty, *ty]);
+            }
+        }
         let builder = allocs.builder(ty, &locals);
         let instructions = if allow_invalid && u.arbitrary().unwrap_or(false) {
             Instructions::Arbitrary(arbitrary_vec_u8(u)?)
@@ -1677,6 +2444,60 @@ where
         })
     }
 
+    fn arbitrary_names(&mut self, u: &mut Unstructured) -> Result<()> {
+        // Every name is remembered so that later names can duplicate it.
+        let mut used = Vec::new();
+        let mut names = Names::default();
+        // Only the outermost module is named: nested modules draw from their
+        // own `used` names, so their names could collide with each other.
+        if self.outers.is_empty() && u.arbitrary()? {
+            names.module = Some(arbitrary_name(&mut used, u)?);
+        }
+        for i in 0..self.funcs.len() as u32 {
+            if u.arbitrary()? {
+                names.funcs.push((i, arbitrary_name(&mut used, u)?));
+            }
+        }
+        let first_defined = self.funcs.len() - self.num_defined_funcs;
+        for (i, code) in self.code.iter().enumerate() {
+            if !u.arbitrary()? {
+                continue;
+            }
+            let func = first_defined + i;
+            let num_locals = self.funcs[func].1.params.len() + code.locals.len();
+            let mut locals = Vec::new();
+            for j in 0..num_locals as u32 {
+                if u.arbitrary()? {
+                    locals.push((j, arbitrary_name(&mut used, u)?));
+                }
+            }
+            names.locals.push((func as u32, locals));
+        }
+        self.names = Some(names);
+        Ok(())
+    }
+
+    fn arbitrary_custom_sections(&mut self, u: &mut Unstructured) -> Result<()> {
+        let mut custom_sections = Vec::new();
+        arbitrary_loop(u, 0, self.config.max_custom_sections(), |u| {
+            let mut name = limited_string(100, u)?;
+            // An arbitrary `name` section would almost never be well-formed.
+            if name == "name" {
+                name.push('_');
+            }
+            let len = u.int_in_range(0..=100)?;
+            custom_sections.push(CustomSection {
+                place: u.int_in_range(0..=CUSTOM_SECTION_PLACES - 1)?,
+                name,
+                data: u.get_bytes(len)?.to_vec(),
+            });
+            Ok(true)
+        })?;
+        custom_sections.sort_by_key(|s| s.place);
+        self.custom_sections = custom_sections;
+        Ok(())
+    }
+
     fn arbitrary_locals(&self, u: &mut Unstructured) -> Result<Vec<ValType>> {
         let mut ret = Vec::new();
         arbitrary_loop(u, 0, 100, |u| {
@@ -1694,43 +2515,77 @@ where
             return Ok(());
         }
 
-        let mut choices: Vec<Box<dyn Fn(&mut Unstructured) -> Result<Instruction>>> = vec![];
+        // Offsets are constant expressions of the memory's index type: either
+        // a constant or an immutable imported global.
+        let mut offset_globals: Vec<(ValType, u32)> = vec![];
+        for (i, g) in self.globals[..self.globals.len() - self.defined_globals.len()]
+            .iter()
+            .enumerate()
+        {
+            if !g.mutable && (g.val_type == ValType::I32 || g.val_type == ValType::I64) {
+                offset_globals.push((g.val_type, i as u32));
+            }
+        }
 
         arbitrary_loop(
             u,
             self.config.min_data_segments(),
             self.config.max_data_segments(),
             |u| {
-                if choices.is_empty() {
-                    choices.push(Box::new(|u| Ok(Instruction::I32Const(u.arbitrary()?))));
-
-                    for (i, g) in self.globals[..self.globals.len() - self.defined_globals.len()]
-                        .iter()
-                        .enumerate()
-                    {
-                        if !g.mutable && g.val_type == ValType::I32 {
-                            choices.push(Box::new(move |_| Ok(Instruction::GlobalGet(i as u32))));
-                        }
-                    }
-                }
-
                 // Passive data can only be generated if bulk memory is enabled.
                 // Otherwise if there are no memories we *only* generate passive
                 // data. Finally if all conditions are met we use an input byte to
                 // determine if it should be passive or active.
-                let kind =
+                let mut kind =
                     if self.config.bulk_memory_enabled() && (memories == 0 || u.arbitrary()?) {
                         DataSegmentKind::Passive
                     } else {
-                        let f = u.choose(&choices)?;
-                        let offset = f(u)?;
-                        let memory_index = u.int_in_range(0..=memories - 1)?;
+                        // Naming a memory other than 0 needs the explicit
+                        // memory index encoding from bulk memory; without it
+                        // the flags byte is read as the memory index.
+                        let memory_index = if self.config.bulk_memory_enabled() {
+                            u.int_in_range(0..=memories - 1)?
+                        } else {
+                            0
+                        };
+                        let ty = self.memories[memory_index as usize].index_type();
+                        let globals = offset_globals
+                            .iter()
+                            .filter(|(t, _)| *t == ty)
+                            .map(|(_, i)| *i)
+                            .collect::<Vec<_>>();
+                        let offset = if !globals.is_empty() && u.arbitrary()? {
+                            Instruction::GlobalGet(*u.choose(&globals)?)
+                        } else if ty == ValType::I64 {
+                            Instruction::I64Const(u.arbitrary()?)
+                        } else {
+                            Instruction::I32Const(u.arbitrary()?)
+                        };
                         DataSegmentKind::Active {
                             offset,
                             memory_index,
                         }
                     };
-                let init = u.arbitrary()?;
+                let mut init: Vec<u8> = u.arbitrary()?;
+                if let DataSegmentKind::Active {
+                    offset,
+                    memory_index,
+                } = &mut kind
+                {
+                    if self.config.disallow_traps() {
+                        // Keep the segment within the memory's minimum size so
+                        // that instantiation can't trap.
+                        let memory = &self.memories[*memory_index as usize];
+                        let bound = u64::from(memory.limits.min) * 65536;
+                        init.truncate(bound as usize);
+                        let start = u.int_in_range(0..=bound - init.len() as u64)?;
+                        *offset = if memory.memory64 {
+                            Instruction::I64Const(start as i64)
+                        } else {
+                            Instruction::I32Const(start as u32 as i32)
+                        };
+                    }
+                }
                 self.data.push(DataSegment { kind, init });
                 Ok(true)
             },
@@ -1768,6 +2623,13 @@ where
                     }
                 }
             }
+            EntityType::Event(expected) => {
+                for (i, actual) in self.events.iter().enumerate() {
+                    if self.is_subtype_event(actual, expected) {
+                        ret.push(Export::Event(i as u32));
+                    }
+                }
+            }
             EntityType::Func(_, expected) => {
                 for (i, (_, actual)) in self.funcs.iter().enumerate() {
                     if self.is_subtype_func(actual, expected) {
@@ -1808,6 +2670,10 @@ where
                 EntityType::Table(b) => self.is_subtype_table(a, b),
                 _ => false,
             },
+            EntityType::Event(a) => match b {
+                EntityType::Event(b) => self.is_subtype_event(a, b),
+                _ => false,
+            },
             EntityType::Func(_, a) => match b {
                 EntityType::Func(_, b) => self.is_subtype_func(a, b),
                 _ => false,
@@ -1830,7 +2696,9 @@ where
 
     // https://webassembly.github.io/spec/core/exec/modules.html#memories
     fn is_subtype_memory(&self, a: &MemoryType, b: &MemoryType) -> bool {
-        self.is_subtype_limits(&a.limits, &b.limits)
+        a.shared == b.shared
+            && a.memory64 == b.memory64
+            && self.is_subtype_limits(&a.limits, &b.limits)
     }
 
     // https://webassembly.github.io/spec/core/exec/modules.html#tables
@@ -1838,6 +2706,10 @@ where
         a.elem_ty == b.elem_ty && self.is_subtype_limits(&a.limits, &b.limits)
     }
 
+    fn is_subtype_event(&self, a: &EventType, b: &EventType) -> bool {
+        a.func_type == b.func_type
+    }
+
     // https://webassembly.github.io/spec/core/exec/modules.html#limits
     fn is_subtype_limits(&self, a: &Limits, b: &Limits) -> bool {
         a.min >= b.min
@@ -1879,6 +2751,16 @@ where
     }
 }
 
+/// The maximum number of memories a module may have, which is at most one
+/// unless the multi-memory proposal is enabled.
+fn max_memories(config: &impl Config) -> usize {
+    if config.multi_memory_enabled() {
+        config.max_memories()
+    } else {
+        std::cmp::min(config.max_memories(), 1)
+    }
+}
+
 pub(crate) fn arbitrary_loop(
     u: &mut Unstructured,
     min: usize,
@@ -1926,6 +2808,21 @@ fn limited_string(max_size: usize, u: &mut Unstructured) -> Result<String> {
     }
 }
 
+/// Generates a name for the `name` section, which is sometimes unusual and
+/// sometimes a duplicate of a name in `used`.
+fn arbitrary_name(used: &mut Vec<String>, u: &mut Unstructured) -> Result<String> {
+    if !used.is_empty() && u.int_in_range(0..=3)? == 0 {
+        return Ok(u.choose(used)?.clone());
+    }
+    let name = if u.int_in_range(0..=3)? == 0 {
+        u.choose(UNUSUAL_NAMES)?.to_string()
+    } else {
+        limited_string(100, u)?
+    };
+    used.push(name.clone());
+    Ok(name)
+}
+
 fn unique_string(
     max_size: usize,
     names: &mut HashSet<String>,
@@ -2048,6 +2945,13 @@ impl AvailableAliases {
                             name: name.clone(),
                         });
                     }
+                    EntityType::Event(_) => {
+                        self.aliases.push(Alias::InstanceExport {
+                            instance,
+                            kind: ItemKind::Event,
+                            name: name.clone(),
+                        });
+                    }
                     EntityType::Instance(_, _) => {
                         self.aliases.push(Alias::InstanceExport {
                             instance,
@@ -2104,7 +3008,11 @@ impl AvailableAliases {
             Alias::InstanceExport {
                 kind: ItemKind::Memory,
                 ..
-            } => module.memories.len() < module.config.max_memories(),
+            } => module.memories.len() < max_memories(&module.config),
+            Alias::InstanceExport {
+                kind: ItemKind::Event,
+                ..
+            } => module.events.len() < module.config.max_events(),
             Alias::InstanceExport {
                 kind: ItemKind::Instance,
                 ..
@@ -2194,6 +3102,7 @@ struct Entities {
     globals: usize,
     memories: usize,
     tables: usize,
+    events: usize,
     funcs: usize,
     modules: usize,
     instances: usize,
@@ -2202,8 +3111,9 @@ struct Entities {
 impl Entities {
     fn max_reached(&self, config: &impl Config) -> bool {
         self.globals >= config.max_globals()
-            || self.memories >= config.max_memories()
+            || self.memories >= max_memories(config)
             || self.tables >= config.max_tables()
+            || self.events >= config.max_events()
             || self.funcs >= config.max_funcs()
             || self.modules >= config.max_modules()
             || self.instances >= config.max_instances()
@@ -1,6 +1,74 @@
 use super::*;
+use std::convert::TryFrom;
 use std::mem;
 
+/// How [`ensure_termination_with`][crate::ConfiguredModule::ensure_termination_with]
+/// meters fuel.
+#[derive(Clone, Debug)]
+pub struct FuelConfig {
+    /// The initial amount of fuel.
+    ///
+    /// This is clamped to `u32::MAX` when the counter is an `i32`.
+    pub fuel: u64,
+
+    /// The type of the fuel counter global.
+    pub fuel_type: FuelType,
+
+    /// Where fuel is charged.
+    pub metering: FuelMetering,
+
+    /// The cost of each instruction, keyed by its name, for example
+    /// `"I32Add"` or `"Call"`.
+    ///
+    /// Only used with [`FuelMetering::BasicBlock`].
+    pub weights: HashMap<String, u32>,
+
+    /// The cost of instructions that aren't listed in `weights`.
+    pub default_weight: u32,
+}
+
+impl FuelConfig {
+    /// Whether `name` is the name of an instruction that wasm-smith can
+    /// generate, and so can be given a weight in [`FuelConfig::weights`].
+    pub fn is_instruction_name(name: &str) -> bool {
+        Instruction::NAMES.contains(&name)
+    }
+}
+
+impl Default for FuelConfig {
+    fn default() -> FuelConfig {
+        FuelConfig {
+            fuel: 100,
+            fuel_type: FuelType::I32,
+            metering: FuelMetering::Loop,
+            weights: HashMap::new(),
+            default_weight: 1,
+        }
+    }
+}
+
+/// The type of the fuel counter global.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuelType {
+    /// An `i32` counter.
+    I32,
+    /// An `i64` counter.
+    I64,
+}
+
+/// Where fuel is charged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FuelMetering {
+    /// One unit of fuel is charged at the head of each loop and function.
+    Loop,
+    /// The summed weight of each basic block's instructions is charged on
+    /// entry to the block.
+    ///
+    /// Every block is charged at least one unit of fuel, so that execution
+    /// still terminates when weights are zero.
+    BasicBlock,
+}
+
 impl Module {
     /// Ensure that all of this Wasm module's functions will terminate when
     /// executed.
@@ -14,6 +82,12 @@ impl Module {
     pub fn ensure_termination(&mut self, default_fuel: u32) -> u32 {
         self.inner.ensure_termination(default_fuel)
     }
+
+    /// Like [`ensure_termination`][Module::ensure_termination], but with
+    /// control over the fuel counter's type and how fuel is charged.
+    pub fn ensure_termination_with(&mut self, config: &FuelConfig) -> u32 {
+        self.inner.ensure_termination_with(config)
+    }
 }
 
 impl<C> ConfiguredModule<C>
@@ -30,30 +104,62 @@ where
     /// The index of the fuel global is returned, so that you may control how
     /// much fuel the module is given.
     pub fn ensure_termination(&mut self, default_fuel: u32) -> u32 {
+        self.ensure_termination_with(&FuelConfig {
+            fuel: default_fuel.into(),
+            ..FuelConfig::default()
+        })
+    }
+
+    /// Like [`ensure_termination`][ConfiguredModule::ensure_termination], but
+    /// with control over the fuel counter's type and how fuel is charged.
+    ///
+    /// When the remaining fuel is less than the amount about to be charged, a
+    /// trap is raised.
+    pub fn ensure_termination_with(&mut self, config: &FuelConfig) -> u32 {
         let fuel_global = self.globals.len() as u32;
+        let (val_type, init) = match config.fuel_type {
+            FuelType::I32 => (
+                ValType::I32,
+                Instruction::I32Const(u32::try_from(config.fuel).unwrap_or(u32::MAX) as i32),
+            ),
+            FuelType::I64 => (ValType::I64, Instruction::I64Const(config.fuel as i64)),
+        };
         self.globals.push(GlobalType {
-            val_type: ValType::I32,
+            val_type,
             mutable: true,
         });
-        self.defined_globals
-            .push((fuel_global, Instruction::I32Const(default_fuel as i32)));
+        self.defined_globals.push((fuel_global, init));
 
-        for code in &mut self.code {
-            let check_fuel = |insts: &mut Vec<Instruction>| {
-                // if fuel == 0 { trap }
-                insts.push(Instruction::GlobalGet(fuel_global));
-                insts.push(Instruction::I32Eqz);
-                insts.push(Instruction::If(BlockType::Empty));
-                insts.push(Instruction::Unreachable);
-                insts.push(Instruction::End);
-
-                // fuel -= 1
-                insts.push(Instruction::GlobalGet(fuel_global));
-                insts.push(Instruction::I32Const(1));
-                insts.push(Instruction::I32Sub);
-                insts.push(Instruction::GlobalSet(fuel_global));
+        let check_fuel = |insts: &mut Vec<Instruction>, cost: u64| {
+            let (cost, lt_u, sub) = match config.fuel_type {
+                FuelType::I32 => (
+                    Instruction::I32Const(u32::try_from(cost).unwrap_or(u32::MAX) as i32),
+                    Instruction::I32LtU,
+                    Instruction::I32Sub,
+                ),
+                FuelType::I64 => (
+                    Instruction::I64Const(cost as i64),
+                    Instruction::I64LtU,
+                    Instruction::I64Sub,
+                ),
             };
 
+            // if fuel < cost { trap }
+            insts.push(Instruction::GlobalGet(fuel_global));
+            insts.push(cost.clone());
+            insts.push(lt_u);
+            insts.push(Instruction::If(BlockType::Empty));
+            insts.push(Instruction::Unreachable);
+            insts.push(Instruction::End);
+
+            // fuel -= cost
+            insts.push(Instruction::GlobalGet(fuel_global));
+            insts.push(cost);
+            insts.push(sub);
+            insts.push(Instruction::GlobalSet(fuel_global));
+        };
+
+        for code in &mut self.code {
             let instrs = match &mut code.instructions {
                 Instructions::Generated(list) => list,
                 // only present on modules contained within
@@ -63,17 +169,42 @@ where
             };
             let mut new_insts = Vec::with_capacity(instrs.len() * 2);
 
-            // Check fuel at the start of functions to deal with infinite
-            // recursion.
-            check_fuel(&mut new_insts);
+            match config.metering {
+                FuelMetering::Loop => {
+                    // Check fuel at the start of functions to deal with
+                    // infinite recursion.
+                    check_fuel(&mut new_insts, 1);
 
-            for inst in mem::replace(instrs, vec![]) {
-                let is_loop = matches!(&inst, Instruction::Loop(_));
-                new_insts.push(inst);
+                    for inst in mem::take(instrs) {
+                        let is_loop = matches!(&inst, Instruction::Loop(_));
+                        new_insts.push(inst);
 
-                // Check fuel at loop heads to deal with infinite loops.
-                if is_loop {
-                    check_fuel(&mut new_insts);
+                        // Check fuel at loop heads to deal with infinite loops.
+                        if is_loop {
+                            check_fuel(&mut new_insts, 1);
+                        }
+                    }
+                }
+                FuelMetering::BasicBlock => {
+                    // Function bodies and loop bodies both start a new basic
+                    // block, so charging every block at least once also deals
+                    // with infinite recursion and infinite loops.
+                    let mut block = vec![];
+                    let mut cost = 0u64;
+                    for inst in mem::take(instrs) {
+                        cost = cost.saturating_add(instruction_weight(config, &inst).into());
+                        let ends_block = ends_basic_block(&inst);
+                        block.push(inst);
+                        if ends_block {
+                            check_fuel(&mut new_insts, cost.max(1));
+                            new_insts.append(&mut block);
+                            cost = 0;
+                        }
+                    }
+                    if !block.is_empty() {
+                        check_fuel(&mut new_insts, cost.max(1));
+                        new_insts.append(&mut block);
+                    }
                 }
             }
 
@@ -83,3 +214,73 @@ where
         fuel_global
     }
 }
+
+fn instruction_weight(config: &FuelConfig, inst: &Instruction) -> u32 {
+    config
+        .weights
+        .get(inst.name())
+        .copied()
+        .unwrap_or(config.default_weight)
+}
+
+fn ends_basic_block(inst: &Instruction) -> bool {
+    matches!(
+        inst,
+        Instruction::Unreachable
+            | Instruction::Block(_)
+            | Instruction::Loop(_)
+            | Instruction::If(_)
+            | Instruction::Else
+            | Instruction::End
+            | Instruction::Br(_)
+            | Instruction::BrIf(_)
+            | Instruction::BrTable(..)
+            | Instruction::Return
+            | Instruction::Call(_)
+            | Instruction::CallIndirect { .. }
+            | Instruction::Try(_)
+            | Instruction::Catch(_)
+            | Instruction::CatchAll
+            | Instruction::Throw(_)
+            | Instruction::Rethrow(_)
+            | Instruction::Unwind
+            | Instruction::ReturnCall(_)
+            | Instruction::ReturnCallIndirect { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_blocks_are_charged_their_weights() {
+        let mut module = ConfiguredModule::<DefaultConfig>::default();
+        module.code.push(Code {
+            locals: vec![],
+            instructions: Instructions::Generated(vec![Instruction::Call(0), Instruction::I64DivU]),
+        });
+        let mut weights = HashMap::new();
+        weights.insert("Call".to_string(), 10);
+        weights.insert("I64DivU".to_string(), 20);
+        let fuel_global = module.ensure_termination_with(&FuelConfig {
+            metering: FuelMetering::BasicBlock,
+            weights,
+            ..FuelConfig::default()
+        });
+
+        let instrs = match &module.code[0].instructions {
+            Instructions::Generated(list) => list,
+            Instructions::Arbitrary(_) => unreachable!(),
+        };
+        assert_eq!(instrs.len(), 22);
+        for (check, cost) in [(&instrs[..10], 10), (&instrs[11..21], 20)].iter() {
+            assert!(matches!(check[0], Instruction::GlobalGet(g) if g == fuel_global));
+            assert!(matches!(check[1], Instruction::I32Const(c) if c == *cost));
+            assert!(matches!(check[7], Instruction::I32Const(c) if c == *cost));
+            assert!(matches!(check[9], Instruction::GlobalSet(g) if g == fuel_global));
+        }
+        assert!(matches!(instrs[10], Instruction::Call(0)));
+        assert!(matches!(instrs[21], Instruction::I64DivU));
+    }
+}
@@ -0,0 +1,302 @@
+use super::*;
+use std::fmt::{self, Write};
+
+/// The maximum number of exported functions invoked by a script from
+/// [`ConfiguredModule::to_wast`].
+const MAX_INVOCATIONS: usize = 100;
+
+/// A value passed to or returned from an exported function in a `.wast`
+/// script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    /// An `i32` value.
+    I32(i32),
+    /// An `i64` value.
+    I64(i64),
+    /// The bits of an `f32` value.
+    F32(u32),
+    /// The bits of an `f64` value.
+    F64(u64),
+    /// A `v128` value.
+    V128(u128),
+    /// A `funcref` value, which is either null or some unspecified function.
+    FuncRef {
+        /// Whether this is the null reference.
+        null: bool,
+    },
+    /// An `externref` value, which is either null or the host reference
+    /// written `(ref.extern N)` in scripts.
+    ExternRef(Option<u32>),
+}
+
+/// The outcome of invoking an exported function, as computed by an
+/// [`Evaluator`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The function returned these results.
+    Return(Vec<Value>),
+    /// The function trapped with this message.
+    Trap(String),
+}
+
+/// Computes the expected outcomes of the invocations in a `.wast` script,
+/// for example by running them in a reference engine.
+///
+/// See [`ConfiguredModule::to_wast`].
+pub trait Evaluator {
+    /// Instantiates `wasm`, the module of the script, before any of its
+    /// exports are invoked.
+    ///
+    /// Returns the trap message if instantiation traps, in which case no
+    /// exports are invoked.
+    fn instantiate(&mut self, wasm: &[u8]) -> std::result::Result<(), String>;
+
+    /// Invokes the exported function `name` of the instance with `args`.
+    ///
+    /// Returns `None` if the outcome is unknown, which leaves a placeholder
+    /// in the script.
+    fn invoke(&mut self, name: &str, args: &[Value]) -> Option<Outcome>;
+}
+
+impl Module {
+    /// Wrap this Wasm module into a `.wast` script that invokes its exported
+    /// functions with arbitrary arguments.
+    ///
+    /// See [`ConfiguredModule::to_wast`].
+    pub fn to_wast(
+        &self,
+        u: &mut Unstructured,
+        evaluator: Option<&mut dyn Evaluator>,
+    ) -> Result<String> {
+        self.inner.to_wast(u, evaluator)
+    }
+}
+
+impl<C> ConfiguredModule<C>
+where
+    C: Config,
+{
+    /// Wrap this Wasm module into a `.wast` script that invokes its exported
+    /// functions with arbitrary arguments.
+    ///
+    /// The script starts with a `(module binary ...)` directive, followed by
+    /// up to 100 invocations of arbitrary exported functions. When
+    /// `evaluator` computes an invocation's outcome, it becomes an
+    /// `assert_return` or `assert_trap` directive. Otherwise it is left as a
+    /// plain `invoke` directive, followed by a `;; expected: ...` placeholder
+    /// comment with the result types.
+    ///
+    /// Scripts of several modules can be concatenated. Modules with imports
+    /// can only be instantiated by a test harness that provides them, see
+    /// [`Config::available_imports`].
+    pub fn to_wast(
+        &self,
+        u: &mut Unstructured,
+        mut evaluator: Option<&mut dyn Evaluator>,
+    ) -> Result<String> {
+        let wasm = self.to_bytes();
+        let mut script = String::new();
+
+        let mut module = String::from("(module binary");
+        for chunk in wasm.chunks(32) {
+            module.push_str("\n  \"");
+            for byte in chunk {
+                write!(module, "\\{:02x}", byte).unwrap();
+            }
+            module.push('"');
+        }
+        module.push(')');
+
+        if let Some(evaluator) = evaluator.as_mut() {
+            if let Err(message) = evaluator.instantiate(&wasm) {
+                writeln!(script, "(assert_trap {} {})", module, wast_string(&message)).unwrap();
+                return Ok(script);
+            }
+        }
+        writeln!(script, "{}", module).unwrap();
+
+        let funcs: Vec<(&str, &FuncType)> = self
+            .exports
+            .iter()
+            .filter_map(|(name, export)| match export {
+                Export::Func(idx) => Some((name.as_str(), &*self.funcs[*idx as usize].1)),
+                _ => None,
+            })
+            .collect();
+        if funcs.is_empty() {
+            return Ok(script);
+        }
+
+        arbitrary_loop(u, 0, MAX_INVOCATIONS, |u| {
+            let (name, ty) = *u.choose(&funcs)?;
+            let args = ty
+                .params
+                .iter()
+                .map(|ty| arbitrary_value(u, *ty))
+                .collect::<Result<Vec<_>>>()?;
+
+            let mut invoke = format!("(invoke {}", wast_string(name));
+            for arg in args.iter() {
+                write!(invoke, " ({})", arg).unwrap();
+            }
+            invoke.push(')');
+
+            match evaluator.as_mut().and_then(|e| e.invoke(name, &args)) {
+                Some(Outcome::Return(results)) => {
+                    write!(script, "(assert_return {}", invoke).unwrap();
+                    for result in results.iter() {
+                        write!(script, " ({})", ResultPattern(*result)).unwrap();
+                    }
+                    script.push_str(")\n");
+                }
+                Some(Outcome::Trap(message)) => {
+                    writeln!(script, "(assert_trap {} {})", invoke, wast_string(&message)).unwrap();
+                }
+                None => {
+                    write!(script, "{}\n;; expected:", invoke).unwrap();
+                    for ty in ty.results.iter() {
+                        write!(script, " {}", valtype_name(*ty)).unwrap();
+                    }
+                    script.push('\n');
+                }
+            }
+            Ok(true)
+        })?;
+
+        Ok(script)
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured, ty: ValType) -> Result<Value> {
+    Ok(match ty {
+        ValType::I32 => Value::I32(u.arbitrary()?),
+        ValType::I64 => Value::I64(u.arbitrary()?),
+        ValType::F32 => Value::F32(u.arbitrary()?),
+        ValType::F64 => Value::F64(u.arbitrary()?),
+        ValType::V128 => Value::V128(u.arbitrary()?),
+        // Only null function references can be written in scripts.
+        ValType::FuncRef => Value::FuncRef { null: true },
+        ValType::ExternRef => Value::ExternRef(u.arbitrary()?),
+    })
+}
+
+fn valtype_name(ty: ValType) -> &'static str {
+    match ty {
+        ValType::I32 => "i32",
+        ValType::I64 => "i64",
+        ValType::F32 => "f32",
+        ValType::F64 => "f64",
+        ValType::V128 => "v128",
+        ValType::FuncRef => "funcref",
+        ValType::ExternRef => "externref",
+    }
+}
+
+/// Quote `s` as a `.wast` string, escaping everything but printable ASCII.
+fn wast_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            0x20..=0x7e if byte != b'"' && byte != b'\\' => quoted.push(byte as char),
+            _ => write!(quoted, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes the exact value of a float with the given bits, as a hexadecimal
+/// float or a NaN with its payload.
+fn write_float(
+    f: &mut fmt::Formatter,
+    bits: u64,
+    exponent_bits: u32,
+    mantissa_bits: u32,
+) -> fmt::Result {
+    let sign = if bits >> (exponent_bits + mantissa_bits) != 0 {
+        "-"
+    } else {
+        ""
+    };
+    let max_exponent = (1 << exponent_bits) - 1;
+    let exponent = (bits >> mantissa_bits) & max_exponent;
+    let mantissa = bits & ((1 << mantissa_bits) - 1);
+    let bias = (max_exponent >> 1) as i64;
+    // Pad the mantissa to a whole number of hex digits.
+    let padding = (4 - mantissa_bits % 4) % 4;
+    let mantissa_hex = mantissa << padding;
+    let digits = ((mantissa_bits + padding) / 4) as usize;
+    match (exponent, mantissa) {
+        (0, 0) => write!(f, "{}0x0p+0", sign),
+        (0, _) => write!(
+            f,
+            "{}0x0.{:0digits$x}p{:+}",
+            sign,
+            mantissa_hex,
+            1 - bias,
+            digits = digits
+        ),
+        (e, 0) if e == max_exponent => write!(f, "{}inf", sign),
+        (e, m) if e == max_exponent => write!(f, "{}nan:0x{:x}", sign, m),
+        (e, _) => write!(
+            f,
+            "{}0x1.{:0digits$x}p{:+}",
+            sign,
+            mantissa_hex,
+            e as i64 - bias,
+            digits = digits
+        ),
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::I32(x) => write!(f, "i32.const {}", x),
+            Value::I64(x) => write!(f, "i64.const {}", x),
+            Value::F32(bits) => {
+                write!(f, "f32.const ")?;
+                write_float(f, bits.into(), 8, 23)
+            }
+            Value::F64(bits) => {
+                write!(f, "f64.const ")?;
+                write_float(f, bits, 11, 52)
+            }
+            Value::V128(x) => write!(f, "v128.const i64x2 0x{:x} 0x{:x}", x as u64, x >> 64),
+            Value::FuncRef { null: true } => write!(f, "ref.null func"),
+            Value::FuncRef { null: false } => write!(f, "ref.func"),
+            Value::ExternRef(None) => write!(f, "ref.null extern"),
+            Value::ExternRef(Some(x)) => write!(f, "ref.extern {}", x),
+        }
+    }
+}
+
+/// Displays a result value as an `assert_return` pattern, which matches NaNs
+/// as `nan:canonical` or `nan:arithmetic` since their exact bits may differ
+/// between engines.
+struct ResultPattern(Value);
+
+impl fmt::Display for ResultPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Value::F32(bits) if bits & 0x7f80_0000 == 0x7f80_0000 && bits & 0x7f_ffff != 0 => {
+                match bits & 0x7f_ffff {
+                    0x40_0000 => write!(f, "f32.const nan:canonical"),
+                    m if m & 0x40_0000 != 0 => write!(f, "f32.const nan:arithmetic"),
+                    _ => self.0.fmt(f),
+                }
+            }
+            Value::F64(bits)
+                if bits & 0x7ff0_0000_0000_0000 == 0x7ff0_0000_0000_0000
+                    && bits & 0xf_ffff_ffff_ffff != 0 =>
+            {
+                match bits & 0xf_ffff_ffff_ffff {
+                    0x8_0000_0000_0000 => write!(f, "f64.const nan:canonical"),
+                    m if m & 0x8_0000_0000_0000 != 0 => write!(f, "f64.const nan:arithmetic"),
+                    _ => self.0.fmt(f),
+                }
+            }
+            value => value.fmt(f),
+        }
+    }
+}
@@ -1,7 +1,16 @@
 use arbitrary::{Arbitrary, Unstructured};
 use rand::{rngs::SmallRng, RngCore, SeedableRng};
-use wasm_smith::{Config, ConfiguredModule, Module, SwarmConfig};
-use wasmparser::{Validator, WasmFeatures};
+use std::collections::{HashMap, HashSet};
+use wasm_encoder::ValType;
+use wasm_smith::{
+    Config, ConfiguredModule, Evaluator, FuelConfig, FuelMetering, FuelType, ImportType,
+    InvalidModule, Module, Outcome, RequiredExport, SwarmConfig, Value, Violation,
+};
+use wasmparser::{
+    DataKind, ElementItem, ElementKind, ExternalKind, ImportSectionEntryType, InitExpr,
+    MemoryImmediate, MemoryType, Name, NameSectionReader, Operator, Parser, Payload, Type, TypeDef,
+    Validator, WasmFeatures,
+};
 
 fn wasm_features() -> WasmFeatures {
     WasmFeatures {
@@ -13,6 +22,28 @@ fn wasm_features() -> WasmFeatures {
     }
 }
 
+/// Generates modules with configuration `C` from a fixed seed, validates each
+/// of them with `features`, and then passes its bytes to `check`.
+fn smoke_test<C>(features: WasmFeatures, mut check: impl FnMut(&[u8]))
+where
+    C: Config + Arbitrary,
+{
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 1024];
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let u = Unstructured::new(&buf);
+        if let Ok(module) = ConfiguredModule::<C>::arbitrary_take_rest(u) {
+            let wasm_bytes = module.to_bytes();
+
+            let mut validator = Validator::new();
+            validator.wasm_features(features);
+            validate(&mut validator, &wasm_bytes);
+            check(&wasm_bytes);
+        }
+    }
+}
+
 #[test]
 fn smoke_test_module() {
     let mut rng = SmallRng::seed_from_u64(0);
@@ -48,6 +79,153 @@ fn smoke_test_ensure_termination() {
     }
 }
 
+#[test]
+fn smoke_test_ensure_termination_per_block() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 1024];
+    let mut weights = HashMap::new();
+    weights.insert("Call".to_string(), 10);
+    weights.insert("I64DivU".to_string(), 20);
+    weights.insert("Nop".to_string(), 0);
+    let mut calls = 0;
+    for fuel_type in &[FuelType::I32, FuelType::I64] {
+        for _ in 0..512 {
+            rng.fill_bytes(&mut buf);
+            let u = Unstructured::new(&buf);
+            if let Ok(mut module) = Module::arbitrary_take_rest(u) {
+                let fuel_global = module.ensure_termination_with(&FuelConfig {
+                    fuel: 1000,
+                    fuel_type: *fuel_type,
+                    metering: FuelMetering::BasicBlock,
+                    weights: weights.clone(),
+                    default_weight: 1,
+                });
+                let wasm_bytes = module.to_bytes();
+
+                let mut validator = Validator::new();
+                validator.wasm_features(wasm_features());
+                validate(&mut validator, &wasm_bytes);
+
+                for payload in Parser::new(0).parse_all(&wasm_bytes) {
+                    if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                        let ops = body
+                            .get_operators_reader()
+                            .unwrap()
+                            .into_iter()
+                            .collect::<Result<Vec<_>, _>>()
+                            .unwrap();
+                        // The function's final `end` isn't part of any block.
+                        let ops = &ops[..ops.len() - 1];
+                        for (cost, block) in fuel_checked_blocks(ops, fuel_global) {
+                            let weight = block.iter().map(block_weight).sum::<u64>();
+                            assert_eq!(cost, weight.max(1), "{:?}", block);
+                            if block.iter().any(|op| matches!(op, Operator::Call { .. })) {
+                                calls += 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    assert!(calls > 0);
+}
+
+/// Splits a function body metered with `FuelMetering::BasicBlock` into its
+/// basic blocks, asserting that each is preceded by a fuel check, and returns
+/// each block with the amount of fuel charged for it.
+fn fuel_checked_blocks<'a, 'b>(
+    mut ops: &'b [Operator<'a>],
+    fuel_global: u32,
+) -> Vec<(u64, &'b [Operator<'a>])> {
+    let mut blocks = vec![];
+    while !ops.is_empty() {
+        let (check, rest) = ops.split_at(ops.len().min(10));
+        assert!(
+            matches!(
+                check,
+                [
+                    Operator::GlobalGet { global_index: g1 },
+                    _,
+                    Operator::I32LtU | Operator::I64LtU,
+                    Operator::If { .. },
+                    Operator::Unreachable,
+                    Operator::End,
+                    Operator::GlobalGet { global_index: g2 },
+                    _,
+                    Operator::I32Sub | Operator::I64Sub,
+                    Operator::GlobalSet { global_index: g3 },
+                ] if [*g1, *g2, *g3] == [fuel_global; 3]
+            ),
+            "missing fuel check: {:?}",
+            check
+        );
+        let cost = const_value(&check[1]);
+        assert_eq!(cost, const_value(&check[7]));
+        ops = rest;
+        let len = ops
+            .iter()
+            .position(ends_basic_block)
+            .map_or(ops.len(), |i| i + 1);
+        blocks.push((cost, &ops[..len]));
+        ops = &ops[len..];
+    }
+    blocks
+}
+
+fn const_value(op: &Operator) -> u64 {
+    match *op {
+        Operator::I32Const { value } => value as u32 as u64,
+        Operator::I64Const { value } => value as u64,
+        ref op => panic!("expected a constant fuel cost, found {:?}", op),
+    }
+}
+
+/// The weights given in `smoke_test_ensure_termination_per_block`.
+fn block_weight(op: &Operator) -> u64 {
+    match op {
+        Operator::Call { .. } => 10,
+        Operator::I64DivU => 20,
+        Operator::Nop => 0,
+        _ => 1,
+    }
+}
+
+fn ends_basic_block(op: &Operator) -> bool {
+    matches!(
+        op,
+        Operator::Unreachable
+            | Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else
+            | Operator::End
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return
+            | Operator::Call { .. }
+            | Operator::CallIndirect { .. }
+            | Operator::Try { .. }
+            | Operator::Catch { .. }
+            | Operator::Throw { .. }
+            | Operator::Rethrow { .. }
+            | Operator::Unwind
+            | Operator::ReturnCall { .. }
+            | Operator::ReturnCallIndirect { .. }
+    )
+}
+
+#[test]
+fn fuel_weight_instruction_names() {
+    for name in &["Call", "I64DivU", "Nop", "I32Load8_S", "V128Load8Lane"] {
+        assert!(FuelConfig::is_instruction_name(name), "{}", name);
+    }
+    for name in &["", "call", "I32Load8S", "I32Addd"] {
+        assert!(!FuelConfig::is_instruction_name(name), "{}", name);
+    }
+}
+
 #[test]
 fn smoke_test_swarm_config() {
     let mut rng = SmallRng::seed_from_u64(0);
@@ -61,10 +239,1296 @@ fn smoke_test_swarm_config() {
             let mut validator = Validator::new();
             let mut features = wasm_features();
             features.module_linking = module.config().module_linking_enabled();
+            features.simd = module.config().simd_enabled();
+            features.threads = module.config().threads_enabled();
+            features.exceptions = module.config().exceptions_enabled();
+            features.tail_call = module.config().tail_call_enabled();
+            features.memory64 = module.config().memory64_enabled();
             validator.wasm_features(features);
             validate(&mut validator, &wasm_bytes);
+            if module.config().deterministic_only() {
+                assert_no_floats(&wasm_bytes);
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct SimdConfig;
+
+impl Config for SimdConfig {
+    fn simd_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_simd() {
+    let features = WasmFeatures {
+        simd: true,
+        ..WasmFeatures::default()
+    };
+    let mut simd_ops = 0;
+    smoke_test::<SimdConfig>(features, |wasm_bytes| {
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    let op = format!("{:?}", ops.read().unwrap());
+                    if ["V128", "I8x16", "I16x8", "I32x4", "I64x2", "F32x4", "F64x2"]
+                        .iter()
+                        .any(|prefix| op.starts_with(prefix))
+                    {
+                        simd_ops += 1;
+                    }
+                }
+            }
+        }
+    });
+    assert!(simd_ops > 0);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct ThreadsConfig;
+
+impl Config for ThreadsConfig {
+    fn threads_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_threads() {
+    let features = WasmFeatures {
+        threads: true,
+        ..WasmFeatures::default()
+    };
+    let mut shared_memories = 0;
+    let mut atomics = 0;
+    smoke_test::<ThreadsConfig>(features, |wasm_bytes| {
+        let mut check_memory = |ty: MemoryType| {
+            if let MemoryType::M32 {
+                limits,
+                shared: true,
+            } = ty
+            {
+                assert!(limits.maximum.is_some());
+                shared_memories += 1;
+            }
+        };
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.unwrap() {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let ImportSectionEntryType::Memory(ty) = import.unwrap().ty {
+                            check_memory(ty);
+                        }
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for ty in reader {
+                        check_memory(ty.unwrap());
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut ops = body.get_operators_reader().unwrap();
+                    while !ops.eof() {
+                        if atomic_memarg(&ops.read().unwrap()).is_some() {
+                            atomics += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    assert!(shared_memories > 0);
+    assert!(atomics > 0);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct ExceptionsConfig;
+
+impl Config for ExceptionsConfig {
+    fn exceptions_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_exceptions() {
+    let features = WasmFeatures {
+        exceptions: true,
+        ..WasmFeatures::default()
+    };
+    let mut events = 0;
+    let mut tries = 0;
+    smoke_test::<ExceptionsConfig>(features, |wasm_bytes| {
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.unwrap() {
+                Payload::EventSection(reader) => events += reader.get_count(),
+                Payload::CodeSectionEntry(body) => {
+                    let mut ops = body.get_operators_reader().unwrap();
+                    while !ops.eof() {
+                        if let Operator::Try { .. } = ops.read().unwrap() {
+                            tries += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    assert!(events > 0);
+    assert!(tries > 0);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct TailCallConfig;
+
+impl Config for TailCallConfig {
+    fn tail_call_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_tail_call() {
+    let features = WasmFeatures {
+        tail_call: true,
+        ..WasmFeatures::default()
+    };
+    let mut return_calls = 0;
+    smoke_test::<TailCallConfig>(features, |wasm_bytes| {
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    if let Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } =
+                        ops.read().unwrap()
+                    {
+                        return_calls += 1;
+                    }
+                }
+            }
+        }
+    });
+    assert!(return_calls > 0);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct Memory64Config;
+
+impl Config for Memory64Config {
+    fn memory64_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_memory64() {
+    let features = WasmFeatures {
+        memory64: true,
+        ..WasmFeatures::default()
+    };
+    let mut memories64 = 0;
+    smoke_test::<Memory64Config>(features, |wasm_bytes| {
+        memories64 += memory_types(wasm_bytes)
+            .iter()
+            .filter(|ty| matches!(ty, MemoryType::M64 { .. }))
+            .count();
+    });
+    assert!(memories64 > 0);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct MultiMemoryConfig;
+
+impl Config for MultiMemoryConfig {
+    fn multi_memory_enabled(&self) -> bool {
+        true
+    }
+
+    fn max_memories(&self) -> usize {
+        4
+    }
+}
+
+#[test]
+fn smoke_test_multi_memory() {
+    let features = WasmFeatures {
+        multi_memory: true,
+        ..WasmFeatures::default()
+    };
+    let mut multi_memory_modules = 0;
+    let mut other_memory_accesses = 0;
+    smoke_test::<MultiMemoryConfig>(features, |wasm_bytes| {
+        if memory_types(wasm_bytes).len() > 1 {
+            multi_memory_modules += 1;
+        }
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    if let Some((memarg, _)) = memory_access(&ops.read().unwrap()) {
+                        if memarg.memory != 0 {
+                            other_memory_accesses += 1;
+                        }
+                    }
+                }
+            }
+        }
+    });
+    assert!(multi_memory_modules > 0);
+    assert!(other_memory_accesses > 0);
+}
+
+/// The types of the imported and defined memories of `wasm_bytes`.
+fn memory_types(wasm_bytes: &[u8]) -> Vec<MemoryType> {
+    let mut memories = vec![];
+    for payload in Parser::new(0).parse_all(wasm_bytes) {
+        match payload.unwrap() {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let ImportSectionEntryType::Memory(ty) = import.unwrap().ty {
+                        memories.push(ty);
+                    }
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for ty in reader {
+                    memories.push(ty.unwrap());
+                }
+            }
+            _ => {}
+        }
+    }
+    memories
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct DeterministicConfig;
+
+impl Config for DeterministicConfig {
+    fn deterministic_only(&self) -> bool {
+        true
+    }
+
+    fn simd_enabled(&self) -> bool {
+        true
+    }
+
+    fn available_imports(&self) -> Option<Vec<(String, String, ImportType)>> {
+        Some(vec![
+            (
+                "env".to_string(),
+                "sqrt".to_string(),
+                ImportType::Func(vec![ValType::F64], vec![ValType::F64]),
+            ),
+            (
+                "env".to_string(),
+                "memory".to_string(),
+                ImportType::Memory(wasm_encoder::MemoryType {
+                    limits: wasm_encoder::Limits {
+                        min: 1,
+                        max: Some(1),
+                    },
+                    shared: true,
+                    memory64: false,
+                }),
+            ),
+        ])
+    }
+}
+
+#[test]
+fn smoke_test_deterministic_only() {
+    let mut features = wasm_features();
+    features.simd = true;
+    features.deterministic_only = true;
+    smoke_test::<DeterministicConfig>(features, assert_no_floats);
+}
+
+fn assert_no_floats(bytes: &[u8]) {
+    let is_float = |ty: &Type| matches!(ty, Type::F32 | Type::F64);
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload.unwrap() {
+            Payload::TypeSection(reader) => {
+                for ty in reader {
+                    if let TypeDef::Func(ty) = ty.unwrap() {
+                        assert!(!ty.params.iter().chain(ty.returns.iter()).any(is_float));
+                    }
+                }
+            }
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    assert!(!is_float(&global.unwrap().ty.content_type));
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                for local in body.get_locals_reader().unwrap() {
+                    assert!(!is_float(&local.unwrap().1));
+                }
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    let op = format!("{:?}", ops.read().unwrap());
+                    assert!(!op.contains("F32") && !op.contains("F64"), "{}", op);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct HostConfig;
+
+impl Config for HostConfig {
+    fn available_imports(&self) -> Option<Vec<(String, String, ImportType)>> {
+        Some(vec![
+            (
+                "env".to_string(),
+                "log".to_string(),
+                ImportType::Func(vec![ValType::I32], vec![]),
+            ),
+            (
+                "env".to_string(),
+                "now".to_string(),
+                ImportType::Func(vec![], vec![ValType::I64]),
+            ),
+            (
+                "env".to_string(),
+                "memory".to_string(),
+                ImportType::Memory(wasm_encoder::MemoryType {
+                    limits: wasm_encoder::Limits { min: 1, max: None },
+                    shared: false,
+                    memory64: false,
+                }),
+            ),
+        ])
+    }
+}
+
+#[test]
+fn smoke_test_available_imports() {
+    let mut calls_to_imports = 0;
+    smoke_test::<HostConfig>(wasm_features(), |wasm_bytes| {
+        let mut num_func_imports = 0;
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.unwrap() {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import.unwrap();
+                        assert_eq!(import.module, "env");
+                        match (import.field, import.ty) {
+                            (Some("log"), ImportSectionEntryType::Function(_))
+                            | (Some("now"), ImportSectionEntryType::Function(_)) => {
+                                num_func_imports += 1
+                            }
+                            (Some("memory"), ImportSectionEntryType::Memory(_)) => {}
+                            other => panic!("unexpected import {:?}", other),
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut ops = body.get_operators_reader().unwrap();
+                    while !ops.eof() {
+                        if let Operator::Call { function_index } = ops.read().unwrap() {
+                            if function_index < num_func_imports {
+                                calls_to_imports += 1;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+    assert!(calls_to_imports > 0);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct EntryPointConfig;
+
+impl Config for EntryPointConfig {
+    fn required_exports(&self) -> Vec<RequiredExport> {
+        vec![
+            RequiredExport {
+                name: "init".to_string(),
+                params: vec![],
+                results: vec![ValType::I32],
+            },
+            RequiredExport {
+                name: "receive".to_string(),
+                params: vec![ValType::I64, ValType::I32],
+                results: vec![ValType::I32],
+            },
+        ]
+    }
+}
+
+#[test]
+fn smoke_test_required_exports() {
+    smoke_test::<EntryPointConfig>(wasm_features(), |wasm_bytes| {
+        let mut types = Vec::new();
+        let mut func_types = Vec::new();
+        let mut exports = Vec::new();
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.unwrap() {
+                Payload::TypeSection(reader) => {
+                    for ty in reader {
+                        types.push(ty.unwrap());
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let ImportSectionEntryType::Function(ty) = import.unwrap().ty {
+                            func_types.push(ty);
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for ty in reader {
+                        func_types.push(ty.unwrap());
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.unwrap();
+                        if let ExternalKind::Function = export.kind {
+                            exports.push((export.field.to_string(), export.index));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let signature = |name: &str| {
+            let (_, func) = exports.iter().find(|(n, _)| n == name).unwrap();
+            match &types[func_types[*func as usize] as usize] {
+                TypeDef::Func(ty) => (ty.params.to_vec(), ty.returns.to_vec()),
+                _ => panic!("not a function type"),
+            }
+        };
+        assert_eq!(signature("init"), (vec![], vec![Type::I32]));
+        assert_eq!(
+            signature("receive"),
+            (vec![Type::I64, Type::I32], vec![Type::I32])
+        );
+    });
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct NoTrapsConfig;
+
+impl Config for NoTrapsConfig {
+    fn disallow_traps(&self) -> bool {
+        true
+    }
+
+    fn bulk_memory_enabled(&self) -> bool {
+        true
+    }
+
+    fn reference_types_enabled(&self) -> bool {
+        true
+    }
+}
+
+/// Like `NoTrapsConfig`, but with more functions and without the instruction
+/// categories that don't need guards, so that bodies made of a few
+/// instructions still exercise the guards.
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct NoTrapsGuardsConfig;
+
+impl Config for NoTrapsGuardsConfig {
+    fn disallow_traps(&self) -> bool {
+        true
+    }
+
+    fn max_imports(&self) -> usize {
+        0
+    }
+
+    fn min_funcs(&self) -> usize {
+        10
+    }
+
+    fn min_memories(&self) -> u32 {
+        1
+    }
+
+    fn min_tables(&self) -> u32 {
+        1
+    }
+
+    fn min_element_segments(&self) -> usize {
+        1
+    }
+
+    fn float_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn conversion_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn control_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn table_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn reference_instructions_enabled(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn smoke_test_disallow_traps() {
+    let mut guards = TrapGuards::default();
+    smoke_test::<NoTrapsConfig>(wasm_features(), |wasm_bytes| {
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    let op = ops.read().unwrap();
+                    assert!(
+                        !matches!(
+                            op,
+                            Operator::Unreachable
+                                | Operator::I32TruncF32S
+                                | Operator::I64TruncF64U
+                                | Operator::TableGet { .. }
+                                | Operator::MemoryCopy { .. }
+                        ),
+                        "{:?}",
+                        op
+                    );
+                }
+            }
+        }
+        assert_traps_guarded(wasm_bytes, &mut guards);
+    });
+    smoke_test::<NoTrapsGuardsConfig>(wasm_features(), |wasm_bytes| {
+        assert_traps_guarded(wasm_bytes, &mut guards);
+    });
+    assert!(guards.divisors > 0, "{:?}", guards);
+    assert!(guards.addresses > 0, "{:?}", guards);
+    assert!(guards.indirect_calls > 0, "{:?}", guards);
+}
+
+/// How many of each kind of guard `assert_traps_guarded` has checked.
+#[derive(Debug, Default)]
+struct TrapGuards {
+    divisors: usize,
+    addresses: usize,
+    indirect_calls: usize,
+}
+
+/// Asserts that every division, memory access and indirect call in a module
+/// generated with `Config::disallow_traps` comes right after its guard, and
+/// that every active segment fits within its memory or table.
+fn assert_traps_guarded(wasm_bytes: &[u8], guards: &mut TrapGuards) {
+    let mut func_types = vec![];
+    let mut num_imported_tables = 0;
+    let mut table_sizes = vec![];
+    let mut memories = vec![];
+    let mut table_entries = HashMap::new();
+    for payload in Parser::new(0).parse_all(wasm_bytes) {
+        match payload.unwrap() {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    match import.unwrap().ty {
+                        ImportSectionEntryType::Function(ty) => func_types.push(ty),
+                        ImportSectionEntryType::Table(ty) => {
+                            table_sizes.push(u64::from(ty.limits.initial));
+                            num_imported_tables += 1;
+                        }
+                        ImportSectionEntryType::Memory(ty) => memories.push(memory_bounds(&ty)),
+                        _ => {}
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                func_types.extend(reader.into_iter().map(|ty| ty.unwrap()));
+            }
+            Payload::TableSection(reader) => {
+                for ty in reader {
+                    table_sizes.push(u64::from(ty.unwrap().limits.initial));
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for ty in reader {
+                    memories.push(memory_bounds(&ty.unwrap()));
+                }
+            }
+            Payload::ElementSection(reader) => {
+                for elem in reader {
+                    let elem = elem.unwrap();
+                    let (table, offset) = match elem.kind {
+                        ElementKind::Active {
+                            table_index,
+                            init_expr,
+                        } => (table_index, init_expr_value(&init_expr)),
+                        _ => continue,
+                    };
+                    let items = elem
+                        .items
+                        .get_items_reader()
+                        .unwrap()
+                        .into_iter()
+                        .map(|item| match item.unwrap() {
+                            ElementItem::Func(f) => Some(f),
+                            ElementItem::Null(_) => None,
+                        })
+                        .collect::<Vec<_>>();
+                    assert!(offset + items.len() as u64 <= table_sizes[table as usize]);
+                    if table >= num_imported_tables {
+                        for (i, item) in items.into_iter().enumerate() {
+                            table_entries.insert((table, offset + i as u64), item);
+                        }
+                    }
+                }
+            }
+            Payload::DataSection(reader) => {
+                for data in reader {
+                    let data = data.unwrap();
+                    if let DataKind::Active {
+                        memory_index,
+                        init_expr,
+                    } = data.kind
+                    {
+                        let (bound, _) = memories[memory_index as usize];
+                        let offset = init_expr_value(&init_expr);
+                        assert!(offset + data.data.len() as u64 <= bound);
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let ops = body
+                    .get_operators_reader()
+                    .unwrap()
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                let mut address_rems = HashSet::new();
+                for (i, op) in ops.iter().enumerate() {
+                    if let Some((memarg, size)) = memory_access(op) {
+                        let (bound, memory64) = memories[memarg.memory as usize];
+                        let rem =
+                            assert_address_guarded(&ops[..i], op, memarg, size, bound, memory64);
+                        address_rems.extend(rem);
+                        guards.addresses += 1;
+                    }
+                }
+                for (i, op) in ops.iter().enumerate() {
+                    match *op {
+                        Operator::I32DivS
+                        | Operator::I32DivU
+                        | Operator::I32RemS
+                        | Operator::I64DivS
+                        | Operator::I64DivU
+                        | Operator::I64RemS => {}
+                        Operator::I32RemU | Operator::I64RemU if !address_rems.contains(&i) => {}
+                        Operator::CallIndirect { index, table_index }
+                        | Operator::ReturnCallIndirect { index, table_index } => {
+                            let slot = match ops[..i] {
+                                [.., Operator::Drop, Operator::I32Const { value }] => value as u32,
+                                _ => panic!("unguarded {:?}", op),
+                            };
+                            let func = table_entries[&(table_index, u64::from(slot))];
+                            assert_eq!(func.map(|f| func_types[f as usize]), Some(index));
+                            guards.indirect_calls += 1;
+                            continue;
+                        }
+                        _ => continue,
+                    }
+                    // `select(x, 1, x + 1 > 1)` replaces a divisor of `0` or
+                    // `-1` with `1`.
+                    let guarded = match ops[..i] {
+                        [.., Operator::LocalSet { local_index: a }, Operator::LocalGet { local_index: b }, Operator::I32Const { value: 1 }, Operator::LocalGet { local_index: c }, Operator::I32Const { value: 1 }, Operator::I32Add, Operator::I32Const { value: 1 }, Operator::I32GtU, Operator::Select] => {
+                            a == b && b == c
+                        }
+                        [.., Operator::LocalSet { local_index: a }, Operator::LocalGet { local_index: b }, Operator::I64Const { value: 1 }, Operator::LocalGet { local_index: c }, Operator::I64Const { value: 1 }, Operator::I64Add, Operator::I64Const { value: 1 }, Operator::I64GtU, Operator::Select] => {
+                            a == b && b == c
+                        }
+                        _ => false,
+                    };
+                    assert!(guarded, "unguarded {:?}", op);
+                    guards.divisors += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Asserts that the address of the memory access `op` is guarded by the end
+/// of `ops`, so that the whole access is within `bound` bytes, and returns the
+/// index of the guard's `rem_u` in `ops`, if it has one.
+fn assert_address_guarded(
+    ops: &[Operator],
+    op: &Operator,
+    memarg: MemoryImmediate,
+    size: u64,
+    bound: u64,
+    memory64: bool,
+) -> Option<usize> {
+    // The operands above the address are saved to scratch locals around the
+    // guard.
+    let name = format!("{:?}", op);
+    let above = if name.contains("Cmpxchg") || name.contains("Wait") {
+        2
+    } else if ["Store", "Rmw", "Notify", "Lane"]
+        .iter()
+        .any(|s| name.contains(s))
+    {
+        1
+    } else {
+        0
+    };
+    let (rest, gets) = ops.split_at(ops.len() - above);
+    let mut end = rest.len();
+
+    let mut align = 1;
+    if let [.., mask, Operator::I32And | Operator::I64And] = &ops[..end] {
+        align = match *mask {
+            Operator::I32Const { value } => u64::from((value as u32).wrapping_neg()),
+            Operator::I64Const { value } => (value as u64).wrapping_neg(),
+            _ => panic!("unguarded {:?}", op),
+        };
+        end -= 2;
+    }
+    let mut max = if memory64 {
+        u64::MAX
+    } else {
+        u64::from(u32::MAX)
+    };
+    let mut rem = None;
+    if let [.., n, Operator::I32RemU | Operator::I64RemU] = &ops[..end] {
+        max = init_expr_op_value(n) - 1;
+        rem = Some(end - 1);
+        end -= 2;
+    }
+    let max = max - max % align;
+    assert!(
+        max.saturating_add(u64::from(memarg.offset))
+            .saturating_add(size)
+            <= bound,
+        "{:?} may access {} past {}",
+        op,
+        max,
+        bound
+    );
+    if atomic_memarg(op).is_some() {
+        assert!(align >= size, "{:?} may be misaligned", op);
+    }
+
+    let sets = &ops[end - above..end];
+    for (set, get) in sets.iter().zip(gets.iter().rev()) {
+        match (set, get) {
+            (Operator::LocalSet { local_index: a }, Operator::LocalGet { local_index: b })
+                if a == b => {}
+            _ => panic!("{:?} doesn't restore its operands", op),
+        }
+    }
+    rem
+}
+
+/// The minimum size in bytes and whether it is a 64-bit memory.
+fn memory_bounds(ty: &MemoryType) -> (u64, bool) {
+    match *ty {
+        MemoryType::M32 { limits, .. } => (u64::from(limits.initial) * 65536, false),
+        MemoryType::M64 { limits, .. } => (limits.initial * 65536, true),
+    }
+}
+
+fn init_expr_value(init_expr: &InitExpr) -> u64 {
+    init_expr_op_value(&init_expr.get_operators_reader().read().unwrap())
+}
+
+fn init_expr_op_value(op: &Operator) -> u64 {
+    match *op {
+        Operator::I32Const { value } => u64::from(value as u32),
+        Operator::I64Const { value } => value as u64,
+        ref op => panic!("expected a constant, found {:?}", op),
+    }
+}
+
+/// The `memarg` and access size of a memory access.
+fn memory_access(op: &Operator) -> Option<(MemoryImmediate, u64)> {
+    if let Some(memarg) = atomic_memarg(op) {
+        // Atomic accesses always have their natural alignment.
+        return Some((memarg, 1 << memarg.align));
+    }
+    let (memarg, size) = match *op {
+        Operator::I32Load8S { memarg }
+        | Operator::I32Load8U { memarg }
+        | Operator::I64Load8S { memarg }
+        | Operator::I64Load8U { memarg }
+        | Operator::I32Store8 { memarg }
+        | Operator::I64Store8 { memarg }
+        | Operator::V128Load8Splat { memarg }
+        | Operator::V128Load8Lane { memarg, .. }
+        | Operator::V128Store8Lane { memarg, .. } => (memarg, 1),
+        Operator::I32Load16S { memarg }
+        | Operator::I32Load16U { memarg }
+        | Operator::I64Load16S { memarg }
+        | Operator::I64Load16U { memarg }
+        | Operator::I32Store16 { memarg }
+        | Operator::I64Store16 { memarg }
+        | Operator::V128Load16Splat { memarg }
+        | Operator::V128Load16Lane { memarg, .. }
+        | Operator::V128Store16Lane { memarg, .. } => (memarg, 2),
+        Operator::I32Load { memarg }
+        | Operator::F32Load { memarg }
+        | Operator::I64Load32S { memarg }
+        | Operator::I64Load32U { memarg }
+        | Operator::I32Store { memarg }
+        | Operator::F32Store { memarg }
+        | Operator::I64Store32 { memarg }
+        | Operator::V128Load32Splat { memarg }
+        | Operator::V128Load32Zero { memarg }
+        | Operator::V128Load32Lane { memarg, .. }
+        | Operator::V128Store32Lane { memarg, .. } => (memarg, 4),
+        Operator::I64Load { memarg }
+        | Operator::F64Load { memarg }
+        | Operator::I64Store { memarg }
+        | Operator::F64Store { memarg }
+        | Operator::V128Load8x8S { memarg }
+        | Operator::V128Load8x8U { memarg }
+        | Operator::V128Load16x4S { memarg }
+        | Operator::V128Load16x4U { memarg }
+        | Operator::V128Load32x2S { memarg }
+        | Operator::V128Load32x2U { memarg }
+        | Operator::V128Load64Splat { memarg }
+        | Operator::V128Load64Zero { memarg }
+        | Operator::V128Load64Lane { memarg, .. }
+        | Operator::V128Store64Lane { memarg, .. } => (memarg, 8),
+        Operator::V128Load { memarg } | Operator::V128Store { memarg } => (memarg, 16),
+        _ => return None,
+    };
+    Some((memarg, size))
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct NoTrapsThreadsConfig;
+
+impl Config for NoTrapsThreadsConfig {
+    fn disallow_traps(&self) -> bool {
+        true
+    }
+
+    fn threads_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_disallow_traps_atomic_offsets() {
+    let mut features = wasm_features();
+    features.threads = true;
+    let mut atomics = 0;
+    let mut guards = TrapGuards::default();
+    smoke_test::<NoTrapsThreadsConfig>(features, |wasm_bytes| {
+        assert_traps_guarded(wasm_bytes, &mut guards);
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    if let Some(memarg) = atomic_memarg(&ops.read().unwrap()) {
+                        // Atomic accesses always have their natural alignment.
+                        let size = 1 << memarg.align;
+                        assert_eq!(memarg.offset % size, 0, "{:?}", memarg);
+                        atomics += 1;
+                    }
+                }
+            }
+        }
+    });
+    assert!(atomics > 0);
+}
+
+fn atomic_memarg(op: &Operator) -> Option<MemoryImmediate> {
+    match *op {
+        Operator::MemoryAtomicNotify { memarg }
+        | Operator::MemoryAtomicWait32 { memarg }
+        | Operator::MemoryAtomicWait64 { memarg }
+        | Operator::I32AtomicLoad { memarg }
+        | Operator::I64AtomicLoad { memarg }
+        | Operator::I32AtomicLoad8U { memarg }
+        | Operator::I32AtomicLoad16U { memarg }
+        | Operator::I64AtomicLoad8U { memarg }
+        | Operator::I64AtomicLoad16U { memarg }
+        | Operator::I64AtomicLoad32U { memarg }
+        | Operator::I32AtomicStore { memarg }
+        | Operator::I64AtomicStore { memarg }
+        | Operator::I32AtomicStore8 { memarg }
+        | Operator::I32AtomicStore16 { memarg }
+        | Operator::I64AtomicStore8 { memarg }
+        | Operator::I64AtomicStore16 { memarg }
+        | Operator::I64AtomicStore32 { memarg }
+        | Operator::I32AtomicRmwAdd { memarg }
+        | Operator::I64AtomicRmwAdd { memarg }
+        | Operator::I32AtomicRmw8AddU { memarg }
+        | Operator::I32AtomicRmw16AddU { memarg }
+        | Operator::I64AtomicRmw8AddU { memarg }
+        | Operator::I64AtomicRmw16AddU { memarg }
+        | Operator::I64AtomicRmw32AddU { memarg }
+        | Operator::I32AtomicRmwSub { memarg }
+        | Operator::I64AtomicRmwSub { memarg }
+        | Operator::I32AtomicRmw8SubU { memarg }
+        | Operator::I32AtomicRmw16SubU { memarg }
+        | Operator::I64AtomicRmw8SubU { memarg }
+        | Operator::I64AtomicRmw16SubU { memarg }
+        | Operator::I64AtomicRmw32SubU { memarg }
+        | Operator::I32AtomicRmwAnd { memarg }
+        | Operator::I64AtomicRmwAnd { memarg }
+        | Operator::I32AtomicRmw8AndU { memarg }
+        | Operator::I32AtomicRmw16AndU { memarg }
+        | Operator::I64AtomicRmw8AndU { memarg }
+        | Operator::I64AtomicRmw16AndU { memarg }
+        | Operator::I64AtomicRmw32AndU { memarg }
+        | Operator::I32AtomicRmwOr { memarg }
+        | Operator::I64AtomicRmwOr { memarg }
+        | Operator::I32AtomicRmw8OrU { memarg }
+        | Operator::I32AtomicRmw16OrU { memarg }
+        | Operator::I64AtomicRmw8OrU { memarg }
+        | Operator::I64AtomicRmw16OrU { memarg }
+        | Operator::I64AtomicRmw32OrU { memarg }
+        | Operator::I32AtomicRmwXor { memarg }
+        | Operator::I64AtomicRmwXor { memarg }
+        | Operator::I32AtomicRmw8XorU { memarg }
+        | Operator::I32AtomicRmw16XorU { memarg }
+        | Operator::I64AtomicRmw8XorU { memarg }
+        | Operator::I64AtomicRmw16XorU { memarg }
+        | Operator::I64AtomicRmw32XorU { memarg }
+        | Operator::I32AtomicRmwXchg { memarg }
+        | Operator::I64AtomicRmwXchg { memarg }
+        | Operator::I32AtomicRmw8XchgU { memarg }
+        | Operator::I32AtomicRmw16XchgU { memarg }
+        | Operator::I64AtomicRmw8XchgU { memarg }
+        | Operator::I64AtomicRmw16XchgU { memarg }
+        | Operator::I64AtomicRmw32XchgU { memarg }
+        | Operator::I32AtomicRmwCmpxchg { memarg }
+        | Operator::I64AtomicRmwCmpxchg { memarg }
+        | Operator::I32AtomicRmw8CmpxchgU { memarg }
+        | Operator::I32AtomicRmw16CmpxchgU { memarg }
+        | Operator::I64AtomicRmw8CmpxchgU { memarg }
+        | Operator::I64AtomicRmw16CmpxchgU { memarg }
+        | Operator::I64AtomicRmw32CmpxchgU { memarg } => Some(memarg),
+        _ => None,
+    }
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct NamesConfig;
+
+impl Config for NamesConfig {
+    fn name_section_enabled(&self) -> bool {
+        true
+    }
+
+    fn max_custom_sections(&self) -> usize {
+        10
+    }
+}
+
+#[test]
+fn smoke_test_name_and_custom_sections() {
+    let mut saw_local_names = false;
+    let mut saw_custom_section = false;
+    smoke_test::<NamesConfig>(wasm_features(), |wasm_bytes| {
+        let mut name_sections = 0;
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            match payload.unwrap() {
+                Payload::CustomSection {
+                    name: "name",
+                    data,
+                    data_offset,
+                } => {
+                    name_sections += 1;
+                    for name in NameSectionReader::new(data, data_offset).unwrap() {
+                        if let Name::Local(_) = name.unwrap() {
+                            saw_local_names = true;
+                        }
+                    }
+                }
+                Payload::CustomSection { .. } => saw_custom_section = true,
+                _ => {}
+            }
+        }
+        assert_eq!(name_sections, 1);
+
+        wasmprinter::print_bytes(wasm_bytes).unwrap();
+    });
+    assert!(saw_local_names);
+    assert!(saw_custom_section);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct NestedNamesConfig;
+
+impl Config for NestedNamesConfig {
+    fn name_section_enabled(&self) -> bool {
+        true
+    }
+
+    fn module_linking_enabled(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn smoke_test_nested_module_names() {
+    let mut features = wasm_features();
+    features.module_linking = true;
+    let mut saw_nested_names = false;
+    smoke_test::<NestedNamesConfig>(features, |wasm_bytes| {
+        // Only the outermost module has a module name, so that the names of
+        // nested modules can't collide.
+        let mut module_names = 0;
+        let mut name_sections = 0;
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CustomSection {
+                name: "name",
+                data,
+                data_offset,
+            } = payload.unwrap()
+            {
+                name_sections += 1;
+                for name in NameSectionReader::new(data, data_offset).unwrap() {
+                    if let Name::Module(_) = name.unwrap() {
+                        module_names += 1;
+                    }
+                }
+            }
+        }
+        assert!(module_names <= 1);
+        saw_nested_names |= name_sections > 1;
+    });
+    assert!(saw_nested_names);
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct FuncCountConfig {
+    num_funcs: usize,
+}
+
+impl Config for FuncCountConfig {
+    fn min_funcs(&self) -> usize {
+        self.num_funcs
+    }
+    fn max_funcs(&self) -> usize {
+        self.num_funcs
+    }
+    fn max_imports(&self) -> usize {
+        0
+    }
+}
+
+#[test]
+fn smoke_test_configured_module_new() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 1024];
+    for num_funcs in 0..64 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        let config = FuncCountConfig { num_funcs };
+        if let Ok(module) = ConfiguredModule::new(config, &mut u) {
+            assert_eq!(module.config().num_funcs, num_funcs);
+            let wasm_bytes = module.to_bytes();
+
+            let mut validator = Validator::new();
+            validator.wasm_features(wasm_features());
+            validate(&mut validator, &wasm_bytes);
+
+            for payload in Parser::new(0).parse_all(&wasm_bytes) {
+                if let Payload::FunctionSection(reader) = payload.unwrap() {
+                    assert_eq!(reader.get_count() as usize, num_funcs);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Arbitrary, Clone, Debug, Default)]
+struct NoControlConfig;
+
+impl Config for NoControlConfig {
+    fn bulk_memory_enabled(&self) -> bool {
+        true
+    }
+
+    fn reference_types_enabled(&self) -> bool {
+        true
+    }
+
+    fn control_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn call_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn memory_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn conversion_instructions_enabled(&self) -> bool {
+        false
+    }
+
+    fn table_instructions_enabled(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn smoke_test_instruction_categories() {
+    smoke_test::<NoControlConfig>(wasm_features(), |wasm_bytes| {
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            if let Payload::CodeSectionEntry(body) = payload.unwrap() {
+                let mut ops = body.get_operators_reader().unwrap();
+                while !ops.eof() {
+                    let op = ops.read().unwrap();
+                    assert!(
+                        !matches!(
+                            op,
+                            Operator::Block { .. }
+                                | Operator::Loop { .. }
+                                | Operator::If { .. }
+                                | Operator::Br { .. }
+                                | Operator::BrTable { .. }
+                                | Operator::Call { .. }
+                                | Operator::CallIndirect { .. }
+                                | Operator::I32Load { .. }
+                                | Operator::I64Store { .. }
+                                | Operator::MemoryGrow { .. }
+                                | Operator::I32WrapI64
+                                | Operator::F64ConvertI32S
+                                | Operator::TableGet { .. }
+                                | Operator::TableSize { .. }
+                        ),
+                        "{:?}",
+                        op
+                    );
+                }
+            }
+        }
+    });
+}
+
+// Cycles through every kind of outcome, including unknown ones.
+#[derive(Default)]
+struct CyclingEvaluator {
+    invocations: usize,
+}
+
+impl Evaluator for CyclingEvaluator {
+    fn instantiate(&mut self, _: &[u8]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn invoke(&mut self, _: &str, args: &[Value]) -> Option<Outcome> {
+        self.invocations += 1;
+        match self.invocations % 3 {
+            0 => None,
+            1 => Some(Outcome::Trap("integer \"overflow\"".to_string())),
+            _ => Some(Outcome::Return(
+                args.iter()
+                    .copied()
+                    .chain(vec![
+                        Value::F32(0x7fc0_0000),
+                        Value::F64(0xfff8_0000_0000_0001),
+                    ])
+                    .collect(),
+            )),
+        }
+    }
+}
+
+#[test]
+fn smoke_test_wast_script() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    for i in 0..256 {
+        rng.fill_bytes(&mut buf);
+        let mut u = Unstructured::new(&buf);
+        if let Ok(module) = Module::arbitrary(&mut u) {
+            let mut evaluator = CyclingEvaluator::default();
+            let evaluator: Option<&mut dyn Evaluator> = if i % 2 == 0 {
+                Some(&mut evaluator)
+            } else {
+                None
+            };
+            let script = module.to_wast(&mut u, evaluator).unwrap();
+
+            let buf = wast::parser::ParseBuffer::new(&script).unwrap();
+            let wast = wast::parser::parse::<wast::Wast>(&buf).unwrap();
+            let mut directives = wast.directives.into_iter();
+            match directives.next() {
+                Some(wast::WastDirective::Module(mut m)) => {
+                    assert_eq!(m.encode().unwrap(), module.to_bytes());
+                }
+                _ => panic!("expected a module directive"),
+            }
+            for directive in directives {
+                assert!(matches!(
+                    directive,
+                    wast::WastDirective::Invoke(_)
+                        | wast::WastDirective::AssertReturn { .. }
+                        | wast::WastDirective::AssertTrap { .. }
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn smoke_test_invalid_module() {
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut buf = vec![0; 2048];
+    let mut seen = HashMap::new();
+    for _ in 0..1024 {
+        rng.fill_bytes(&mut buf);
+        let u = Unstructured::new(&buf);
+        if let Ok(module) = InvalidModule::<SwarmConfig>::arbitrary_take_rest(u) {
+            let wasm_bytes = module.to_bytes();
+            let config = module.module().config();
+
+            let mut validator = Validator::new();
+            validator.wasm_features(WasmFeatures {
+                simd: config.simd_enabled(),
+                threads: config.threads_enabled(),
+                exceptions: config.exceptions_enabled(),
+                tail_call: config.tail_call_enabled(),
+                memory64: config.memory64_enabled(),
+                module_linking: config.module_linking_enabled(),
+                ..wasm_features()
+            });
+            let err = match validator.validate_all(&wasm_bytes) {
+                Ok(()) => panic!("{:?} module validated", module.violation()),
+                Err(e) => e.to_string(),
+            };
+            let expected = match module.violation() {
+                Violation::TypeMismatch => "type mismatch",
+                Violation::IndexOutOfBounds => "index out of bounds",
+                Violation::MissingEnd => "control frames remain",
+                Violation::DisabledFeature => "support is not enabled",
+                Violation::LimitExceeded => "size minimum must not be greater than maximum",
+            };
+            assert!(err.contains(expected), "{:?}: {}", module.violation(), err);
+            *seen.entry(module.violation()).or_insert(0) += 1;
         }
     }
+    assert_eq!(seen.len(), 5, "{:?}", seen);
 }
 
 fn validate(validator: &mut Validator, bytes: &[u8]) {
@@ -72,9 +1536,15 @@ fn validate(validator: &mut Validator, bytes: &[u8]) {
         Ok(()) => return,
         Err(e) => e,
     };
-    drop(std::fs::write("test.wasm", &bytes));
+    // Dump the module outside of the source tree, so it can't be committed.
+    let dir = std::env::temp_dir();
+    drop(std::fs::write(dir.join("test.wasm"), bytes));
     if let Ok(text) = wasmprinter::print_bytes(bytes) {
-        drop(std::fs::write("test.wat", &text));
+        drop(std::fs::write(dir.join("test.wat"), &text));
     }
-    panic!("wasm failed to validate {:?}", err);
+    panic!(
+        "wasm failed to validate {:?}; wrote it to {}",
+        err,
+        dir.join("test.wasm").display()
+    );
 }
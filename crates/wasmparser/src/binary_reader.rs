@@ -20,23 +20,14 @@ use std::str;
 use crate::limits::*;
 
 use crate::primitives::{
-    BinaryReaderError, BrTable, CustomSectionKind, ExternalKind, FuncType, GlobalType, Ieee32,
-    Ieee64, LinkingType, MemoryImmediate, MemoryType, NameType, Operator, RelocType,
-    ResizableLimits, ResizableLimits64, Result, SIMDLaneIndex, SectionCode, TableType, Type,
-    TypeOrFuncType, V128,
+    BinaryReaderError, BrTable, ExternalKind, FuncType, GlobalType, Ieee32, Ieee64,
+    MemoryImmediate, MemoryType, NameType, Operator, RelocType, ResizableLimits, ResizableLimits64,
+    Result, SIMDLaneIndex, SectionCode, TableType, Type, TypeOrFuncType, V128,
 };
 use crate::{EventType, ExportType, Import, ImportSectionEntryType, InstanceType, ModuleType};
 
 const MAX_WASM_BR_TABLE_SIZE: usize = MAX_WASM_FUNCTION_SIZE;
 
-fn is_name(name: &str, expected: &'static str) -> bool {
-    name == expected
-}
-
-fn is_name_prefix(name: &str, prefix: &'static str) -> bool {
-    name.starts_with(prefix)
-}
-
 const WASM_MAGIC_NUMBER: &[u8; 4] = b"\0asm";
 const WASM_EXPERIMENTAL_VERSION: u32 = 0xd;
 const WASM_SUPPORTED_VERSION: u32 = 0x1;
@@ -418,46 +409,6 @@ impl<'a> BinaryReader<'a> {
         })
     }
 
-    pub(crate) fn read_section_code(&mut self, id: u32, offset: usize) -> Result<SectionCode<'a>> {
-        match id {
-            0 => {
-                let name = self.read_string()?;
-                let kind = if is_name(name, "name") {
-                    CustomSectionKind::Name
-                } else if is_name(name, "producers") {
-                    CustomSectionKind::Producers
-                } else if is_name(name, "sourceMappingURL") {
-                    CustomSectionKind::SourceMappingURL
-                } else if is_name_prefix(name, "reloc.") {
-                    CustomSectionKind::Reloc
-                } else if is_name(name, "linking") {
-                    CustomSectionKind::Linking
-                } else {
-                    CustomSectionKind::Unknown
-                };
-                Ok(SectionCode::Custom { name, kind })
-            }
-            1 => Ok(SectionCode::Type),
-            2 => Ok(SectionCode::Import),
-            3 => Ok(SectionCode::Function),
-            4 => Ok(SectionCode::Table),
-            5 => Ok(SectionCode::Memory),
-            6 => Ok(SectionCode::Global),
-            7 => Ok(SectionCode::Export),
-            8 => Ok(SectionCode::Start),
-            9 => Ok(SectionCode::Element),
-            10 => Ok(SectionCode::Code),
-            11 => Ok(SectionCode::Data),
-            12 => Ok(SectionCode::DataCount),
-            13 => Ok(SectionCode::Event),
-            14 => Ok(SectionCode::Module),
-            15 => Ok(SectionCode::Instance),
-            16 => Ok(SectionCode::Alias),
-            17 => Ok(SectionCode::ModuleCode),
-            _ => Err(BinaryReaderError::new("Invalid section code", offset)),
-        }
-    }
-
     fn read_br_table(&mut self) -> Result<BrTable<'a>> {
         let targets_len = self.read_var_u32()? as usize;
         if targets_len > MAX_WASM_BR_TABLE_SIZE {
@@ -1847,19 +1798,6 @@ impl<'a> BinaryReader<'a> {
         }
     }
 
-    pub(crate) fn read_linking_type(&mut self) -> Result<LinkingType> {
-        let ty = self.read_var_u32()?;
-        Ok(match ty {
-            1 => LinkingType::StackPointer(self.read_var_u32()?),
-            _ => {
-                return Err(BinaryReaderError::new(
-                    "Invalid linking type",
-                    self.original_position() - 1,
-                ));
-            }
-        })
-    }
-
     pub(crate) fn read_reloc_type(&mut self) -> Result<RelocType> {
         let code = self.read_var_u7()?;
         match code {
@@ -1871,6 +1809,12 @@ impl<'a> BinaryReader<'a> {
             5 => Ok(RelocType::GlobalAddrI32),
             6 => Ok(RelocType::TypeIndexLEB),
             7 => Ok(RelocType::GlobalIndexLEB),
+            8 => Ok(RelocType::FunctionOffsetI32),
+            9 => Ok(RelocType::SectionOffsetI32),
+            10 => Ok(RelocType::EventIndexLEB),
+            11 => Ok(RelocType::GlobalAddrRelSLEB),
+            12 => Ok(RelocType::TableIndexRelSLEB),
+            13 => Ok(RelocType::GlobalIndexI32),
             _ => Err(BinaryReaderError::new(
                 "Invalid reloc type",
                 self.original_position() - 1,
@@ -244,6 +244,11 @@ impl OperatorValidator {
         }
     }
 
+    /// Whether the rest of the current control frame is unreachable.
+    pub(crate) fn is_unreachable(&self) -> bool {
+        matches!(self.control.last(), Some(control) if control.unreachable)
+    }
+
     /// Flags the current control frame as unreachable, additionally truncating
     /// the currently active operand stack.
     fn unreachable(&mut self) {
@@ -274,12 +274,11 @@ pub enum NameType {
     Local,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum LinkingType {
-    StackPointer(u32),
-}
-
-#[derive(Debug, Copy, Clone)]
+/// A relocation type from the WebAssembly tool conventions.
+///
+/// The `GlobalAddr*` relocations refer to addresses in linear memory, and
+/// are called `R_WASM_MEMORY_ADDR_*` in the tool conventions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RelocType {
     FunctionIndexLEB,
     TableIndexSLEB,
@@ -289,6 +288,34 @@ pub enum RelocType {
     GlobalAddrI32,
     TypeIndexLEB,
     GlobalIndexLEB,
+    FunctionOffsetI32,
+    SectionOffsetI32,
+    EventIndexLEB,
+    GlobalAddrRelSLEB,
+    TableIndexRelSLEB,
+    GlobalIndexI32,
+}
+
+impl RelocType {
+    /// Whether relocations of this type carry an addend.
+    pub fn has_addend(&self) -> bool {
+        match self {
+            RelocType::GlobalAddrLEB
+            | RelocType::GlobalAddrSLEB
+            | RelocType::GlobalAddrI32
+            | RelocType::GlobalAddrRelSLEB
+            | RelocType::FunctionOffsetI32
+            | RelocType::SectionOffsetI32 => true,
+            RelocType::FunctionIndexLEB
+            | RelocType::TableIndexSLEB
+            | RelocType::TableIndexI32
+            | RelocType::TypeIndexLEB
+            | RelocType::GlobalIndexLEB
+            | RelocType::EventIndexLEB
+            | RelocType::TableIndexRelSLEB
+            | RelocType::GlobalIndexI32 => false,
+        }
+    }
 }
 
 /// A br_table entries representation.
@@ -13,46 +13,350 @@
  * limitations under the License.
  */
 
-use super::{
-    BinaryReader, LinkingType, Range, Result, SectionIteratorLimited, SectionReader,
-    SectionWithLimitedItems,
-};
+use super::{BinaryReader, BinaryReaderError, Range, Result, SectionIterator, SectionReader};
 
+/// The version of the "linking" section understood by this reader.
+pub const LINKING_SECTION_VERSION: u32 = 2;
+
+/// Symbol flags, as found in the `flags` of a [`SymbolInfo`].
+pub mod symbol_flags {
+    pub const BINDING_WEAK: u32 = 0x1;
+    pub const BINDING_LOCAL: u32 = 0x2;
+    pub const VISIBILITY_HIDDEN: u32 = 0x4;
+    pub const UNDEFINED: u32 = 0x10;
+    pub const EXPORTED: u32 = 0x20;
+    pub const EXPLICIT_NAME: u32 = 0x40;
+    pub const NO_STRIP: u32 = 0x80;
+}
+
+/// A subsection of the "linking" custom section.
+#[derive(Debug, Clone)]
+pub enum Linking<'a> {
+    SegmentInfo(SegmentInfoReader<'a>),
+    InitFuncs(InitFuncReader<'a>),
+    ComdatInfo(ComdatReader<'a>),
+    SymbolTable(SymbolTableReader<'a>),
+    Unknown { ty: u32, data: &'a [u8] },
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct SegmentInfo<'a> {
+    pub name: &'a str,
+    /// The alignment of the segment, as a power of two.
+    pub alignment: u32,
+    pub flags: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct InitFunc {
+    pub priority: u32,
+    pub symbol_index: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comdat<'a> {
+    pub name: &'a str,
+    pub flags: u32,
+    pub symbols: Box<[ComdatSymbol]>,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ComdatSymbolKind {
+    Data,
+    Function,
+    Global,
+    Event,
+    Table,
+    Section,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ComdatSymbol {
+    pub kind: ComdatSymbolKind,
+    pub index: u32,
+}
+
+/// The location of a defined data symbol.
+#[derive(Debug, Copy, Clone)]
+pub struct DefinedDataSymbol {
+    pub index: u32,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum SymbolInfo<'a> {
+    Func {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+    Data {
+        flags: u32,
+        name: &'a str,
+        symbol: Option<DefinedDataSymbol>,
+    },
+    Global {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+    Section {
+        flags: u32,
+        section: u32,
+    },
+    Event {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+    Table {
+        flags: u32,
+        index: u32,
+        name: Option<&'a str>,
+    },
+}
+
+macro_rules! subsection_reader {
+    ($(#[$attr:meta])* $name:ident => $item:ty, $read:ident) => {
+        $(#[$attr])*
+        ///
+        /// This is also an iterator over the remaining entries.
+        #[derive(Debug, Clone)]
+        pub struct $name<'a> {
+            reader: BinaryReader<'a>,
+            count: u32,
+            remaining: u32,
+        }
+
+        impl<'a> $name<'a> {
+            fn new(data: &'a [u8], offset: usize) -> Result<$name<'a>> {
+                let mut reader = BinaryReader::new_with_offset(data, offset);
+                let count = reader.read_var_u32()?;
+                Ok($name {
+                    reader,
+                    count,
+                    remaining: count,
+                })
+            }
+
+            pub fn get_count(&self) -> u32 {
+                self.count
+            }
+
+            pub fn original_position(&self) -> usize {
+                self.reader.original_position()
+            }
+
+            pub fn read(&mut self) -> Result<$item> {
+                self.remaining = self.remaining.saturating_sub(1);
+                $read(&mut self.reader)
+            }
+        }
+
+        impl<'a> Iterator for $name<'a> {
+            type Item = Result<$item>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                if self.remaining == 0 {
+                    return None;
+                }
+                let result = self.read();
+                if result.is_err() {
+                    self.remaining = 0;
+                }
+                Some(result)
+            }
+        }
+    };
+}
+
+subsection_reader! {
+    /// Reads the entries of a `WASM_SEGMENT_INFO` subsection.
+    SegmentInfoReader => SegmentInfo<'a>, read_segment_info
+}
+subsection_reader! {
+    /// Reads the entries of a `WASM_INIT_FUNCS` subsection.
+    InitFuncReader => InitFunc, read_init_func
+}
+subsection_reader! {
+    /// Reads the entries of a `WASM_COMDAT_INFO` subsection.
+    ComdatReader => Comdat<'a>, read_comdat
+}
+subsection_reader! {
+    /// Reads the entries of a `WASM_SYMBOL_TABLE` subsection.
+    SymbolTableReader => SymbolInfo<'a>, read_symbol_info
+}
+
+fn read_segment_info<'a>(reader: &mut BinaryReader<'a>) -> Result<SegmentInfo<'a>> {
+    Ok(SegmentInfo {
+        name: reader.read_string()?,
+        alignment: reader.read_var_u32()?,
+        flags: reader.read_var_u32()?,
+    })
+}
+
+fn read_init_func(reader: &mut BinaryReader<'_>) -> Result<InitFunc> {
+    Ok(InitFunc {
+        priority: reader.read_var_u32()?,
+        symbol_index: reader.read_var_u32()?,
+    })
+}
+
+fn read_comdat<'a>(reader: &mut BinaryReader<'a>) -> Result<Comdat<'a>> {
+    let name = reader.read_string()?;
+    let flags = reader.read_var_u32()?;
+    let count = reader.read_var_u32()?;
+    let mut symbols = Vec::new();
+    for _ in 0..count {
+        let offset = reader.original_position();
+        let kind = match reader.read_u8()? {
+            0 => ComdatSymbolKind::Data,
+            1 => ComdatSymbolKind::Function,
+            2 => ComdatSymbolKind::Global,
+            3 => ComdatSymbolKind::Event,
+            4 => ComdatSymbolKind::Table,
+            5 => ComdatSymbolKind::Section,
+            _ => return Err(BinaryReaderError::new("Invalid comdat symbol kind", offset)),
+        };
+        let index = reader.read_var_u32()?;
+        symbols.push(ComdatSymbol { kind, index });
+    }
+    Ok(Comdat {
+        name,
+        flags,
+        symbols: symbols.into_boxed_slice(),
+    })
+}
+
+fn read_symbol_info<'a>(reader: &mut BinaryReader<'a>) -> Result<SymbolInfo<'a>> {
+    let offset = reader.original_position();
+    let kind = reader.read_u8()?;
+    let flags = reader.read_var_u32()?;
+
+    // Function, global, event and table symbols only carry a name if they are
+    // defined or explicitly named.
+    let has_name = flags & symbol_flags::UNDEFINED == 0 || flags & symbol_flags::EXPLICIT_NAME != 0;
+    let indexed = |reader: &mut BinaryReader<'a>| -> Result<(u32, Option<&'a str>)> {
+        let index = reader.read_var_u32()?;
+        let name = if has_name {
+            Some(reader.read_string()?)
+        } else {
+            None
+        };
+        Ok((index, name))
+    };
+
+    Ok(match kind {
+        0 => {
+            let (index, name) = indexed(reader)?;
+            SymbolInfo::Func { flags, index, name }
+        }
+        1 => {
+            let name = reader.read_string()?;
+            let symbol = if flags & symbol_flags::UNDEFINED == 0 {
+                Some(DefinedDataSymbol {
+                    index: reader.read_var_u32()?,
+                    offset: reader.read_var_u32()?,
+                    size: reader.read_var_u32()?,
+                })
+            } else {
+                None
+            };
+            SymbolInfo::Data {
+                flags,
+                name,
+                symbol,
+            }
+        }
+        2 => {
+            let (index, name) = indexed(reader)?;
+            SymbolInfo::Global { flags, index, name }
+        }
+        3 => SymbolInfo::Section {
+            flags,
+            section: reader.read_var_u32()?,
+        },
+        4 => {
+            let (index, name) = indexed(reader)?;
+            SymbolInfo::Event { flags, index, name }
+        }
+        5 => {
+            let (index, name) = indexed(reader)?;
+            SymbolInfo::Table { flags, index, name }
+        }
+        _ => return Err(BinaryReaderError::new("Invalid symbol kind", offset)),
+    })
+}
+
+/// Reads the subsections of a "linking" custom section, as described by the
+/// WebAssembly tool conventions.
 pub struct LinkingSectionReader<'a> {
     reader: BinaryReader<'a>,
-    count: u32,
+    version: u32,
 }
 
 impl<'a> LinkingSectionReader<'a> {
     pub fn new(data: &'a [u8], offset: usize) -> Result<LinkingSectionReader<'a>> {
         let mut reader = BinaryReader::new_with_offset(data, offset);
-        let count = reader.read_var_u32()?;
-        Ok(LinkingSectionReader { reader, count })
+        let version = reader.read_var_u32()?;
+        if version != LINKING_SECTION_VERSION {
+            return Err(BinaryReaderError::new(
+                "Unsupported linking section version",
+                offset,
+            ));
+        }
+        Ok(LinkingSectionReader { reader, version })
     }
 
-    pub fn get_count(&self) -> u32 {
-        self.count
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn eof(&self) -> bool {
+        self.reader.eof()
     }
 
     pub fn original_position(&self) -> usize {
         self.reader.original_position()
     }
 
-    pub fn read<'b>(&mut self) -> Result<LinkingType>
+    pub fn read<'b>(&mut self) -> Result<Linking<'b>>
     where
         'a: 'b,
     {
-        Ok(self.reader.read_linking_type()?)
+        let ty = self.reader.read_u8()?;
+        let payload_len = self.reader.read_var_u32()? as usize;
+        let payload_start = self.reader.position;
+        let payload_end = match payload_start.checked_add(payload_len) {
+            Some(end) if end <= self.reader.buffer.len() => end,
+            _ => {
+                return Err(BinaryReaderError::new(
+                    "Linking subsection extends past end of the linking section",
+                    self.reader.original_offset + self.reader.buffer.len(),
+                ));
+            }
+        };
+        let offset = self.reader.original_offset + payload_start;
+        let data = &self.reader.buffer[payload_start..payload_end];
+        self.reader.skip_to(payload_end);
+        Ok(match ty {
+            5 => Linking::SegmentInfo(SegmentInfoReader::new(data, offset)?),
+            6 => Linking::InitFuncs(InitFuncReader::new(data, offset)?),
+            7 => Linking::ComdatInfo(ComdatReader::new(data, offset)?),
+            8 => Linking::SymbolTable(SymbolTableReader::new(data, offset)?),
+            ty => Linking::Unknown { ty, data },
+        })
     }
 }
 
 impl<'a> SectionReader for LinkingSectionReader<'a> {
-    type Item = LinkingType;
+    type Item = Linking<'a>;
     fn read(&mut self) -> Result<Self::Item> {
         LinkingSectionReader::read(self)
     }
     fn eof(&self) -> bool {
-        self.reader.eof()
+        LinkingSectionReader::eof(self)
     }
     fn original_position(&self) -> usize {
         LinkingSectionReader::original_position(self)
@@ -62,17 +366,24 @@ impl<'a> SectionReader for LinkingSectionReader<'a> {
     }
 }
 
-impl<'a> SectionWithLimitedItems for LinkingSectionReader<'a> {
-    fn get_count(&self) -> u32 {
-        LinkingSectionReader::get_count(self)
+impl<'a> IntoIterator for LinkingSectionReader<'a> {
+    type Item = Result<Linking<'a>>;
+    type IntoIter = SectionIterator<LinkingSectionReader<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SectionIterator::new(self)
     }
 }
 
-impl<'a> IntoIterator for LinkingSectionReader<'a> {
-    type Item = Result<LinkingType>;
-    type IntoIter = SectionIteratorLimited<LinkingSectionReader<'a>>;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn into_iter(self) -> Self::IntoIter {
-        SectionIteratorLimited::new(self)
+    #[test]
+    fn subsection_past_end_is_an_error() {
+        // Version 2, then a symbol table subsection claiming `u32::MAX` bytes.
+        let data = [2, 8, 0xff, 0xff, 0xff, 0xff, 0x0f, 0];
+        let mut reader = LinkingSectionReader::new(&data, 0).unwrap();
+        assert!(reader.read().is_err());
     }
 }
@@ -14,8 +14,8 @@
  */
 
 use super::{
-    BinaryReader, BinaryReaderError, EventType, ExternalKind, GlobalType, LinkingType, MemoryType,
-    NameType, Naming, Operator, Range, RelocType, Result, SectionCode, TableType, Type,
+    BinaryReader, BinaryReaderError, EventType, ExternalKind, GlobalType, MemoryType, NameType,
+    Naming, Operator, Range, RelocType, Result, TableType, Type,
 };
 
 pub use self::alias_section::*;
@@ -44,6 +44,24 @@ impl<T: WasmModuleResources> FuncValidator<T> {
         self.validator.operands.len() as u32
     }
 
+    /// Get the type of the operand `depth` values below the top of the
+    /// operand stack, e.g. `0` for the top.
+    ///
+    /// Returns `None` if the operand stack isn't that high. The type itself
+    /// is `None` if it's unknown, which happens for values produced by
+    /// instructions in unreachable code.
+    pub fn get_operand_type(&self, depth: usize) -> Option<Option<Type>> {
+        let operands = &self.validator.operands;
+        let i = operands.len().checked_sub(depth + 1)?;
+        Some(operands[i])
+    }
+
+    /// Whether the rest of the current control frame is unreachable, e.g.
+    /// after a `br` or `unreachable` instruction.
+    pub fn is_unreachable(&self) -> bool {
+        self.validator.is_unreachable()
+    }
+
     /// Convenience function to validate an entire function's body.
     ///
     /// You may not end up using this in final implementations because you'll
@@ -44,6 +44,16 @@ pub struct Printer {
     result: String,
     state: ModuleState,
     nesting: u32,
+    print_custom_sections: bool,
+    fold_instructions: bool,
+    print_offsets: bool,
+    print_stack_types: bool,
+    /// The line of each printed item, as `(offset, line)` pairs sorted by
+    /// offset once printing is done.
+    lines: Vec<(usize, usize)>,
+    /// The number of newlines in `result` up to `lines_counted`.
+    newlines: usize,
+    lines_counted: usize,
 }
 
 #[derive(Default)]
@@ -56,6 +66,7 @@ struct ModuleState {
     global: u32,
     table: u32,
     types: Vec<Option<FuncType>>,
+    func_types: Vec<Option<u32>>,
     names: HashMap<u32, Naming>,
     local_names: HashMap<u32, HashMap<u32, Naming>>,
     module_name: Option<Naming>,
@@ -97,6 +108,81 @@ impl Printer {
         self.printers.insert(section.to_string(), Box::new(printer));
     }
 
+    /// Whether to print custom sections without a registered printer as
+    /// `@custom` annotations.
+    ///
+    /// Each annotation is anchored to the last non-empty section before it in
+    /// the binary, e.g. `(@custom "producers" (after code) "...")`, so that
+    /// parsing the text again places it at the same position with the same
+    /// contents. The `name` section is always printed as identifiers instead.
+    ///
+    /// With module linking, sections of the same kind may appear several
+    /// times, which an anchor can't tell apart. Custom sections are never
+    /// anchored to such sections, so they may move to an earlier position
+    /// when the text is parsed again.
+    ///
+    /// Defaults to `false`, which ignores such custom sections.
+    pub fn print_custom_sections(&mut self, print: bool) -> &mut Self {
+        self.print_custom_sections = print;
+        self
+    }
+
+    /// Whether to print instructions in folded form, e.g.
+    /// `(i32.add (local.get 0) (i32.const 1))`.
+    ///
+    /// Operands are only nested into the instruction that consumes them when
+    /// they are evaluated right before it, so the folded text encodes the
+    /// same instructions in the same order. Everything else, including all
+    /// control instructions, is printed flat.
+    ///
+    /// Defaults to `false`, which prints all instructions flat.
+    pub fn fold_instructions(&mut self, fold: bool) -> &mut Self {
+        self.fold_instructions = fold;
+        self
+    }
+
+    /// Whether to prefix each printed section item and instruction with its
+    /// offset in the binary, e.g. `(;@1a3f  ;)`.
+    ///
+    /// The offsets are printed as block comments in a fixed-width column, so
+    /// the output can still be parsed and stays indented.
+    ///
+    /// Defaults to `false`.
+    pub fn print_offsets(&mut self, print: bool) -> &mut Self {
+        self.print_offsets = print;
+        self
+    }
+
+    /// Whether to follow each instruction with a comment that shows the
+    /// types on the operand stack after it, e.g. `;; [i32 i64]`.
+    ///
+    /// The types are computed by validating each function while it's printed.
+    /// Types that are unknown because they were produced in unreachable code
+    /// are shown as `?`, and instructions in unreachable code are marked as
+    /// `unreachable`. The first validation error in a function is shown
+    /// instead of its stack, after which the function isn't annotated
+    /// anymore. Functions aren't annotated at all if the module is invalid
+    /// outside of its function bodies, or if it uses the module linking
+    /// proposal.
+    ///
+    /// Instructions are never folded in this mode. Defaults to `false`.
+    pub fn print_stack_types(&mut self, print: bool) -> &mut Self {
+        self.print_stack_types = print;
+        self
+    }
+
+    /// Returns the zero-based line, in the text most recently returned by
+    /// [`Printer::print`], of the item or instruction at `offset` in the
+    /// binary.
+    ///
+    /// This is the line of the last printed item that starts at or before
+    /// `offset`, e.g. to locate an error reported by a validator. Returns
+    /// `None` if no item starts at or before `offset`.
+    pub fn line_for_offset(&self, offset: usize) -> Option<usize> {
+        let i = self.lines.partition_point(|&(start, _)| start <= offset);
+        Some(self.lines.get(i.checked_sub(1)?)?.1)
+    }
+
     /// Gets the output result of this `Printer`, or where all output is going.
     pub fn result_mut(&mut self) -> &mut String {
         &mut self.result
@@ -107,9 +193,13 @@ impl Printer {
     /// This function takes an entire `wasm` binary blob and will print it to
     /// the WebAssembly Text Format and return the result as a `String`.
     pub fn print(&mut self, mut wasm: &[u8]) -> Result<String> {
+        self.lines.clear();
+        self.newlines = 0;
+        self.lines_counted = 0;
         self.start_group("module");
         self.print_contents(Parser::new(0), &mut wasm, "")?;
         self.end_group();
+        self.lines.sort_unstable();
         Ok(mem::take(&mut self.result))
     }
 
@@ -123,9 +213,36 @@ impl Printer {
         // pretty names everywhere. Also look for the `code` section so we can
         // print out functions as soon as we hit the function section.
         let mut code = Vec::new();
+        let mut func_validators = Vec::new();
+        let mut validator = if self.print_stack_types {
+            let mut validator = Validator::new();
+            validator.wasm_features(WasmFeatures {
+                reference_types: true,
+                // This would forbid duplicate import names, which are valid
+                // otherwise, and nested modules are skipped here anyway.
+                module_linking: false,
+                simd: true,
+                multi_value: true,
+                threads: true,
+                tail_call: true,
+                bulk_memory: true,
+                deterministic_only: false,
+                multi_memory: true,
+                exceptions: true,
+                memory64: true,
+            });
+            Some(validator)
+        } else {
+            None
+        };
         let mut pre_parser = parser.clone();
         let prev = mem::take(&mut self.state);
         let mut bytes = *wasm;
+        // The anchors of sections that appear more than once, apart from
+        // directly after each other, which custom sections can't refer to.
+        let mut repeated_places = HashSet::new();
+        let mut seen_places = HashSet::new();
+        let mut last_place = None;
         loop {
             let payload = match pre_parser.parse(bytes, true)? {
                 Chunk::NeedMoreData(_) => unreachable!(),
@@ -134,6 +251,21 @@ impl Printer {
                     payload
                 }
             };
+            // Functions can only be validated if everything before them is
+            // valid.
+            if let Some(v) = &mut validator {
+                match v.payload(&payload) {
+                    Ok(ValidPayload::Func(func, _)) => func_validators.push(func),
+                    Ok(_) => {}
+                    Err(_) => validator = None,
+                }
+            }
+            if let Some(place) = custom_place_after(&payload) {
+                if last_place != Some(place) && !seen_places.insert(place) {
+                    repeated_places.insert(place);
+                }
+                last_place = Some(place);
+            }
             match payload {
                 Payload::CodeSectionEntry(f) => code.push(f),
                 Payload::ModuleSectionStart { size, .. } => {
@@ -159,6 +291,9 @@ impl Printer {
             name.write(&mut self.result);
         }
         self.result.push_str(module_ty);
+        // Where custom sections printed as `@custom` annotations are placed,
+        // relative to the last non-empty known section.
+        let mut custom_place = "before first";
         loop {
             let payload = match parser.parse(*wasm, true)? {
                 Chunk::NeedMoreData(_) => unreachable!(),
@@ -167,6 +302,11 @@ impl Printer {
                     payload
                 }
             };
+            if let Some(place) = custom_place_after(&payload) {
+                if !repeated_places.contains(place) {
+                    custom_place = place;
+                }
+            }
             match payload {
                 Payload::CustomSection {
                     name,
@@ -176,8 +316,20 @@ impl Printer {
                     let mut printers = mem::replace(&mut self.printers, HashMap::new());
                     if let Some(printer) = printers.get_mut(name) {
                         printer(self, data_offset, data)?;
+                    } else if name != "name" && self.print_custom_sections {
+                        self.newline();
+                        self.start_group("@custom ");
+                        self.print_str(name)?;
+                        write!(self.result, " ({}) ", custom_place)?;
+                        self.print_bytes(data)?;
+                        self.end_group();
                     }
                     self.printers = printers;
+                    // The `name` section is encoded last when parsing the
+                    // text, so anything after it goes after the last section.
+                    if name == "name" {
+                        custom_place = "after last";
+                    }
                 }
                 Payload::TypeSection(s) => self.print_types(s)?,
                 Payload::ImportSection(s) => self.print_imports(s)?,
@@ -185,15 +337,15 @@ impl Printer {
                     if reader.get_count() == 0 {
                         continue;
                     }
-                    self.print_code(&code, reader)?;
+                    self.print_code(&code, mem::take(&mut func_validators), reader)?;
                 }
                 Payload::TableSection(s) => self.print_tables(s)?,
                 Payload::MemorySection(s) => self.print_memories(s)?,
                 Payload::EventSection(s) => self.print_events(s)?,
                 Payload::GlobalSection(s) => self.print_globals(s)?,
                 Payload::ExportSection(s) => self.print_exports(s)?,
-                Payload::StartSection { func, .. } => {
-                    self.newline();
+                Payload::StartSection { func, range } => {
+                    self.newline_at(range.start);
                     self.start_group("start ");
                     self.print_func_idx(func)?;
                     self.end_group();
@@ -215,8 +367,8 @@ impl Printer {
                 Payload::InstanceSection(s) => self.print_instances(s)?,
 
                 Payload::ModuleSectionStart { .. } => {}
-                Payload::ModuleSectionEntry { parser, .. } => {
-                    self.newline();
+                Payload::ModuleSectionEntry { parser, range } => {
+                    self.newline_at(range.start);
                     self.start_group("module");
                     self.print_contents(parser, wasm, &format!(" (;{};)", self.state.module))?;
                     self.end_group();
@@ -285,11 +437,12 @@ impl Printer {
     }
 
     fn print_types(&mut self, parser: TypeSectionReader<'_>) -> Result<()> {
-        for ty in parser {
-            self.newline();
+        for ty in with_offsets(parser) {
+            let (offset, ty) = ty?;
+            self.newline_at(offset);
             self.start_group("type");
             write!(self.result, " (;{};) ", self.state.types.len())?;
-            let ty = match ty? {
+            let ty = match ty {
                 TypeDef::Func(ty) => {
                     self.start_group("func");
                     self.print_functype(&ty, None)?;
@@ -299,6 +452,7 @@ impl Printer {
                     self.newline();
                     self.start_group("module");
                     for import in ty.imports.iter() {
+                        self.newline();
                         self.print_import(import, false)?;
                     }
                     for export in ty.exports.iter() {
@@ -408,8 +562,8 @@ impl Printer {
     }
 
     fn print_imports(&mut self, parser: ImportSectionReader<'_>) -> Result<()> {
-        for import in parser {
-            let import = import?;
+        for import in with_offsets(parser) {
+            let (offset, import) = import?;
 
             // Handle the module linking proposal here where the first time we
             // see the module-name of a two-level import that translates to an
@@ -423,9 +577,13 @@ impl Printer {
                     self.state.instance += 1;
                 }
             }
+            self.newline_at(offset);
             self.print_import(&import, true)?;
             match import.ty {
-                ImportSectionEntryType::Function(_) => self.state.func += 1,
+                ImportSectionEntryType::Function(ty) => {
+                    self.state.func += 1;
+                    self.state.func_types.push(Some(ty));
+                }
                 ImportSectionEntryType::Module(_) => self.state.module += 1,
                 ImportSectionEntryType::Instance(_) => self.state.instance += 1,
                 ImportSectionEntryType::Table(_) => self.state.table += 1,
@@ -438,7 +596,6 @@ impl Printer {
     }
 
     fn print_import(&mut self, import: &Import<'_>, index: bool) -> Result<()> {
-        self.newline();
         self.start_group("import ");
         self.print_str(import.module)?;
         if let Some(field) = import.field {
@@ -557,9 +714,9 @@ impl Printer {
     }
 
     fn print_tables(&mut self, parser: TableSectionReader<'_>) -> Result<()> {
-        for table in parser {
-            let table = table?;
-            self.newline();
+        for table in with_offsets(parser) {
+            let (offset, table) = table?;
+            self.newline_at(offset);
             self.print_table_type(&table, true)?;
             self.end_group();
             self.state.table += 1;
@@ -568,9 +725,9 @@ impl Printer {
     }
 
     fn print_memories(&mut self, parser: MemorySectionReader<'_>) -> Result<()> {
-        for memory in parser {
-            let memory = memory?;
-            self.newline();
+        for memory in with_offsets(parser) {
+            let (offset, memory) = memory?;
+            self.newline_at(offset);
             self.print_memory_type(&memory, true)?;
             self.end_group();
             self.state.memory += 1;
@@ -579,9 +736,9 @@ impl Printer {
     }
 
     fn print_events(&mut self, parser: EventSectionReader<'_>) -> Result<()> {
-        for exn in parser {
-            let exn = exn?;
-            self.newline();
+        for exn in with_offsets(parser) {
+            let (offset, exn) = exn?;
+            self.newline_at(offset);
             self.print_event_type(&exn, true)?;
             self.end_group();
             self.state.event += 1;
@@ -590,9 +747,9 @@ impl Printer {
     }
 
     fn print_globals(&mut self, parser: GlobalSectionReader<'_>) -> Result<()> {
-        for global in parser {
-            let global = global?;
-            self.newline();
+        for global in with_offsets(parser) {
+            let (offset, global) = global?;
+            self.newline_at(offset);
             self.print_global_type(&global.ty, true)?;
             self.result.push_str(" ");
             self.print_init_expr(&global.init_expr)?;
@@ -605,11 +762,17 @@ impl Printer {
     fn print_code(
         &mut self,
         code: &[FunctionBody<'_>],
+        validators: Vec<FuncValidator<ValidatorResources>>,
         mut funcs: FunctionSectionReader<'_>,
     ) -> Result<()> {
+        let mut validators = validators.into_iter();
+        for ty in funcs.clone() {
+            self.state.func_types.push(Some(ty?));
+        }
         for body in code {
             let ty = funcs.read()?;
-            self.newline();
+            let mut validator = validators.next();
+            self.newline_at(body.range().start);
             self.start_group("func ");
             match self.state.names.get(&self.state.func) {
                 Some(name) => name.write(&mut self.result),
@@ -624,6 +787,11 @@ impl Printer {
             let mut locals = NamedLocalPrinter::new("local");
             for local in body.get_locals_reader()? {
                 let (cnt, ty) = local?;
+                if let Some(v) = &mut validator {
+                    if v.define_locals(body.range().start, cnt, ty).is_err() {
+                        validator = None;
+                    }
+                }
                 if MAX_LOCALS
                     .checked_sub(local_idx)
                     .and_then(|s| s.checked_sub(cnt))
@@ -650,13 +818,41 @@ impl Printer {
             locals.finish(&mut self.result);
 
             let nesting_start = self.nesting;
+            let mut folded = Vec::new();
             let mut reader = body.get_operators_reader()?;
+            let mut final_error = None;
             while !reader.eof() {
-                let operator = reader.read()?;
+                let (operator, offset) = reader.read_with_offset()?;
+                let error = match &mut validator {
+                    Some(v) => v.op(offset, &operator).err(),
+                    None => None,
+                };
+                if error.is_some() {
+                    validator = None;
+                }
+                if self.fold_instructions && !self.print_stack_types {
+                    match self.operator_arity(&operator) {
+                        Some((pops, pushes)) if pops <= folded.len() => {
+                            self.fold_operator(
+                                &operator,
+                                offset,
+                                pops,
+                                pushes,
+                                &mut folded,
+                                nesting_start,
+                            )?;
+                            continue;
+                        }
+                        _ => self.flush_folded(&mut folded),
+                    }
+                }
                 match operator {
                     // The final `end` in a reader is not printed, it's implied
                     // in the text format.
-                    Operator::End if reader.eof() => break,
+                    Operator::End if reader.eof() => {
+                        final_error = error;
+                        break;
+                    }
 
                     // When we start a block we newline to the current
                     // indentation, then we increase the indentation so further
@@ -665,7 +861,7 @@ impl Printer {
                     | Operator::Block { .. }
                     | Operator::Loop { .. }
                     | Operator::Try { .. } => {
-                        self.newline();
+                        self.newline_at(offset);
                         self.nesting += 1;
                     }
 
@@ -674,7 +870,7 @@ impl Printer {
                     // our nesting level.
                     Operator::Else | Operator::Catch { .. } | Operator::Unwind => {
                         self.nesting -= 1;
-                        self.newline();
+                        self.newline_at(offset);
                         self.nesting += 1;
                     }
 
@@ -682,14 +878,29 @@ impl Printer {
                     // level.
                     Operator::End if self.nesting > nesting_start => {
                         self.nesting -= 1;
-                        self.newline();
+                        self.newline_at(offset);
                     }
 
                     // .. otherwise everything else just has a normal newline
                     // out in front.
-                    _ => self.newline(),
+                    _ => self.newline_at(offset),
                 }
                 self.print_operator(&operator, nesting_start)?;
+                self.print_stack_comment(validator.as_ref(), error.as_ref())?;
+            }
+            self.flush_folded(&mut folded);
+            if let Some(v) = &mut validator {
+                final_error = v.finish(reader.original_position()).err();
+            }
+            if self.print_stack_types {
+                if let Some(error) = final_error {
+                    self.newline();
+                    write!(self.result, ";; error: {}", error.message())?;
+                }
+                // The closing paren can't follow a line comment.
+                self.nesting -= 1;
+                self.newline();
+                self.nesting += 1;
             }
             self.end_group();
 
@@ -698,6 +909,296 @@ impl Printer {
         Ok(())
     }
 
+    /// Prints the operand stack of `validator` as a comment, or `error` if
+    /// validating the instruction just printed failed.
+    fn print_stack_comment(
+        &mut self,
+        validator: Option<&FuncValidator<ValidatorResources>>,
+        error: Option<&BinaryReaderError>,
+    ) -> Result<()> {
+        if let Some(error) = error {
+            write!(self.result, "  ;; error: {}", error.message())?;
+            return Ok(());
+        }
+        let validator = match validator {
+            Some(validator) => validator,
+            None => return Ok(()),
+        };
+        self.result.push_str("  ;; [");
+        for depth in (0..validator.operand_stack_height() as usize).rev() {
+            match validator.get_operand_type(depth) {
+                Some(Some(ty)) => self.print_valtype(ty)?,
+                _ => self.result.push('?'),
+            }
+            if depth > 0 {
+                self.result.push(' ');
+            }
+        }
+        self.result.push(']');
+        if validator.is_unreachable() {
+            self.result.push_str(" unreachable");
+        }
+        Ok(())
+    }
+
+    /// Folds the `pops` topmost expressions of `folded` into `op`.
+    ///
+    /// The result is kept in `folded` for a later instruction to consume if
+    /// `op` produces exactly one value, and is printed otherwise.
+    fn fold_operator(
+        &mut self,
+        op: &Operator<'_>,
+        offset: usize,
+        pops: usize,
+        pushes: usize,
+        folded: &mut Vec<(usize, String)>,
+        nesting_start: u32,
+    ) -> Result<()> {
+        let operands = folded.split_off(folded.len() - pops);
+        // The expression starts with its first operand.
+        let offset = operands.first().map_or(offset, |(offset, _)| *offset);
+        let prev = mem::replace(&mut self.result, String::from("("));
+        let printed = self.print_operator(op, nesting_start);
+        let mut expr = mem::replace(&mut self.result, prev);
+        printed?;
+        for (_, operand) in operands {
+            expr.push(' ');
+            expr.push_str(&operand);
+        }
+        expr.push(')');
+        if pushes == 1 {
+            folded.push((offset, expr));
+        } else {
+            self.flush_folded(folded);
+            self.newline_at(offset);
+            self.result.push_str(&expr);
+        }
+        Ok(())
+    }
+
+    /// Prints the expressions that are still waiting to be folded into a
+    /// consumer, in the order they are evaluated.
+    fn flush_folded(&mut self, folded: &mut Vec<(usize, String)>) {
+        for (offset, expr) in folded.drain(..) {
+            self.newline_at(offset);
+            self.result.push_str(&expr);
+        }
+    }
+
+    /// Returns how many values `op` pops from and pushes onto the operand
+    /// stack, or `None` if it can't be folded.
+    fn operator_arity(&self, op: &Operator<'_>) -> Option<(usize, usize)> {
+        use Operator::*;
+        let func_type_arity = |idx: u32| match self.state.types.get(idx as usize) {
+            Some(Some(ty)) => Some((ty.params.len(), ty.returns.len())),
+            _ => None,
+        };
+        Some(match op {
+            Call { function_index } => {
+                let ty = self.state.func_types.get(*function_index as usize)?;
+                return func_type_arity((*ty)?);
+            }
+            CallIndirect { index, .. } => {
+                let (params, results) = func_type_arity(*index)?;
+                (params + 1, results)
+            }
+
+            LocalGet { .. }
+            | GlobalGet { .. }
+            | I32Const { .. }
+            | I64Const { .. }
+            | F32Const { .. }
+            | F64Const { .. }
+            | RefNull { .. }
+            | RefFunc { .. }
+            | MemorySize { .. }
+            | TableSize { .. } => (0, 1),
+
+            Drop | LocalSet { .. } | GlobalSet { .. } => (1, 0),
+
+            LocalTee { .. }
+            | MemoryGrow { .. }
+            | TableGet { .. }
+            | RefIsNull
+            | I32Load { .. }
+            | I64Load { .. }
+            | F32Load { .. }
+            | F64Load { .. }
+            | I32Load8S { .. }
+            | I32Load8U { .. }
+            | I32Load16S { .. }
+            | I32Load16U { .. }
+            | I64Load8S { .. }
+            | I64Load8U { .. }
+            | I64Load16S { .. }
+            | I64Load16U { .. }
+            | I64Load32S { .. }
+            | I64Load32U { .. }
+            | I32Eqz
+            | I64Eqz
+            | I32Clz
+            | I32Ctz
+            | I32Popcnt
+            | I64Clz
+            | I64Ctz
+            | I64Popcnt
+            | F32Abs
+            | F32Neg
+            | F32Ceil
+            | F32Floor
+            | F32Trunc
+            | F32Nearest
+            | F32Sqrt
+            | F64Abs
+            | F64Neg
+            | F64Ceil
+            | F64Floor
+            | F64Trunc
+            | F64Nearest
+            | F64Sqrt
+            | I32WrapI64
+            | I32TruncF32S
+            | I32TruncF32U
+            | I32TruncF64S
+            | I32TruncF64U
+            | I64ExtendI32S
+            | I64ExtendI32U
+            | I64TruncF32S
+            | I64TruncF32U
+            | I64TruncF64S
+            | I64TruncF64U
+            | F32ConvertI32S
+            | F32ConvertI32U
+            | F32ConvertI64S
+            | F32ConvertI64U
+            | F32DemoteF64
+            | F64ConvertI32S
+            | F64ConvertI32U
+            | F64ConvertI64S
+            | F64ConvertI64U
+            | F64PromoteF32
+            | I32ReinterpretF32
+            | I64ReinterpretF64
+            | F32ReinterpretI32
+            | F64ReinterpretI64
+            | I32Extend8S
+            | I32Extend16S
+            | I64Extend8S
+            | I64Extend16S
+            | I64Extend32S
+            | I32TruncSatF32S
+            | I32TruncSatF32U
+            | I32TruncSatF64S
+            | I32TruncSatF64U
+            | I64TruncSatF32S
+            | I64TruncSatF32U
+            | I64TruncSatF64S
+            | I64TruncSatF64U => (1, 1),
+
+            I32Store { .. }
+            | I64Store { .. }
+            | F32Store { .. }
+            | F64Store { .. }
+            | I32Store8 { .. }
+            | I32Store16 { .. }
+            | I64Store8 { .. }
+            | I64Store16 { .. }
+            | I64Store32 { .. }
+            | TableSet { .. } => (2, 0),
+
+            TableGrow { .. }
+            | I32Eq
+            | I32Ne
+            | I32LtS
+            | I32LtU
+            | I32GtS
+            | I32GtU
+            | I32LeS
+            | I32LeU
+            | I32GeS
+            | I32GeU
+            | I64Eq
+            | I64Ne
+            | I64LtS
+            | I64LtU
+            | I64GtS
+            | I64GtU
+            | I64LeS
+            | I64LeU
+            | I64GeS
+            | I64GeU
+            | F32Eq
+            | F32Ne
+            | F32Lt
+            | F32Gt
+            | F32Le
+            | F32Ge
+            | F64Eq
+            | F64Ne
+            | F64Lt
+            | F64Gt
+            | F64Le
+            | F64Ge
+            | I32Add
+            | I32Sub
+            | I32Mul
+            | I32DivS
+            | I32DivU
+            | I32RemS
+            | I32RemU
+            | I32And
+            | I32Or
+            | I32Xor
+            | I32Shl
+            | I32ShrS
+            | I32ShrU
+            | I32Rotl
+            | I32Rotr
+            | I64Add
+            | I64Sub
+            | I64Mul
+            | I64DivS
+            | I64DivU
+            | I64RemS
+            | I64RemU
+            | I64And
+            | I64Or
+            | I64Xor
+            | I64Shl
+            | I64ShrS
+            | I64ShrU
+            | I64Rotl
+            | I64Rotr
+            | F32Add
+            | F32Sub
+            | F32Mul
+            | F32Div
+            | F32Min
+            | F32Max
+            | F32Copysign
+            | F64Add
+            | F64Sub
+            | F64Mul
+            | F64Div
+            | F64Min
+            | F64Max
+            | F64Copysign => (2, 1),
+
+            Select | TypedSelect { .. } => (3, 1),
+
+            MemoryInit { .. }
+            | MemoryCopy { .. }
+            | MemoryFill { .. }
+            | TableInit { .. }
+            | TableCopy { .. }
+            | TableFill { .. } => (3, 0),
+
+            // Control instructions are printed flat, and the rest only
+            // haven't been needed yet.
+            _ => return None,
+        })
+    }
+
     fn newline(&mut self) {
         self.result.push_str("\n");
         for _ in 0..self.nesting {
@@ -705,6 +1206,21 @@ impl Printer {
         }
     }
 
+    /// Like `newline`, but for a line that prints the item at `offset` in the
+    /// binary.
+    fn newline_at(&mut self, offset: usize) {
+        self.result.push('\n');
+        self.newlines += self.result[self.lines_counted..].matches('\n').count();
+        self.lines_counted = self.result.len();
+        self.lines.push((offset, self.newlines));
+        if self.print_offsets {
+            write!(self.result, "(;@{:<6x};)", offset).unwrap();
+        }
+        for _ in 0..self.nesting {
+            self.result.push_str("  ");
+        }
+    }
+
     fn print_operator(&mut self, op: &Operator<'_>, nesting_start: u32) -> Result<()> {
         use Operator::*;
         let cur_label = self.nesting - nesting_start;
@@ -1480,9 +1996,9 @@ impl Printer {
     }
 
     fn print_exports(&mut self, data: ExportSectionReader) -> Result<()> {
-        for export in data {
-            let export = export?;
-            self.newline();
+        for export in with_offsets(data) {
+            let (offset, export) = export?;
+            self.newline_at(offset);
             self.start_group("export ");
             self.print_str(export.field)?;
             self.result.push_str(" ");
@@ -1546,9 +2062,9 @@ impl Printer {
     }
 
     fn print_elems(&mut self, data: ElementSectionReader) -> Result<()> {
-        for (i, elem) in data.into_iter().enumerate() {
-            let mut elem = elem?;
-            self.newline();
+        for (i, elem) in with_offsets(data).enumerate() {
+            let (offset, mut elem) = elem?;
+            self.newline_at(offset);
             self.start_group("elem");
             write!(self.result, " (;{};)", i)?;
             match &mut elem.kind {
@@ -1596,9 +2112,9 @@ impl Printer {
     }
 
     fn print_data(&mut self, data: DataSectionReader) -> Result<()> {
-        for (i, data) in data.into_iter().enumerate() {
-            let data = data?;
-            self.newline();
+        for (i, data) in with_offsets(data).enumerate() {
+            let (offset, data) = data?;
+            self.newline_at(offset);
             self.start_group("data");
             write!(self.result, " (;{};) ", i)?;
             match &data.kind {
@@ -1621,9 +2137,9 @@ impl Printer {
     }
 
     fn print_instances(&mut self, instances: InstanceSectionReader) -> Result<()> {
-        for instance in instances.into_iter() {
-            let instance = instance?;
-            self.newline();
+        for instance in with_offsets(instances) {
+            let (offset, instance) = instance?;
+            self.newline_at(offset);
             self.start_group("instance");
             write!(self.result, " (;{};)", self.state.instance)?;
             self.newline();
@@ -1644,9 +2160,9 @@ impl Printer {
     }
 
     fn print_aliases(&mut self, aliases: AliasSectionReader) -> Result<()> {
-        for alias in aliases {
-            let alias = alias?;
-            self.newline();
+        for alias in with_offsets(aliases) {
+            let (offset, alias) = alias?;
+            self.newline_at(offset);
             self.start_group("alias ");
             match alias {
                 Alias::InstanceExport {
@@ -1661,6 +2177,7 @@ impl Printer {
                                 None => write!(self.result, "(;{};)", self.state.func)?,
                             }
                             self.state.func += 1;
+                            self.state.func_types.push(None);
                         }
                         ExternalKind::Table => {
                             write!(self.result, "(;{};)", self.state.table)?;
@@ -1792,6 +2309,51 @@ impl Printer {
     }
 }
 
+/// Iterates over the items of a section along with their offsets in the
+/// binary.
+fn with_offsets<R>(mut reader: R) -> impl Iterator<Item = Result<(usize, R::Item)>>
+where
+    R: SectionReader + SectionWithLimitedItems,
+{
+    (0..reader.get_count()).map(move |_| {
+        let offset = reader.original_position();
+        Ok((offset, reader.read()?))
+    })
+}
+
+/// The placement of a custom section that directly follows `payload`, if
+/// `payload` is a known section with items.
+///
+/// Empty sections aren't anchors, since the text format only encodes a
+/// section when it has items.
+fn custom_place_after(payload: &Payload<'_>) -> Option<&'static str> {
+    let (count, place) = match payload {
+        Payload::TypeSection(s) => (s.get_count(), "after type"),
+        Payload::ImportSection(s) => (s.get_count(), "after import"),
+        Payload::FunctionSection(s) => (s.get_count(), "after func"),
+        Payload::TableSection(s) => (s.get_count(), "after table"),
+        Payload::MemorySection(s) => (s.get_count(), "after memory"),
+        Payload::EventSection(s) => (s.get_count(), "after event"),
+        Payload::GlobalSection(s) => (s.get_count(), "after global"),
+        Payload::ExportSection(s) => (s.get_count(), "after export"),
+        Payload::StartSection { .. } => (1, "after start"),
+        Payload::ElementSection(s) => (s.get_count(), "after elem"),
+        // The data count section can't be an anchor, but it is always
+        // encoded right before where the code section goes.
+        Payload::DataCountSection { .. } => (1, "before code"),
+        Payload::CodeSectionStart { count, .. } => (*count, "after code"),
+        Payload::DataSection(s) => (s.get_count(), "after data"),
+        Payload::AliasSection(s) => (s.get_count(), "after alias"),
+        Payload::InstanceSection(s) => (s.get_count(), "after instance"),
+        Payload::ModuleSectionStart { count, .. } => (*count, "after module"),
+        _ => return None,
+    };
+    if count == 0 {
+        return None;
+    }
+    Some(place)
+}
+
 struct NamedLocalPrinter {
     group_name: &'static str,
     in_group: bool,
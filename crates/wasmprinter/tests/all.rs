@@ -113,3 +113,229 @@ fn no_panic_dangling_else() {
     .unwrap();
     wasmprinter::print_bytes(&bytes).unwrap();
 }
+
+#[test]
+fn custom_sections_roundtrip() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (@custom "first" (before first) "\00\01")
+                (@custom "after-type" (after type) "a")
+                (type (func))
+                (memory 1)
+                (func (type 0) i32.const 0 i32.const 0 i32.const 0 memory.init 0)
+                (@custom "before-code" (before code) "b")
+                (data "x")
+                (func $named (type 0))
+                (@custom "after-data" (after data) "c")
+                (@custom "last" "d\"\\")
+            )
+        "#,
+    )
+    .unwrap();
+
+    // Custom sections are dropped by default...
+    let text = wasmprinter::print_bytes(&bytes).unwrap();
+    assert!(!text.contains("@custom"), "{}", text);
+
+    // ... but can be printed such that they round-trip exactly.
+    let text = wasmprinter::Printer::new()
+        .print_custom_sections(true)
+        .print(&bytes)
+        .unwrap();
+    assert!(
+        text.contains(r#"(@custom "before-code" (before code) "b")"#),
+        "{}",
+        text
+    );
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes, "{}", text);
+}
+
+#[test]
+fn custom_sections_after_empty_sections() {
+    let module = wat::parse_str(
+        r#"
+            (module
+                (import "i" (instance $i (export "f" (func))))
+                (alias $f (func $i "f"))
+                (func call $f)
+            )
+        "#,
+    )
+    .unwrap();
+    // An empty type section followed by a custom section, which the text
+    // format can't anchor to the type section since it has no types.
+    let mut bytes = module[..8].to_vec();
+    bytes.extend(&[1, 1, 0]);
+    bytes.extend(&[0, 3, 1, b'c', b'x']);
+    bytes.extend(&module[8..]);
+
+    let text = wasmprinter::Printer::new()
+        .print_custom_sections(true)
+        .print(&bytes)
+        .unwrap();
+    assert!(
+        text.contains(r#"(@custom "c" (before first) "x")"#),
+        "{}",
+        text
+    );
+    let mut expected = module[..8].to_vec();
+    expected.extend(&[0, 3, 1, b'c', b'x']);
+    expected.extend(&module[8..]);
+    assert_eq!(wat::parse_str(&text).unwrap(), expected, "{}", text);
+}
+
+#[test]
+fn custom_sections_after_repeated_sections() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "i" (instance $i (export "f" (func)) (export "g" (func))))
+                (alias $f (func $i "f"))
+                (type (func))
+                (alias $g (func $i "g"))
+                (@custom "c" (before func) "x")
+                (func call $f call $g)
+            )
+        "#,
+    )
+    .unwrap();
+
+    // The custom section follows the second `alias` and `type` sections, so
+    // it can't be anchored to either of them without appearing twice.
+    let text = wasmprinter::Printer::new()
+        .print_custom_sections(true)
+        .print(&bytes)
+        .unwrap();
+    assert!(
+        text.contains(r#"(@custom "c" (after import) "x")"#),
+        "{}",
+        text
+    );
+    let customs = wasmparser::Parser::new(0)
+        .parse_all(&wat::parse_str(&text).unwrap())
+        .filter(|payload| {
+            matches!(
+                payload,
+                Ok(wasmparser::Payload::CustomSection { name: "c", .. })
+            )
+        })
+        .count();
+    assert_eq!(customs, 1, "{}", text);
+}
+
+#[test]
+fn fold_instructions() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (func $f (param i32) (result i32)
+                    local.get 0
+                    i32.const 1
+                    i32.add
+                    local.set 0
+                    call $g
+                    block
+                        local.get 0
+                        br_if 0
+                    end
+                    local.get 0
+                    i32.eqz
+                    local.get 0)
+                (func $g (result i32 i32)
+                    i32.const 1
+                    i32.const 2)
+            )
+        "#,
+    )
+    .unwrap();
+    let text = wasmprinter::Printer::new()
+        .fold_instructions(true)
+        .print(&bytes)
+        .unwrap();
+    assert!(
+        text.contains("(local.set 0 (i32.add (local.get 0) (i32.const 1)))"),
+        "{}",
+        text
+    );
+    // Results that aren't consumed by the next instruction, and control
+    // instructions, are printed flat.
+    assert!(text.contains("\n    (call $g)\n    block"), "{}", text);
+    assert!(text.contains("(local.get 0)\n      br_if 0"), "{}", text);
+    assert!(
+        text.contains("(i32.eqz (local.get 0))\n    (local.get 0)"),
+        "{}",
+        text
+    );
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes, "{}", text);
+}
+
+#[test]
+fn print_offsets() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (memory 1)
+                (func (export "f") (result i32)
+                    i32.const 1
+                    i32.const 2
+                    i32.add)
+            )
+        "#,
+    )
+    .unwrap();
+    let mut printer = wasmprinter::Printer::new();
+    let text = printer.print_offsets(true).print(&bytes).unwrap();
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes, "{}", text);
+
+    // `i32.add` is the second to last byte, before the function's `end`.
+    let offset = bytes.len() - 2;
+    assert_eq!(bytes[offset], 0x6a);
+    let line = printer.line_for_offset(offset).unwrap();
+    let expected = format!("(;@{:<6x};)    i32.add)", offset);
+    assert_eq!(text.lines().nth(line), Some(&expected[..]), "{}", text);
+
+    // Offsets within an instruction map to the line of that instruction.
+    let line = printer.line_for_offset(offset - 1).unwrap();
+    assert!(text.lines().nth(line).unwrap().ends_with("i32.const 2"));
+    assert_eq!(printer.line_for_offset(0), None);
+}
+
+#[test]
+fn print_stack_types() {
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (func (param i32) (result i32)
+                    local.get 0
+                    i64.const 1
+                    drop
+                    block
+                        br 0
+                        drop
+                    end)
+                (func (result i32)
+                    i64.const 1
+                    i32.eqz)
+            )
+        "#,
+    )
+    .unwrap();
+    let text = wasmprinter::Printer::new()
+        .print_stack_types(true)
+        .print(&bytes)
+        .unwrap();
+    for expected in [
+        "i64.const 1  ;; [i32 i64]",
+        "drop  ;; [i32]",
+        "br 0 (;@1;)  ;; [i32] unreachable",
+        "drop  ;; [i32] unreachable",
+        "end  ;; [i32]",
+        "i32.eqz  ;; error: type mismatch: expected i32, found i64",
+    ]
+    .iter()
+    {
+        assert!(text.contains(expected), "{}", text);
+    }
+    assert_eq!(wat::parse_str(&text).unwrap(), bytes, "{}", text);
+}
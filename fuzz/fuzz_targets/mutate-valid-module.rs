@@ -0,0 +1,32 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasm_mutate::WasmMutate;
+use wasm_smith::Module;
+
+fuzz_target!(|input: (Module, u64)| {
+    let (m, seed) = input;
+    let bytes = m.to_bytes();
+
+    let features = wasmparser::WasmFeatures {
+        multi_value: true,
+        ..wasmparser::WasmFeatures::default()
+    };
+    let mutated = match WasmMutate::new()
+        .features(features)
+        .preserve_export_names(false)
+        .mutate_with_seed(&bytes, seed)
+    {
+        Ok(mutated) => mutated,
+        Err(_) => return,
+    };
+
+    // Mutations must never turn a valid module into an invalid one.
+    let mut validator = wasmparser::Validator::new();
+    validator.wasm_features(features);
+    if let Err(e) = validator.validate_all(&mutated) {
+        std::fs::write("test.wasm", bytes).unwrap();
+        std::fs::write("mutated.wasm", mutated).unwrap();
+        panic!("Invalid mutated module: {}", e);
+    }
+});
@@ -1,16 +1,46 @@
 #![no_main]
 
+use libfuzzer_sys::arbitrary::{Arbitrary, Result, Unstructured};
 use libfuzzer_sys::fuzz_target;
-use wasm_smith::Module;
+use wasm_smith::{Config, ConfiguredModule};
 
-fuzz_target!(|module: Module| {
+/// The default configuration, plus SIMD, and maybe a name section and custom
+/// sections.
+#[derive(Debug, Default, Copy, Clone)]
+struct SimdConfig {
+    name_section_enabled: bool,
+}
+
+impl Arbitrary for SimdConfig {
+    fn arbitrary(u: &mut Unstructured) -> Result<Self> {
+        Ok(SimdConfig {
+            name_section_enabled: u.arbitrary()?,
+        })
+    }
+}
+
+impl Config for SimdConfig {
+    fn simd_enabled(&self) -> bool {
+        true
+    }
+
+    fn name_section_enabled(&self) -> bool {
+        self.name_section_enabled
+    }
+
+    fn max_custom_sections(&self) -> usize {
+        5
+    }
+}
+
+fuzz_target!(|module: ConfiguredModule<SimdConfig>| {
     let bytes = module.to_bytes();
 
-    let wat_string = wasmprinter::print_bytes(&bytes).unwrap_or_else(|e| {
+    let wat_string = print(&bytes, false).unwrap_or_else(|e| {
         fail(
             &bytes,
             &e,
-            "failed first disassembly of Wasm into wat with `wasmprinter::print_bytes`",
+            "failed first disassembly of Wasm into wat with `wasmprinter::Printer`",
         )
     });
     let wasm_bytes = wat::parse_str(&wat_string).unwrap_or_else(|e| {
@@ -20,11 +50,11 @@ fuzz_target!(|module: Module| {
             "failed to assemble wat into Wasm with `wat::parse_str`",
         )
     });
-    let wat_string2 = wasmprinter::print_bytes(&wasm_bytes).unwrap_or_else(|e| {
+    let wat_string2 = print(&wasm_bytes, false).unwrap_or_else(|e| {
         fail(
             &bytes,
             &e,
-            "failed second disassembly of Wasm into wat with `wasmprinter::print_bytes`",
+            "failed second disassembly of Wasm into wat with `wasmprinter::Printer`",
         )
     });
 
@@ -35,8 +65,39 @@ fuzz_target!(|module: Module| {
             "failed to roundtrip valid module",
         );
     }
+
+    let folded = print(&wasm_bytes, true).unwrap_or_else(|e| {
+        fail(
+            &bytes,
+            &e,
+            "failed folded disassembly of Wasm into wat with `wasmprinter::Printer`",
+        )
+    });
+    let folded_bytes = wat::parse_str(&folded).unwrap_or_else(|e| {
+        fail(
+            &bytes,
+            &e,
+            "failed to assemble folded wat into Wasm with `wat::parse_str`",
+        )
+    });
+    if folded_bytes != wasm_bytes {
+        fail(
+            &bytes,
+            &"folded disassembly assembles to different Wasm",
+            "failed to roundtrip valid module through folded wat",
+        );
+    }
 });
 
+/// Prints `bytes` with its custom sections as `@custom` annotations, so that
+/// their placement round-trips too.
+fn print(bytes: &[u8], fold: bool) -> std::result::Result<String, impl std::fmt::Display> {
+    wasmprinter::Printer::new()
+        .print_custom_sections(true)
+        .fold_instructions(fold)
+        .print(bytes)
+}
+
 fn fail(bytes: &[u8], error: &impl std::fmt::Display, msg: &str) -> ! {
     eprintln!("Writing test case to `test.wasm`.");
     std::fs::write("test.wasm", bytes).unwrap();
@@ -0,0 +1,44 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use wasm_smith::{Config, InvalidModule, SwarmConfig, Violation};
+
+// Each invalid module violates one known rule, so the validator must reject
+// it with an error for that rule.
+fuzz_target!(|m: InvalidModule<SwarmConfig>| {
+    let bytes = m.to_bytes();
+    let config = m.module().config();
+
+    let mut validator = wasmparser::Validator::new();
+    validator.wasm_features(wasmparser::WasmFeatures {
+        multi_value: true,
+        multi_memory: true,
+        bulk_memory: true,
+        reference_types: true,
+        simd: config.simd_enabled(),
+        threads: config.threads_enabled(),
+        exceptions: config.exceptions_enabled(),
+        tail_call: config.tail_call_enabled(),
+        memory64: config.memory64_enabled(),
+        deterministic_only: config.deterministic_only(),
+        module_linking: config.module_linking_enabled(),
+    });
+    let expected = match m.violation() {
+        Violation::TypeMismatch => "type mismatch",
+        Violation::IndexOutOfBounds => "index out of bounds",
+        Violation::MissingEnd => "control frames remain",
+        Violation::DisabledFeature => "support is not enabled",
+        Violation::LimitExceeded => "size minimum must not be greater than maximum",
+    };
+    let message = match validator.validate_all(&bytes) {
+        Ok(()) => None,
+        Err(e) => Some(e.to_string()),
+    };
+    if !matches!(&message, Some(m) if m.contains(expected)) {
+        std::fs::write("test.wasm", &bytes).unwrap();
+        if let Ok(wat) = wasmprinter::print_bytes(&bytes) {
+            std::fs::write("test.wat", wat).unwrap();
+        }
+        panic!("expected {:?} error, found {:?}", m.violation(), message);
+    }
+});
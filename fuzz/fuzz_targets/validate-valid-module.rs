@@ -17,6 +17,11 @@ fuzz_target!(|m: ConfiguredModule<SwarmConfig>| {
         multi_memory: true,
         bulk_memory: true,
         reference_types: true,
+        simd: m.config().simd_enabled(),
+        threads: m.config().threads_enabled(),
+        exceptions: m.config().exceptions_enabled(),
+        tail_call: m.config().tail_call_enabled(),
+        memory64: m.config().memory64_enabled(),
         module_linking: m.config().module_linking_enabled(),
         ..wasmparser::WasmFeatures::default()
     });
@@ -5,6 +5,22 @@ use std::env;
 fn main() -> anyhow::Result<()> {
     let mut opts = Options::new();
     opts.optopt("o", "", "set output file name", "NAME");
+    opts.optflag(
+        "",
+        "custom-sections",
+        "print custom sections as `@custom` annotations",
+    );
+    opts.optflag("", "fold-instructions", "print instructions in folded form");
+    opts.optflag(
+        "",
+        "offsets",
+        "prefix items and instructions with their binary offsets",
+    );
+    opts.optflag(
+        "",
+        "stack-types",
+        "follow instructions with the operand stack types after them",
+    );
     opts.optflag("h", "help", "print this help menu");
     let matches = opts.parse(env::args_os().skip(1))?;
     if matches.opt_present("h") {
@@ -18,7 +34,13 @@ fn main() -> anyhow::Result<()> {
         std::process::exit(1);
     };
 
-    let wit = wasmprinter::print_file(&input)?;
+    let wasm = std::fs::read(&input).context(format!("failed to read `{}`", input))?;
+    let wit = wasmprinter::Printer::new()
+        .print_custom_sections(matches.opt_present("custom-sections"))
+        .fold_instructions(matches.opt_present("fold-instructions"))
+        .print_offsets(matches.opt_present("offsets"))
+        .print_stack_types(matches.opt_present("stack-types"))
+        .print(&wasm)?;
     if let Some(output) = matches.opt_str("o") {
         std::fs::write(&output, wit).context(format!("failed to write `{}`", output))?;
     } else {